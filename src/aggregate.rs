@@ -0,0 +1,209 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that reduce an observable to a single value.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// The reason that `single()` did not produce a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SingleError<E> {
+    /// The observable failed with the error.
+    Failed(E),
+
+    /// The observable completed without pushing a value.
+    Empty,
+
+    /// The observable pushed more than one value.
+    Multiple,
+}
+
+impl<E: fmt::Display> fmt::Display for SingleError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SingleError::Failed(ref error) => write!(f, "observable failed: {}", error),
+            SingleError::Empty => write!(f, "observable pushed no value"),
+            SingleError::Multiple => write!(f, "observable pushed more than one value"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for SingleError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SingleError::Failed(ref error) => Some(error),
+            SingleError::Empty | SingleError::Multiple => None,
+        }
+    }
+}
+
+/// The result of calling `single()` or `single_or_default()` on an observable.
+pub struct SingleObservable<Source, T> {
+    source: Source,
+
+    // The value to push when the source completes without a value.
+    default: Option<T>,
+}
+
+struct SingleObserver<O, T> {
+    // The observer is gone once it failed.
+    observer: Option<O>,
+    item: Option<T>,
+    default: Option<T>,
+}
+
+impl<Source, T> SingleObservable<Source, T> {
+    pub fn new(source: Source, default: Option<T>) -> SingleObservable<Source, T> {
+        SingleObservable {
+            source: source,
+            default: default,
+        }
+    }
+}
+
+impl<Source, T> Observable for SingleObservable<Source, T>
+where Source: Observable<Item = T>,
+      T: Clone + 'static {
+    type Item = T;
+    type Error = SingleError<Source::Error>;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let single_observer = SingleObserver {
+            observer: Some(observer),
+            item: None,
+            default: self.default.clone(),
+        };
+        self.source.subscribe(single_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("single", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<T, E> for SingleObserver<O, T>
+    where O: Observer<T, SingleError<E>> {
+    fn on_next(&mut self, item: T) {
+        if self.item.is_none() {
+            self.item = Some(item);
+        } else if let Some(observer) = self.observer.take() {
+            // Values after the second one are ignored.
+            observer.on_error(SingleError::Multiple);
+        }
+    }
+
+    fn on_completed(self) {
+        let mut observer = match self.observer {
+            Some(observer) => observer,
+            None => return,
+        };
+        match self.item.or(self.default) {
+            Some(item) => {
+                observer.on_next(item);
+                observer.on_completed();
+            }
+            None => observer.on_error(SingleError::Empty),
+        }
+    }
+
+    fn on_error(self, error: E) {
+        if let Some(observer) = self.observer {
+            observer.on_error(SingleError::Failed(error));
+        }
+    }
+}
+
+/// The result of calling `min_by_key()` or `max_by_key()` on an observable.
+pub struct MinMaxByKeyObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+
+    is_max: bool,
+}
+
+struct MinMaxByKeyObserver<O, F, T, K> {
+    observer: O,
+    f: Rc<F>,
+    is_max: bool,
+
+    // The best value so far, with its key.
+    best: Option<(K, T)>,
+}
+
+impl<Source, F> MinMaxByKeyObservable<Source, F> {
+    pub fn new(source: Source, f: F, is_max: bool) -> MinMaxByKeyObservable<Source, F> {
+        MinMaxByKeyObservable {
+            source: source,
+            f: Rc::new(f),
+            is_max: is_max,
+        }
+    }
+}
+
+impl<Source, F, K> Observable for MinMaxByKeyObservable<Source, F>
+where Source: Observable,
+      Source::Item: 'static,
+      F: Fn(&Source::Item) -> K + 'static,
+      K: Ord + 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let min_max_observer = MinMaxByKeyObserver {
+            observer: observer,
+            f: self.f.clone(),
+            is_max: self.is_max,
+            best: None,
+        };
+        self.source.subscribe(min_max_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        let name = if self.is_max { "max_by_key" } else { "min_by_key" };
+        Graph::operator::<Self>(name, vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, F, K> Observer<T, E> for MinMaxByKeyObserver<O, F, T, K>
+where O: Observer<T, E>,
+      F: Fn(&T) -> K,
+      K: Ord {
+    fn on_next(&mut self, item: T) {
+        let key = (self.f)(&item);
+        // Like for iterators, the first minimum and the last maximum win.
+        let is_better = match self.best {
+            Some((ref best_key, _)) if self.is_max => key >= *best_key,
+            Some((ref best_key, _)) => key < *best_key,
+            None => true,
+        };
+        if is_better {
+            self.best = Some((key, item));
+        }
+    }
+
+    fn on_completed(mut self) {
+        if let Some((_key, item)) = self.best {
+            self.observer.on_next(item);
+        }
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
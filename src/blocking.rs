@@ -0,0 +1,311 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Adapters that block the current thread to consume an observable.
+
+use observable::Observable;
+use observer::Observer;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// The number of values that a blocking iterator buffers.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+// The queue between the observer and the blocking iterator. The condition
+// variable is signalled whenever the state changes, both to wake up the
+// iterator and producers that wait for room in the queue.
+struct Channel<T, E> {
+    state: Mutex<ChannelState<T, E>>,
+    changed: Condvar,
+    capacity: usize,
+
+    // The thread that iterates. A producer on that thread must not wait for
+    // room in the queue, because nobody would make room.
+    consumer: ThreadId,
+}
+
+struct ChannelState<T, E> {
+    queue: VecDeque<T>,
+    error: Option<E>,
+
+    // Set upon completion or failure.
+    terminated: bool,
+
+    // Set when the iterator is dropped.
+    closed: bool,
+}
+
+impl<T, E> Channel<T, E> {
+    fn lock<'s>(&'s self) -> MutexGuard<'s, ChannelState<T, E>> {
+        // The state is consistent even if a thread panicked while holding the
+        // lock, so the poisoning can be ignored.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait<'s>(&'s self, guard: MutexGuard<'s, ChannelState<T, E>>)
+                -> MutexGuard<'s, ChannelState<T, E>> {
+        self.changed.wait(guard).unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait_timeout<'s>(&'s self, guard: MutexGuard<'s, ChannelState<T, E>>, timeout: Duration)
+                        -> MutexGuard<'s, ChannelState<T, E>> {
+        match self.changed.wait_timeout(guard, timeout) {
+            Ok((guard, _)) => guard,
+            Err(poisoned) => poisoned.into_inner().0,
+        }
+    }
+}
+
+/// An observer that pushes into the queue of a blocking iterator.
+pub struct BlockingObserver<T, E> {
+    channel: Arc<Channel<T, E>>,
+}
+
+impl<T, E> Observer<T, E> for BlockingObserver<T, E> {
+    fn on_next(&mut self, item: T) {
+        let mut state = self.channel.lock();
+        if thread::current().id() != self.channel.consumer {
+            while state.queue.len() >= self.channel.capacity && !state.closed {
+                state = self.channel.wait(state);
+            }
+        }
+        if !state.closed {
+            state.queue.push_back(item);
+            self.channel.changed.notify_all();
+        }
+    }
+
+    fn on_completed(self) {
+        self.channel.lock().terminated = true;
+        self.channel.changed.notify_all();
+    }
+
+    fn on_error(self, error: E) {
+        let mut state = self.channel.lock();
+        state.error = Some(error);
+        state.terminated = true;
+        self.channel.changed.notify_all();
+    }
+}
+
+/// The result of calling `into_blocking_iter()` on an observable.
+///
+/// The iterator yields `Ok(item)` for every value, and it ends after the
+/// observable completes. If the observable fails, the last element is
+/// `Err(error)`. If the observable never completes, the iterator blocks
+/// forever once it has taken all values. Dropping the iterator drops the
+/// subscription.
+///
+/// Values are buffered until the iterator takes them. When the buffer is
+/// full, an observable that pushes from a different thread is blocked until
+/// there is room again. An observable that pushes on the iterating thread is
+/// never blocked, because that would deadlock; such an observable must push
+/// before `next()` is called, for instance upon subscription.
+pub struct BlockingIter<T, E, Subs> {
+    channel: Arc<Channel<T, E>>,
+    _subscription: Subs,
+}
+
+/// Creates a blocking iterator from a function that subscribes its observer.
+pub fn blocking_iter<T, E, Subs, F>(subscribe: F) -> BlockingIter<T, E, Subs>
+    where F: FnOnce(BlockingObserver<T, E>) -> Subs {
+    let channel = Arc::new(Channel {
+        state: Mutex::new(ChannelState {
+            queue: VecDeque::new(),
+            error: None,
+            terminated: false,
+            closed: false,
+        }),
+        changed: Condvar::new(),
+        capacity: DEFAULT_CAPACITY,
+        consumer: thread::current().id(),
+    });
+    let observer = BlockingObserver {
+        channel: channel.clone(),
+    };
+    BlockingIter {
+        _subscription: subscribe(observer),
+        channel: channel,
+    }
+}
+
+impl<T, E, Subs> BlockingIter<T, E, Subs> {
+    // As `next()`, but returns `Err(())` if the deadline passes first.
+    fn next_before(&mut self, deadline: Option<Instant>) -> Result<Option<Result<T, E>>, ()> {
+        let mut state = self.channel.lock();
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                // There is room in the queue now.
+                self.channel.changed.notify_all();
+                return Ok(Some(Ok(item)));
+            }
+            if let Some(error) = state.error.take() {
+                return Ok(Some(Err(error)));
+            }
+            if state.terminated {
+                return Ok(None);
+            }
+            state = match deadline {
+                None => self.channel.wait(state),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(());
+                    }
+                    self.channel.wait_timeout(state, deadline - now)
+                }
+            };
+        }
+    }
+}
+
+impl<T, E, Subs> Iterator for BlockingIter<T, E, Subs> {
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Result<T, E>> {
+        self.next_before(None).unwrap_or(None)
+    }
+}
+
+impl<T, E, Subs> Drop for BlockingIter<T, E, Subs> {
+    fn drop(&mut self) {
+        // Release producers that wait for room. The subscription is dropped
+        // after this.
+        self.channel.lock().closed = true;
+        self.channel.changed.notify_all();
+    }
+}
+
+/// The reason that a blocking operation did not produce a result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockingError<E> {
+    /// The observable failed with the error.
+    Failed(E),
+
+    /// The timeout passed before the observable produced the result.
+    TimedOut,
+}
+
+impl<E: fmt::Display> fmt::Display for BlockingError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BlockingError::Failed(ref error) => write!(f, "observable failed: {}", error),
+            BlockingError::TimedOut => write!(f, "timed out waiting for observable"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for BlockingError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            BlockingError::Failed(ref error) => Some(error),
+            BlockingError::TimedOut => None,
+        }
+    }
+}
+
+/// The result of calling `blocking()` on an observable.
+///
+/// Every method subscribes to the observable, and blocks the current thread
+/// until the result is known. The subscription is dropped before the method
+/// returns. See `BlockingIter` for which observables can be waited for.
+pub struct BlockingObservable<Source> {
+    source: Source,
+    timeout: Option<Duration>,
+}
+
+impl<Source: Observable> BlockingObservable<Source>
+where Source::Item: 'static, Source::Error: 'static {
+    pub fn new(source: Source) -> BlockingObservable<Source> {
+        BlockingObservable {
+            source: source,
+            timeout: None,
+        }
+    }
+
+    /// Gives up waiting after the duration.
+    ///
+    /// The timeout applies to every call separately. When it passes, the
+    /// method returns `Err(BlockingError::TimedOut)`.
+    pub fn timeout(mut self, timeout: Duration) -> BlockingObservable<Source> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Calls `f` with every value until it returns false, or until the
+    /// observable completes or fails.
+    fn for_each<F>(&mut self, mut f: F) -> Result<(), BlockingError<Source::Error>>
+        where F: FnMut(Source::Item) -> bool {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let source = &mut self.source;
+        let mut iter = blocking_iter(|observer| source.subscribe(observer));
+        loop {
+            match iter.next_before(deadline) {
+                Ok(Some(Ok(item))) => {
+                    if !f(item) {
+                        return Ok(());
+                    }
+                }
+                Ok(Some(Err(error))) => return Err(BlockingError::Failed(error)),
+                Ok(None) => return Ok(()),
+                Err(()) => return Err(BlockingError::TimedOut),
+            }
+        }
+    }
+
+    /// Returns the first value, or `None` if the observable completes without
+    /// producing a value.
+    pub fn first(&mut self) -> Result<Option<Source::Item>, BlockingError<Source::Error>> {
+        let mut first = None;
+        self.for_each(|item| { first = Some(item); false })?;
+        Ok(first)
+    }
+
+    /// Returns the last value once the observable completes, or `None` if it
+    /// completes without producing a value.
+    pub fn last(&mut self) -> Result<Option<Source::Item>, BlockingError<Source::Error>> {
+        let mut last = None;
+        self.for_each(|item| { last = Some(item); true })?;
+        Ok(last)
+    }
+
+    /// Returns all values once the observable completes.
+    pub fn collect_vec(&mut self) -> Result<Vec<Source::Item>, BlockingError<Source::Error>> {
+        let mut items = Vec::new();
+        self.for_each(|item| { items.push(item); true })?;
+        Ok(items)
+    }
+
+    /// Waits until the observable completes, and discards its values.
+    pub fn wait_for_completion(&mut self) -> Result<(), BlockingError<Source::Error>> {
+        self.for_each(|_item| true)
+    }
+}
+
+/// Subscribes to the observable and returns its values once it completes.
+///
+/// See `ObservableExt::collect_blocking()`.
+pub fn collect<Source>(mut source: Source) -> Result<Vec<Source::Item>, Source::Error>
+    where Source: Observable, Source::Item: 'static, Source::Error: 'static {
+    let mut iter = blocking_iter(|observer| source.subscribe(observer));
+    let mut items = Vec::new();
+    loop {
+        // The observer need not be `Send`, so the observable pushes on this
+        // thread. If it has not terminated by now, it will not terminate
+        // while this thread waits, so do not wait at all.
+        match iter.next_before(Some(Instant::now())) {
+            Ok(Some(Ok(item))) => items.push(item),
+            Ok(Some(Err(error))) => return Err(error),
+            Ok(None) => return Ok(items),
+            Err(()) => panic!("collect_blocking() called on an observable that did not complete"),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Type-erased observables.
+
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use subscription::Subscription;
+
+/// Object-safe counterpart of `Observable`.
+///
+/// `Observable::subscribe()` is generic over the observer, so it cannot be
+/// called on a trait object. This trait takes the observer as a box instead,
+/// and it boxes the subscription.
+trait ErasedObservable<T, E> {
+    fn subscribe_erased(&mut self, observer: Box<dyn BoxedObserver<T, E>>) -> Box<dyn Subscription>;
+
+    fn describe_erased(&self) -> Graph;
+}
+
+impl<Source> ErasedObservable<Source::Item, Source::Error> for Source
+where Source: Observable,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Source::Subscription: 'static {
+    fn subscribe_erased(&mut self,
+                        observer: Box<dyn BoxedObserver<Source::Item, Source::Error>>)
+                        -> Box<dyn Subscription> {
+        Box::new(self.subscribe(observer))
+    }
+
+    fn describe_erased(&self) -> Graph {
+        self.describe()
+    }
+}
+
+/// An observable whose concrete type has been erased, see `ObservableExt::boxed()`.
+///
+/// All observables with the same item and error type box to the same type, so
+/// they can be stored in struct fields or collections, and returned from trait
+/// methods. Subscribing costs an allocation for the observer and one for the
+/// subscription, and every notification goes through a virtual call.
+pub struct BoxedObservable<T, E> {
+    source: Box<dyn ErasedObservable<T, E>>,
+}
+
+impl<T: 'static, E: 'static> BoxedObservable<T, E> {
+    /// Erases the type of the observable.
+    pub fn new<Source>(source: Source) -> BoxedObservable<T, E>
+        where Source: Observable<Item = T, Error = E> + 'static,
+              Source::Subscription: 'static {
+        BoxedObservable {
+            source: Box::new(source),
+        }
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Observable for BoxedObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = Box<dyn Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Box<dyn Subscription>
+        where O: Observer<T, E> + 'static {
+        self.source.subscribe_erased(observer.boxed())
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("boxed", vec![self.source.describe_erased()])
+    }
+}
@@ -0,0 +1,577 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that collect values into batches or windows.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use subject::{Subject, SubjectObservable};
+use subscription::Subscription;
+
+/// The result of calling `buffer_time_or_count()` on an observable.
+pub struct BufferTimeOrCountObservable<Source, Sch> {
+    source: Source,
+    duration: Duration,
+    count: usize,
+    scheduler: Sch,
+}
+
+/// The result of subscribing to a `buffer_time_or_count()` or `buffer_with()`
+/// observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the current batch.
+pub struct BufferSubscription<Subs, T, E, Trigger> {
+    subscription: Subs,
+    state: Rc<RefCell<BufferState<T, E, Trigger>>>,
+}
+
+struct BufferState<T, E, Trigger> {
+    observer: ObserverSlot<Vec<T>, E>,
+    batch: Vec<T>,
+
+    // For `buffer_time_or_count()`, the action that pushes the batch when its
+    // time is up. It is scheduled when the first value of a batch arrives.
+    // For `buffer_with()`, the subscription to the boundary.
+    trigger: Option<Trigger>,
+}
+
+struct BufferTimeOrCountObserver<T, E, Sch: Scheduler> {
+    state: Rc<RefCell<BufferState<T, E, Sch::Subscription>>>,
+    duration: Duration,
+    count: usize,
+    scheduler: Sch,
+}
+
+impl<Source, Sch> BufferTimeOrCountObservable<Source, Sch> {
+    pub fn new(source: Source, duration: Duration, count: usize, scheduler: Sch)
+               -> BufferTimeOrCountObservable<Source, Sch> {
+        BufferTimeOrCountObservable {
+            source: source,
+            duration: duration,
+            // An empty batch would be full before any value arrives.
+            count: cmp::max(count, 1),
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, Sch, T, E> Observable for BufferTimeOrCountObservable<Source, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = Vec<T>;
+    type Error = E;
+    type Subscription = BufferSubscription<Source::Subscription, T, E, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Vec<T>, E> + 'static {
+        let state = Rc::new(RefCell::new(BufferState {
+            observer: ObserverSlot::new("buffer_time_or_count subscription", observer.boxed()),
+            batch: Vec::new(),
+            trigger: None,
+        }));
+        let buffer_observer = BufferTimeOrCountObserver {
+            state: state.clone(),
+            duration: self.duration,
+            count: self.count,
+            scheduler: self.scheduler.clone(),
+        };
+        BufferSubscription {
+            subscription: self.source.subscribe(buffer_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("buffer_time_or_count", vec![self.source.describe()])
+    }
+}
+
+// Pushes the current batch, if it is not empty.
+fn flush<T, E, Trigger>(state: &RefCell<BufferState<T, E, Trigger>>) {
+    // The state is not borrowed while the observer is called, so the source
+    // can push meanwhile. Values that it pushes go into the next batch.
+    let batch = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() || state.batch.is_empty() {
+            return;
+        }
+        mem::take(&mut state.batch)
+    };
+    operator::push(state, batch);
+}
+
+impl<T, E, Trigger> OperatorState for BufferState<T, E, Trigger> {
+    type Item = Vec<T>;
+    type Error = E;
+    type Released = (Option<Trigger>, Vec<T>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<Vec<T>, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<Trigger>, Vec<T>) {
+        (self.trigger.take(), mem::take(&mut self.batch))
+    }
+}
+
+impl<T, E, Sch> Observer<T, E> for BufferTimeOrCountObserver<T, E, Sch>
+where Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static {
+    fn on_next(&mut self, item: T) {
+        let (is_full, needs_timer) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            state.batch.push(item);
+            (state.batch.len() >= self.count, state.batch.len() == 1)
+        };
+        if is_full {
+            // The next batch starts its own timer.
+            let timer = self.state.borrow_mut().trigger.take();
+            drop(timer);
+            flush(&self.state);
+        } else if needs_timer {
+            // The scheduler might run the action immediately,
+            // so the state must not be borrowed here.
+            let state = Rc::downgrade(&self.state);
+            let timer = self.scheduler.schedule_after(self.duration, move || flush_timer(&state));
+            let timer = {
+                let mut state = self.state.borrow_mut();
+                // The action might have run already, or a value that the
+                // action pushed might have scheduled a newer one.
+                if state.observer.is_done() || state.batch.is_empty() || state.trigger.is_some() {
+                    Some(timer)
+                } else {
+                    state.trigger = Some(timer);
+                    None
+                }
+            };
+            drop(timer);
+        }
+    }
+
+    fn on_completed(self) {
+        complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+// Pushes the batch whose time is up.
+fn flush_timer<T, E, SchSubs>(state: &Weak<RefCell<BufferState<T, E, SchSubs>>>) {
+    if let Some(state) = state.upgrade() {
+        let timer = state.borrow_mut().trigger.take();
+        drop(timer);
+        flush(&state);
+    }
+}
+
+// Pushes the partial batch, and then completion.
+fn complete<T, E, Trigger>(state: &RefCell<BufferState<T, E, Trigger>>) {
+    let batch = mem::take(&mut state.borrow_mut().batch);
+    if batch.is_empty() {
+        operator::complete(state);
+    } else {
+        operator::complete_with(state, batch);
+    }
+}
+
+impl<Subs, T, E, Trigger> Subscription for BufferSubscription<Subs, T, E, Trigger>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, Trigger> Drop for BufferSubscription<Subs, T, E, Trigger> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
+
+/// The result of calling `buffer_with()` on an observable.
+pub struct BufferWithObservable<Source, Boundary> {
+    source: Source,
+    boundary: Boundary,
+}
+
+struct BufferWithObserver<T, E, BdSubs> {
+    state: Rc<RefCell<BufferState<T, E, BdSubs>>>,
+}
+
+struct BoundaryObserver<T, E, BdSubs> {
+    state: Rc<RefCell<BufferState<T, E, BdSubs>>>,
+}
+
+impl<Source, Boundary> BufferWithObservable<Source, Boundary> {
+    pub fn new(source: Source, boundary: Boundary) -> BufferWithObservable<Source, Boundary> {
+        BufferWithObservable {
+            source: source,
+            boundary: boundary,
+        }
+    }
+}
+
+impl<Source, Boundary, T, E> Observable for BufferWithObservable<Source, Boundary>
+where Source: Observable<Item = T, Error = E>,
+      Boundary: Observable,
+      Boundary::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = Vec<T>;
+    type Error = E;
+    type Subscription = BufferSubscription<Source::Subscription, T, E, Boundary::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Vec<T>, E> + 'static {
+        let state = Rc::new(RefCell::new(BufferState {
+            observer: ObserverSlot::new("buffer_with subscription", observer.boxed()),
+            batch: Vec::new(),
+            trigger: None,
+        }));
+
+        // The boundary is subscribed to first, so it is in place for the
+        // values that the source pushes upon subscription.
+        let boundary = self.boundary.subscribe(BoundaryObserver { state: state.clone() });
+        state.borrow_mut().trigger = Some(boundary);
+
+        let buffer_observer = BufferWithObserver { state: state.clone() };
+        BufferSubscription {
+            subscription: self.source.subscribe(buffer_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.boundary.describe()];
+        Graph::operator::<Self>("buffer_with", sources)
+    }
+}
+
+impl<T, E, BdSubs> Observer<T, E> for BufferWithObserver<T, E, BdSubs> {
+    fn on_next(&mut self, item: T) {
+        let mut state = self.state.borrow_mut();
+        if !state.observer.is_done() {
+            state.batch.push(item);
+        }
+    }
+
+    fn on_completed(self) {
+        complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, BdSubs, U, F> Observer<U, F> for BoundaryObserver<T, E, BdSubs> {
+    fn on_next(&mut self, _item: U) {
+        flush(&self.state);
+    }
+
+    fn on_completed(self) {
+        // Without a boundary, the last batch is pushed when the source completes.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the boundary is ignored, like its completion.
+    }
+}
+
+/// The result of calling `window_time()` on an observable.
+pub struct WindowTimeObservable<Source, Sch> {
+    source: Source,
+    duration: Duration,
+    scheduler: Sch,
+}
+
+/// The result of subscribing to a `window_time()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// stops opening new windows. The current window is not completed.
+pub struct WindowTimeSubscription<Subs, T, E, SchSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<WindowState<T, E, SchSubs>>>,
+}
+
+struct WindowState<T, E, SchSubs> {
+    observer: ObserverSlot<SubjectObservable<T, E>, E>,
+
+    // The subject is shared, so values can be pushed into it while the state
+    // is not borrowed.
+    window: Option<Rc<Subject<T, E>>>,
+
+    // The periodic action that closes the current window and opens the next.
+    timer: Option<SchSubs>,
+}
+
+struct WindowTimeObserver<T, E, SchSubs> {
+    state: Rc<RefCell<WindowState<T, E, SchSubs>>>,
+}
+
+impl<Source, Sch> WindowTimeObservable<Source, Sch> {
+    pub fn new(source: Source, duration: Duration, scheduler: Sch)
+               -> WindowTimeObservable<Source, Sch> {
+        WindowTimeObservable {
+            source: source,
+            duration: duration,
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, Sch, T, E> Observable for WindowTimeObservable<Source, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = SubjectObservable<T, E>;
+    type Error = E;
+    type Subscription = WindowTimeSubscription<Source::Subscription, T, E, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<SubjectObservable<T, E>, E> + 'static {
+        let state = Rc::new(RefCell::new(WindowState {
+            observer: ObserverSlot::new("window_time subscription", observer.boxed()),
+            window: None,
+            timer: None,
+        }));
+
+        // The first window opens upon subscription, before the source can push.
+        next_window(&state);
+
+        // The scheduler might run the action immediately,
+        // so the state must not be borrowed here.
+        let weak_state = Rc::downgrade(&state);
+        let timer = self.scheduler.schedule_periodic(self.duration, move || {
+            if let Some(state) = weak_state.upgrade() {
+                next_window(&state);
+            }
+        });
+        let timer = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                Some(timer)
+            } else {
+                state.timer = Some(timer);
+                None
+            }
+        };
+        drop(timer);
+
+        let window_observer = WindowTimeObserver { state: state.clone() };
+        WindowTimeSubscription {
+            subscription: self.source.subscribe(window_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("window_time", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, SchSubs> OperatorState for WindowState<T, E, SchSubs> {
+    type Item = SubjectObservable<T, E>;
+    type Error = E;
+    type Released = (Option<SchSubs>, Option<Rc<Subject<T, E>>>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<SubjectObservable<T, E>, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<SchSubs>, Option<Rc<Subject<T, E>>>) {
+        (self.timer.take(), self.window.take())
+    }
+}
+
+// Completes the current window, if any, and pushes a new one.
+fn next_window<T, E, SchSubs>(state: &RefCell<WindowState<T, E, SchSubs>>)
+    where T: Clone, E: Clone {
+    let window = Rc::new(Subject::new());
+    let previous = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            return;
+        }
+        state.window.replace(window.clone())
+    };
+    if let Some(previous) = previous {
+        (&*previous).on_completed();
+    }
+    operator::push(state, window.observable());
+}
+
+impl<T, E, SchSubs> Observer<T, E> for WindowTimeObserver<T, E, SchSubs>
+where T: Clone,
+      E: Clone {
+    fn on_next(&mut self, item: T) {
+        // The state is not borrowed while the window pushes the value, so its
+        // observers can cause the source to push meanwhile.
+        let window = self.state.borrow().window.clone();
+        if let Some(window) = window {
+            (&*window).on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        let (ending, (timer, window)) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            (state.observer.complete(None), state.release())
+        };
+        drop(timer);
+        if let Some(window) = window {
+            (&*window).on_completed();
+        }
+        if let Some(ending) = ending {
+            ending.deliver();
+        }
+    }
+
+    fn on_error(self, error: E) {
+        let (ending, (timer, window)) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            (state.observer.fail(error.clone()), state.release())
+        };
+        drop(timer);
+        if let Some(window) = window {
+            (&*window).on_error(error);
+        }
+        if let Some(ending) = ending {
+            ending.deliver();
+        }
+    }
+}
+
+impl<Subs, T, E, SchSubs> Subscription for WindowTimeSubscription<Subs, T, E, SchSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, SchSubs> Drop for WindowTimeSubscription<Subs, T, E, SchSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
+
+/// The result of calling `sliding_window()` on an observable.
+pub struct SlidingWindowObservable<Source> {
+    source: Source,
+    size: usize,
+    step: usize,
+}
+
+struct SlidingWindowObserver<O, T> {
+    observer: O,
+    size: usize,
+    step: usize,
+
+    // The latest values, at most `size` of them.
+    window: VecDeque<T>,
+
+    // The number of values until the next window is pushed.
+    remaining: usize,
+}
+
+impl<Source> SlidingWindowObservable<Source> {
+    pub fn new(source: Source, size: usize, step: usize) -> SlidingWindowObservable<Source> {
+        SlidingWindowObservable {
+            source: source,
+            // An empty window would be full before any value arrives,
+            // and without a step, the same window would be pushed forever.
+            size: cmp::max(size, 1),
+            step: cmp::max(step, 1),
+        }
+    }
+}
+
+impl<Source> Observable for SlidingWindowObservable<Source>
+where Source: Observable,
+      Source::Item: 'static {
+    type Item = Vec<Source::Item>;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let window_observer = SlidingWindowObserver {
+            observer: observer,
+            size: self.size,
+            step: self.step,
+            window: VecDeque::with_capacity(self.size),
+            remaining: self.size,
+        };
+        self.source.subscribe(window_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("sliding_window", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<T, E> for SlidingWindowObserver<O, T>
+where T: Clone,
+      O: Observer<Vec<T>, E> {
+    fn on_next(&mut self, item: T) {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(item);
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.remaining = self.step;
+            self.observer.on_next(self.window.iter().cloned().collect());
+        }
+    }
+
+    fn on_completed(self) {
+        // Only full windows are pushed.
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
@@ -0,0 +1,325 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Bridges between observables and the channels of `std::sync::mpsc`.
+
+use {NoError, Notification};
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::fmt::Debug;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::time::Duration;
+use subscription::Subscription;
+
+/// How long `from_receiver()` waits before it checks an empty channel again,
+/// unless configured otherwise.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The receiving end of a channel that `from_receiver()` can drain.
+pub trait TryReceive {
+    /// The message type of the channel.
+    type Item;
+
+    /// Receives a message without blocking.
+    fn try_receive(&self) -> Result<Self::Item, TryRecvError>;
+}
+
+impl<T> TryReceive for Receiver<T> {
+    type Item = T;
+
+    fn try_receive(&self) -> Result<T, TryRecvError> {
+        self.try_recv()
+    }
+}
+
+// A receiver that is gone behaves like a disconnected channel.
+impl<R: TryReceive> TryReceive for Option<R> {
+    type Item = R::Item;
+
+    fn try_receive(&self) -> Result<R::Item, TryRecvError> {
+        match *self {
+            Some(ref receiver) => receiver.try_receive(),
+            None => Err(TryRecvError::Disconnected),
+        }
+    }
+}
+
+/// The result of calling `from_receiver()` or `from_notification_receiver()`.
+pub struct FromReceiver<R: TryReceive, Sch, T, E> {
+    receiver: Rc<R>,
+    scheduler: Sch,
+    poll_interval: Duration,
+    to_notification: fn(R::Item) -> Notification<T, E>,
+}
+
+/// The result of subscribing to a `from_receiver()` observable.
+///
+/// Dropping this subscription stops draining the channel.
+pub struct FromReceiverSubscription<R: TryReceive, Sch: Scheduler, T, E> {
+    state: Rc<RefCell<ReceiverState<R, Sch, T, E>>>,
+}
+
+struct ReceiverState<R: TryReceive, Sch: Scheduler, T, E> {
+    receiver: Rc<R>,
+    scheduler: Sch,
+    poll_interval: Duration,
+    to_notification: fn(R::Item) -> Notification<T, E>,
+
+    // The observer is taken out while it is being called, and it is gone for
+    // good after termination or cancellation.
+    observer: Option<Box<dyn BoxedObserver<T, E>>>,
+    is_cancelled: bool,
+
+    // Whether the observer received completion or failure.
+    is_done: bool,
+
+    // The action that checks the channel next.
+    poll: Option<Sch::Subscription>,
+}
+
+/// Creates an observable that pushes the messages received from a channel.
+///
+/// Upon subscription, an action on the scheduler pushes the messages that are
+/// in the channel. When the channel is empty, the action checks it again after
+/// the poll interval, see `FromReceiver::poll_interval()`. The observable
+/// completes when all senders have been dropped, and the channel is empty.
+/// The observable does not fail, its error type is `NoError`.
+///
+/// A message can be received only once. If multiple observers subscribe, every
+/// message is pushed to one of them. To push every message to all observers,
+/// use `share()`.
+///
+/// With a `CurrentThreadScheduler`, subscribing blocks until the senders
+/// disconnect. With an `EventLoop`, the messages are pushed while the loop
+/// runs.
+///
+/// The receiver can be a `std::sync::mpsc::Receiver`, or with the `crossbeam`
+/// feature, a `crossbeam_channel::Receiver`.
+pub fn from_receiver<R, Sch>(receiver: R, scheduler: Sch)
+                             -> FromReceiver<R, Sch, R::Item, NoError>
+    where R: TryReceive, R::Item: Clone, Sch: Scheduler {
+    FromReceiver {
+        receiver: Rc::new(receiver),
+        scheduler: scheduler,
+        poll_interval: DEFAULT_POLL_INTERVAL,
+        to_notification: Notification::Next,
+    }
+}
+
+/// Creates an observable that pushes the notifications received from a channel.
+///
+/// This is the counterpart of `ObservableExt::subscribe_channel()`: it pushes
+/// a `Notification::Next` message as a value, and it completes or fails upon
+/// a `Notification::Completed` or `Notification::Error` message. Like an
+/// observable that is created with `from_receiver()`, it also completes when
+/// all senders have been dropped, and the channel is empty.
+pub fn from_notification_receiver<R, Sch, T, E>(receiver: R, scheduler: Sch)
+                                                -> FromReceiver<R, Sch, T, E>
+    where R: TryReceive<Item = Notification<T, E>>, Sch: Scheduler {
+    FromReceiver {
+        receiver: Rc::new(receiver),
+        scheduler: scheduler,
+        poll_interval: DEFAULT_POLL_INTERVAL,
+        to_notification: |notification| notification,
+    }
+}
+
+impl<R: TryReceive, Sch, T, E> FromReceiver<R, Sch, T, E> {
+    /// Sets how long to wait before checking an empty channel again.
+    ///
+    /// By default, this is one millisecond.
+    pub fn poll_interval(mut self, interval: Duration) -> FromReceiver<R, Sch, T, E> {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl<R, Sch, T, E> Observable for FromReceiver<R, Sch, T, E>
+where R: TryReceive + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = FromReceiverSubscription<R, Sch, T, E>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(ReceiverState {
+            receiver: self.receiver.clone(),
+            scheduler: self.scheduler.clone(),
+            poll_interval: self.poll_interval,
+            to_notification: self.to_notification,
+            observer: Some(observer.boxed()),
+            is_cancelled: false,
+            is_done: false,
+            poll: None,
+        }));
+        schedule_poll(&state, Duration::from_secs(0));
+        FromReceiverSubscription {
+            state: state,
+        }
+    }
+}
+
+// Schedules an action that pushes the messages in the channel.
+fn schedule_poll<R, Sch, T, E>(state: &Rc<RefCell<ReceiverState<R, Sch, T, E>>>, delay: Duration)
+    where R: TryReceive + 'static,
+          Sch: Scheduler + Clone + 'static,
+          Sch::Subscription: 'static,
+          T: 'static,
+          E: 'static {
+    // The scheduler might run the action immediately,
+    // so the state must not be borrowed here.
+    let scheduler = state.borrow().scheduler.clone();
+    let weak_state = Rc::downgrade(state);
+    let poll_action = scheduler.schedule_after(delay, move || poll(weak_state));
+    let previous = {
+        let mut state = state.borrow_mut();
+        if state.is_cancelled {
+            None
+        } else {
+            state.poll.replace(poll_action)
+        }
+    };
+    drop(previous);
+}
+
+// Pushes the messages in the channel to the observer, until it is empty.
+fn poll<R, Sch, T, E>(state: Weak<RefCell<ReceiverState<R, Sch, T, E>>>)
+    where R: TryReceive + 'static,
+          Sch: Scheduler + Clone + 'static,
+          Sch::Subscription: 'static,
+          T: 'static,
+          E: 'static {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    loop {
+        // The state is not borrowed while the observer is called, so the
+        // observer can drop its subscription.
+        let (message, observer) = {
+            let mut state = state.borrow_mut();
+            match state.observer.take() {
+                Some(observer) => {
+                    let message = state.receiver.try_receive().map(state.to_notification);
+                    (message, observer)
+                }
+                None => return,
+            }
+        };
+        match message {
+            Ok(Notification::Next(item)) => {
+                let mut observer = observer;
+                observer.on_next(item);
+                let mut state = state.borrow_mut();
+                if !state.is_cancelled {
+                    state.observer = Some(observer);
+                }
+            }
+            Ok(Notification::Completed) => {
+                state.borrow_mut().is_done = true;
+                observer.on_completed_box();
+                return;
+            }
+            Ok(Notification::Error(error)) => {
+                state.borrow_mut().is_done = true;
+                observer.on_error_box(error);
+                return;
+            }
+            Err(TryRecvError::Empty) => {
+                let poll_interval = {
+                    let mut state = state.borrow_mut();
+                    state.observer = Some(observer);
+                    state.poll_interval
+                };
+                schedule_poll(&state, poll_interval);
+                return;
+            }
+            Err(TryRecvError::Disconnected) => {
+                state.borrow_mut().is_done = true;
+                observer.on_completed_box();
+                return;
+            }
+        }
+    }
+}
+
+impl<R: TryReceive, Sch: Scheduler, T, E> FromReceiverSubscription<R, Sch, T, E> {
+    fn cancel(&mut self) {
+        let (observer, poll) = {
+            let mut state = self.state.borrow_mut();
+            state.is_cancelled = true;
+            (state.observer.take(), state.poll.take())
+        };
+        drop(poll);
+        drop(observer);
+    }
+}
+
+impl<R: TryReceive, Sch: Scheduler, T, E> Subscription for FromReceiverSubscription<R, Sch, T, E> {
+    fn unsubscribe(&mut self) {
+        self.cancel();
+    }
+
+    fn is_active(&self) -> bool {
+        let state = self.state.borrow();
+        !state.is_cancelled && !state.is_done
+    }
+}
+
+impl<R: TryReceive, Sch: Scheduler, T, E> Drop for FromReceiverSubscription<R, Sch, T, E> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// An observer that sends every notification into a channel.
+pub struct NotificationSender<T, E> {
+    pub sender: Sender<Notification<T, E>>,
+}
+
+/// An observer that sends every value into a channel.
+pub struct ItemSender<T> {
+    pub sender: Sender<T>,
+}
+
+// Sending fails only when the receiver has been dropped. Nobody is interested
+// in the notifications then, so the errors are ignored.
+
+impl<T, E> Observer<T, E> for NotificationSender<T, E> {
+    fn on_next(&mut self, item: T) {
+        let _ = self.sender.send(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        let _ = self.sender.send(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        let _ = self.sender.send(Notification::Error(error));
+    }
+}
+
+impl<T, E: Debug> Observer<T, E> for ItemSender<T> {
+    fn on_next(&mut self, item: T) {
+        let _ = self.sender.send(item);
+    }
+
+    fn on_completed(self) {
+        // Dropping the sender disconnects it.
+    }
+
+    fn on_error(self, error: E) {
+        panic!("observer received error: {:?}", error);
+    }
+}
@@ -0,0 +1,260 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `circuit_breaker()` operator, which stops subscribing to a failing source.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use subscription::Subscription;
+
+/// When `circuit_breaker()` opens the circuit, and for how long.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    /// The number of failures in a row that opens the circuit.
+    pub failure_threshold: usize,
+
+    /// How long the circuit stays open before the source is tried again.
+    pub cooldown: Duration,
+}
+
+/// The error of a `circuit_breaker()` observable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitError<E> {
+    /// The source failed with the error.
+    Failed(E),
+
+    /// The circuit is open, so the source was not subscribed to.
+    Open,
+}
+
+/// The result of calling `circuit_breaker()` on an observable.
+///
+/// The circuit is shared by all subscriptions to this observable, so the
+/// failures of one subscription open the circuit for the next.
+pub struct CircuitBreakerObservable<Source, Sch> {
+    source: Source,
+    scheduler: Sch,
+    policy: CircuitBreakerPolicy,
+    circuit: Rc<RefCell<Circuit>>,
+}
+
+/// The result of subscribing to a `circuit_breaker()` observable.
+///
+/// Dropping this subscription drops the subscription to the source. If the
+/// subscription was probing the source and it did not succeed or fail yet,
+/// the next subscription probes the source instead.
+pub struct CircuitBreakerSubscription<Subs> {
+    // There is no subscription to the source if the circuit was open.
+    subscription: Option<Subs>,
+    circuit: Rc<RefCell<Circuit>>,
+    probe: Option<u64>,
+}
+
+enum CircuitState {
+    // Counts the failures in a row.
+    Closed(usize),
+
+    // Holds the moment that the circuit opened.
+    Open(Instant),
+
+    // A single subscription probes the source. If it is dropped without an
+    // outcome, the circuit is open since the same moment as before.
+    HalfOpen(u64, Instant),
+}
+
+struct Circuit {
+    state: CircuitState,
+
+    // Counts the probes, so a subscription can tell whether it still probes.
+    probes: u64,
+}
+
+struct CircuitBreakerObserver<O, Sch> {
+    observer: O,
+    scheduler: Sch,
+    failure_threshold: usize,
+    circuit: Rc<RefCell<Circuit>>,
+
+    // The probe that this subscription is, if it was admitted as one.
+    probe: Option<u64>,
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CircuitError::Failed(ref error) => write!(f, "observable failed: {}", error),
+            CircuitError::Open => write!(f, "circuit is open"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for CircuitError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            CircuitError::Failed(ref error) => Some(error),
+            CircuitError::Open => None,
+        }
+    }
+}
+
+impl<Source, Sch> CircuitBreakerObservable<Source, Sch> {
+    pub fn new(source: Source, policy: CircuitBreakerPolicy, scheduler: Sch)
+               -> CircuitBreakerObservable<Source, Sch> {
+        CircuitBreakerObservable {
+            source: source,
+            scheduler: scheduler,
+            policy: policy,
+            circuit: Rc::new(RefCell::new(Circuit {
+                state: CircuitState::Closed(0),
+                probes: 0,
+            })),
+        }
+    }
+}
+
+impl Circuit {
+    // Returns whether a subscription may go through, and if so, whether it
+    // probes the source.
+    fn admit(&mut self, now: Instant, cooldown: Duration) -> Result<Option<u64>, ()> {
+        match self.state {
+            CircuitState::Closed(..) => Ok(None),
+            CircuitState::Open(since) if now >= since + cooldown => {
+                self.probes += 1;
+                self.state = CircuitState::HalfOpen(self.probes, since);
+                Ok(Some(self.probes))
+            }
+            CircuitState::Open(..) | CircuitState::HalfOpen(..) => Err(()),
+        }
+    }
+
+    // Only the current probe closes an open circuit. A subscription from
+    // before the circuit opened can still push values, but those say nothing
+    // about whether the source recovered.
+    fn succeed(&mut self, probe: Option<u64>) {
+        let is_closing = match self.state {
+            CircuitState::Closed(..) => true,
+            CircuitState::HalfOpen(current, _) => probe == Some(current),
+            CircuitState::Open(..) => false,
+        };
+        if is_closing {
+            self.state = CircuitState::Closed(0);
+        }
+    }
+
+    fn fail(&mut self, now: Instant, failure_threshold: usize) {
+        self.state = match self.state {
+            CircuitState::Closed(failures) if failures + 1 < failure_threshold => {
+                CircuitState::Closed(failures + 1)
+            }
+            _ => CircuitState::Open(now),
+        };
+    }
+
+    fn abandon(&mut self, probe: u64) {
+        if let CircuitState::HalfOpen(current, since) = self.state {
+            if current == probe {
+                self.state = CircuitState::Open(since);
+            }
+        }
+    }
+}
+
+impl<Source, Sch, T, E> Observable for CircuitBreakerObservable<Source, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Sch: Scheduler + Clone + 'static,
+      T: Clone,
+      E: Clone {
+    type Item = T;
+    type Error = CircuitError<E>;
+    type Subscription = CircuitBreakerSubscription<Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, CircuitError<E>> + 'static {
+        let now = self.scheduler.now_instant();
+        let admitted = self.circuit.borrow_mut().admit(now, self.policy.cooldown);
+        let probe = match admitted {
+            Ok(probe) => probe,
+            Err(()) => {
+                observer.on_error(CircuitError::Open);
+                return CircuitBreakerSubscription {
+                    subscription: None,
+                    circuit: self.circuit.clone(),
+                    probe: None,
+                };
+            }
+        };
+        let circuit_observer = CircuitBreakerObserver {
+            observer: observer,
+            scheduler: self.scheduler.clone(),
+            failure_threshold: self.policy.failure_threshold,
+            circuit: self.circuit.clone(),
+            probe: probe,
+        };
+        CircuitBreakerSubscription {
+            subscription: Some(self.source.subscribe(circuit_observer)),
+            circuit: self.circuit.clone(),
+            probe: probe,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("circuit_breaker", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, Sch> Observer<T, E> for CircuitBreakerObserver<O, Sch>
+where O: Observer<T, CircuitError<E>>,
+      Sch: Scheduler {
+    fn on_next(&mut self, item: T) {
+        self.circuit.borrow_mut().succeed(self.probe);
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.circuit.borrow_mut().succeed(self.probe);
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.circuit.borrow_mut().fail(self.scheduler.now_instant(), self.failure_threshold);
+        self.observer.on_error(CircuitError::Failed(error));
+    }
+}
+
+impl<Subs> CircuitBreakerSubscription<Subs> {
+    fn release(&mut self) {
+        if let Some(probe) = self.probe.take() {
+            self.circuit.borrow_mut().abandon(probe);
+        }
+    }
+}
+
+impl<Subs: Subscription> Subscription for CircuitBreakerSubscription<Subs> {
+    fn unsubscribe(&mut self) {
+        if let Some(ref mut subscription) = self.subscription {
+            subscription.unsubscribe();
+        }
+        self.release();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.as_ref().is_some_and(|subscription| subscription.is_active())
+    }
+}
+
+impl<Subs> Drop for CircuitBreakerSubscription<Subs> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
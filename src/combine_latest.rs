@@ -0,0 +1,240 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `combine_latest()` operator for tuples of observables.
+
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// Combines the latest values of a tuple of observables.
+///
+/// This is implemented for tuples of two up to eight observables with the
+/// same error type.
+pub trait CombineLatest: Sized {
+    /// Pushes a tuple of the latest values, whenever one of the observables pushes.
+    ///
+    /// Nothing is pushed until every observable pushed a value. After that,
+    /// every value of any observable is pushed together with the latest
+    /// values of the others. The result completes when all observables
+    /// complete, or as soon as one completes without having pushed a value.
+    /// A failure of any observable is pushed right away.
+    fn combine_latest(self) -> CombineLatestObservable<Self>;
+}
+
+/// The result of calling `combine_latest()` on a tuple of observables.
+pub struct CombineLatestObservable<Sources> {
+    sources: Sources,
+}
+
+/// The result of subscribing to a `combine_latest()` observable.
+///
+/// Dropping this subscription drops the subscriptions to all observables.
+pub struct CombineLatestSubscription<S, U, E> {
+    state: Rc<RefCell<CombineState<S, U, E>>>,
+}
+
+struct CombineState<S, U, E> {
+    observer: ObserverSlot<U, E>,
+
+    // A tuple with the latest value of every observable, once it pushed one.
+    latest: S,
+
+    // Returns the combined value, if every observable pushed a value.
+    combine: fn(&S) -> Option<U>,
+
+    // The number of observables that did not complete yet.
+    active: usize,
+
+    // The subscriptions are stored once `subscribe()` returns, unless the
+    // operator was done by then.
+    sources: Vec<Box<dyn Subscription>>,
+}
+
+struct CombineObserver<S, U, E, T> {
+    state: Rc<RefCell<CombineState<S, U, E>>>,
+
+    // Stores a value of this observable in the tuple of latest values.
+    set: fn(&mut S, T),
+
+    has_value: bool,
+}
+
+impl<Sources> CombineLatestObservable<Sources> {
+    pub fn new(sources: Sources) -> CombineLatestObservable<Sources> {
+        CombineLatestObservable {
+            sources: sources,
+        }
+    }
+}
+
+impl<S, U, E> OperatorState for CombineState<S, U, E> {
+    type Item = U;
+    type Error = E;
+    type Released = Vec<Box<dyn Subscription>>;
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<U, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> Vec<Box<dyn Subscription>> {
+        mem::take(&mut self.sources)
+    }
+}
+
+fn new_state<S, U, E>(observer: Box<dyn BoxedObserver<U, E>>,
+                      latest: S,
+                      combine: fn(&S) -> Option<U>,
+                      count: usize)
+                      -> Rc<RefCell<CombineState<S, U, E>>> {
+    Rc::new(RefCell::new(CombineState {
+        observer: ObserverSlot::new("combine_latest subscription", observer),
+        latest: latest,
+        combine: combine,
+        active: count,
+        sources: Vec::new(),
+    }))
+}
+
+fn subscribe_source<Source, S, U, E>(state: &Rc<RefCell<CombineState<S, U, E>>>,
+                                     source: &mut Source,
+                                     set: fn(&mut S, Source::Item))
+    where Source: Observable<Error = E>,
+          Source::Subscription: 'static,
+          Source::Item: 'static,
+          S: 'static,
+          U: 'static,
+          E: 'static {
+    if state.borrow().observer.is_done() {
+        return;
+    }
+    let combine_observer = CombineObserver {
+        state: state.clone(),
+        set: set,
+        has_value: false,
+    };
+    let subscription = source.subscribe(combine_observer);
+    let subscription = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            Some(subscription)
+        } else {
+            state.sources.push(Box::new(subscription));
+            None
+        }
+    };
+    drop(subscription);
+}
+
+impl<S, U, E, T> Observer<T, E> for CombineObserver<S, U, E, T> {
+    fn on_next(&mut self, item: T) {
+        self.has_value = true;
+        let combined = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            (self.set)(&mut state.latest, item);
+            match (state.combine)(&state.latest) {
+                Some(combined) => combined,
+                None => return,
+            }
+        };
+        operator::push(&self.state, combined);
+    }
+
+    fn on_completed(self) {
+        let is_exhausted = {
+            let mut state = self.state.borrow_mut();
+            state.active -= 1;
+            // Without a value of this observable, nothing can be pushed anymore.
+            state.active == 0 || !self.has_value
+        };
+        if is_exhausted {
+            operator::complete(&self.state);
+        }
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+macro_rules! impl_combine_latest {
+    ($($source:ident . $index:tt => $item:ident),+) => {
+        impl<E, $($source),+> CombineLatest for ($($source,)+)
+        where $($source: Observable<Error = E>),+ {
+            fn combine_latest(self) -> CombineLatestObservable<Self> {
+                CombineLatestObservable::new(self)
+            }
+        }
+
+        impl<E, $($source),+> Observable for CombineLatestObservable<($($source,)+)>
+        where E: Clone + 'static,
+              $($source: Observable<Error = E>,
+                $source::Subscription: 'static,
+                $source::Item: 'static),+ {
+            type Item = ($($source::Item,)+);
+            type Error = E;
+            type Subscription = CombineLatestSubscription<($(Option<$source::Item>,)+),
+                                                          Self::Item, E>;
+
+            fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+                where O: Observer<Self::Item, E> + 'static {
+                let latest = ($(None::<$source::Item>,)+);
+                let count = [$(stringify!($source)),+].len();
+                let state = new_state(observer.boxed(), latest, |latest| {
+                    match *latest {
+                        ($(Some(ref $item),)+) => Some(($($item.clone(),)+)),
+                        _ => None,
+                    }
+                }, count);
+                $(
+                    subscribe_source(&state, &mut self.sources.$index, |latest, item| {
+                        latest.$index = Some(item);
+                    });
+                )+
+                CombineLatestSubscription { state: state }
+            }
+
+            fn describe(&self) -> Graph {
+                let sources = vec![$(self.sources.$index.describe()),+];
+                Graph::operator::<Self>("combine_latest", sources)
+            }
+        }
+    }
+}
+
+impl_combine_latest!(A.0 => a, B.1 => b);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c, D.3 => d);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c, D.3 => d, F.4 => f);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c, D.3 => d, F.4 => f, G.5 => g);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c, D.3 => d, F.4 => f, G.5 => g, H.6 => h);
+impl_combine_latest!(A.0 => a, B.1 => b, C.2 => c, D.3 => d, F.4 => f, G.5 => g, H.6 => h,
+                     I.7 => i);
+
+impl<S, U, E> Subscription for CombineLatestSubscription<S, U, E> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<S, U, E> Drop for CombineLatestSubscription<S, U, E> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,171 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Bridges between observables and crossbeam channels.
+//!
+//! This module is only available with the `crossbeam` feature.
+
+use {Notification, UncancellableSubscription};
+use channel::TryReceive;
+use crossbeam_channel::{self, Receiver, Sender, TrySendError};
+use observable::Observable;
+use observer::Observer;
+use std::sync::mpsc::TryRecvError;
+use std::thread::{self, JoinHandle};
+use subscription::Subscription;
+
+impl<T> TryReceive for Receiver<T> {
+    type Item = T;
+
+    fn try_receive(&self) -> Result<T, TryRecvError> {
+        self.try_recv().map_err(|error| match error {
+            crossbeam_channel::TryRecvError::Empty => TryRecvError::Empty,
+            crossbeam_channel::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })
+    }
+}
+
+/// What a bounded channel sink does with a value when the channel is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendPolicy {
+    /// Blocks the thread that pushes the value until the channel has room.
+    Block,
+
+    /// Drops the new value.
+    DropNewest,
+}
+
+/// An observer that sends every notification into a crossbeam channel.
+///
+/// Completion and failure are never dropped: when the channel is full, they
+/// wait for room regardless of the policy.
+pub struct BoundedSender<T, E> {
+    pub sender: Sender<Notification<T, E>>,
+    pub policy: SendPolicy,
+}
+
+// Sending fails only when the receiver has been dropped. Nobody is interested
+// in the notifications then, so the errors are ignored.
+
+impl<T, E> Observer<T, E> for BoundedSender<T, E> {
+    fn on_next(&mut self, item: T) {
+        match self.policy {
+            SendPolicy::Block => {
+                let _ = self.sender.send(Notification::Next(item));
+            }
+            SendPolicy::DropNewest => {
+                match self.sender.try_send(Notification::Next(item)) {
+                    Ok(()) | Err(TrySendError::Full(..)) | Err(TrySendError::Disconnected(..)) => {}
+                }
+            }
+        }
+    }
+
+    fn on_completed(self) {
+        let _ = self.sender.send(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        let _ = self.sender.send(Notification::Error(error));
+    }
+}
+
+/// The observable that `pipe_between_threads()` passes to the downstream thread.
+///
+/// Subscribing blocks the downstream thread, and pushes the notifications of
+/// the upstream observable as they arrive. It returns when the upstream
+/// observable completes or fails, or when the upstream subscription is dropped.
+pub struct PipeObservable<T, E> {
+    receiver: Receiver<Notification<T, E>>,
+}
+
+impl<T: Clone, E: Clone> Observable for PipeObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = UncancellableSubscription;
+
+    fn subscribe<O>(&mut self, mut observer: O) -> UncancellableSubscription
+        where O: Observer<T, E> + 'static {
+        loop {
+            match self.receiver.recv() {
+                Ok(Notification::Next(item)) => observer.on_next(item),
+                Ok(Notification::Completed) => {
+                    observer.on_completed();
+                    break;
+                }
+                Ok(Notification::Error(error)) => {
+                    observer.on_error(error);
+                    break;
+                }
+                // The upstream subscription was dropped.
+                Err(..) => break,
+            }
+        }
+        UncancellableSubscription
+    }
+}
+
+/// The result of calling `pipe_between_threads()` on an observable.
+///
+/// This is the subscription to the upstream observable. Dropping it stops the
+/// upstream half of the pipeline, and the downstream half ends when it has
+/// taken the remaining values. Use `join()` to wait for the downstream thread.
+pub struct PipeSubscription<Subs, R> {
+    subscription: Subs,
+    thread: JoinHandle<R>,
+}
+
+impl<Subs, R> PipeSubscription<Subs, R> {
+    /// Waits for the downstream thread, and returns its result.
+    ///
+    /// This keeps the upstream subscription until the downstream thread
+    /// finishes.
+    pub fn join(self) -> thread::Result<R> {
+        let PipeSubscription { subscription, thread } = self;
+        let result = thread.join();
+        drop(subscription);
+        result
+    }
+}
+
+impl<Subs: Subscription, R> Subscription for PipeSubscription<Subs, R> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+/// Runs `downstream` on a new thread, with an observable of the values of `source`.
+///
+/// See `ObservableExt::pipe_between_threads()`.
+pub fn pipe_between_threads<Source, F, R>(mut source: Source,
+                                          capacity: usize,
+                                          downstream: F)
+                                          -> PipeSubscription<Source::Subscription, R>
+    where Source: Observable,
+          Source::Item: Send + 'static,
+          Source::Error: Send + 'static,
+          F: FnOnce(PipeObservable<Source::Item, Source::Error>) -> R + Send + 'static,
+          R: Send + 'static {
+    let (sender, receiver) = crossbeam_channel::bounded(capacity);
+    let thread = thread::spawn(move || {
+        downstream(PipeObservable {
+            receiver: receiver,
+        })
+    });
+    let observer = BoundedSender {
+        sender: sender,
+        policy: SendPolicy::Block,
+    };
+    PipeSubscription {
+        subscription: source.subscribe(observer),
+        thread: thread,
+    }
+}
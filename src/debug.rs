@@ -0,0 +1,33 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Helpers for finding subscriptions that leak.
+//!
+//! A subscription to a long-lived subject keeps its observer alive for as
+//! long as the subscription is not dropped and the subject does not
+//! terminate. With multicast observables, that is easy to get wrong. After
+//! `set_leak_tracking(true)`, every subject and every subscription, to a
+//! subject, an operator, a shared observable, or a scheduled action, records
+//! where it was created, until it is dropped or cancelled.
+//! `dump_live_subscriptions()` then lists the ones that are still alive:
+//!
+//! ```
+//! use rx::{ObservableExt, Subject};
+//! use rx::debug;
+//! debug::set_leak_tracking(true);
+//! let subject = Subject::<u8, ()>::new();
+//! let subscription = subject.observable().subscribe_next(|_x| {});
+//! // Prints the subject and the subscription, neither has terminated.
+//! eprint!("{}", debug::dump_live_subscriptions());
+//! # drop(subscription);
+//! ```
+//!
+//! A leaked subscription to an operator shows up together with the
+//! subscriptions to its sources that it keeps alive.
+//! Recording only happens in builds with debug assertions.
+
+pub use leak::{LiveSubscription, dump_live_subscriptions, live_subscriptions, set_leak_tracking};
@@ -0,0 +1,311 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `delay_each()` operator, which delays every value by its own duration.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+use subscription::Subscription;
+
+/// The result of calling `delay_each()` on an observable.
+pub struct DelayEachObservable<Source, F, Sch> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+
+    scheduler: Sch,
+}
+
+/// The result of subscribing to a `delay_each()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the values that have not been pushed yet.
+pub struct DelayEachSubscription<Subs, T, E, SchSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<DelayEachState<T, E, SchSubs>>>,
+}
+
+struct DelayEachState<T, E, SchSubs> {
+    observer: ObserverSlot<T, E>,
+    pending: BinaryHeap<Pending<T>>,
+    next_sequence: u64,
+
+    // Whether the source completed. The observer completes once the pending
+    // values have been pushed.
+    is_completed: bool,
+
+    // Whether values are being pushed. Values that become due meanwhile are
+    // picked up by the loop that pushes.
+    is_draining: bool,
+
+    // The action that pushes the earliest pending value, with its due time.
+    // There is at most one, and it is replaced when an earlier value arrives.
+    timer: Option<(Instant, SchSubs)>,
+
+    // Incremented whenever the timer is replaced or runs, so an action that
+    // ran while it was being scheduled is not stored.
+    timer_generation: u64,
+}
+
+struct Pending<T> {
+    due: Instant,
+
+    // Values that are due at the same time are pushed in the order of this
+    // number, which is the order in which they arrived.
+    sequence: u64,
+
+    item: T,
+}
+
+struct DelayEachObserver<T, E, F, Sch: Scheduler> {
+    state: Rc<RefCell<DelayEachState<T, E, Sch::Subscription>>>,
+    f: Rc<F>,
+    scheduler: Sch,
+}
+
+impl<Source, F, Sch> DelayEachObservable<Source, F, Sch> {
+    pub fn new(source: Source, f: F, scheduler: Sch) -> DelayEachObservable<Source, F, Sch> {
+        DelayEachObservable {
+            source: source,
+            f: Rc::new(f),
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, F, Sch, T, E> Observable for DelayEachObservable<Source, F, Sch>
+where Source: Observable<Item = T, Error = E>,
+      F: Fn(&T) -> Duration + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = DelayEachSubscription<Source::Subscription, T, E, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(DelayEachState {
+            observer: ObserverSlot::new("delay_each subscription", observer.boxed()),
+            pending: BinaryHeap::new(),
+            next_sequence: 0,
+            is_completed: false,
+            is_draining: false,
+            timer: None,
+            timer_generation: 0,
+        }));
+        let delay_observer = DelayEachObserver {
+            state: state.clone(),
+            f: self.f.clone(),
+            scheduler: self.scheduler.clone(),
+        };
+        DelayEachSubscription {
+            subscription: self.source.subscribe(delay_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("delay_each", vec![self.source.describe()])
+    }
+}
+
+impl<T> PartialEq for Pending<T> {
+    fn eq(&self, other: &Pending<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Pending<T> {}
+
+impl<T> PartialOrd for Pending<T> {
+    fn partial_cmp(&self, other: &Pending<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Pending<T> {
+    fn cmp(&self, other: &Pending<T>) -> Ordering {
+        // The binary heap is a max-heap, but the earliest value should be on top.
+        (&other.due, other.sequence).cmp(&(&self.due, self.sequence))
+    }
+}
+
+impl<T, E, SchSubs> OperatorState for DelayEachState<T, E, SchSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<SchSubs>, BinaryHeap<Pending<T>>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<SchSubs>, BinaryHeap<Pending<T>>) {
+        let timer = self.timer.take().map(|(_due, timer)| timer);
+        (timer, mem::take(&mut self.pending))
+    }
+}
+
+// Pushes the pending values that are due at `now`, in order.
+fn drain<T, E, SchSubs>(state: &RefCell<DelayEachState<T, E, SchSubs>>, now: Instant) {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_draining || state.observer.is_done() {
+            return;
+        }
+        state.is_draining = true;
+    }
+    loop {
+        // The state is not borrowed while the observer is called, so the
+        // source can push meanwhile.
+        let item = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let is_due = match state.pending.peek() {
+                Some(pending) => pending.due <= now,
+                None => false,
+            };
+            if !is_due {
+                if state.pending.is_empty() && state.is_completed {
+                    break;
+                }
+                state.is_draining = false;
+                return;
+            }
+            state.pending.pop().unwrap().item
+        };
+        operator::push(state, item);
+    }
+    operator::complete(state);
+}
+
+// Schedules the timer for the earliest pending value, unless it is scheduled already.
+fn arm<T, E, Sch>(state: &Rc<RefCell<DelayEachState<T, E, Sch::Subscription>>>, scheduler: &Sch)
+    where Sch: Scheduler + Clone + 'static,
+          Sch::Subscription: 'static,
+          T: 'static,
+          E: 'static {
+    let (due, generation) = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            return;
+        }
+        let due = match state.pending.peek() {
+            Some(pending) => pending.due,
+            None => return,
+        };
+        match state.timer {
+            Some((timer_due, _)) if timer_due <= due => return,
+            _ => {}
+        }
+        state.timer_generation += 1;
+        (due, state.timer_generation)
+    };
+    let delay = due.checked_duration_since(scheduler.now_instant())
+                   .unwrap_or(Duration::from_secs(0));
+
+    // The scheduler might run the action immediately,
+    // so the state must not be borrowed here.
+    let weak_state = Rc::downgrade(state);
+    let action_scheduler = scheduler.clone();
+    let timer = scheduler.schedule_after(delay, move || fire(&weak_state, &action_scheduler));
+    let previous = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() || state.timer_generation != generation {
+            Some(timer)
+        } else {
+            state.timer.replace((due, timer)).map(|(_due, timer)| timer)
+        }
+    };
+    drop(previous);
+}
+
+// Pushes the values that are due, and schedules the timer for the next one.
+fn fire<T, E, Sch>(state: &Weak<RefCell<DelayEachState<T, E, Sch::Subscription>>>,
+                   scheduler: &Sch)
+    where Sch: Scheduler + Clone + 'static,
+          Sch::Subscription: 'static,
+          T: 'static,
+          E: 'static {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    let timer = {
+        let mut state = state.borrow_mut();
+        state.timer_generation += 1;
+        state.timer.take()
+    };
+    drop(timer);
+    drain(&state, scheduler.now_instant());
+    arm(&state, scheduler);
+}
+
+impl<T, E, F, Sch> Observer<T, E> for DelayEachObserver<T, E, F, Sch>
+where F: Fn(&T) -> Duration,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static {
+    fn on_next(&mut self, item: T) {
+        let due = self.scheduler.now_instant() + (self.f)(&item);
+        {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.pending.push(Pending {
+                due: due,
+                sequence: sequence,
+                item: item,
+            });
+        }
+        arm(&self.state, &self.scheduler);
+    }
+
+    fn on_completed(self) {
+        self.state.borrow_mut().is_completed = true;
+        drain(&self.state, self.scheduler.now_instant());
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Subs, T, E, SchSubs> Subscription for DelayEachSubscription<Subs, T, E, SchSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, SchSubs> Drop for DelayEachSubscription<Subs, T, E, SchSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,150 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The graph of a pipeline, see `Observable::describe()`.
+
+use observable::Observable;
+use std::any;
+use std::fmt::Write;
+
+/// An observable in the graph of a pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// The name of the operator, for instance `map`, or the name of the type
+    /// for sources that do not provide a name.
+    pub name: String,
+
+    /// The type of the values of the observable.
+    pub item: String,
+
+    /// The type of the error of the observable.
+    pub error: String,
+}
+
+/// The topology of a pipeline: its observables, and which feeds which.
+///
+/// There is a node for every observable, and an edge from every source to
+/// the observable that subscribes to it. The last node is the observable
+/// that was described. A source that is used twice, for instance by two
+/// halves of `split_results()`, occurs in the graph twice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<(usize, usize)>,
+}
+
+// Removes the module paths from a type name, so
+// `core::option::Option<alloc::string::String>` becomes `Option<String>`.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            short.truncate(segment_start);
+        } else {
+            short.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = short.len();
+            }
+        }
+    }
+    short
+}
+
+// Returns the name of the type without its module path and its parameters.
+pub fn type_name<T: ?Sized>() -> String {
+    let mut name = short_type_name(any::type_name::<T>());
+    if let Some(index) = name.find('<') {
+        name.truncate(index);
+    }
+    name
+}
+
+impl Node {
+    /// Creates a node for an observable with item type `T` and error type `E`.
+    pub fn new<T: ?Sized, E: ?Sized>(name: &str) -> Node {
+        Node {
+            name: name.to_string(),
+            item: short_type_name(any::type_name::<T>()),
+            error: short_type_name(any::type_name::<E>()),
+        }
+    }
+}
+
+impl Graph {
+    /// Creates the graph of an observable that subscribes to the sources.
+    pub fn new(node: Node, sources: Vec<Graph>) -> Graph {
+        let mut graph = Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        let mut roots = Vec::with_capacity(sources.len());
+        for source in sources {
+            let offset = graph.nodes.len();
+            graph.nodes.extend(source.nodes);
+            let edges = source.edges.iter().map(|&(from, to)| (from + offset, to + offset));
+            graph.edges.extend(edges);
+            roots.push(graph.nodes.len() - 1);
+        }
+        let root = graph.nodes.len();
+        graph.nodes.push(node);
+        graph.edges.extend(roots.into_iter().map(|from| (from, root)));
+        graph
+    }
+
+    /// Creates the graph of an observable of type `O` that has no sources.
+    pub fn source<O: Observable + ?Sized>(name: &str) -> Graph {
+        Graph::new(Node::new::<O::Item, O::Error>(name), Vec::new())
+    }
+
+    /// Creates the graph of an operator of type `O` that subscribes to the sources.
+    pub fn operator<O: Observable + ?Sized>(name: &str, sources: Vec<Graph>) -> Graph {
+        Graph::new(Node::new::<O::Item, O::Error>(name), sources)
+    }
+
+    /// Creates the graph of an observable of type `O` that does not describe itself.
+    ///
+    /// The name of the node is the name of the type, without its parameters.
+    pub fn unnamed<O: Observable + ?Sized>() -> Graph {
+        Graph::source::<O>(&type_name::<O>())
+    }
+
+    /// Returns the nodes of the graph. The last one is the described observable.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes[..]
+    }
+
+    /// Returns the edges, as indices into `nodes()`, from a source to its subscriber.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges[..]
+    }
+
+    /// Returns the node of the described observable.
+    pub fn root(&self) -> &Node {
+        // There is at least one node, because only `new()` creates graphs.
+        self.nodes.last().unwrap()
+    }
+
+    /// Formats the graph in the DOT language, for rendering with Graphviz.
+    ///
+    /// Every node is labelled with its name and its item and error type,
+    /// and values flow along the edges.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph pipeline {\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = format!("{}\n<{}, {}>", node.name, node.item, node.error);
+            writeln!(dot, "    n{} [label={:?}];", i, label).unwrap();
+        }
+        for &(from, to) in &self.edges {
+            writeln!(dot, "    n{} -> n{};", from, to).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,111 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `distinct_within()` operator, which drops recent duplicates.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The result of calling `distinct_within()` or `distinct_within_by_key()` on an observable.
+pub struct DistinctWithinObservable<Source, F, Sch> {
+    source: Source,
+
+    // Every observer shares the key function.
+    f: Rc<F>,
+
+    duration: Duration,
+    scheduler: Sch,
+}
+
+struct DistinctWithinObserver<O, F, K, Sch> {
+    observer: O,
+    f: Rc<F>,
+    duration: Duration,
+    scheduler: Sch,
+
+    // The keys of the values that were pushed within the duration.
+    seen: HashSet<K>,
+
+    // The same keys, oldest first, so expired keys can be removed cheaply.
+    expiry: VecDeque<(Instant, K)>,
+}
+
+impl<Source, F, Sch> DistinctWithinObservable<Source, F, Sch> {
+    pub fn new(source: Source, duration: Duration, f: F, scheduler: Sch)
+               -> DistinctWithinObservable<Source, F, Sch> {
+        DistinctWithinObservable {
+            source: source,
+            f: Rc::new(f),
+            duration: duration,
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, F, K, Sch> Observable for DistinctWithinObservable<Source, F, Sch>
+where Source: Observable,
+      F: Fn(&Source::Item) -> K + 'static,
+      K: Hash + Eq + Clone + 'static,
+      Sch: Scheduler + Clone + 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let distinct_observer = DistinctWithinObserver {
+            observer: observer,
+            f: self.f.clone(),
+            duration: self.duration,
+            scheduler: self.scheduler.clone(),
+            seen: HashSet::new(),
+            expiry: VecDeque::new(),
+        };
+        self.source.subscribe(distinct_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("distinct_within", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, F, K, Sch> Observer<T, E> for DistinctWithinObserver<O, F, K, Sch>
+where O: Observer<T, E>,
+      F: Fn(&T) -> K,
+      K: Hash + Eq + Clone,
+      Sch: Scheduler {
+    fn on_next(&mut self, item: T) {
+        let now = self.scheduler.now_instant();
+        while self.expiry.front().is_some_and(|&(time, _)| time + self.duration <= now) {
+            // A key is queued only while it is absent from the map, so the
+            // queue holds every key once.
+            let (_, key) = self.expiry.pop_front().unwrap();
+            self.seen.remove(&key);
+        }
+        let key = (self.f)(&item);
+        if self.seen.contains(&key) {
+            return;
+        }
+        self.seen.insert(key.clone());
+        self.expiry.push_back((now, key));
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
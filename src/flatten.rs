@@ -0,0 +1,586 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that flatten observables whose values are observables.
+
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `switch()` on an observable.
+pub struct SwitchObservable<Source> {
+    source: Source,
+}
+
+/// The result of calling `exhaust_map()` on an observable.
+pub struct ExhaustMapObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+}
+
+/// The result of subscribing to a `switch()` or `exhaust_map()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and to
+/// the current inner observable.
+pub struct SwitchSubscription<Subs, T, E, InnerSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<SwitchState<T, E, InnerSubs>>>,
+}
+
+struct SwitchState<T, E, InnerSubs> {
+    observer: ObserverSlot<T, E>,
+
+    // Counts the inner observables, so notifications of inner observables
+    // that were switched away from can be told apart.
+    generation: u64,
+
+    // Whether the current inner observable did not complete yet.
+    is_inner_active: bool,
+
+    // The subscription is stored once `subscribe()` returns, unless the
+    // inner observable ended by then.
+    inner: Option<InnerSubs>,
+
+    // Whether the source completed. The observer completes once the current
+    // inner observable completes too.
+    is_completed: bool,
+}
+
+type SwitchShared<T, E, InnerSubs> = Rc<RefCell<SwitchState<T, E, InnerSubs>>>;
+
+struct SwitchObserver<T, E, InnerSubs> {
+    state: SwitchShared<T, E, InnerSubs>,
+}
+
+struct ExhaustMapObserver<T, E, InnerSubs, F> {
+    state: SwitchShared<T, E, InnerSubs>,
+    f: Rc<F>,
+}
+
+struct SwitchInnerObserver<T, E, InnerSubs> {
+    state: SwitchShared<T, E, InnerSubs>,
+    generation: u64,
+}
+
+impl<Source> SwitchObservable<Source> {
+    pub fn new(source: Source) -> SwitchObservable<Source> {
+        SwitchObservable {
+            source: source,
+        }
+    }
+}
+
+impl<Source, F> ExhaustMapObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> ExhaustMapObservable<Source, F> {
+        ExhaustMapObservable {
+            source: source,
+            f: Rc::new(f),
+        }
+    }
+}
+
+fn new_state<T, E, InnerSubs>(kind: &'static str, observer: Box<dyn BoxedObserver<T, E>>)
+                              -> SwitchShared<T, E, InnerSubs> {
+    Rc::new(RefCell::new(SwitchState {
+        observer: ObserverSlot::new(kind, observer),
+        generation: 0,
+        is_inner_active: false,
+        inner: None,
+        is_completed: false,
+    }))
+}
+
+impl<Source, Inner> Observable for SwitchObservable<Source>
+where Source: Observable<Item = Inner>,
+      Inner: Observable<Error = Source::Error>,
+      Inner::Subscription: 'static,
+      Inner::Item: 'static,
+      Source::Error: 'static {
+    type Item = Inner::Item;
+    type Error = Source::Error;
+    type Subscription = SwitchSubscription<Source::Subscription, Inner::Item, Source::Error,
+                                           Inner::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let state = new_state("switch subscription", observer.boxed());
+        let switch_observer = SwitchObserver { state: state.clone() };
+        SwitchSubscription {
+            subscription: self.source.subscribe(switch_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("switch", vec![self.source.describe()])
+    }
+}
+
+impl<Source, Inner, F> Observable for ExhaustMapObservable<Source, F>
+where Source: Observable,
+      Inner: Observable<Error = Source::Error>,
+      Inner::Subscription: 'static,
+      Inner::Item: 'static,
+      Source::Error: 'static,
+      F: Fn(Source::Item) -> Inner + 'static {
+    type Item = Inner::Item;
+    type Error = Source::Error;
+    type Subscription = SwitchSubscription<Source::Subscription, Inner::Item, Source::Error,
+                                           Inner::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let state = new_state("exhaust_map subscription", observer.boxed());
+        let exhaust_map_observer = ExhaustMapObserver {
+            state: state.clone(),
+            f: self.f.clone(),
+        };
+        SwitchSubscription {
+            subscription: self.source.subscribe(exhaust_map_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("exhaust_map", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, InnerSubs> OperatorState for SwitchState<T, E, InnerSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = Option<InnerSubs>;
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> Option<InnerSubs> {
+        self.inner.take()
+    }
+}
+
+// Subscribes to the inner observable of the given generation.
+fn subscribe_inner<T, E, Inner>(state: &SwitchShared<T, E, Inner::Subscription>,
+                                generation: u64,
+                                inner: &mut Inner)
+    where Inner: Observable<Item = T, Error = E>,
+          Inner::Subscription: 'static,
+          T: 'static,
+          E: 'static {
+    let inner_observer = SwitchInnerObserver {
+        state: state.clone(),
+        generation: generation,
+    };
+    let subscription = inner.subscribe(inner_observer);
+    let subscription = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() || state.generation != generation || !state.is_inner_active {
+            Some(subscription)
+        } else {
+            state.inner = Some(subscription);
+            None
+        }
+    };
+    drop(subscription);
+}
+
+fn complete_outer<T, E, InnerSubs>(state: &RefCell<SwitchState<T, E, InnerSubs>>) {
+    let is_finished = {
+        let mut state = state.borrow_mut();
+        state.is_completed = true;
+        !state.is_inner_active
+    };
+    if is_finished {
+        operator::complete(state);
+    }
+}
+
+impl<T, E, Inner> Observer<Inner, E> for SwitchObserver<T, E, Inner::Subscription>
+where Inner: Observable<Item = T, Error = E>,
+      Inner::Subscription: 'static,
+      T: 'static,
+      E: 'static {
+    fn on_next(&mut self, mut item: Inner) {
+        let (generation, previous) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            state.generation += 1;
+            state.is_inner_active = true;
+            (state.generation, state.inner.take())
+        };
+        // The previous inner observable is dropped before the next one is
+        // subscribed to.
+        drop(previous);
+        subscribe_inner(&self.state, generation, &mut item);
+    }
+
+    fn on_completed(self) {
+        complete_outer(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, U, Inner, F> Observer<U, E> for ExhaustMapObserver<T, E, Inner::Subscription, F>
+where Inner: Observable<Item = T, Error = E>,
+      Inner::Subscription: 'static,
+      F: Fn(U) -> Inner,
+      T: 'static,
+      E: 'static {
+    fn on_next(&mut self, item: U) {
+        let generation = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() || state.is_inner_active {
+                return;
+            }
+            state.generation += 1;
+            state.is_inner_active = true;
+            state.generation
+        };
+        // The function is user code, so it is called outside of the borrow.
+        let mut inner = (self.f)(item);
+        subscribe_inner(&self.state, generation, &mut inner);
+    }
+
+    fn on_completed(self) {
+        complete_outer(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, InnerSubs> SwitchInnerObserver<T, E, InnerSubs> {
+    // Returns whether this is the observer of the current inner observable.
+    fn is_current(&self) -> bool {
+        let state = self.state.borrow();
+        !state.observer.is_done() && state.generation == self.generation
+    }
+}
+
+impl<T, E, InnerSubs> Observer<T, E> for SwitchInnerObserver<T, E, InnerSubs> {
+    fn on_next(&mut self, item: T) {
+        if self.state.borrow().generation == self.generation {
+            operator::push(&self.state, item);
+        }
+    }
+
+    fn on_completed(self) {
+        if !self.is_current() {
+            return;
+        }
+        let (is_finished, inner) = {
+            let mut state = self.state.borrow_mut();
+            state.is_inner_active = false;
+            (state.is_completed, state.inner.take())
+        };
+        drop(inner);
+        if is_finished {
+            operator::complete(&self.state);
+        }
+    }
+
+    fn on_error(self, error: E) {
+        if self.is_current() {
+            operator::fail(&self.state, error);
+        }
+    }
+}
+
+impl<Subs, T, E, InnerSubs> Subscription for SwitchSubscription<Subs, T, E, InnerSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, InnerSubs> Drop for SwitchSubscription<Subs, T, E, InnerSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
+
+/// The result of calling `expand()` on an observable.
+pub struct ExpandObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+}
+
+/// The result of subscribing to an `expand()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and to
+/// the current expansion, and it discards the values that were not expanded
+/// yet.
+pub struct ExpandSubscription<Subs, T, E, InnerSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<ExpandState<T, E, InnerSubs>>>,
+}
+
+struct ExpandState<T, E, InnerSubs> {
+    observer: ObserverSlot<T, E>,
+
+    // The values that were pushed, but not expanded yet.
+    queue: VecDeque<T>,
+
+    // Counts the expansions, so notifications of an expansion that ended
+    // can be told apart.
+    generation: u64,
+
+    // Whether the current expansion did not complete yet.
+    is_inner_active: bool,
+
+    // The subscription is stored once `subscribe()` returns, unless the
+    // expansion ended by then.
+    inner: Option<InnerSubs>,
+
+    // Whether the source completed. The observer completes once every value
+    // has been expanded.
+    is_completed: bool,
+
+    // Whether expansions are being subscribed to. Expansions that end
+    // meanwhile are picked up by the loop that subscribes.
+    is_draining: bool,
+}
+
+type ExpandShared<T, E, InnerSubs> = Rc<RefCell<ExpandState<T, E, InnerSubs>>>;
+
+struct ExpandObserver<T, E, InnerSubs, F> {
+    state: ExpandShared<T, E, InnerSubs>,
+    f: Rc<F>,
+}
+
+struct ExpandInnerObserver<T, E, InnerSubs, F> {
+    state: ExpandShared<T, E, InnerSubs>,
+    f: Rc<F>,
+    generation: u64,
+}
+
+impl<Source, F> ExpandObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> ExpandObservable<Source, F> {
+        ExpandObservable {
+            source: source,
+            f: Rc::new(f),
+        }
+    }
+}
+
+impl<Source, Inner, F> Observable for ExpandObservable<Source, F>
+where Source: Observable,
+      Source::Item: Clone + 'static,
+      Source::Error: 'static,
+      Inner: Observable<Item = Source::Item, Error = Source::Error>,
+      Inner::Subscription: 'static,
+      F: Fn(Source::Item) -> Inner + 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = ExpandSubscription<Source::Subscription, Source::Item, Source::Error,
+                                           Inner::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let state = Rc::new(RefCell::new(ExpandState {
+            observer: ObserverSlot::new("expand subscription", observer.boxed()),
+            queue: VecDeque::new(),
+            generation: 0,
+            is_inner_active: false,
+            inner: None,
+            is_completed: false,
+            is_draining: false,
+        }));
+        let expand_observer = ExpandObserver {
+            state: state.clone(),
+            f: self.f.clone(),
+        };
+        ExpandSubscription {
+            subscription: self.source.subscribe(expand_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("expand", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, InnerSubs> OperatorState for ExpandState<T, E, InnerSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<InnerSubs>, VecDeque<T>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<InnerSubs>, VecDeque<T>) {
+        (self.inner.take(), mem::take(&mut self.queue))
+    }
+}
+
+// Pushes the value to the observer, and queues it to be expanded.
+fn push_expanded<T: Clone, E, InnerSubs>(state: &RefCell<ExpandState<T, E, InnerSubs>>, item: T) {
+    {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            return;
+        }
+        state.queue.push_back(item.clone());
+    }
+    operator::push(state, item);
+}
+
+// Subscribes to the expansions of the queued values, one at a time, so the
+// values are expanded breadth-first.
+fn expand_queued<T, E, F, Inner>(state: &ExpandShared<T, E, Inner::Subscription>, f: &Rc<F>)
+    where Inner: Observable<Item = T, Error = E>,
+          Inner::Subscription: 'static,
+          F: Fn(T) -> Inner + 'static,
+          T: Clone + 'static,
+          E: 'static {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_draining || state.observer.is_done() {
+            return;
+        }
+        state.is_draining = true;
+    }
+    loop {
+        let (item, generation) = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if state.is_inner_active {
+                state.is_draining = false;
+                return;
+            }
+            match state.queue.pop_front() {
+                Some(item) => {
+                    state.generation += 1;
+                    state.is_inner_active = true;
+                    (item, state.generation)
+                }
+                None if state.is_completed => break,
+                None => {
+                    state.is_draining = false;
+                    return;
+                }
+            }
+        };
+        // The function is user code, so it is called outside of the borrow.
+        let mut inner = f(item);
+        let inner_observer = ExpandInnerObserver {
+            state: state.clone(),
+            f: f.clone(),
+            generation: generation,
+        };
+        let subscription = inner.subscribe(inner_observer);
+        let subscription = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() || state.generation != generation ||
+               !state.is_inner_active {
+                Some(subscription)
+            } else {
+                state.inner = Some(subscription);
+                None
+            }
+        };
+        drop(subscription);
+    }
+    operator::complete(state);
+}
+
+impl<T, E, Inner, F> Observer<T, E> for ExpandObserver<T, E, Inner::Subscription, F>
+where Inner: Observable<Item = T, Error = E>,
+      Inner::Subscription: 'static,
+      F: Fn(T) -> Inner + 'static,
+      T: Clone + 'static,
+      E: 'static {
+    fn on_next(&mut self, item: T) {
+        push_expanded(&self.state, item);
+        expand_queued(&self.state, &self.f);
+    }
+
+    fn on_completed(self) {
+        self.state.borrow_mut().is_completed = true;
+        expand_queued(&self.state, &self.f);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Inner, F> Observer<T, E> for ExpandInnerObserver<T, E, Inner::Subscription, F>
+where Inner: Observable<Item = T, Error = E>,
+      Inner::Subscription: 'static,
+      F: Fn(T) -> Inner + 'static,
+      T: Clone + 'static,
+      E: 'static {
+    fn on_next(&mut self, item: T) {
+        if self.state.borrow().generation == self.generation {
+            push_expanded(&self.state, item);
+        }
+    }
+
+    fn on_completed(self) {
+        let inner = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() || state.generation != self.generation {
+                return;
+            }
+            state.is_inner_active = false;
+            state.inner.take()
+        };
+        drop(inner);
+        expand_queued(&self.state, &self.f);
+    }
+
+    fn on_error(self, error: E) {
+        if self.state.borrow().generation == self.generation {
+            operator::fail(&self.state, error);
+        }
+    }
+}
+
+impl<Subs, T, E, InnerSubs> Subscription for ExpandSubscription<Subs, T, E, InnerSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, InnerSubs> Drop for ExpandSubscription<Subs, T, E, InnerSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
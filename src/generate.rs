@@ -5,9 +5,14 @@
 // you may not use this file except in compliance with the License.
 // A copy of the License has been included in the root of the repository.
 
+use describe::Graph;
+use lifeline;
 use observable::Observable;
-use observer::Observer;
+use observer::{Observer, BoxedObserver};
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::rc::Rc;
+use subscription::Subscription;
 
 /// An observable that never pushes a value and never completes.
 pub struct Never<T: Clone, E: Clone> {
@@ -18,8 +23,10 @@ pub struct Never<T: Clone, E: Clone> {
 /// The result of subscribing to a never observable.
 ///
 /// Note that dropping this subscription has no effect, as a never observable
-/// never pushes a value anyway.
-pub struct NeverSubscription;
+/// never pushes a value anyway. It is active until it is unsubscribed.
+pub struct NeverSubscription {
+    is_active: bool,
+}
 
 impl<T: Clone, E: Clone> Never<T, E> {
     /// Creates an observable that never pushes a value and never completes.
@@ -31,6 +38,12 @@ impl<T: Clone, E: Clone> Never<T, E> {
     }
 }
 
+impl<T: Clone, E: Clone> Default for Never<T, E> {
+    fn default() -> Never<T, E> {
+        Never::new()
+    }
+}
+
 impl<T: Clone, E: Clone> Observable for Never<T, E> {
     type Item = T;
     type Error = E;
@@ -39,7 +52,19 @@ impl<T: Clone, E: Clone> Observable for Never<T, E> {
     fn subscribe<O>(&mut self, _observer: O) -> Self::Subscription
         where O: Observer<Self::Item, Self::Error> {
         // Do nothing, forget about the observer.
-        NeverSubscription
+        NeverSubscription {
+            is_active: true,
+        }
+    }
+}
+
+impl Subscription for NeverSubscription {
+    fn unsubscribe(&mut self) {
+        self.is_active = false;
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_active
     }
 }
 
@@ -48,3 +73,231 @@ impl Drop for NeverSubscription {
         // This is a no-op.
     }
 }
+
+/// A handle for pushing values to an observer of a `create()` observable.
+///
+/// The emitter enforces the observer contract: `completed()` and `error()`
+/// consume the emitter, so no values can be pushed after a terminal event.
+/// When the subscription is dropped, the emitter becomes a no-op.
+pub struct Emitter<T, E> {
+    observer: lifeline::Owner<Box<dyn BoxedObserver<T, E>>>,
+}
+
+/// An observable that invokes a function with an emitter upon subscription.
+pub struct Create<T, E, F> {
+    subscribe_fn: F,
+    _phantom_t: PhantomData<T>,
+    _phantom_e: PhantomData<E>,
+}
+
+/// The result of subscribing to a `create()` observable.
+///
+/// Dropping this subscription turns the emitter into a no-op.
+pub struct CreateSubscription<T, E> {
+    alive: Option<lifeline::Lifeline<Box<dyn BoxedObserver<T, E>>>>,
+}
+
+impl<T, E> Emitter<T, E> {
+    /// Pushes a value to the observer, unless the subscription was dropped.
+    pub fn next(&mut self, item: T) {
+        self.observer.with_mut_value(|observer| observer.on_next(item));
+    }
+
+    /// Completes the observable, unless the subscription was dropped.
+    pub fn completed(self) {
+        if let Some(observer) = self.observer.take() {
+            observer.on_completed_box();
+        }
+    }
+
+    /// Fails the observable with the error, unless the subscription was dropped.
+    pub fn error(self, error: E) {
+        if let Some(observer) = self.observer.take() {
+            observer.on_error_box(error);
+        }
+    }
+
+    /// Returns whether the subscription has been dropped.
+    ///
+    /// Long-running producers can use this to stop doing work when nobody is
+    /// listening any more.
+    pub fn is_unsubscribed(&self) -> bool {
+        !self.observer.is_alive()
+    }
+}
+
+/// Creates an observable from a function that pushes values to an emitter.
+///
+/// The function is called once for every subscription, with a new emitter. It
+/// may push values immediately, or it may store the emitter and push values
+/// later on. The following example pushes two values and then completes:
+///
+/// ```
+/// use rx::ObservableExt;
+/// let mut observable = rx::create(|mut emitter: rx::Emitter<u8, ()>| {
+///     emitter.next(2);
+///     emitter.next(3);
+///     emitter.completed();
+/// });
+/// observable.subscribe_next(|x| println!("received {}", x));
+/// ```
+pub fn create<T, E, F>(subscribe_fn: F) -> Create<T, E, F>
+    where T: Clone, E: Clone, F: FnMut(Emitter<T, E>) {
+    Create {
+        subscribe_fn: subscribe_fn,
+        _phantom_t: PhantomData,
+        _phantom_e: PhantomData,
+    }
+}
+
+impl<T: Clone, E: Clone, F> Observable for Create<T, E, F> where F: FnMut(Emitter<T, E>) {
+    type Item = T;
+    type Error = E;
+    type Subscription = CreateSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let boxed: Box<dyn BoxedObserver<T, E>> = Box::new(observer);
+        let (alive, owner) = lifeline::new(boxed);
+        let emitter = Emitter {
+            observer: owner,
+        };
+        (self.subscribe_fn)(emitter);
+        CreateSubscription {
+            alive: Some(alive),
+        }
+    }
+}
+
+impl<T, E> Subscription for CreateSubscription<T, E> {
+    fn unsubscribe(&mut self) {
+        // Dropping the lifeline turns the emitter into a no-op.
+        self.alive = None;
+    }
+
+    fn is_active(&self) -> bool {
+        self.alive.as_ref().is_some_and(|alive| alive.is_alive())
+    }
+}
+
+impl<T, E> Drop for CreateSubscription<T, E> {
+    fn drop(&mut self) {
+        // Nothing to do, the lifeline already does the right thing.
+    }
+}
+
+/// An observable that acquires a resource for every subscription.
+pub struct Using<F, G> {
+    resource_fn: F,
+    observable_fn: G,
+}
+
+/// The result of subscribing to a `using()` observable.
+///
+/// Dropping this subscription drops the subscription to the inner observable,
+/// and then it releases the resource.
+pub struct UsingSubscription<Subs, R> {
+    // The subscription is taken out when the subscription is dropped, so it
+    // can be dropped before the resource.
+    subscription: Option<Subs>,
+    resource: Rc<RefCell<Option<R>>>,
+}
+
+struct UsingObserver<O, R> {
+    observer: O,
+
+    // The resource is shared with the subscription, whichever ends first
+    // releases it.
+    resource: Rc<RefCell<Option<R>>>,
+}
+
+/// Creates an observable whose resource lives as long as the subscription.
+///
+/// For every subscription, `resource_fn` acquires a resource, and
+/// `observable_fn` returns the observable to subscribe to, which may use the
+/// resource. The resource is released by dropping it, as soon as the
+/// observable completes or fails, or when the subscription is dropped. This
+/// way, files, sockets, or locks do not outlive the pipeline that uses them.
+///
+/// ```
+/// use rx::ObservableExt;
+/// let mut observable = rx::using(|| vec![2, 3, 5], |primes| Some(primes.len()));
+/// observable.subscribe_next(|x| println!("received {}", x));
+/// ```
+pub fn using<R, Inner, F, G>(resource_fn: F, observable_fn: G) -> Using<F, G>
+    where F: FnMut() -> R, G: FnMut(&mut R) -> Inner, Inner: Observable {
+    Using {
+        resource_fn: resource_fn,
+        observable_fn: observable_fn,
+    }
+}
+
+impl<R, Inner, F, G> Observable for Using<F, G>
+where F: FnMut() -> R,
+      G: FnMut(&mut R) -> Inner,
+      Inner: Observable,
+      R: 'static {
+    type Item = Inner::Item;
+    type Error = Inner::Error;
+    type Subscription = UsingSubscription<Inner::Subscription, R>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let mut resource = (self.resource_fn)();
+        let mut observable = (self.observable_fn)(&mut resource);
+        let resource = Rc::new(RefCell::new(Some(resource)));
+        let using_observer = UsingObserver {
+            observer: observer,
+            resource: resource.clone(),
+        };
+        UsingSubscription {
+            subscription: Some(observable.subscribe(using_observer)),
+            resource: resource,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::source::<Self>("using")
+    }
+}
+
+fn release<R>(resource: &RefCell<Option<R>>) {
+    let resource = resource.borrow_mut().take();
+    drop(resource);
+}
+
+impl<T, E, O, R> Observer<T, E> for UsingObserver<O, R> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+        release(&self.resource);
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+        release(&self.resource);
+    }
+}
+
+impl<Subs: Subscription, R> Subscription for UsingSubscription<Subs, R> {
+    fn unsubscribe(&mut self) {
+        if let Some(ref mut subscription) = self.subscription {
+            subscription.unsubscribe();
+        }
+        release(&self.resource);
+    }
+
+    fn is_active(&self) -> bool {
+        self.resource.borrow().is_some()
+    }
+}
+
+impl<Subs, R> Drop for UsingSubscription<Subs, R> {
+    fn drop(&mut self) {
+        drop(self.subscription.take());
+        release(&self.resource);
+    }
+}
@@ -0,0 +1,323 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `heartbeat()` and `watchdog()` operators, which act when the source is
+//! silent for too long.
+
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use operator::{self, ObserverSlot, OperatorState};
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use subscription::Subscription;
+
+/// The result of calling `heartbeat()` on an observable.
+pub struct HeartbeatObservable<Source, F, Sch> {
+    source: Source,
+    duration: Duration,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+
+    scheduler: Sch,
+}
+
+/// The result of calling `watchdog()` on an observable.
+pub struct WatchdogObservable<Source, F, Sch> {
+    source: Source,
+    duration: Duration,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+
+    scheduler: Sch,
+}
+
+/// The result of subscribing to a `heartbeat()` or `watchdog()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// stops the timer.
+pub struct HeartbeatSubscription<T, E, Subs, SchSubs> {
+    state: Rc<RefCell<HeartbeatState<T, E, Subs, SchSubs>>>,
+}
+
+struct HeartbeatState<T, E, Subs, SchSubs> {
+    observer: ObserverSlot<T, E>,
+
+    // The subscription is stored once `subscribe()` on the source returns,
+    // unless the operator was done by then.
+    source: Option<Subs>,
+
+    // The action that runs when the source has been silent for the duration.
+    // It is replaced by a new one whenever the source pushes a value.
+    timer: Option<SchSubs>,
+}
+
+type HeartbeatShared<T, E, Subs, SchSubs> = Rc<RefCell<HeartbeatState<T, E, Subs, SchSubs>>>;
+
+struct HeartbeatObserver<T, E, Subs, F, Sch: Scheduler> {
+    state: HeartbeatShared<T, E, Subs, Sch::Subscription>,
+    duration: Duration,
+    f: Rc<F>,
+    scheduler: Sch,
+}
+
+struct WatchdogObserver<T, E, Subs, F, Sch: Scheduler> {
+    state: HeartbeatShared<T, E, Subs, Sch::Subscription>,
+    duration: Duration,
+    f: Rc<F>,
+    scheduler: Sch,
+}
+
+impl<Source, F, Sch> HeartbeatObservable<Source, F, Sch> {
+    pub fn new(source: Source, duration: Duration, f: F, scheduler: Sch)
+               -> HeartbeatObservable<Source, F, Sch> {
+        HeartbeatObservable {
+            source: source,
+            duration: duration,
+            f: Rc::new(f),
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, F, Sch> WatchdogObservable<Source, F, Sch> {
+    pub fn new(source: Source, duration: Duration, f: F, scheduler: Sch)
+               -> WatchdogObservable<Source, F, Sch> {
+        WatchdogObservable {
+            source: source,
+            duration: duration,
+            f: Rc::new(f),
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, F, Sch, T, E> Observable for HeartbeatObservable<Source, F, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Source::Subscription: 'static,
+      F: Fn() -> T + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = HeartbeatSubscription<T, E, Source::Subscription, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = new_state("heartbeat subscription", observer.boxed());
+        let heartbeat_observer = HeartbeatObserver {
+            state: state.clone(),
+            duration: self.duration,
+            f: self.f.clone(),
+            scheduler: self.scheduler.clone(),
+        };
+        // The silence counts from the subscription until the first value.
+        heartbeat_observer.restart();
+        let source = self.source.subscribe(heartbeat_observer);
+        store_source(&state, source);
+        HeartbeatSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("heartbeat", vec![self.source.describe()])
+    }
+}
+
+impl<Source, F, Sch, T, E> Observable for WatchdogObservable<Source, F, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Source::Subscription: 'static,
+      F: Fn() -> E + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = HeartbeatSubscription<T, E, Source::Subscription, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = new_state("watchdog subscription", observer.boxed());
+        let watchdog_observer = WatchdogObserver {
+            state: state.clone(),
+            duration: self.duration,
+            f: self.f.clone(),
+            scheduler: self.scheduler.clone(),
+        };
+        // The silence counts from the subscription until the first value.
+        watchdog_observer.restart();
+        let source = self.source.subscribe(watchdog_observer);
+        store_source(&state, source);
+        HeartbeatSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("watchdog", vec![self.source.describe()])
+    }
+}
+
+fn new_state<T, E, Subs, SchSubs>(kind: &'static str, observer: Box<dyn BoxedObserver<T, E>>)
+                                  -> HeartbeatShared<T, E, Subs, SchSubs> {
+    Rc::new(RefCell::new(HeartbeatState {
+        observer: ObserverSlot::new(kind, observer),
+        source: None,
+        timer: None,
+    }))
+}
+
+fn store_source<T, E, Subs, SchSubs>(state: &RefCell<HeartbeatState<T, E, Subs, SchSubs>>,
+                                     source: Subs) {
+    let source = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            Some(source)
+        } else {
+            state.source = Some(source);
+            None
+        }
+    };
+    drop(source);
+}
+
+fn store_timer<T, E, Subs, SchSubs>(state: &RefCell<HeartbeatState<T, E, Subs, SchSubs>>,
+                                    timer: SchSubs) {
+    let previous = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            Some(timer)
+        } else {
+            state.timer.replace(timer)
+        }
+    };
+    drop(previous);
+}
+
+impl<T, E, Subs, SchSubs> OperatorState for HeartbeatState<T, E, Subs, SchSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<SchSubs>, Option<Subs>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<SchSubs>, Option<Subs>) {
+        (self.timer.take(), self.source.take())
+    }
+}
+
+impl<T, E, Subs, F, Sch> HeartbeatObserver<T, E, Subs, F, Sch>
+where F: Fn() -> T + 'static,
+      Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static,
+      Subs: 'static {
+    // Replaces the timer by one that starts counting now.
+    fn restart(&self) {
+        // The scheduler might run the action immediately,
+        // so the state must not be borrowed here.
+        let state = Rc::downgrade(&self.state);
+        let f = self.f.clone();
+        let timer = self.scheduler.schedule_periodic(self.duration, move || beat(&state, &*f));
+        store_timer(&self.state, timer);
+    }
+}
+
+// Pushes a filler value, for a source that has been silent.
+fn beat<T, E, Subs, SchSubs, F>(state: &Weak<RefCell<HeartbeatState<T, E, Subs, SchSubs>>>, f: &F)
+    where F: Fn() -> T {
+    if let Some(state) = state.upgrade() {
+        operator::push(&state, f());
+    }
+}
+
+impl<T, E, Subs, F, Sch> Observer<T, E> for HeartbeatObserver<T, E, Subs, F, Sch>
+where F: Fn() -> T + 'static,
+      Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static,
+      Subs: 'static {
+    fn on_next(&mut self, item: T) {
+        operator::push(&self.state, item);
+        self.restart();
+    }
+
+    fn on_completed(self) {
+        operator::complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Subs, F, Sch> WatchdogObserver<T, E, Subs, F, Sch>
+where F: Fn() -> E + 'static,
+      Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static,
+      Subs: 'static {
+    // Replaces the timer by one that starts counting now.
+    fn restart(&self) {
+        // The scheduler might run the action immediately,
+        // so the state must not be borrowed here.
+        let state = Rc::downgrade(&self.state);
+        let f = self.f.clone();
+        let timer = self.scheduler.schedule_after(self.duration, move || {
+            if let Some(state) = state.upgrade() {
+                operator::fail(&state, f());
+            }
+        });
+        store_timer(&self.state, timer);
+    }
+}
+
+impl<T, E, Subs, F, Sch> Observer<T, E> for WatchdogObserver<T, E, Subs, F, Sch>
+where F: Fn() -> E + 'static,
+      Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static,
+      Subs: 'static {
+    fn on_next(&mut self, item: T) {
+        operator::push(&self.state, item);
+        self.restart();
+    }
+
+    fn on_completed(self) {
+        operator::complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Subs, SchSubs> Subscription for HeartbeatSubscription<T, E, Subs, SchSubs> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<T, E, Subs, SchSubs> Drop for HeartbeatSubscription<T, E, Subs, SchSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,109 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `instrument()` operator, which reports a pipeline stage to `tracing`.
+//!
+//! This module is only available with the `tracing` feature.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::fmt::Debug;
+use std::rc::Rc;
+use subscription::Subscription;
+use tracing::Span;
+
+/// The result of calling `instrument()` on an observable.
+pub struct InstrumentObservable<Source> {
+    source: Source,
+
+    // Every subscription shares the name.
+    name: Rc<String>,
+}
+
+/// The result of subscribing to an `instrument()` observable.
+///
+/// The span of the subscription is closed when this subscription and the
+/// observer that the source holds have both been dropped.
+pub struct InstrumentSubscription<Subs> {
+    subscription: Subs,
+    span: Span,
+}
+
+struct InstrumentObserver<O> {
+    observer: O,
+    span: Span,
+}
+
+impl<Source> InstrumentObservable<Source> {
+    pub fn new(source: Source, name: String) -> InstrumentObservable<Source> {
+        InstrumentObservable {
+            source: source,
+            name: Rc::new(name),
+        }
+    }
+}
+
+impl<Source> Observable for InstrumentObservable<Source>
+where Source: Observable,
+      Source::Item: Debug,
+      Source::Error: Debug {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = InstrumentSubscription<Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let span = info_span!(target: "rx", "subscription", name = %self.name);
+        let instrument_observer = InstrumentObserver {
+            observer: observer,
+            span: span.clone(),
+        };
+        // Anything that the source does while subscribing happens in the span.
+        let subscription = span.in_scope(|| self.source.subscribe(instrument_observer));
+        InstrumentSubscription {
+            subscription: subscription,
+            span: span,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("instrument", vec![self.source.describe()])
+    }
+}
+
+impl<T: Debug, E: Debug, O> Observer<T, E> for InstrumentObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        let _entered = self.span.enter();
+        trace!(target: "rx", item = ?item, "next");
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        let _entered = self.span.enter();
+        debug!(target: "rx", "completed");
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        let _entered = self.span.enter();
+        warn!(target: "rx", error = ?error, "error");
+        self.observer.on_error(error);
+    }
+}
+
+impl<Subs: Subscription> Subscription for InstrumentSubscription<Subs> {
+    fn unsubscribe(&mut self) {
+        let _entered = self.span.enter();
+        debug!(target: "rx", "unsubscribe");
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
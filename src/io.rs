@@ -0,0 +1,332 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Observables that read from files and standard input.
+//!
+//! Reading blocks, so it happens on a background thread. The lines are sent
+//! to the subscribing thread through a channel, which is drained by an action
+//! on a scheduler, as with `from_notification_receiver()`.
+//!
+//! With the `notify` feature, this module can also watch the file system.
+
+use Notification;
+use channel::{self, FromReceiver, FromReceiverSubscription};
+#[cfg(feature = "notify")]
+use lines::LineBuffer;
+#[cfg(feature = "notify")]
+use notify::{self, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use observable::Observable;
+use observer::Observer;
+use scheduler::{CurrentThreadScheduler, Scheduler};
+#[cfg(feature = "notify")]
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Stdin};
+#[cfg(feature = "notify")]
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(feature = "notify")]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+#[cfg(feature = "notify")]
+use subscription::Subscription;
+
+/// The error of observables that read.
+///
+/// An `io::Error` cannot be cloned, so it is shared instead.
+pub type Error = Arc<io::Error>;
+
+type LineReceiver = Receiver<Notification<String, Error>>;
+
+/// The result of calling `read_lines()` or `stdin_lines()`.
+pub struct ReadLines<R, Sch: Scheduler> {
+    // The reader is moved to the background thread upon the first subscription.
+    reader: Option<R>,
+    scheduler: Sch,
+    lines: Option<FromReceiver<LineReceiver, Sch, String, Error>>,
+}
+
+/// Creates an observable of the lines of a reader.
+///
+/// Upon the first subscription, a background thread starts reading the lines,
+/// without their line ending. The lines are pushed by an action on the
+/// scheduler. The observable completes at the end of the input, and it fails
+/// if reading fails.
+///
+/// A line is read only once. If multiple observers subscribe, every line is
+/// pushed to one of them. To push every line to all observers, use `share()`.
+///
+/// A thread that is blocked on reading cannot be interrupted. When the
+/// observable and all of its subscriptions have been dropped, the thread
+/// stops after it reads the next line.
+pub fn read_lines<R, Sch>(reader: R, scheduler: Sch) -> ReadLines<R, Sch>
+    where R: BufRead + Send + 'static, Sch: Scheduler {
+    ReadLines {
+        reader: Some(reader),
+        scheduler: scheduler,
+        lines: None,
+    }
+}
+
+/// Creates an observable of the lines of standard input.
+///
+/// This is `read_lines()` with a `CurrentThreadScheduler`, so subscribing
+/// blocks until the end of the input, which suits command-line tools. To push
+/// the lines from an event loop instead, use `read_lines()` with a
+/// `BufReader` around `io::stdin()`.
+pub fn stdin_lines() -> ReadLines<BufReader<Stdin>, CurrentThreadScheduler> {
+    read_lines(BufReader::new(io::stdin()), CurrentThreadScheduler)
+}
+
+// Sends the lines of the reader until the input ends, reading fails, or the
+// receiver is dropped.
+fn send_lines<R: BufRead>(reader: R, sender: Sender<Notification<String, Error>>) {
+    for line in reader.lines() {
+        let notification = match line {
+            Ok(line) => Notification::Next(line),
+            Err(error) => {
+                let _ = sender.send(Notification::Error(Arc::new(error)));
+                return;
+            }
+        };
+        if sender.send(notification).is_err() {
+            return;
+        }
+    }
+    let _ = sender.send(Notification::Completed);
+}
+
+impl<R, Sch> Observable for ReadLines<R, Sch>
+where R: BufRead + Send + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static {
+    type Item = String;
+    type Error = Error;
+    type Subscription = FromReceiverSubscription<LineReceiver, Sch, String, Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<String, Error> + 'static {
+        if let Some(reader) = self.reader.take() {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || send_lines(reader, sender));
+            let lines = channel::from_notification_receiver(receiver, self.scheduler.clone());
+            self.lines = Some(lines);
+        }
+        match self.lines {
+            Some(ref mut lines) => lines.subscribe(observer),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// A change to the file system, as pushed by `watch_path()`.
+#[cfg(feature = "notify")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathEvent {
+    /// The file or directory at the path was created.
+    Created(PathBuf),
+
+    /// The contents or the metadata of the file or directory were modified.
+    Modified(PathBuf),
+
+    /// The file or directory at the path was removed.
+    Removed(PathBuf),
+}
+
+// Handles the events of a watcher, and sends the resulting values.
+#[cfg(feature = "notify")]
+type EventHandler = Box<dyn FnMut(notify::Result<Event>) + Send>;
+
+// Creates the event handler for a new watcher of the path.
+#[cfg(feature = "notify")]
+type HandlerFactory<T> = fn(&Path, Sender<Notification<T, Error>>) -> io::Result<EventHandler>;
+
+/// The result of calling `watch_path()` or `tail_file()`.
+#[cfg(feature = "notify")]
+pub struct WatchObservable<T, Sch> {
+    path: PathBuf,
+    scheduler: Sch,
+    new_handler: HandlerFactory<T>,
+}
+
+/// The result of subscribing to a `watch_path()` or `tail_file()` observable.
+///
+/// Dropping this subscription stops the watcher.
+#[cfg(feature = "notify")]
+pub struct WatchSubscription<T, Sch: Scheduler> {
+    // The watcher is gone when watching failed, or after unsubscribing.
+    watcher: Option<RecommendedWatcher>,
+    subscription: FromReceiverSubscription<Receiver<Notification<T, Error>>, Sch, T, Error>,
+}
+
+#[cfg(feature = "notify")]
+fn notify_error(error: notify::Error) -> io::Error {
+    match error.kind {
+        notify::ErrorKind::Io(error) => error,
+        _ => io::Error::other(error),
+    }
+}
+
+/// Creates an observable of the changes to a file or directory.
+///
+/// Every subscription starts a watcher for the path, and the changes are
+/// pushed by an action on the scheduler. For a directory, changes to its
+/// entries are pushed, but not changes further down. The observable fails if
+/// the path cannot be watched. It does not complete.
+///
+/// This function is only available with the `notify` feature.
+#[cfg(feature = "notify")]
+pub fn watch_path<P, Sch>(path: P, scheduler: Sch) -> WatchObservable<PathEvent, Sch>
+    where P: AsRef<Path>, Sch: Scheduler {
+    WatchObservable {
+        path: path.as_ref().to_path_buf(),
+        scheduler: scheduler,
+        new_handler: path_event_handler,
+    }
+}
+
+/// Creates an observable of the lines that are appended to a file.
+///
+/// Every subscription opens the file and starts watching it. Lines that are
+/// in the file already are skipped; lines that are appended later are pushed
+/// without their line ending, by an action on the scheduler. A line is pushed
+/// once its line ending has been written. When the file is truncated, it is
+/// read from the start again. The observable fails if the file cannot be
+/// opened, watched, or read. It does not complete.
+///
+/// This function is only available with the `notify` feature.
+#[cfg(feature = "notify")]
+pub fn tail_file<P, Sch>(path: P, scheduler: Sch) -> WatchObservable<String, Sch>
+    where P: AsRef<Path>, Sch: Scheduler {
+    WatchObservable {
+        path: path.as_ref().to_path_buf(),
+        scheduler: scheduler,
+        new_handler: tail_event_handler,
+    }
+}
+
+#[cfg(feature = "notify")]
+fn path_event_handler(_path: &Path, sender: Sender<Notification<PathEvent, Error>>)
+                      -> io::Result<EventHandler> {
+    Ok(Box::new(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                let _ = sender.send(Notification::Error(Arc::new(notify_error(error))));
+                return;
+            }
+        };
+        let to_path_event: fn(PathBuf) -> PathEvent = if event.kind.is_create() {
+            PathEvent::Created
+        } else if event.kind.is_modify() {
+            PathEvent::Modified
+        } else if event.kind.is_remove() {
+            PathEvent::Removed
+        } else {
+            return;
+        };
+        for path in event.paths {
+            let _ = sender.send(Notification::Next(to_path_event(path)));
+        }
+    }))
+}
+
+#[cfg(feature = "notify")]
+fn tail_event_handler(path: &Path, sender: Sender<Notification<String, Error>>)
+                      -> io::Result<EventHandler> {
+    let mut tail = Tail {
+        position: 0,
+        partial_line: LineBuffer::new(),
+        file: File::open(path)?,
+    };
+    tail.position = tail.file.seek(SeekFrom::End(0))?;
+    Ok(Box::new(move |event: notify::Result<Event>| {
+        let result = match event {
+            Ok(ref event) if event.kind.is_modify() => tail.send_appended_lines(&sender),
+            Ok(..) => Ok(()),
+            Err(error) => Err(notify_error(error)),
+        };
+        if let Err(error) = result {
+            let _ = sender.send(Notification::Error(Arc::new(error)));
+        }
+    }))
+}
+
+// A file that is being tailed.
+#[cfg(feature = "notify")]
+struct Tail {
+    file: File,
+
+    // The offset up to which the file has been read.
+    position: u64,
+    partial_line: LineBuffer,
+}
+
+#[cfg(feature = "notify")]
+impl Tail {
+    fn send_appended_lines(&mut self, sender: &Sender<Notification<String, Error>>)
+                           -> io::Result<()> {
+        if self.file.metadata()?.len() < self.position {
+            // The file was truncated, start over.
+            self.position = 0;
+            self.partial_line.bytes.clear();
+        }
+        self.file.seek(SeekFrom::Start(self.position))?;
+        self.position += self.file.read_to_end(&mut self.partial_line.bytes)? as u64;
+        while let Some(line) = self.partial_line.next_line() {
+            let _ = sender.send(Notification::Next(line?));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "notify")]
+impl<T, Sch> Observable for WatchObservable<T, Sch>
+where T: Clone + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static {
+    type Item = T;
+    type Error = Error;
+    type Subscription = WatchSubscription<T, Sch>;
+
+    fn subscribe<O>(&mut self, observer: O) -> WatchSubscription<T, Sch>
+        where O: Observer<T, Error> + 'static {
+        let (sender, receiver) = mpsc::channel();
+        let path = &self.path;
+        let watcher = (self.new_handler)(path, sender.clone()).and_then(|handler| {
+            let mut watcher = notify::recommended_watcher(handler).map_err(notify_error)?;
+            watcher.watch(path, RecursiveMode::NonRecursive).map_err(notify_error)?;
+            Ok(watcher)
+        });
+        let watcher = match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(error) => {
+                let _ = sender.send(Notification::Error(Arc::new(error)));
+                None
+            }
+        };
+        // From now on, only the event handler of the watcher can send.
+        drop(sender);
+        let mut values = channel::from_notification_receiver(receiver, self.scheduler.clone());
+        WatchSubscription {
+            watcher: watcher,
+            subscription: values.subscribe(observer),
+        }
+    }
+}
+
+#[cfg(feature = "notify")]
+impl<T, Sch: Scheduler> Subscription for WatchSubscription<T, Sch> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.watcher = None;
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
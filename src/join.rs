@@ -0,0 +1,622 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `join()` and `group_join()` operators, which correlate the values of
+//! two observables whose lifetimes overlap.
+
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::rc::{Rc, Weak};
+use subject::{Subject, SubjectObservable};
+use subscription::Subscription;
+
+/// The result of calling `join()` on an observable.
+pub struct JoinObservable<Source, Other, FL, FR, F> {
+    source: Source,
+    other: Other,
+
+    // Every observer shares the functions.
+    left_duration: Rc<FL>,
+    right_duration: Rc<FR>,
+    f: Rc<F>,
+}
+
+/// The result of calling `group_join()` on an observable.
+pub struct GroupJoinObservable<Source, Other, FL, FR, F> {
+    source: Source,
+    other: Other,
+
+    // Every observer shares the functions.
+    left_duration: Rc<FL>,
+    right_duration: Rc<FR>,
+    f: Rc<F>,
+}
+
+/// The result of subscribing to a `join()` observable.
+///
+/// Dropping this subscription drops the subscriptions to both observables,
+/// and to the durations of their values.
+pub struct JoinSubscription<L, R, U, E> {
+    state: Rc<RefCell<JoinState<L, R, U, E>>>,
+}
+
+/// The result of subscribing to a `group_join()` observable.
+///
+/// Dropping this subscription drops the subscriptions to both observables,
+/// and to the durations of their values. The open windows complete.
+pub struct GroupJoinSubscription<R: Clone, U, E: Clone> {
+    state: JoinShared<Window<R, E>, R, U, E>,
+}
+
+// The values of one side whose duration has not ended yet.
+struct Windows<T> {
+    values: BTreeMap<u64, T>,
+
+    // The subscriptions to the durations are stored once `subscribe()`
+    // returns, unless the duration ended by then.
+    durations: BTreeMap<u64, Box<dyn Subscription>>,
+
+    is_completed: bool,
+}
+
+// A window of `group_join()`, which completes when it is dropped.
+struct Window<R: Clone, E: Clone> {
+    subject: Rc<Subject<R, E>>,
+}
+
+struct JoinState<L, R, U, E> {
+    observer: ObserverSlot<U, E>,
+    left: Windows<L>,
+    right: Windows<R>,
+    next_id: u64,
+
+    // Whether the observer completes once the right side has no open
+    // values and cannot push more. Otherwise only the left side counts.
+    completes_with_right: bool,
+
+    // The subscriptions are stored once `subscribe()` returns, unless the
+    // operator was done by then.
+    sources: Vec<Box<dyn Subscription>>,
+}
+
+type JoinShared<L, R, U, E> = Rc<RefCell<JoinState<L, R, U, E>>>;
+
+struct JoinLeftObserver<L, R, U, E, FL, F> {
+    state: JoinShared<L, R, U, E>,
+    duration: Rc<FL>,
+    f: Rc<F>,
+}
+
+struct JoinRightObserver<L, R, U, E, FR, F> {
+    state: JoinShared<L, R, U, E>,
+    duration: Rc<FR>,
+    f: Rc<F>,
+}
+
+struct GroupJoinLeftObserver<R: Clone, U, E: Clone, FL, F> {
+    state: JoinShared<Window<R, E>, R, U, E>,
+    duration: Rc<FL>,
+    f: Rc<F>,
+}
+
+struct GroupJoinRightObserver<R: Clone, U, E: Clone, FR> {
+    state: JoinShared<Window<R, E>, R, U, E>,
+    duration: Rc<FR>,
+}
+
+// Ends the window of a value when its duration pushes a value or ends.
+struct DurationObserver<L, R, U, E> {
+    state: Weak<RefCell<JoinState<L, R, U, E>>>,
+    is_left: bool,
+    id: u64,
+}
+
+impl<Source, Other, FL, FR, F> JoinObservable<Source, Other, FL, FR, F> {
+    pub fn new(source: Source, other: Other, left_duration: FL, right_duration: FR, f: F)
+               -> JoinObservable<Source, Other, FL, FR, F> {
+        JoinObservable {
+            source: source,
+            other: other,
+            left_duration: Rc::new(left_duration),
+            right_duration: Rc::new(right_duration),
+            f: Rc::new(f),
+        }
+    }
+}
+
+impl<Source, Other, FL, FR, F> GroupJoinObservable<Source, Other, FL, FR, F> {
+    pub fn new(source: Source, other: Other, left_duration: FL, right_duration: FR, f: F)
+               -> GroupJoinObservable<Source, Other, FL, FR, F> {
+        GroupJoinObservable {
+            source: source,
+            other: other,
+            left_duration: Rc::new(left_duration),
+            right_duration: Rc::new(right_duration),
+            f: Rc::new(f),
+        }
+    }
+}
+
+impl<T> Windows<T> {
+    fn new() -> Windows<T> {
+        Windows {
+            values: BTreeMap::new(),
+            durations: BTreeMap::new(),
+            is_completed: false,
+        }
+    }
+
+    // Whether no value of this side can match anymore.
+    fn is_exhausted(&self) -> bool {
+        self.is_completed && self.values.is_empty()
+    }
+}
+
+impl<R: Clone, E: Clone> Drop for Window<R, E> {
+    fn drop(&mut self) {
+        // After failure, the subject ignores this.
+        (&*self.subject).on_completed();
+    }
+}
+
+impl<L, R, U, E> JoinState<L, R, U, E> {
+    fn new(kind: &'static str, observer: Box<dyn BoxedObserver<U, E>>, completes_with_right: bool)
+           -> JoinState<L, R, U, E> {
+        JoinState {
+            observer: ObserverSlot::new(kind, observer),
+            left: Windows::new(),
+            right: Windows::new(),
+            next_id: 0,
+            completes_with_right: completes_with_right,
+            sources: Vec::new(),
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.left.is_exhausted() || (self.completes_with_right && self.right.is_exhausted())
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+impl<L, R, U, E> OperatorState for JoinState<L, R, U, E> {
+    type Item = U;
+    type Error = E;
+    type Released = (Vec<Box<dyn Subscription>>, Windows<L>, Windows<R>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<U, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Vec<Box<dyn Subscription>>, Windows<L>, Windows<R>) {
+        (mem::take(&mut self.sources),
+         mem::replace(&mut self.left, Windows::new()),
+         mem::replace(&mut self.right, Windows::new()))
+    }
+}
+
+fn subscribe_source<L, R, U, E, Ob, O>(state: &RefCell<JoinState<L, R, U, E>>,
+                                       source: &mut Ob,
+                                       observer: O)
+    where Ob: Observable,
+          Ob::Subscription: 'static,
+          O: Observer<Ob::Item, Ob::Error> + 'static {
+    if state.borrow().observer.is_done() {
+        return;
+    }
+    let subscription = source.subscribe(observer);
+    let subscription = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            Some(subscription)
+        } else {
+            state.sources.push(Box::new(subscription));
+            None
+        }
+    };
+    drop(subscription);
+}
+
+// Subscribes to the duration of the value with the given id.
+fn subscribe_duration<L, R, U, E, D>(state: &Rc<RefCell<JoinState<L, R, U, E>>>,
+                                     mut duration: D,
+                                     is_left: bool,
+                                     id: u64)
+    where D: Observable,
+          D::Subscription: 'static,
+          L: 'static,
+          R: 'static,
+          U: 'static,
+          E: 'static {
+    let duration_observer = DurationObserver {
+        state: Rc::downgrade(state),
+        is_left: is_left,
+        id: id,
+    };
+    let subscription = duration.subscribe(duration_observer);
+    let subscription = {
+        let mut state = state.borrow_mut();
+        let is_open = if is_left {
+            state.left.values.contains_key(&id)
+        } else {
+            state.right.values.contains_key(&id)
+        };
+        if state.observer.is_done() || !is_open {
+            Some(subscription)
+        } else if is_left {
+            state.left.durations.insert(id, Box::new(subscription));
+            None
+        } else {
+            state.right.durations.insert(id, Box::new(subscription));
+            None
+        }
+    };
+    drop(subscription);
+}
+
+// Pushes the values to the observer.
+fn push_all<L, R, U, E>(state: &RefCell<JoinState<L, R, U, E>>, items: Vec<U>) {
+    for item in items {
+        operator::push(state, item);
+    }
+}
+
+// Completes the observer, if no more values can be pushed.
+fn complete_if_exhausted<L, R, U, E>(state: &RefCell<JoinState<L, R, U, E>>) {
+    let is_exhausted = state.borrow().is_exhausted();
+    if is_exhausted {
+        operator::complete(state);
+    }
+}
+
+fn complete_side<L, R, U, E>(state: &RefCell<JoinState<L, R, U, E>>, is_left: bool) {
+    {
+        let mut state = state.borrow_mut();
+        if is_left {
+            state.left.is_completed = true;
+        } else {
+            state.right.is_completed = true;
+        }
+    }
+    complete_if_exhausted(state);
+}
+
+// Fails the open windows of `group_join()` as well as the observer.
+fn fail_windows<R, U, E>(state: &RefCell<JoinState<Window<R, E>, R, U, E>>, error: E)
+    where R: Clone, E: Clone {
+    let (ending, (sources, left, right)) = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            return;
+        }
+        (state.observer.fail(error.clone()), state.release())
+    };
+    drop(sources);
+    drop(right);
+    for window in left.values.values() {
+        (&*window.subject).on_error(error.clone());
+    }
+    drop(left);
+    if let Some(ending) = ending {
+        ending.deliver();
+    }
+}
+
+impl<Source, Other, FL, FR, F, DL, DR, U> Observable for JoinObservable<Source, Other, FL, FR, F>
+where Source: Observable,
+      Source::Subscription: 'static,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Other: Observable<Error = Source::Error>,
+      Other::Subscription: 'static,
+      Other::Item: 'static,
+      FL: Fn(&Source::Item) -> DL + 'static,
+      DL: Observable,
+      DL::Subscription: 'static,
+      FR: Fn(&Other::Item) -> DR + 'static,
+      DR: Observable,
+      DR::Subscription: 'static,
+      F: Fn(&Source::Item, &Other::Item) -> U + 'static,
+      U: Clone + 'static {
+    type Item = U;
+    type Error = Source::Error;
+    type Subscription = JoinSubscription<Source::Item, Other::Item, U, Source::Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<U, Source::Error> + 'static {
+        let state = JoinState::new("join subscription", observer.boxed(), true);
+        let state = Rc::new(RefCell::new(state));
+        let left_observer = JoinLeftObserver {
+            state: state.clone(),
+            duration: self.left_duration.clone(),
+            f: self.f.clone(),
+        };
+        subscribe_source(&state, &mut self.source, left_observer);
+        let right_observer = JoinRightObserver {
+            state: state.clone(),
+            duration: self.right_duration.clone(),
+            f: self.f.clone(),
+        };
+        subscribe_source(&state, &mut self.other, right_observer);
+        JoinSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.other.describe()];
+        Graph::operator::<Self>("join", sources)
+    }
+}
+
+impl<L, R, U, E, FL, F, DL> Observer<L, E> for JoinLeftObserver<L, R, U, E, FL, F>
+where L: Clone + 'static,
+      R: Clone + 'static,
+      U: 'static,
+      E: 'static,
+      FL: Fn(&L) -> DL,
+      DL: Observable,
+      DL::Subscription: 'static,
+      F: Fn(&L, &R) -> U {
+    fn on_next(&mut self, item: L) {
+        let (id, rights) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let id = state.next_id();
+            state.left.values.insert(id, item.clone());
+            (id, state.right.values.values().cloned().collect::<Vec<R>>())
+        };
+        // The functions run user code, so they run outside of the borrow.
+        let results = rights.iter().map(|right| (self.f)(&item, right)).collect();
+        push_all(&self.state, results);
+        subscribe_duration(&self.state, (self.duration)(&item), true, id);
+    }
+
+    fn on_completed(self) {
+        complete_side(&self.state, true);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<L, R, U, E, FR, F, DR> Observer<R, E> for JoinRightObserver<L, R, U, E, FR, F>
+where L: Clone + 'static,
+      R: Clone + 'static,
+      U: 'static,
+      E: 'static,
+      FR: Fn(&R) -> DR,
+      DR: Observable,
+      DR::Subscription: 'static,
+      F: Fn(&L, &R) -> U {
+    fn on_next(&mut self, item: R) {
+        let (id, lefts) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let id = state.next_id();
+            state.right.values.insert(id, item.clone());
+            (id, state.left.values.values().cloned().collect::<Vec<L>>())
+        };
+        // The functions run user code, so they run outside of the borrow.
+        let results = lefts.iter().map(|left| (self.f)(left, &item)).collect();
+        push_all(&self.state, results);
+        subscribe_duration(&self.state, (self.duration)(&item), false, id);
+    }
+
+    fn on_completed(self) {
+        complete_side(&self.state, false);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Source, Other, FL, FR, F, DL, DR, U> Observable
+    for GroupJoinObservable<Source, Other, FL, FR, F>
+where Source: Observable,
+      Source::Subscription: 'static,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Other: Observable<Error = Source::Error>,
+      Other::Subscription: 'static,
+      Other::Item: 'static,
+      FL: Fn(&Source::Item) -> DL + 'static,
+      DL: Observable,
+      DL::Subscription: 'static,
+      FR: Fn(&Other::Item) -> DR + 'static,
+      DR: Observable,
+      DR::Subscription: 'static,
+      F: Fn(Source::Item, SubjectObservable<Other::Item, Source::Error>) -> U + 'static,
+      U: Clone + 'static {
+    type Item = U;
+    type Error = Source::Error;
+    type Subscription = GroupJoinSubscription<Other::Item, U, Source::Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<U, Source::Error> + 'static {
+        let state = JoinState::new("group_join subscription", observer.boxed(), false);
+        let state = Rc::new(RefCell::new(state));
+        let left_observer = GroupJoinLeftObserver {
+            state: state.clone(),
+            duration: self.left_duration.clone(),
+            f: self.f.clone(),
+        };
+        subscribe_source(&state, &mut self.source, left_observer);
+        let right_observer = GroupJoinRightObserver {
+            state: state.clone(),
+            duration: self.right_duration.clone(),
+        };
+        subscribe_source(&state, &mut self.other, right_observer);
+        GroupJoinSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.other.describe()];
+        Graph::operator::<Self>("group_join", sources)
+    }
+}
+
+impl<L, R, U, E, FL, F, DL> Observer<L, E> for GroupJoinLeftObserver<R, U, E, FL, F>
+where R: Clone + 'static,
+      U: 'static,
+      E: Clone + 'static,
+      FL: Fn(&L) -> DL,
+      DL: Observable,
+      DL::Subscription: 'static,
+      F: Fn(L, SubjectObservable<R, E>) -> U {
+    fn on_next(&mut self, item: L) {
+        let subject = Rc::new(Subject::new());
+        // The duration is determined before the value moves into `f`.
+        let duration = (self.duration)(&item);
+        let (id, rights) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let id = state.next_id();
+            state.left.values.insert(id, Window { subject: subject.clone() });
+            (id, state.right.values.values().cloned().collect::<Vec<R>>())
+        };
+        // The window is pushed before it receives the right values that are
+        // open already, so the observer can subscribe to it in time.
+        push_all(&self.state, vec![(self.f)(item, subject.observable())]);
+        for right in rights {
+            (&*subject).on_next(right);
+        }
+        subscribe_duration(&self.state, duration, true, id);
+    }
+
+    fn on_completed(self) {
+        complete_side(&self.state, true);
+    }
+
+    fn on_error(self, error: E) {
+        fail_windows(&self.state, error);
+    }
+}
+
+impl<R, U, E, FR, DR> Observer<R, E> for GroupJoinRightObserver<R, U, E, FR>
+where R: Clone + 'static,
+      U: 'static,
+      E: Clone + 'static,
+      FR: Fn(&R) -> DR,
+      DR: Observable,
+      DR::Subscription: 'static {
+    fn on_next(&mut self, item: R) {
+        let (id, windows) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let id = state.next_id();
+            state.right.values.insert(id, item.clone());
+            let windows: Vec<_> = state.left.values.values()
+                .map(|window| window.subject.clone())
+                .collect();
+            (id, windows)
+        };
+        // The windows are not borrowed while they push, so their observers
+        // can cause more values to be pushed meanwhile.
+        for window in windows {
+            (&*window).on_next(item.clone());
+        }
+        subscribe_duration(&self.state, (self.duration)(&item), false, id);
+    }
+
+    fn on_completed(self) {
+        complete_side(&self.state, false);
+    }
+
+    fn on_error(self, error: E) {
+        fail_windows(&self.state, error);
+    }
+}
+
+impl<L, R, U, E> DurationObserver<L, R, U, E> {
+    // Removes the value, so it matches no more values of the other side.
+    fn close(&self) {
+        let state = match self.state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+        let (left, right, duration) = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if self.is_left {
+                (state.left.values.remove(&self.id), None, state.left.durations.remove(&self.id))
+            } else {
+                (None, state.right.values.remove(&self.id), state.right.durations.remove(&self.id))
+            }
+        };
+        drop(duration);
+        drop(left);
+        drop(right);
+        complete_if_exhausted(&state);
+    }
+}
+
+impl<L, R, U, E, V, G> Observer<V, G> for DurationObserver<L, R, U, E> {
+    fn on_next(&mut self, _item: V) {
+        self.close();
+    }
+
+    fn on_completed(self) {
+        self.close();
+    }
+
+    fn on_error(self, _error: G) {
+        // Failure of a duration ends it, like its completion.
+        self.close();
+    }
+}
+
+impl<L, R, U, E> Subscription for JoinSubscription<L, R, U, E> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<L, R, U, E> Drop for JoinSubscription<L, R, U, E> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
+
+impl<R: Clone, U, E: Clone> Subscription for GroupJoinSubscription<R, U, E> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<R: Clone, U, E: Clone> Drop for GroupJoinSubscription<R, U, E> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,191 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Notifications as JSON Lines, one serialized `Notification` per line.
+//!
+//! This module is only available with the `serde` feature.
+
+use Notification;
+use channel::{self, FromReceiver, FromReceiverSubscription};
+use io;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// The error of an observable that `from_jsonl_reader()` returns.
+#[derive(Clone, Debug)]
+pub enum JsonlError<E> {
+    /// The serialized observable failed with the error.
+    Failed(E),
+
+    /// Reading failed, or a line is not a serialized notification.
+    Io(io::Error),
+}
+
+impl<E: fmt::Display> fmt::Display for JsonlError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonlError::Failed(ref error) => write!(f, "observable failed: {}", error),
+            JsonlError::Io(ref error) => write!(f, "reading notifications failed: {}", error),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for JsonlError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            JsonlError::Failed(ref error) => Some(error),
+            JsonlError::Io(ref error) => Some(error),
+        }
+    }
+}
+
+type NotificationReceiver<T, E> = Receiver<Notification<T, JsonlError<E>>>;
+
+type Notifications<Sch, T, E> = FromReceiver<NotificationReceiver<T, E>, Sch, T, JsonlError<E>>;
+
+/// The result of calling `from_jsonl_reader()`.
+pub struct FromJsonlReader<R, Sch: Scheduler, T, E> {
+    // The reader is moved to the background thread upon the first subscription.
+    reader: Option<R>,
+    scheduler: Sch,
+    notifications: Option<Notifications<Sch, T, E>>,
+}
+
+/// Creates an observable of the notifications that a reader holds as JSON Lines.
+///
+/// This is the counterpart of `ObservableExt::subscribe_jsonl()`. Upon the
+/// first subscription, a background thread starts reading lines, as for
+/// `io::read_lines()`, and every line is deserialized into a notification.
+/// The observable pushes the values, and it completes or fails with the
+/// serialized observable. It fails with `JsonlError::Io` if reading fails or
+/// a line is not a notification, and it completes at the end of the input.
+/// Empty lines are skipped.
+///
+/// This function is only available with the `serde` feature.
+pub fn from_jsonl_reader<R, Sch, T, E>(reader: R, scheduler: Sch) -> FromJsonlReader<R, Sch, T, E>
+    where R: BufRead + Send + 'static,
+          Sch: Scheduler,
+          T: DeserializeOwned + Send + 'static,
+          E: DeserializeOwned + Send + 'static {
+    FromJsonlReader {
+        reader: Some(reader),
+        scheduler: scheduler,
+        notifications: None,
+    }
+}
+
+// Sends the notifications of the reader until a terminal notification, the
+// end of the input, a failure, or until the receiver is dropped.
+fn send_notifications<R, T, E>(reader: R, sender: Sender<Notification<T, JsonlError<E>>>)
+    where R: BufRead, T: DeserializeOwned, E: DeserializeOwned {
+    for line in reader.lines() {
+        let notification = match line {
+            Ok(ref line) if line.trim().is_empty() => continue,
+            Ok(line) => match serde_json::from_str(&line) {
+                Ok(Notification::Next(item)) => Notification::Next(item),
+                Ok(Notification::Completed) => Notification::Completed,
+                Ok(Notification::Error(error)) => Notification::Error(JsonlError::Failed(error)),
+                Err(error) => Notification::Error(JsonlError::Io(Arc::new(error.into()))),
+            },
+            Err(error) => Notification::Error(JsonlError::Io(Arc::new(error))),
+        };
+        let is_terminal = match notification {
+            Notification::Next(..) => false,
+            Notification::Completed | Notification::Error(..) => true,
+        };
+        if sender.send(notification).is_err() || is_terminal {
+            return;
+        }
+    }
+    let _ = sender.send(Notification::Completed);
+}
+
+impl<R, Sch, T, E> Observable for FromJsonlReader<R, Sch, T, E>
+where R: BufRead + Send + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: DeserializeOwned + Clone + Send + 'static,
+      E: DeserializeOwned + Clone + Send + 'static {
+    type Item = T;
+    type Error = JsonlError<E>;
+    type Subscription = FromReceiverSubscription<NotificationReceiver<T, E>, Sch, T,
+                                                 JsonlError<E>>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, JsonlError<E>> + 'static {
+        if let Some(reader) = self.reader.take() {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || send_notifications(reader, sender));
+            let notifications = channel::from_notification_receiver(receiver,
+                                                                    self.scheduler.clone());
+            self.notifications = Some(notifications);
+        }
+        match self.notifications {
+            Some(ref mut notifications) => notifications.subscribe(observer),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// An observer that writes every notification as a line of JSON.
+pub struct JsonlWriter<W> {
+    // The writer is dropped once writing fails.
+    writer: Option<W>,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    pub fn new(writer: W) -> JsonlWriter<W> {
+        JsonlWriter {
+            writer: Some(writer),
+        }
+    }
+
+    fn write<T: Serialize, E: Serialize>(&mut self, notification: Notification<T, E>) {
+        let is_written = match self.writer {
+            Some(ref mut writer) => {
+                serde_json::to_writer(&mut *writer, &notification).is_ok() &&
+                writer.write_all(b"\n").is_ok()
+            }
+            None => return,
+        };
+        if !is_written {
+            self.writer = None;
+        }
+    }
+
+    fn finish(mut self) {
+        if let Some(ref mut writer) = self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+impl<T: Serialize, E: Serialize, W: Write> Observer<T, E> for JsonlWriter<W> {
+    fn on_next(&mut self, item: T) {
+        self.write(Notification::Next::<T, E>(item));
+    }
+
+    fn on_completed(mut self) {
+        self.write(Notification::Completed::<T, E>);
+        self.finish();
+    }
+
+    fn on_error(mut self, error: E) {
+        self.write(Notification::Error::<T, E>(error));
+        self.finish();
+    }
+}
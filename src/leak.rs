@@ -0,0 +1,203 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The registry of live subjects, subscriptions and operators, see the `debug` module.
+
+use observer::Observer;
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    entries: BTreeMap::new(),
+    next_id: 0,
+});
+
+struct Registry {
+    entries: BTreeMap<u64, Arc<Entry>>,
+    next_id: u64,
+}
+
+struct Entry {
+    kind: &'static str,
+    backtrace: Backtrace,
+    is_terminated: AtomicBool,
+}
+
+/// A subject, subscription or operator that was created but not dropped yet.
+#[derive(Clone, Debug)]
+pub struct LiveSubscription {
+    /// What is alive, for instance `subject`, `subject subscription` or
+    /// `merge subscription`.
+    pub kind: &'static str,
+
+    /// Whether the subject or the observer of the subscription completed or
+    /// failed, or the scheduled action ran for the last time.
+    pub is_terminated: bool,
+
+    /// Where it was created.
+    pub backtrace: String,
+}
+
+/// Starts or stops recording subjects, subscriptions and operators as they
+/// are created.
+///
+/// Recording captures a backtrace for every subject, every subscription to a
+/// subject, to an operator, to a shared observable, or to a scheduled action,
+/// which is slow, so it is off by default. In builds without debug assertions,
+/// nothing is ever recorded. Only those created while recording is on are
+/// listed later.
+pub fn set_leak_tracking(is_enabled: bool) {
+    IS_ENABLED.store(is_enabled, Ordering::SeqCst);
+}
+
+/// Returns the recorded subjects and subscriptions that were neither dropped
+/// nor cancelled yet, oldest first.
+pub fn live_subscriptions() -> Vec<LiveSubscription> {
+    // The entries are formatted outside of the lock, because formatting
+    // a backtrace resolves its symbols, which takes a while.
+    let entries: Vec<Arc<Entry>> = {
+        let registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        registry.entries.values().cloned().collect()
+    };
+    entries.iter().map(|entry| LiveSubscription {
+        kind: entry.kind,
+        is_terminated: entry.is_terminated.load(Ordering::SeqCst),
+        backtrace: entry.backtrace.to_string(),
+    }).collect()
+}
+
+/// Returns a report of the recorded subjects and subscriptions that were
+/// neither dropped nor cancelled yet, with where they were created, oldest
+/// first.
+///
+/// Those that did not terminate either are the likely leaks: nothing will
+/// ever complete them, and nothing dropped them. Print the report at the end
+/// of a test or before shutdown to find them.
+pub fn dump_live_subscriptions() -> String {
+    let mut report = String::new();
+    for live in live_subscriptions() {
+        let state = if live.is_terminated { "terminated" } else { "not terminated" };
+        writeln!(report, "live {} ({}), created at:", live.kind, state).unwrap();
+        for line in live.backtrace.lines() {
+            writeln!(report, "    {}", line).unwrap();
+        }
+    }
+    report
+}
+
+/// Keeps a subject or subscription in the registry until it is dropped.
+pub struct Tracker {
+    // There is no entry if recording was off upon creation.
+    entry: Option<(u64, Arc<Entry>)>,
+}
+
+impl Tracker {
+    /// Records a subject or subscription of the given kind, if recording is on.
+    pub fn new(kind: &'static str) -> Tracker {
+        if !cfg!(debug_assertions) || !IS_ENABLED.load(Ordering::SeqCst) {
+            return Tracker { entry: None };
+        }
+        let entry = Arc::new(Entry {
+            kind: kind,
+            backtrace: Backtrace::force_capture(),
+            is_terminated: AtomicBool::new(false),
+        });
+        let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.entries.insert(id, entry.clone());
+        Tracker {
+            entry: Some((id, entry)),
+        }
+    }
+
+    /// Records that the subject or subscription terminated.
+    pub fn terminate(&self) {
+        if let Some((_, ref entry)) = self.entry {
+            entry.is_terminated.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Removes the subject or subscription from the registry ahead of the drop,
+    /// for subscriptions that are cancelled before their state is dropped.
+    pub fn untrack(&mut self) {
+        if let Some((id, _)) = self.entry.take() {
+            let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+            registry.entries.remove(&id);
+        }
+    }
+
+    /// Returns a handle that records that the subscription terminated, for
+    /// code that does not have access to the tracker, like a scheduled action.
+    pub fn termination(&self) -> Termination {
+        Termination {
+            entry: self.entry.as_ref().map(|(_, entry)| entry.clone()),
+        }
+    }
+
+    /// Wraps the observer of the subscription, to record when it terminates.
+    pub fn observer<O>(&self, observer: O) -> TrackedObserver<O> {
+        TrackedObserver {
+            observer: observer,
+            entry: self.entry.as_ref().map(|(_, entry)| entry.clone()),
+        }
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.untrack();
+    }
+}
+
+/// Records in the registry that a subscription terminated.
+pub struct Termination {
+    entry: Option<Arc<Entry>>,
+}
+
+impl Termination {
+    /// Records that the subscription terminated.
+    pub fn terminate(&self) {
+        if let Some(ref entry) = self.entry {
+            entry.is_terminated.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// An observer that records in the registry when it terminates.
+pub struct TrackedObserver<O> {
+    observer: O,
+    entry: Option<Arc<Entry>>,
+}
+
+impl<O> TrackedObserver<O> {
+    fn terminate(&self) {
+        if let Some(ref entry) = self.entry {
+            entry.is_terminated.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<T, E, O> Observer<T, E> for TrackedObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.terminate();
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.terminate();
+        self.observer.on_error(error);
+    }
+}
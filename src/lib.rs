@@ -29,7 +29,7 @@
 //! example prints “received 7” twice.
 //!
 //! ```
-//! use rx::Observable;
+//! use rx::ObservableExt;
 //! let mut some = Some(7);
 //! some.subscribe_next(|x| println!("received {}", x));
 //! some.subscribe_next(|x| println!("received {}", x));
@@ -50,27 +50,217 @@
 //! following example prints the first five prime numbers:
 //!
 //! ```
-//! use rx::Observable;
+//! use rx::ObservableExt;
 //! let mut primes = &[2u32, 3, 5, 7, 11];
 //! primes.subscribe_next(|x| println!("received {}", x));
 //! ```
 
 #![warn(missing_docs)]
-#![feature(fn_traits, unboxed_closures)]
+// The `field: field` style of struct initialization is used throughout.
+#![allow(clippy::redundant_field_names)]
 
+#[cfg(feature = "crossbeam")]
+extern crate crossbeam_channel;
+#[cfg(feature = "futures")]
+extern crate futures;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "notify")]
+extern crate notify;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(all(feature = "signals", unix))]
+extern crate signal_hook;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+// The code generated by `#[wasm_bindgen]` refers to `::core`.
+#[cfg(feature = "wasm")]
+extern crate core;
+
+use std::error::Error;
+use std::fmt;
 use std::iter::IntoIterator;
 
+mod aggregate;
+mod blocking;
+mod boxed;
+mod buffer;
+mod channel;
+mod circuit_breaker;
+mod combine_latest;
+#[cfg(feature = "crossbeam")]
+mod crossbeam;
+mod delay;
+mod describe;
+mod distinct;
+mod flatten;
 mod generate;
+mod heartbeat;
+#[cfg(feature = "tracing")]
+mod instrument;
+mod join;
+#[cfg(feature = "serde")]
+mod jsonl;
+mod leak;
+mod lifecycle;
 mod lifeline;
+mod lines;
+mod logging;
+mod macros;
+mod merge;
+mod metrics;
+mod multicast;
 mod observable;
+mod observe_on;
 mod observer;
+mod operator;
+mod pausable;
+mod property;
+mod pull;
+mod rate_limit;
+mod record;
+mod repeat;
+mod results;
+mod safe;
+mod sample;
+mod slab;
+mod split;
+mod stats;
+mod scheduler;
+mod sequence_equal;
+#[cfg(feature = "futures")]
+mod stream;
 mod subject;
+mod subscription;
+mod timestamp;
+#[cfg(feature = "tokio")]
+mod tokio_scheduler;
 mod transform;
+mod until;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub mod debug;
+pub mod io;
+pub mod net;
+#[cfg(all(feature = "signals", unix))]
+pub mod os;
+pub mod testing;
+
+pub use aggregate::SingleError;
+pub use blocking::{BlockingError, BlockingIter};
+pub use boxed::BoxedObservable;
+pub use channel::{TryReceive, from_notification_receiver, from_receiver};
+pub use circuit_breaker::{CircuitBreakerPolicy, CircuitError};
+pub use combine_latest::CombineLatest;
+#[cfg(feature = "crossbeam")]
+pub use crossbeam::{PipeObservable, PipeSubscription, SendPolicy};
+pub use describe::{Graph, Node};
+pub use generate::{Emitter, Never, create, using};
+#[cfg(feature = "serde")]
+pub use jsonl::{JsonlError, from_jsonl_reader};
+pub use logging::{LogEvent, LogRecord, LogSink, set_log_sink};
+pub use metrics::{Counter, Gauge, MetricsSink, set_metrics_sink};
+pub use multicast::Multicast;
+pub use observable::{Observable, ObservableExt};
+pub use observe_on::OverflowPolicy;
+pub use observer::{BoxedObserver, Observer};
+pub use property::{Computed, ComputedInputs, Property, ReadProperty, batch, computed};
+pub use pull::{PullObservable, PullSubscription, pull_iter};
+pub use record::Recording;
+pub use scheduler::{CurrentThreadScheduler, EventLoop, ImmediateScheduler, NewThreadScheduler,
+                    ScheduledSubscription, Scheduler, SendScheduler, TestScheduler,
+                    ThreadPoolScheduler, ThreadSubscription};
+#[cfg(feature = "futures")]
+pub use stream::{AsyncSubscription, FirstFuture, FromFuture, ObservableStream, from_future,
+                 from_stream};
+pub use stats::{Histogram, Quantiles, Sketch, Stats};
+pub use subject::{AsyncSubject, BehaviorSubject, ReplaySubject, SharedSubject, Subject,
+                  SubjectObservable, SubjectSink, SyncSubject};
+pub use subscription::{CompositeKey, CompositeSubscription, SerialSubscription, Subscription};
+#[cfg(feature = "tokio")]
+pub use tokio_scheduler::{TaskSubscription, TokioScheduler};
+#[cfg(feature = "wasm")]
+pub use wasm::{TimerSubscription, WasmScheduler};
+
+/// The error type of observables that cannot fail.
+///
+/// This type has no values, so an observer will never receive an error when
+/// the error type is `NoError`. Use `ObservableExt::infallible()` to combine such
+/// an observable with observables that can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NoError {}
+
+impl NoError {
+    /// Converts the error into any type, because there is no error.
+    pub fn unreachable<T>(self) -> T {
+        match self {}
+    }
+}
+
+impl fmt::Display for NoError {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl Error for NoError {}
+
+/// A value, completion, or failure, as pushed to an observer.
+///
+/// This reifies the calls on an observer, so they can be stored, or sent
+/// to a different thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Notification<T, E> {
+    /// A value, pushed with `on_next()`.
+    Next(T),
+
+    /// Completion, pushed with `on_completed()`.
+    Completed,
 
-pub use generate::Never;
-pub use observable::Observable;
-pub use observer::Observer;
-pub use subject::Subject;
+    /// Failure, pushed with `on_error()`.
+    Error(E),
+}
+
+/// A value of one of two types, to tell apart the values of two sources.
+///
+/// See `ObservableExt::merge_either()` and `ObservableExt::route()`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Either<L, R> {
+    /// A value of the first type.
+    Left(L),
+
+    /// A value of the second type.
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Returns the value if it is `Left`.
+    pub fn left(self) -> Option<L> {
+        match self {
+            Either::Left(value) => Some(value),
+            Either::Right(..) => None,
+        }
+    }
+
+    /// Returns the value if it is `Right`.
+    pub fn right(self) -> Option<R> {
+        match self {
+            Either::Left(..) => None,
+            Either::Right(value) => Some(value),
+        }
+    }
+}
 
 /// A subscription where `drop()` is a no-op.
 pub struct UncancellableSubscription;
@@ -79,15 +269,26 @@ impl Drop for UncancellableSubscription {
     fn drop(&mut self) { }
 }
 
+/// An uncancellable subscription is never active: by the time it is returned,
+/// the observable has completed, or the action has run.
+impl Subscription for UncancellableSubscription {
+    fn unsubscribe(&mut self) { }
+
+    fn is_active(&self) -> bool {
+        false
+    }
+}
+
 /// Observable implementation for types that can be converted into an iterator.
 ///
 /// Upon subscription, this pushes a value for every value returned by the
 /// iterator and then completes (if the iterator is finite). The returned
 /// subscription is not cancellable: if the observable completes, it completes
-/// before the call to `subscribe()` returns. This observable does not fail.
+/// before the call to `subscribe()` returns. This observable does not fail,
+/// its error type is `NoError`.
 impl<'i, I> Observable for &'i I where &'i I: IntoIterator, <&'i I as IntoIterator>::Item: Clone {
     type Item = <&'i I as IntoIterator>::Item;
-    type Error = ();
+    type Error = NoError;
     type Subscription = UncancellableSubscription;
 
     fn subscribe<O>(&mut self, mut observer: O) -> UncancellableSubscription
@@ -131,10 +332,11 @@ impl<T: Clone, E: Clone> Observable for Result<T, E> {
 /// Upon subscription, this pushes the value if the option is `Some`, and then
 /// completes. If the option is `None` it completes immediately. The returned
 /// subscription is not cancellable: the observable completes before the call to
-/// `subscribe()` returns. This observable does not fail.
+/// `subscribe()` returns. This observable does not fail, its error type is
+/// `NoError`.
 impl<T: Clone> Observable for Option<T> {
     type Item = T;
-    type Error = ();
+    type Error = NoError;
     type Subscription = UncancellableSubscription;
 
     fn subscribe<O>(&mut self, mut observer: O) -> UncancellableSubscription
@@ -0,0 +1,123 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `do_on_subscribe()` and `do_on_unsubscribe()` operators.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `do_on_subscribe()` on an observable.
+pub struct DoOnSubscribeObservable<Source, F> {
+    source: Source,
+    f: F,
+}
+
+/// The result of calling `do_on_unsubscribe()` on an observable.
+pub struct DoOnUnsubscribeObservable<Source, F> {
+    source: Source,
+
+    // Every subscription shares the function.
+    f: Rc<F>,
+}
+
+/// The result of subscribing to a `do_on_unsubscribe()` observable.
+///
+/// Unsubscribing or dropping this subscription calls the function.
+pub struct DoOnUnsubscribeSubscription<Subs, F: Fn()> {
+    subscription: Subs,
+
+    // The function is gone once it has been called.
+    f: Option<Rc<F>>,
+}
+
+impl<Source, F> DoOnSubscribeObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> DoOnSubscribeObservable<Source, F> {
+        DoOnSubscribeObservable {
+            source: source,
+            f: f,
+        }
+    }
+}
+
+impl<Source, F> DoOnUnsubscribeObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> DoOnUnsubscribeObservable<Source, F> {
+        DoOnUnsubscribeObservable {
+            source: source,
+            f: Rc::new(f),
+        }
+    }
+}
+
+impl<Source, F> Observable for DoOnSubscribeObservable<Source, F>
+where Source: Observable,
+      F: FnMut() {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        // The function runs first, so it also precedes values that the
+        // source pushes upon subscription.
+        (self.f)();
+        self.source.subscribe(observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("do_on_subscribe", vec![self.source.describe()])
+    }
+}
+
+impl<Source, F> Observable for DoOnUnsubscribeObservable<Source, F>
+where Source: Observable,
+      F: Fn() {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = DoOnUnsubscribeSubscription<Source::Subscription, F>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        DoOnUnsubscribeSubscription {
+            subscription: self.source.subscribe(observer),
+            f: Some(self.f.clone()),
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("do_on_unsubscribe", vec![self.source.describe()])
+    }
+}
+
+impl<Subs, F: Fn()> DoOnUnsubscribeSubscription<Subs, F> {
+    // Calls the function, only the first time this is called.
+    fn call_once(&mut self) {
+        if let Some(f) = self.f.take() {
+            f();
+        }
+    }
+}
+
+impl<Subs: Subscription, F: Fn()> Subscription for DoOnUnsubscribeSubscription<Subs, F> {
+    fn unsubscribe(&mut self) {
+        self.call_once();
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<Subs, F: Fn()> Drop for DoOnUnsubscribeSubscription<Subs, F> {
+    fn drop(&mut self) {
+        // The source subscription is dropped after this.
+        self.call_once();
+    }
+}
@@ -12,22 +12,48 @@
 //! value. If the lifeline is dropped, the stored value is dropped as well. The
 //! owner will not be able to use the value then. If the owner consumes the
 //! value, dropping the lifeline is a no-op.
+//!
+//! The `SyncLifeline` and `SyncOwner` pair is the thread-safe equivalent, based
+//! on `Arc` and `Mutex` instead of `Rc` and `RefCell`.
 
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use std::sync::{self, Arc, Mutex};
 
 /// Struct that controls the lifetime of the value in the lifeline-owner pair.
 pub struct Lifeline<T> {
-    #[allow(dead_code)] // This code is not dead, the Rc keeps the value alive.
     value: Rc<RefCell<Option<T>>>,
 }
 
 
+impl<T> Lifeline<T> {
+    /// Returns whether the owner has not consumed the stored value.
+    pub fn is_alive(&self) -> bool {
+        // If the value is borrowed, the owner is using it, so it is alive.
+        self.value.try_borrow().map(|value| value.is_some()).unwrap_or(true)
+    }
+
+    /// Applies the function to the stored value if the owner has not consumed it,
+    /// returns `on_dead` otherwise. The value must not be in use by the owner.
+    pub fn with_value_or<R, F: FnOnce(&T) -> R>(&self, on_alive: F, on_dead: R) -> R {
+        match *self.value.borrow() {
+            Some(ref value) => on_alive(value),
+            None => on_dead,
+        }
+    }
+}
+
 /// Struct that allows access to the value in the lifeline-owner pair.
 pub struct Owner<T> {
     value: Weak<RefCell<Option<T>>>,
 }
 
+impl<T> Clone for Owner<T> {
+    fn clone(&self) -> Owner<T> {
+        Owner { value: self.value.clone() }
+    }
+}
+
 impl<T> Owner<T> {
     /// Performs the action on the stored value if it is still alive.
     pub fn with_mut_value<F: FnOnce(&mut T)>(&mut self, action: F) {
@@ -38,18 +64,14 @@ impl<T> Owner<T> {
         }
     }
 
-    /// Performs the action on the stored value if it is still alive,
-    /// calls `on_dead` otherwise.
-    pub fn with_mut_value_or<F: FnOnce(&mut T), G: FnOnce()>(&mut self,
-                                                             on_alive: F,
-                                                             on_dead: G) {
+    /// Returns whether the stored value is still alive.
+    pub fn is_alive(&self) -> bool {
         if let Some(cell) = self.value.upgrade() {
-            if let Some(ref mut value) = *cell.borrow_mut() {
-                on_alive(value);
-                return;
-            }
+            // If the value is borrowed, an owner is using it, so it is alive.
+            cell.try_borrow().map(|value| value.is_some()).unwrap_or(true)
+        } else {
+            false
         }
-        on_dead();
     }
 
     /// Returns the stored value if it is still alive.
@@ -69,3 +91,69 @@ pub fn new<T>(value: T) -> (Lifeline<T>, Owner<T>) {
     let lifeline = Lifeline { value: rc };
     (lifeline, owner)
 }
+
+/// Thread-safe struct that controls the lifetime of the value in the pair.
+pub struct SyncLifeline<T> {
+    value: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> SyncLifeline<T> {
+    /// Returns whether the owner has not consumed the stored value.
+    ///
+    /// If a previous action panicked while holding the value, the value is
+    /// considered dead.
+    pub fn is_alive(&self) -> bool {
+        match self.value.try_lock() {
+            Ok(guard) => guard.is_some(),
+            // The owner is using the value, so it is alive.
+            Err(sync::TryLockError::WouldBlock) => true,
+            Err(sync::TryLockError::Poisoned(..)) => false,
+        }
+    }
+}
+
+/// Thread-safe struct that allows access to the value in the pair.
+pub struct SyncOwner<T> {
+    value: sync::Weak<Mutex<Option<T>>>,
+}
+
+impl<T> SyncOwner<T> {
+    /// Performs the action on the stored value if it is still alive,
+    /// calls `on_dead` otherwise.
+    ///
+    /// If a previous action panicked while holding the value, the value is
+    /// considered dead.
+    pub fn with_mut_value_or<F: FnOnce(&mut T), G: FnOnce()>(&mut self,
+                                                             on_alive: F,
+                                                             on_dead: G) {
+        if let Some(cell) = self.value.upgrade() {
+            if let Ok(mut guard) = cell.lock() {
+                if let Some(ref mut value) = *guard {
+                    on_alive(value);
+                    return;
+                }
+            }
+        }
+        on_dead();
+    }
+
+    /// Returns the stored value if it is still alive.
+    pub fn take(self) -> Option<T> {
+        match self.value.upgrade() {
+            Some(cell) => match cell.lock() {
+                Ok(mut guard) => guard.take(),
+                Err(..) => None,
+            },
+            None => None,
+        }
+    }
+}
+
+/// Creates a value with decoupled lifetime and ownership that can be shared
+/// between threads.
+pub fn new_sync<T>(value: T) -> (SyncLifeline<T>, SyncOwner<T>) {
+    let arc = Arc::new(Mutex::new(Some(value)));
+    let owner = SyncOwner { value: Arc::downgrade(&arc) };
+    let lifeline = SyncLifeline { value: arc };
+    (lifeline, owner)
+}
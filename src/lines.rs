@@ -0,0 +1,45 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Splitting of bytes that arrive in chunks into lines.
+
+use std::io;
+
+/// The bytes after the last line ending that has been taken.
+pub struct LineBuffer {
+    pub bytes: Vec<u8>,
+}
+
+fn decode(mut line: Vec<u8>) -> io::Result<String> {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+impl LineBuffer {
+    pub fn new() -> LineBuffer {
+        LineBuffer { bytes: Vec::new() }
+    }
+
+    /// Takes the first complete line, without its line ending.
+    pub fn next_line(&mut self) -> Option<io::Result<String>> {
+        let end = self.bytes.iter().position(|&b| b == b'\n')?;
+        let mut line: Vec<u8> = self.bytes.drain(..end + 1).collect();
+        line.pop();
+        Some(decode(line))
+    }
+
+    /// Takes the final line, which has no line ending, if it is not empty.
+    pub fn last_line(&mut self) -> Option<io::Result<String>> {
+        if self.bytes.is_empty() {
+            None
+        } else {
+            Some(decode(self.bytes.split_off(0)))
+        }
+    }
+}
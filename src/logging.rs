@@ -0,0 +1,220 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `log()` operator, which reports every event of a pipeline stage.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subscription::Subscription;
+
+/// An event that `log()` reports.
+pub enum LogEvent<'a> {
+    /// An observer subscribed.
+    Subscribe,
+
+    /// The source pushed a value.
+    Next(&'a dyn Debug),
+
+    /// The source completed.
+    Completed,
+
+    /// The source failed with the error.
+    Error(&'a dyn Debug),
+
+    /// The subscription was unsubscribed or dropped.
+    Unsubscribe,
+}
+
+/// An event that `log()` reports, with its tag and the time it happened.
+///
+/// The record displays as a single line, for instance
+/// `[1476093771.250] parse: next 42`.
+pub struct LogRecord<'a> {
+    /// The tag that was passed to `log()`.
+    pub tag: &'a str,
+
+    /// The system time at which the event happened.
+    pub time: SystemTime,
+
+    /// What happened.
+    pub event: LogEvent<'a>,
+}
+
+/// Where `log()` reports events, see `set_log_sink()`.
+pub enum LogSink {
+    /// Prints every record as a line on standard error. This is the default.
+    Stderr,
+
+    /// Reports every record at debug level through the `log` crate, with
+    /// target `rx`.
+    ///
+    /// This is only available with the `log` feature.
+    #[cfg(feature = "log")]
+    Log,
+
+    /// Calls the function for every record.
+    Custom(Box<dyn Fn(&LogRecord) + Send + Sync>),
+}
+
+// The sink is cloned out of the lock before it is called, so a sink that
+// replaces the sink does not deadlock.
+static SINK: Mutex<Option<Arc<LogSink>>> = Mutex::new(None);
+
+/// Sets where `log()` reports events, for all threads.
+pub fn set_log_sink(sink: LogSink) {
+    let previous = {
+        let mut current = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+        current.replace(Arc::new(sink))
+    };
+    drop(previous);
+}
+
+fn report(tag: &str, event: LogEvent) {
+    let record = LogRecord {
+        tag: tag,
+        time: SystemTime::now(),
+        event: event,
+    };
+    let sink = SINK.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    match sink.as_deref() {
+        None | Some(&LogSink::Stderr) => eprintln!("{}", record),
+        #[cfg(feature = "log")]
+        Some(&LogSink::Log) => ::log::debug!(target: "rx", "{}", record),
+        Some(LogSink::Custom(f)) => f(&record),
+    }
+}
+
+impl<'a> fmt::Display for LogEvent<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LogEvent::Subscribe => write!(f, "subscribe"),
+            LogEvent::Next(item) => write!(f, "next {:?}", item),
+            LogEvent::Completed => write!(f, "completed"),
+            LogEvent::Error(error) => write!(f, "error {:?}", error),
+            LogEvent::Unsubscribe => write!(f, "unsubscribe"),
+        }
+    }
+}
+
+impl<'a> fmt::Display for LogRecord<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // A clock before the epoch is printed as the epoch.
+        let since_epoch = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        write!(f, "[{}.{:03}] {}: {}",
+               since_epoch.as_secs(),
+               since_epoch.subsec_millis(),
+               self.tag,
+               self.event)
+    }
+}
+
+/// The result of calling `log()` on an observable.
+pub struct LogObservable<Source> {
+    source: Source,
+
+    // Every observer and subscription shares the tag.
+    tag: Rc<String>,
+}
+
+/// The result of subscribing to a `log()` observable.
+///
+/// Unsubscribing or dropping this subscription reports `LogEvent::Unsubscribe`.
+pub struct LogSubscription<Subs> {
+    subscription: Subs,
+
+    // The tag is gone once the unsubscription has been reported.
+    tag: Option<Rc<String>>,
+}
+
+struct LogObserver<O> {
+    observer: O,
+    tag: Rc<String>,
+}
+
+impl<Source> LogObservable<Source> {
+    pub fn new(source: Source, tag: String) -> LogObservable<Source> {
+        LogObservable {
+            source: source,
+            tag: Rc::new(tag),
+        }
+    }
+}
+
+impl<Source> Observable for LogObservable<Source>
+where Source: Observable,
+      Source::Item: Debug,
+      Source::Error: Debug {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = LogSubscription<Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        report(&self.tag, LogEvent::Subscribe);
+        let log_observer = LogObserver {
+            observer: observer,
+            tag: self.tag.clone(),
+        };
+        LogSubscription {
+            subscription: self.source.subscribe(log_observer),
+            tag: Some(self.tag.clone()),
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("log", vec![self.source.describe()])
+    }
+}
+
+impl<T: Debug, E: Debug, O> Observer<T, E> for LogObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        report(&self.tag, LogEvent::Next(&item));
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        report(&self.tag, LogEvent::Completed);
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        report(&self.tag, LogEvent::Error(&error));
+        self.observer.on_error(error);
+    }
+}
+
+impl<Subs> LogSubscription<Subs> {
+    // Reports the unsubscription, only the first time this is called.
+    fn report_unsubscribe(&mut self) {
+        if let Some(tag) = self.tag.take() {
+            report(&tag, LogEvent::Unsubscribe);
+        }
+    }
+}
+
+impl<Subs: Subscription> Subscription for LogSubscription<Subs> {
+    fn unsubscribe(&mut self) {
+        self.report_unsubscribe();
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<Subs> Drop for LogSubscription<Subs> {
+    fn drop(&mut self) {
+        // The source subscription is dropped after this.
+        self.report_unsubscribe();
+    }
+}
@@ -0,0 +1,56 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Macros that combine any number of observables.
+
+/// Merges any number of observables into one.
+///
+/// `merge!(a, b, c)` is `a.merge(b).merge(c)`: the result pushes the values
+/// of all observables as they arrive, and it completes when all of them
+/// complete.
+///
+/// ```
+/// # extern crate rx;
+/// # use rx::ObservableExt;
+/// # fn main() {
+/// let mut merged = rx::merge!(Some(2), Some(3), Some(5));
+/// merged.subscribe_next(|x| println!("received {}", x));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! merge {
+    ($first:expr) => { $first };
+    ($first:expr, $second:expr $(, $rest:expr)*) => {
+        $crate::merge!($crate::ObservableExt::merge($first, $second) $(, $rest)*)
+    };
+    ($($observable:expr),+ ,) => { $crate::merge!($($observable),+) };
+}
+
+/// Joins any number of observables sequentially.
+///
+/// `concat!(a, b, c)` is `a.continue_with(b).continue_with(c)`: every
+/// observable is subscribed to after the previous one completes.
+///
+/// When imported with `#[macro_use]`, this shadows the `concat!` macro of the
+/// standard library. Invoke it as `rx::concat!` to avoid that.
+///
+/// ```
+/// # extern crate rx;
+/// # use rx::ObservableExt;
+/// # fn main() {
+/// let mut joined = rx::concat!(Some(2), Some(3), Some(5));
+/// joined.subscribe_next(|x| println!("received {}", x));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! concat {
+    ($first:expr) => { $first };
+    ($first:expr, $second:expr $(, $rest:expr)*) => {
+        $crate::concat!($crate::ObservableExt::continue_with($first, $second) $(, $rest)*)
+    };
+    ($($observable:expr),+ ,) => { $crate::concat!($($observable),+) };
+}
@@ -0,0 +1,216 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that push the values of several observables as they arrive.
+
+use Either;
+use describe::Graph;
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use subscription::Subscription;
+use transform;
+
+/// The result of calling `merge()` on an observable.
+pub struct MergeObservable<Source, Other> {
+    source: Source,
+    other: Other,
+}
+
+/// The result of calling `merge_either()` on an observable.
+pub struct MergeEitherObservable<Source, Other> {
+    source: Source,
+    other: Other,
+}
+
+/// The result of subscribing to a merged observable.
+///
+/// Dropping this subscription drops the subscriptions to all sources.
+pub struct MergeSubscription<T, E> {
+    state: Rc<RefCell<MergeState<T, E>>>,
+}
+
+struct MergeState<T, E> {
+    observer: ObserverSlot<T, E>,
+
+    // The number of sources that did not complete yet.
+    active: usize,
+
+    // The subscriptions are stored once `subscribe()` returns, unless the
+    // operator was done by then.
+    sources: Vec<Box<dyn Subscription>>,
+}
+
+struct MergeObserver<A, T, E> {
+    state: Rc<RefCell<MergeState<T, E>>>,
+
+    // Converts the values of this source into the values of the result.
+    wrap: fn(A) -> T,
+}
+
+impl<Source, Other> MergeObservable<Source, Other> {
+    pub fn new(source: Source, other: Other) -> MergeObservable<Source, Other> {
+        MergeObservable {
+            source: source,
+            other: other,
+        }
+    }
+}
+
+impl<Source, Other> MergeEitherObservable<Source, Other> {
+    pub fn new(source: Source, other: Other) -> MergeEitherObservable<Source, Other> {
+        MergeEitherObservable {
+            source: source,
+            other: other,
+        }
+    }
+}
+
+impl<T, E> MergeState<T, E> {
+    fn new(kind: &'static str, observer: Box<dyn BoxedObserver<T, E>>, active: usize)
+           -> Rc<RefCell<MergeState<T, E>>> {
+        Rc::new(RefCell::new(MergeState {
+            observer: ObserverSlot::new(kind, observer),
+            active: active,
+            sources: Vec::new(),
+        }))
+    }
+}
+
+impl<T, E> OperatorState for MergeState<T, E> {
+    type Item = T;
+    type Error = E;
+    type Released = Vec<Box<dyn Subscription>>;
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> Vec<Box<dyn Subscription>> {
+        mem::take(&mut self.sources)
+    }
+}
+
+// Subscribes to one of the sources, whose values are converted with `wrap`.
+fn subscribe_source<Source, T, E>(state: &Rc<RefCell<MergeState<T, E>>>,
+                                  source: &mut Source,
+                                  wrap: fn(Source::Item) -> T)
+    where Source: Observable<Error = E>,
+          Source::Subscription: 'static,
+          Source::Item: 'static,
+          T: 'static,
+          E: 'static {
+    if state.borrow().observer.is_done() {
+        return;
+    }
+    let merge_observer = MergeObserver {
+        state: state.clone(),
+        wrap: wrap,
+    };
+    let subscription = source.subscribe(merge_observer);
+    let subscription = {
+        let mut state = state.borrow_mut();
+        if state.observer.is_done() {
+            Some(subscription)
+        } else {
+            state.sources.push(Box::new(subscription));
+            None
+        }
+    };
+    drop(subscription);
+}
+
+impl<A, T, E> Observer<A, E> for MergeObserver<A, T, E> {
+    fn on_next(&mut self, item: A) {
+        operator::push(&self.state, (self.wrap)(item));
+    }
+
+    fn on_completed(self) {
+        let is_exhausted = {
+            let mut state = self.state.borrow_mut();
+            state.active -= 1;
+            state.active == 0
+        };
+        if is_exhausted {
+            operator::complete(&self.state);
+        }
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Source, Other> Observable for MergeObservable<Source, Other>
+where Source: Observable,
+      Source::Subscription: 'static,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Other: Observable<Item = Source::Item, Error = Source::Error>,
+      Other::Subscription: 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = MergeSubscription<Source::Item, Source::Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Source::Item, Source::Error> + 'static {
+        let state = MergeState::new("merge subscription", observer.boxed(), 2);
+        subscribe_source(&state, &mut self.source, transform::identity);
+        subscribe_source(&state, &mut self.other, transform::identity);
+        MergeSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.other.describe()];
+        Graph::operator::<Self>("merge", sources)
+    }
+}
+
+impl<Source, Other> Observable for MergeEitherObservable<Source, Other>
+where Source: Observable,
+      Source::Subscription: 'static,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Other: Observable<Error = Source::Error>,
+      Other::Subscription: 'static,
+      Other::Item: 'static {
+    type Item = Either<Source::Item, Other::Item>;
+    type Error = Source::Error;
+    type Subscription = MergeSubscription<Self::Item, Source::Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Source::Error> + 'static {
+        let state = MergeState::new("merge_either subscription", observer.boxed(), 2);
+        subscribe_source(&state, &mut self.source, Either::Left);
+        subscribe_source(&state, &mut self.other, Either::Right);
+        MergeSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.other.describe()];
+        Graph::operator::<Self>("merge_either", sources)
+    }
+}
+
+impl<T, E> Subscription for MergeSubscription<T, E> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<T, E> Drop for MergeSubscription<T, E> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,154 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `metered()` operator, and the sink that metrics are reported to.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A metric that only goes up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// The number of values that were pushed.
+    Items,
+
+    /// The number of failures.
+    Errors,
+
+    /// The number of completions.
+    Completions,
+}
+
+/// A metric that is set to its current value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gauge {
+    /// The number of values per second, measured over about a second.
+    Throughput,
+
+    /// The number of values that are queued, but not pushed yet.
+    QueueDepth,
+}
+
+/// Receives the metrics of pipelines, see `set_metrics_sink()`.
+///
+/// Implement this to forward metrics to a monitoring system such as
+/// Prometheus or statsd. Every metric carries the name that was passed to
+/// `metered()`, so the name identifies the pipeline. A sink is called on
+/// the thread that pushes, possibly on many threads at once.
+pub trait MetricsSink {
+    /// Increments the counter of the named pipeline by one.
+    fn increment(&self, name: &str, counter: Counter);
+
+    /// Sets the gauge of the named pipeline to the value.
+    fn gauge(&self, name: &str, gauge: Gauge, value: f64);
+}
+
+// As for the log sink, the sink is cloned out of the lock before it is called.
+static SINK: Mutex<Option<Arc<dyn MetricsSink + Send + Sync>>> = Mutex::new(None);
+
+/// Sets where metrics are reported, for all threads.
+///
+/// Until a sink is set, metrics are discarded.
+pub fn set_metrics_sink<S>(sink: S) where S: MetricsSink + Send + Sync + 'static {
+    let previous = {
+        let mut current = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+        current.replace(Arc::new(sink))
+    };
+    drop(previous);
+}
+
+fn sink() -> Option<Arc<dyn MetricsSink + Send + Sync>> {
+    SINK.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn increment(name: &str, counter: Counter) {
+    if let Some(sink) = sink() {
+        sink.increment(name, counter);
+    }
+}
+
+pub fn gauge(name: &str, gauge: Gauge, value: f64) {
+    if let Some(sink) = sink() {
+        sink.gauge(name, gauge, value);
+    }
+}
+
+/// The result of calling `metered()` on an observable.
+pub struct MeteredObservable<Source> {
+    source: Source,
+
+    // Every observer shares the name.
+    name: Rc<String>,
+}
+
+struct MeteredObserver<O> {
+    observer: O,
+    name: Rc<String>,
+
+    // The values since the start of the current throughput window.
+    window_start: Instant,
+    window_count: u64,
+}
+
+impl<Source> MeteredObservable<Source> {
+    pub fn new(source: Source, name: String) -> MeteredObservable<Source> {
+        MeteredObservable {
+            source: source,
+            name: Rc::new(name),
+        }
+    }
+}
+
+impl<Source: Observable> Observable for MeteredObservable<Source> {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let metered_observer = MeteredObserver {
+            observer: observer,
+            name: self.name.clone(),
+            window_start: Instant::now(),
+            window_count: 0,
+        };
+        self.source.subscribe(metered_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("metered", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<T, E> for MeteredObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        increment(&self.name, Counter::Items);
+        self.window_count += 1;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let throughput = self.window_count as f64 / elapsed.as_secs_f64();
+            gauge(&self.name, Gauge::Throughput, throughput);
+            self.window_start += elapsed;
+            self.window_count = 0;
+        }
+        self.observer.on_next(item);
+    }
+
+    fn on_completed(self) {
+        increment(&self.name, Counter::Completions);
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        increment(&self.name, Counter::Errors);
+        self.observer.on_error(error);
+    }
+}
@@ -0,0 +1,418 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that share a single subscription to an observable among
+//! multiple observers.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use leak::Tracker;
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use subject::{AsyncSubject, AsyncSubjectSink, BehaviorSubject, BehaviorSubjectSink};
+use subject::{ReplaySubject, ReplaySubjectSink, Subject, SubjectSink, SubjectSubscription};
+use subscription::Subscription;
+
+/// A subject through which an observable can be shared.
+///
+/// A connectable observable pushes the values of its source into the sink of
+/// the subject, and it subscribes its own observers to the subject. See
+/// `ObservableExt::multicast()`.
+pub trait Multicast<T, E> {
+    /// The observer that pushes into the subject.
+    type Sink: Observer<T, E> + 'static;
+
+    /// The result of subscribing to the subject.
+    type Subscription: Subscription;
+
+    /// Returns an observer that pushes into the subject.
+    fn sink(&self) -> Self::Sink;
+
+    /// Subscribes an observer to the subject.
+    fn subscribe<O>(&self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static;
+
+    /// Returns whether the subject completed or failed.
+    ///
+    /// A connectable observable created with a subject factory replaces a
+    /// terminated subject when it connects again. By default, the subject is
+    /// never replaced.
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+/// The result of calling `publish()` on an observable.
+///
+/// A connectable observable shares a single subscription to the source among
+/// all of its observers. It does not subscribe to the source until `connect()`
+/// is called, so observers can subscribe before the source starts pushing.
+pub struct ConnectableObservable<Source: Observable, S> {
+    source: Source,
+    subject: S,
+
+    // Creates a new subject when connecting after the subject terminated.
+    factory: Option<Box<dyn FnMut() -> S>>,
+
+    // The connection is shared with the subscriptions of a ref count
+    // observable, so the last one to be dropped can disconnect.
+    connection: Rc<RefCell<Connection<Source::Subscription>>>,
+}
+
+struct Connection<S> {
+    subscription: Option<S>,
+    ref_count: usize,
+
+    // Schedules the disconnect after the last subscription is dropped, if the
+    // ref count observable lingers.
+    linger: Option<Linger<S>>,
+    pending_disconnect: Option<Box<dyn Any>>,
+}
+
+// The result cancels the disconnect when it is dropped.
+type Linger<S> = Box<dyn FnMut(Weak<RefCell<Connection<S>>>) -> Box<dyn Any>>;
+
+impl<Source, S> ConnectableObservable<Source, S>
+where Source: Observable,
+      S: Multicast<Source::Item, Source::Error> {
+    pub fn new(source: Source, subject: S) -> ConnectableObservable<Source, S> {
+        ConnectableObservable {
+            source: source,
+            subject: subject,
+            factory: None,
+            connection: Rc::new(RefCell::new(Connection {
+                subscription: None,
+                ref_count: 0,
+                linger: None,
+                pending_disconnect: None,
+            })),
+        }
+    }
+
+    /// Creates a connectable observable that gets its subject from the factory.
+    ///
+    /// Unlike with `new()`, the observable can connect again after the source
+    /// terminated: if the subject completed or failed, `connect()` replaces it
+    /// with a new one from the factory, and a ref count observable does so when
+    /// its first observer subscribes. Observers that subscribed to the old
+    /// subject are not moved to the new one.
+    pub fn with_factory<F>(source: Source, mut factory: F) -> ConnectableObservable<Source, S>
+        where F: FnMut() -> S + 'static {
+        let mut connectable = ConnectableObservable::new(source, factory());
+        connectable.factory = Some(Box::new(factory));
+        connectable
+    }
+
+    /// Subscribes to the source, unless it is connected already.
+    ///
+    /// From then on, the values pushed by the source are pushed to all
+    /// observers. If the source pushes values upon subscription, the observers
+    /// receive them before `connect()` returns.
+    pub fn connect(&mut self) {
+        self.renew_subject();
+        if !self.is_connected() {
+            let subscription = self.source.subscribe(self.subject.sink());
+            self.connection.borrow_mut().subscription = Some(subscription);
+        }
+    }
+
+    /// Drops the subscription to the source.
+    ///
+    /// The observers remain subscribed, and they receive values again after
+    /// the next call to `connect()`.
+    pub fn disconnect(&mut self) {
+        let subscription = self.connection.borrow_mut().subscription.take();
+        drop(subscription);
+    }
+
+    // Replaces the subject with a new one from the factory if it terminated.
+    // The connection to the terminated source is dropped with it.
+    fn renew_subject(&mut self) {
+        let subject = match self.factory {
+            Some(ref mut factory) if self.subject.is_terminated() => factory(),
+            _ => return,
+        };
+        self.disconnect();
+        self.subject = subject;
+    }
+
+    /// Returns whether the observable is subscribed to the source.
+    pub fn is_connected(&self) -> bool {
+        self.connection.borrow().subscription.is_some()
+    }
+
+    /// Connects automatically while there are observers.
+    ///
+    /// The returned observable connects when the first observer subscribes,
+    /// and it disconnects when the last subscription is dropped.
+    pub fn ref_count(self) -> RefCountObservable<Source, S> {
+        RefCountObservable {
+            connectable: self,
+        }
+    }
+
+    /// Connects automatically while there are observers, and stays connected
+    /// for the linger duration after the last subscription is dropped.
+    ///
+    /// If an observer subscribes before the linger duration has passed, the
+    /// existing connection is reused. The disconnect is scheduled on the
+    /// scheduler.
+    pub fn ref_count_linger<Sch>(self,
+                                 linger: Duration,
+                                 scheduler: Sch)
+                                 -> RefCountObservable<Source, S>
+        where Sch: Scheduler + 'static, Sch::Subscription: 'static, Source::Subscription: 'static {
+        let schedule_disconnect = move |connection: Weak<RefCell<Connection<Source::Subscription>>>| {
+            let scheduled = scheduler.schedule_after(linger, move || {
+                if let Some(connection) = connection.upgrade() {
+                    let subscription = {
+                        let mut connection = connection.borrow_mut();
+                        if connection.ref_count == 0 {
+                            connection.subscription.take()
+                        } else {
+                            None
+                        }
+                    };
+                    drop(subscription);
+                }
+            });
+            Box::new(scheduled) as Box<dyn Any>
+        };
+        self.connection.borrow_mut().linger = Some(Box::new(schedule_disconnect));
+        self.ref_count()
+    }
+}
+
+impl<Source, S> Observable for ConnectableObservable<Source, S>
+where Source: Observable,
+      S: Multicast<Source::Item, Source::Error> {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = S::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        self.subject.subscribe(observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("multicast", vec![self.source.describe()])
+    }
+}
+
+/// The result of calling `share()` on an observable,
+/// or `ref_count()` on a connectable observable.
+pub struct RefCountObservable<Source: Observable, S> {
+    connectable: ConnectableObservable<Source, S>,
+}
+
+/// The result of subscribing to a ref count observable.
+///
+/// Dropping this subscription will prevent further calls on the observer.
+/// Dropping the last subscription also drops the subscription to the source.
+pub struct RefCountSubscription<Subs, SourceSubs> {
+    subscription: Subs,
+    connection: Weak<RefCell<Connection<SourceSubs>>>,
+    tracker: Tracker,
+}
+
+impl<Source, S> Observable for RefCountObservable<Source, S>
+where Source: Observable,
+      S: Multicast<Source::Item, Source::Error> {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = RefCountSubscription<S::Subscription, Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        // The first observer gets a new subject if the last one terminated.
+        if self.connectable.connection.borrow().ref_count == 0 {
+            self.connectable.renew_subject();
+        }
+        // Subscribe before connecting, so the first observer receives
+        // the values that the source pushes upon subscription.
+        let tracker = Tracker::new("ref_count subscription");
+        let subscription = self.connectable.subscribe(tracker.observer(observer));
+        let (is_first, pending_disconnect) = {
+            let mut connection = self.connectable.connection.borrow_mut();
+            connection.ref_count += 1;
+            (connection.ref_count == 1, connection.pending_disconnect.take())
+        };
+        // If the observable is lingering, this cancels the disconnect.
+        drop(pending_disconnect);
+        if is_first {
+            self.connectable.connect();
+        }
+        RefCountSubscription {
+            subscription: subscription,
+            connection: Rc::downgrade(&self.connectable.connection),
+            tracker: tracker,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("ref_count", vec![self.connectable.describe()])
+    }
+}
+
+impl<Subs, SourceSubs> RefCountSubscription<Subs, SourceSubs> {
+    // Gives up this subscription's share of the connection. This only has an
+    // effect the first time it is called.
+    fn release(&mut self) {
+        self.tracker.untrack();
+        let connection = mem::replace(&mut self.connection, Weak::new());
+        if let Some(connection) = connection.upgrade() {
+            let (subscription, linger) = {
+                let mut connection = connection.borrow_mut();
+                connection.ref_count -= 1;
+                if connection.ref_count > 0 {
+                    (None, None)
+                } else if connection.linger.is_some() {
+                    (None, connection.linger.take())
+                } else {
+                    (connection.subscription.take(), None)
+                }
+            };
+            // The source subscription is dropped outside of the borrow,
+            // in case dropping it ends up in this observable again. The
+            // scheduler might run the disconnect immediately, so it is
+            // called outside of the borrow too.
+            drop(subscription);
+            if let Some(mut linger) = linger {
+                let pending_disconnect = linger(Rc::downgrade(&connection));
+                let mut connection = connection.borrow_mut();
+                connection.linger = Some(linger);
+                connection.pending_disconnect = Some(pending_disconnect);
+            }
+        }
+    }
+}
+
+impl<Subs: Subscription, SourceSubs> Subscription for RefCountSubscription<Subs, SourceSubs> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.release();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<Subs, SourceSubs> Drop for RefCountSubscription<Subs, SourceSubs> {
+    fn drop(&mut self) {
+        // The observer subscription is dropped after this, with the fields.
+        self.release();
+    }
+}
+
+/// The result of calling `cache()` on an observable.
+///
+/// The source is subscribed to when the first observer subscribes, and that
+/// subscription is never dropped, not even when every observer is gone.
+pub struct CacheObservable<Source: Observable> {
+    connectable: ConnectableObservable<Source, ReplaySubject<Source::Item, Source::Error>>,
+}
+
+impl<Source> CacheObservable<Source>
+where Source: Observable,
+      Source::Item: 'static,
+      Source::Error: 'static {
+    pub fn new(source: Source) -> CacheObservable<Source> {
+        CacheObservable {
+            connectable: ConnectableObservable::new(source, ReplaySubject::new()),
+        }
+    }
+}
+
+impl<Source> Observable for CacheObservable<Source>
+where Source: Observable,
+      Source::Item: 'static,
+      Source::Error: 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = SubjectSubscription<Source::Item, Source::Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        // Subscribe before connecting, so the first observer receives
+        // the values that the source pushes upon subscription. Later
+        // observers receive them from the replay subject.
+        let subscription = self.connectable.subscribe(observer);
+        self.connectable.connect();
+        subscription
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("cache", vec![self.connectable.source.describe()])
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Multicast<T, E> for Subject<T, E> {
+    type Sink = SubjectSink<T, E>;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn sink(&self) -> SubjectSink<T, E> {
+        Subject::sink(self)
+    }
+
+    fn subscribe<O>(&self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        self.observable().subscribe(observer)
+    }
+
+    fn is_terminated(&self) -> bool {
+        Subject::is_terminated(self)
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Multicast<T, E> for ReplaySubject<T, E> {
+    type Sink = ReplaySubjectSink<T, E>;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn sink(&self) -> ReplaySubjectSink<T, E> {
+        ReplaySubject::sink(self)
+    }
+
+    fn subscribe<O>(&self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        self.observable().subscribe(observer)
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Multicast<T, E> for BehaviorSubject<T, E> {
+    type Sink = BehaviorSubjectSink<T, E>;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn sink(&self) -> BehaviorSubjectSink<T, E> {
+        BehaviorSubject::sink(self)
+    }
+
+    fn subscribe<O>(&self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        self.observable().subscribe(observer)
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Multicast<T, E> for AsyncSubject<T, E> {
+    type Sink = AsyncSubjectSink<T, E>;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn sink(&self) -> AsyncSubjectSink<T, E> {
+        AsyncSubject::sink(self)
+    }
+
+    fn subscribe<O>(&self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        self.observable().subscribe(observer)
+    }
+}
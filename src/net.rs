@@ -0,0 +1,290 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Observables of network input.
+//!
+//! The sockets are nonblocking, and an action on a scheduler polls them, as
+//! with `from_receiver()`. When a socket has no input, the action checks it
+//! again after the poll interval.
+
+use Notification;
+use channel::{self, FromReceiverSubscription, TryReceive};
+use io::Error;
+use lines::LineBuffer;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use std::cell::{Cell, RefCell};
+use std::io::{self, ErrorKind, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::sync::mpsc::TryRecvError;
+
+/// The result of calling `tcp_incoming()`.
+pub struct TcpIncoming<A, Sch> {
+    addr: A,
+    scheduler: Sch,
+}
+
+/// The result of calling `tcp_accept()`, `udp_datagrams()`, or `framed_lines()`.
+///
+/// The socket is polled by the first subscription. Later subscriptions
+/// complete immediately.
+pub struct SocketObservable<R, Sch> {
+    receiver: Option<R>,
+    scheduler: Sch,
+}
+
+/// A listener that is polled for connections.
+///
+/// This is the channel that the subscription of `tcp_incoming()` or
+/// `tcp_accept()` drains.
+/// Dropping it closes the listener.
+pub struct Listener {
+    listener: Option<TcpListener>,
+
+    // Set when binding failed, until the error has been received.
+    error: RefCell<Option<io::Error>>,
+}
+
+/// A socket that is polled for datagrams.
+///
+/// This is the channel that the subscription of `udp_datagrams()` drains.
+pub struct DatagramReceiver {
+    socket: UdpSocket,
+    buffer: RefCell<Vec<u8>>,
+
+    // Set when the socket could not be made nonblocking.
+    error: RefCell<Option<io::Error>>,
+}
+
+/// A connection that is polled for lines.
+///
+/// This is the channel that the subscription of `framed_lines()` drains.
+pub struct LineReceiver {
+    stream: TcpStream,
+    lines: RefCell<LineBuffer>,
+
+    // Set when the peer closed the connection.
+    is_closed: Cell<bool>,
+
+    // Set when the connection could not be made nonblocking.
+    error: RefCell<Option<io::Error>>,
+}
+
+/// The size of the largest datagram that `udp_datagrams()` can receive.
+///
+/// Longer datagrams are truncated.
+pub const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// Creates an observable of the connections to a new listener.
+///
+/// Every subscription binds a listener to the address, and an action on the
+/// scheduler pushes the accepted connections. The connections are blocking,
+/// and both `Read` and `Write` are implemented for `&TcpStream`, so they can
+/// be used through the `Arc`. The observable fails if binding or accepting
+/// fails. It does not complete.
+///
+/// Dropping the subscription closes the listener.
+pub fn tcp_incoming<A, Sch>(addr: A, scheduler: Sch) -> TcpIncoming<A, Sch>
+    where A: ToSocketAddrs, Sch: Scheduler {
+    TcpIncoming {
+        addr: addr,
+        scheduler: scheduler,
+    }
+}
+
+/// Creates an observable of the connections to an existing listener.
+///
+/// This is like `tcp_incoming()`, but the first subscription takes the
+/// listener, so this is useful to listen on a port chosen by the operating
+/// system. Later subscriptions complete immediately.
+pub fn tcp_accept<Sch>(listener: TcpListener, scheduler: Sch) -> SocketObservable<Listener, Sch>
+    where Sch: Scheduler {
+    SocketObservable {
+        receiver: Some(Listener::new(Ok(listener))),
+        scheduler: scheduler,
+    }
+}
+
+/// Creates an observable of the datagrams that a socket receives.
+///
+/// The first subscription takes the socket, and an action on the scheduler
+/// pushes every datagram with the address that sent it. The observable fails
+/// if receiving fails. It does not complete. Dropping the subscription closes
+/// the socket.
+pub fn udp_datagrams<Sch>(socket: UdpSocket, scheduler: Sch)
+                          -> SocketObservable<DatagramReceiver, Sch>
+    where Sch: Scheduler {
+    let error = socket.set_nonblocking(true).err();
+    SocketObservable {
+        receiver: Some(DatagramReceiver {
+            socket: socket,
+            buffer: RefCell::new(vec![0; MAX_DATAGRAM_SIZE]),
+            error: RefCell::new(error),
+        }),
+        scheduler: scheduler,
+    }
+}
+
+/// Creates an observable of the lines that a connection receives.
+///
+/// The first subscription takes the connection, and an action on the
+/// scheduler pushes every line without its line ending. The observable
+/// completes when the peer closes the connection, and it fails if reading
+/// fails, or if a line is not valid UTF-8. Dropping the subscription closes
+/// the connection.
+pub fn framed_lines<Sch>(stream: TcpStream, scheduler: Sch)
+                         -> SocketObservable<LineReceiver, Sch>
+    where Sch: Scheduler {
+    let error = stream.set_nonblocking(true).err();
+    SocketObservable {
+        receiver: Some(LineReceiver {
+            stream: stream,
+            lines: RefCell::new(LineBuffer::new()),
+            is_closed: Cell::new(false),
+            error: RefCell::new(error),
+        }),
+        scheduler: scheduler,
+    }
+}
+
+impl Listener {
+    fn new(listener: io::Result<TcpListener>) -> Listener {
+        let listener = listener.and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        });
+        match listener {
+            Ok(listener) => Listener {
+                listener: Some(listener),
+                error: RefCell::new(None),
+            },
+            Err(error) => Listener {
+                listener: None,
+                error: RefCell::new(Some(error)),
+            },
+        }
+    }
+}
+
+impl TryReceive for Listener {
+    type Item = Notification<Arc<TcpStream>, Error>;
+
+    fn try_receive(&self) -> Result<Self::Item, TryRecvError> {
+        if let Some(error) = self.error.borrow_mut().take() {
+            return Ok(Notification::Error(Arc::new(error)));
+        }
+        let listener = match self.listener {
+            Some(ref listener) => listener,
+            None => return Err(TryRecvError::Disconnected),
+        };
+        // Whether an accepted connection inherits nonblocking mode differs
+        // between platforms, so it is set explicitly.
+        let connection = listener.accept().and_then(|(stream, _addr)| {
+            stream.set_nonblocking(false)?;
+            Ok(stream)
+        });
+        match connection {
+            Ok(stream) => Ok(Notification::Next(Arc::new(stream))),
+            Err(ref error) if is_transient(error) => Err(TryRecvError::Empty),
+            Err(error) => Ok(Notification::Error(Arc::new(error))),
+        }
+    }
+}
+
+// An error for which polling should simply be retried later.
+fn is_transient(error: &io::Error) -> bool {
+    error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::Interrupted
+}
+
+impl TryReceive for DatagramReceiver {
+    type Item = Notification<(SocketAddr, Vec<u8>), Error>;
+
+    fn try_receive(&self) -> Result<Self::Item, TryRecvError> {
+        if let Some(error) = self.error.borrow_mut().take() {
+            return Ok(Notification::Error(Arc::new(error)));
+        }
+        let mut buffer = self.buffer.borrow_mut();
+        match self.socket.recv_from(&mut buffer[..]) {
+            Ok((len, addr)) => Ok(Notification::Next((addr, buffer[..len].to_vec()))),
+            Err(ref error) if is_transient(error) => Err(TryRecvError::Empty),
+            Err(error) => Ok(Notification::Error(Arc::new(error))),
+        }
+    }
+}
+
+impl TryReceive for LineReceiver {
+    type Item = Notification<String, Error>;
+
+    fn try_receive(&self) -> Result<Self::Item, TryRecvError> {
+        if let Some(error) = self.error.borrow_mut().take() {
+            return Ok(Notification::Error(Arc::new(error)));
+        }
+        let mut lines = self.lines.borrow_mut();
+        let mut chunk = [0; 4096];
+        loop {
+            let line = if self.is_closed.get() {
+                match lines.last_line() {
+                    Some(line) => line,
+                    None => return Ok(Notification::Completed),
+                }
+            } else {
+                match lines.next_line() {
+                    Some(line) => line,
+                    None => {
+                        match (&self.stream).read(&mut chunk) {
+                            Ok(0) => self.is_closed.set(true),
+                            Ok(len) => lines.bytes.extend_from_slice(&chunk[..len]),
+                            Err(ref error) if is_transient(error) => {
+                                return Err(TryRecvError::Empty);
+                            }
+                            Err(error) => return Ok(Notification::Error(Arc::new(error))),
+                        }
+                        continue;
+                    }
+                }
+            };
+            return Ok(match line {
+                Ok(line) => Notification::Next(line),
+                Err(error) => Notification::Error(Arc::new(error)),
+            });
+        }
+    }
+}
+
+impl<A, Sch> Observable for TcpIncoming<A, Sch>
+where A: ToSocketAddrs,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static {
+    type Item = Arc<TcpStream>;
+    type Error = Error;
+    type Subscription = FromReceiverSubscription<Listener, Sch, Arc<TcpStream>, Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Arc<TcpStream>, Error> + 'static {
+        let listener = Listener::new(TcpListener::bind(&self.addr));
+        channel::from_notification_receiver(listener, self.scheduler.clone()).subscribe(observer)
+    }
+}
+
+impl<R, Sch, T, E> Observable for SocketObservable<R, Sch>
+where R: TryReceive<Item = Notification<T, E>> + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = FromReceiverSubscription<Option<R>, Sch, T, E>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let receiver = self.receiver.take();
+        channel::from_notification_receiver(receiver, self.scheduler.clone()).subscribe(observer)
+    }
+}
@@ -5,10 +5,69 @@
 // you may not use this file except in compliance with the License.
 // A copy of the License has been included in the root of the repository.
 
-use observer::Observer;
+use {Either, NoError, Notification};
+use generate::Never;
+use aggregate::{MinMaxByKeyObservable, SingleObservable};
+use blocking::{self, BlockingIter, BlockingObservable};
+use boxed::BoxedObservable;
+use buffer::{BufferTimeOrCountObservable, BufferWithObservable, SlidingWindowObservable,
+             WindowTimeObservable};
+use channel::{ItemSender, NotificationSender};
+use circuit_breaker::{CircuitBreakerObservable, CircuitBreakerPolicy};
+#[cfg(feature = "crossbeam")]
+use crossbeam::{self, BoundedSender, PipeObservable, PipeSubscription, SendPolicy};
+#[cfg(feature = "crossbeam")]
+use crossbeam_channel;
+use delay::DelayEachObservable;
+use describe::Graph;
+use distinct::DistinctWithinObservable;
+use flatten::{ExhaustMapObservable, ExpandObservable, SwitchObservable};
+use heartbeat::{HeartbeatObservable, WatchdogObservable};
+#[cfg(feature = "tracing")]
+use instrument::InstrumentObservable;
+use join::{GroupJoinObservable, JoinObservable};
+use lifecycle::{DoOnSubscribeObservable, DoOnUnsubscribeObservable};
+#[cfg(feature = "serde")]
+use jsonl::JsonlWriter;
+use merge::{MergeEitherObservable, MergeObservable};
+use metrics::MeteredObservable;
+use logging::LogObservable;
+use multicast::{CacheObservable, ConnectableObservable, Multicast, RefCountObservable};
+use observe_on::{ObserveOnObservable, ObserveOnThreadObservable, OverflowPolicy};
+use observer::{BoxedObserver, Observer};
 use observer::{NextObserver, CompletedObserver, ErrorObserver, OptionObserver, ResultObserver};
+use pausable::PausableObservable;
+use pull::{self, IntoPullObservable};
+use rate_limit::RateLimitObservable;
+use record::{self, RecordingSubscription};
+use repeat::RepeatWhenObservable;
+use results::{FlattenResultsObservable, OkOrElseObservable};
+use safe::SafeObservable;
+use sample::SampleObservable;
+use scheduler::{Scheduler, SendScheduler};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use split::{self, SplitObservable};
+use sequence_equal::SequenceEqualObservable;
+use stats::{EmaObservable, Histogram, Quantiles, SketchObservable, StatsObservable};
 use std::fmt::Debug;
-use transform::{ContinueWithObservable, MapErrorObservable, MapObservable};
+use std::hash::Hash;
+#[cfg(feature = "serde")]
+use std::io::Write;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+#[cfg(feature = "futures")]
+use futures::future::Future;
+#[cfg(feature = "futures")]
+use futures::task::{LocalSpawn, SpawnError};
+#[cfg(feature = "futures")]
+use stream::{self, AsyncSubscription, FirstFuture, ObservableStream};
+use subject::{ReplaySubject, Subject, SubjectObservable};
+use subscription::Subscription;
+use timestamp::{TimeIntervalObservable, TimestampObservable};
+use transform::{self, ConcatMapIterObservable, ContinueWithObservable, LiftObservable,
+                MapErrorObservable, MapObservable};
+use until::{SkipUntilObservable, TakeUntilObservable};
 
 /// A stream of values.
 ///
@@ -43,8 +102,7 @@ pub trait Observable {
     type Error: Clone;
 
     /// The result of subscribing an observer.
-    // TODO: This drop bound is not required and it only complicates stuff, remove it.
-    type Subscription: Drop;
+    type Subscription: Subscription;
 
     /// Subscribes an observer and returns the subscription.
     ///
@@ -60,20 +118,56 @@ pub trait Observable {
     ///
     /// The returned value represents the subscription. Dropping the subscription
     /// will prevent further calls on the observer.
+    ///
+    /// The observer must be `'static`, because the observable might store it,
+    /// and the subscription does not borrow the observable.
     fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
-        where O: Observer<Self::Item, Self::Error>;
+        where O: Observer<Self::Item, Self::Error> + 'static;
+
+    /// Returns the graph of the pipeline that ends in this observable.
+    ///
+    /// An operator describes itself as a node named after the operator, with
+    /// edges from the graphs of its sources. Other observables describe
+    /// themselves as a node without sources, named after their type. Use
+    /// `Graph::to_dot()` to render the graph with Graphviz.
+    ///
+    /// Operators defined outside of this crate can override this method,
+    /// with `Graph::operator()`, to show up in the graph with their sources.
+    fn describe(&self) -> Graph {
+        Graph::unnamed::<Self>()
+    }
+}
+
+// The result of `flatten_iter()` and `filter_some()`.
+type FlattenIterObservable<Source> = ConcatMapIterObservable<Source,
+    fn(<Source as Observable>::Item) -> <Source as Observable>::Item>;
+
+// The result of `route()`, the observables of the left and the right values.
+type RouteObservables<Source, F, L, R> =
+    (SplitObservable<MapObservable<Source, F>, L>, SplitObservable<MapObservable<Source, F>, R>);
+
+// The result of `distinct_within()`, which compares the values themselves.
+type DistinctValuesWithinObservable<Source, Sch> = DistinctWithinObservable<Source,
+    fn(&<Source as Observable>::Item) -> <Source as Observable>::Item, Sch>;
 
+/// Operators and subscription helpers for observables.
+///
+/// Every observable implements this trait automatically, so implementing a
+/// source only requires `Observable::subscribe()`. The operators live here
+/// rather than in `Observable`, so other crates can add their own extension
+/// traits without colliding with the methods of the core trait.
+pub trait ObservableExt: Observable {
     /// Subscribes a function to handle values produced by the observable.
     ///
     /// For every value produced by the observable, `on_next` is called.
     ///
     /// **This subscription panics if the observable fails with an error.**
     ///
-    /// See also [`subscribe()`](#tymethod.subscribe).
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
     fn subscribe_next<FnNext>(&mut self,
                               on_next: FnNext)
                               -> Self::Subscription
-        where Self::Error: Debug, FnNext: FnMut(Self::Item) {
+        where Self::Error: Debug, FnNext: FnMut(Self::Item) + 'static {
         let observer = NextObserver {
             fn_next: on_next,
         };
@@ -89,12 +183,14 @@ pub trait Observable {
     ///
     /// **This subscription panics if the observable fails with an error.**
     ///
-    /// See also [`subscribe()`](#tymethod.subscribe).
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
     fn subscribe_completed<FnNext, FnCompleted>(&mut self,
                                                 on_next: FnNext,
                                                 on_completed: FnCompleted)
                                                 -> Self::Subscription
-        where Self::Error: Debug, FnNext: FnMut(Self::Item), FnCompleted: FnOnce() {
+        where Self::Error: Debug,
+              FnNext: FnMut(Self::Item) + 'static,
+              FnCompleted: FnOnce() + 'static {
         let observer = CompletedObserver {
             fn_next: on_next,
             fn_completed: on_completed,
@@ -109,13 +205,15 @@ pub trait Observable {
     /// is called. After `on_completed` or `on_error` have been called, it is
     /// guaranteed that none of the three functions are called again.
     ///
-    /// See also [`subscribe()`](#tymethod.subscribe).
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
     fn subscribe_error<FnNext, FnCompleted, FnError>(&mut self,
                                                      on_next: FnNext,
                                                      on_completed: FnCompleted,
                                                      on_error: FnError)
                                                      -> Self::Subscription
-        where FnNext: FnMut(Self::Item), FnCompleted: FnOnce(), FnError: FnOnce(Self::Error) {
+        where FnNext: FnMut(Self::Item) + 'static,
+              FnCompleted: FnOnce() + 'static,
+              FnError: FnOnce(Self::Error) + 'static {
         let observer = ErrorObserver {
             fn_next: on_next,
             fn_completed: on_completed,
@@ -137,11 +235,11 @@ pub trait Observable {
     ///
     /// **This subscription panics if the observable fails with an error.**
     ///
-    /// See also [`subscribe()`](#tymethod.subscribe).
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
     fn subscribe_option<FnOption>(&mut self,
                                   on_next_or_completed: FnOption)
                                   -> Self::Subscription
-        where Self::Error: Debug, FnOption: FnMut(Option<Self::Item>) {
+        where Self::Error: Debug, FnOption: FnMut(Option<Self::Item>) + 'static {
         let observer = OptionObserver {
             fn_option: on_next_or_completed
         };
@@ -159,37 +257,1041 @@ pub trait Observable {
     /// After the function has been called with `Ok(None)` or `Err(error)`,
     /// it is guaranteed never to be called again.
     ///
-    /// See also [`subscribe()`](#tymethod.subscribe).
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
     fn subscribe_result<FnResult>(&mut self,
                                   on_next_or_completed_or_error: FnResult)
                                   -> Self::Subscription
-        where FnResult: FnMut(Result<Option<Self::Item>, Self::Error>) {
+        where FnResult: FnMut(Result<Option<Self::Item>, Self::Error>) + 'static {
         let observer = ResultObserver {
             fn_result: on_next_or_completed_or_error
         };
         self.subscribe(observer)
     }
 
+    /// Subscribes a channel that receives every notification.
+    ///
+    /// This makes it possible to consume the observable on a different thread,
+    /// with an ordinary loop over the receiver. The sender is dropped after it
+    /// sent completion or failure, so such a loop ends unless there are other
+    /// senders. If the receiver is dropped, notifications are discarded.
+    ///
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
+    fn subscribe_channel(&mut self,
+                         sender: Sender<Notification<Self::Item, Self::Error>>)
+                         -> Self::Subscription
+        where Self::Item: 'static, Self::Error: 'static {
+        let observer = NotificationSender {
+            sender: sender,
+        };
+        self.subscribe(observer)
+    }
+
+    /// Subscribes a channel that receives every value.
+    ///
+    /// The sender is dropped when the observable completes. If the receiver
+    /// is dropped, values are discarded.
+    ///
+    /// **This subscription panics if the observable fails with an error.**
+    ///
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
+    fn subscribe_channel_items(&mut self, sender: Sender<Self::Item>) -> Self::Subscription
+        where Self::Item: 'static, Self::Error: Debug + 'static {
+        let observer = ItemSender {
+            sender: sender,
+        };
+        self.subscribe(observer)
+    }
+
+    /// Subscribes a recorder that stores every notification with its timing.
+    ///
+    /// The time of every notification since subscription is read from the
+    /// clock of the scheduler. Call `recording()` on the subscription to get
+    /// the notifications recorded so far, and `replay()` on the recording to
+    /// push them again, for instance in a test with a `TestScheduler`.
+    fn record<Sch>(&mut self, scheduler: Sch)
+                   -> RecordingSubscription<Self::Subscription, Self::Item, Self::Error>
+        where Self::Item: 'static, Self::Error: 'static, Sch: Scheduler + 'static {
+        record::record(self, scheduler)
+    }
+
+    /// Subscribes a writer that receives every notification as a line of JSON.
+    ///
+    /// Every notification is serialized as a `Notification`, followed by a
+    /// newline, so the observable can be restored with `from_jsonl_reader()`.
+    /// The writer is flushed after completion or failure. If writing fails,
+    /// the remaining notifications are discarded. Wrap the writer in a
+    /// `BufWriter` to avoid a write call for every notification.
+    ///
+    /// This method is only available with the `serde` feature.
+    ///
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
+    #[cfg(feature = "serde")]
+    fn subscribe_jsonl<W>(&mut self, writer: W) -> Self::Subscription
+        where Self::Item: Serialize + 'static,
+              Self::Error: Serialize + 'static,
+              W: Write + 'static {
+        self.subscribe(JsonlWriter::new(writer))
+    }
+
+    /// Subscribes a bounded crossbeam channel that receives every notification.
+    ///
+    /// When the channel is full, the policy determines whether the observable
+    /// is blocked until there is room, or whether the value is dropped.
+    /// Completion and failure are never dropped. Blocking deadlocks if the
+    /// observable pushes on the thread that receives from the channel.
+    ///
+    /// This method is only available with the `crossbeam` feature.
+    ///
+    /// See also [`Observable::subscribe()`](trait.Observable.html#tymethod.subscribe).
+    #[cfg(feature = "crossbeam")]
+    fn subscribe_bounded(&mut self,
+                         sender: crossbeam_channel::Sender<Notification<Self::Item, Self::Error>>,
+                         policy: SendPolicy)
+                         -> Self::Subscription
+        where Self::Item: 'static, Self::Error: 'static {
+        let observer = BoundedSender {
+            sender: sender,
+            policy: policy,
+        };
+        self.subscribe(observer)
+    }
+
+    /// Subscribes an async function to handle values produced by the observable.
+    ///
+    /// This spawns a task on the executor that calls `handler` for every value,
+    /// and awaits the returned future before it handles the next value. Values
+    /// that arrive in the meantime are queued as by `into_stream()`. For
+    /// handlers that may run concurrently, use `subscribe_async_concurrent()`.
+    ///
+    /// If the observable fails, the task stops, and the error is available
+    /// through `AsyncSubscription::error()`.
+    ///
+    /// This method is only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    fn subscribe_async<Sp, F, Fut>(self, spawner: &Sp, handler: F)
+                                   -> Result<AsyncSubscription<Self::Error>, SpawnError>
+        where Self: Sized + 'static,
+              Self::Item: 'static,
+              Self::Error: 'static,
+              Sp: LocalSpawn,
+              F: FnMut(Self::Item) -> Fut + 'static,
+              Fut: Future<Output = ()> + 'static {
+        stream::subscribe_async(self, spawner, 1, handler)
+    }
+
+    /// Subscribes an async function that may handle several values at once.
+    ///
+    /// This is like `subscribe_async()`, but at most `limit` futures returned
+    /// by `handler` run at the same time, so they can complete out of order.
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// This method is only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    fn subscribe_async_concurrent<Sp, F, Fut>(self, spawner: &Sp, limit: usize, handler: F)
+                                              -> Result<AsyncSubscription<Self::Error>, SpawnError>
+        where Self: Sized + 'static,
+              Self::Item: 'static,
+              Self::Error: 'static,
+              Sp: LocalSpawn,
+              F: FnMut(Self::Item) -> Fut + 'static,
+              Fut: Future<Output = ()> + 'static {
+        stream::subscribe_async(self, spawner, limit, handler)
+    }
+
     /// Transforms an observable by applying f to every value produced.
-    fn map<'s, U, F>(&'s mut self, f: F) -> MapObservable<'s, Self, F>
-        where F: Fn(Self::Item) -> U {
+    ///
+    /// Operators like this one take the observable by value, so the result
+    /// can be stored or returned. To keep using the current observable, call
+    /// the operator on a mutable reference instead: `(&mut observable).map(f)`.
+    fn map<U, F>(self, f: F) -> MapObservable<Self, F>
+        where Self: Sized, F: Fn(Self::Item) -> U {
         MapObservable::new(self, f)
     }
 
+    /// Pushes the elements of every value, for an observable of iterables.
+    ///
+    /// The elements are pushed in order, before the next value is handled.
+    /// Unlike with an inner observable, there is nothing to subscribe to, so
+    /// this is a cheap way to unpack values that carry a batch each.
+    fn flatten_iter(self) -> FlattenIterObservable<Self>
+        where Self: Sized, Self::Item: IntoIterator, <Self::Item as IntoIterator>::Item: Clone {
+        ConcatMapIterObservable::new(self, transform::identity)
+    }
+
+    /// Pushes the elements of the iterable that f returns for every value.
+    ///
+    /// This is `map(f)` followed by `flatten_iter()`.
+    fn concat_map_iter<I, F>(self, f: F) -> ConcatMapIterObservable<Self, F>
+        where Self: Sized, I: IntoIterator, F: Fn(Self::Item) -> I {
+        ConcatMapIterObservable::new(self, f)
+    }
+
+    /// Pushes the contents of the values that are `Some`, and drops the `None`s.
+    ///
+    /// This is `flatten_iter()` for an observable of options.
+    fn filter_some<T>(self) -> FlattenIterObservable<Self>
+        where Self: Sized + Observable<Item = Option<T>>, T: Clone {
+        self.flatten_iter()
+    }
+
+    /// Converts values that are options into results, like `Option::ok_or_else()`.
+    ///
+    /// A `None` becomes `Err(f())`. Follow this with `flatten_results()` to
+    /// fail on the first `None`.
+    fn ok_or_else<T, U, F>(self, f: F) -> OkOrElseObservable<Self, F>
+        where Self: Sized + Observable<Item = Option<T>>, F: Fn() -> U {
+        OkOrElseObservable::new(self, f)
+    }
+
+    /// Pushes the values of the results, and fails with the first error.
+    ///
+    /// For an observable of results, this moves the errors into the error
+    /// channel, so observers do not have to match on every value. Values
+    /// after the first error are ignored. An observable that cannot fail
+    /// can be converted with `infallible()` first.
+    fn flatten_results<T>(self) -> FlattenResultsObservable<Self>
+        where Self: Sized + Observable<Item = Result<T, <Self as Observable>::Error>> {
+        FlattenResultsObservable::new(self)
+    }
+
+    /// Splits an observable of results into the values and the errors.
+    ///
+    /// The first observable pushes the value of every `Ok`, and the second
+    /// one pushes the error of every `Err`. Both end when the current
+    /// observable ends. They share a single subscription to it, which is
+    /// made when the first observer subscribes to either of them, so
+    /// observers of both should subscribe before it starts pushing.
+    fn split_results<T, E>(self) -> (SplitObservable<Self, T>, SplitObservable<Self, E>)
+        where Self: Sized + Observable<Item = Result<T, E>> {
+        split::split(self, Result::ok, Result::err)
+    }
+
+    /// Divides the values between two observables, as f decides for every value.
+    ///
+    /// The values for which f returns `Either::Left` go to the first
+    /// observable, and those for which it returns `Either::Right` go to the
+    /// second one. Like with `split_results()`, both share a single
+    /// subscription to the current observable.
+    fn route<L, R, F>(self, f: F) -> RouteObservables<Self, F, L, R>
+        where Self: Sized,
+              L: Clone,
+              R: Clone,
+              F: Fn(Self::Item) -> Either<L, R> + 'static {
+        split::split(self.map(f), Either::left, Either::right)
+    }
+
     /// Transforms an observable by applying f the error in case of failure.
-    fn map_error<'s, F, G>(&'s mut self, f: G) -> MapErrorObservable<'s, Self, G>
-        where G: Fn(Self::Error) -> F {
+    fn map_error<F, G>(self, f: G) -> MapErrorObservable<Self, G>
+        where Self: Sized, G: Fn(Self::Error) -> F {
         MapErrorObservable::new(self, f)
     }
 
+    /// Converts the error in case of failure with `Into`, like `?` does.
+    ///
+    /// This is `map_error(F::from)`, for unifying the error types of
+    /// observables that are combined.
+    fn err_into<F>(self) -> MapErrorObservable<Self, fn(Self::Error) -> F>
+        where Self: Sized, Self::Error: Into<F>, F: Clone {
+        MapErrorObservable::new(self, Into::into as fn(Self::Error) -> F)
+    }
+
+    /// Pairs every value with the time at which it arrived.
+    ///
+    /// The time is read from the clock of the scheduler, so with a
+    /// `TestScheduler` the timestamps follow virtual time.
+    fn timestamp<Sch>(self, scheduler: Sch) -> TimestampObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        TimestampObservable::new(self, scheduler)
+    }
+
+    /// Pairs every value with the time that passed since the previous value.
+    ///
+    /// The first value is paired with the time since subscription. The time
+    /// is read from the clock of the scheduler, like for `timestamp()`.
+    fn time_interval<Sch>(self, scheduler: Sch) -> TimeIntervalObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        TimeIntervalObservable::new(self, scheduler)
+    }
+
+    /// Drops values that equal a value pushed less than `duration` ago.
+    ///
+    /// The time is read from the clock of the scheduler. Dropped values do
+    /// not extend the window, so a value that keeps repeating is pushed
+    /// once every `duration`. Only the values of the last `duration` are
+    /// remembered, so memory does not grow with the number of distinct values
+    /// over the lifetime of the observable.
+    fn distinct_within<Sch>(self, duration: Duration, scheduler: Sch)
+                            -> DistinctValuesWithinObservable<Self, Sch>
+        where Self: Sized, Self::Item: Hash + Eq, Sch: Scheduler {
+        DistinctWithinObservable::new(self, duration, Clone::clone, scheduler)
+    }
+
+    /// Drops values whose key equals the key of a value pushed less than `duration` ago.
+    ///
+    /// This is like `distinct_within()`, but values are compared by the key
+    /// that `f` returns for them.
+    fn distinct_within_by_key<K, F, Sch>(self, duration: Duration, f: F, scheduler: Sch)
+                                         -> DistinctWithinObservable<Self, F, Sch>
+        where Self: Sized, F: Fn(&Self::Item) -> K, K: Hash + Eq, Sch: Scheduler {
+        DistinctWithinObservable::new(self, duration, f, scheduler)
+    }
+
+    /// Delays every value by the duration that `f` returns for it.
+    ///
+    /// The delay counts from the moment the value arrives, so values with
+    /// different delays can be pushed in a different order than they
+    /// arrived; values that become due at the same time keep their order.
+    /// With the time differences of a recorded trace as delays, this replays
+    /// the trace with its original timing. Completion waits for the pending
+    /// values; failure is pushed right away, and the pending values are
+    /// dropped.
+    fn delay_each<F, Sch>(self, f: F, scheduler: Sch) -> DelayEachObservable<Self, F, Sch>
+        where Self: Sized, F: Fn(&Self::Item) -> Duration, Sch: Scheduler {
+        DelayEachObservable::new(self, f, scheduler)
+    }
+
+    /// Pushes `f()` whenever the current observable is silent for `duration`.
+    ///
+    /// The silence counts from subscription or from the latest value. While
+    /// the current observable stays silent, a filler value is pushed every
+    /// `duration`. This lets a monitoring pipeline tell a quiet source from a
+    /// broken connection.
+    fn heartbeat<F, Sch>(self, duration: Duration, f: F, scheduler: Sch)
+                         -> HeartbeatObservable<Self, F, Sch>
+        where Self: Sized, F: Fn() -> Self::Item, Sch: Scheduler {
+        HeartbeatObservable::new(self, duration, f, scheduler)
+    }
+
+    /// Fails with `f()` when the current observable is silent for `duration`.
+    ///
+    /// The silence counts from subscription or from the latest value. When
+    /// the observer fails this way, the subscription to the current
+    /// observable is dropped.
+    fn watchdog<F, Sch>(self, duration: Duration, f: F, scheduler: Sch)
+                        -> WatchdogObservable<Self, F, Sch>
+        where Self: Sized, F: Fn() -> Self::Error, Sch: Scheduler {
+        WatchdogObservable::new(self, duration, f, scheduler)
+    }
+
+    /// Collects values into batches of at most `count` values, or at most `duration` apart.
+    ///
+    /// A batch is pushed as soon as it holds `count` values, or when
+    /// `duration` has passed since its first value arrived, whichever comes
+    /// first. The timer is an action on the scheduler. Empty batches are not
+    /// pushed. When the current observable completes, the partial batch is
+    /// pushed before completion; when it fails, the partial batch is dropped.
+    fn buffer_time_or_count<Sch>(self, duration: Duration, count: usize, scheduler: Sch)
+                                 -> BufferTimeOrCountObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        BufferTimeOrCountObservable::new(self, duration, count, scheduler)
+    }
+
+    /// Collects the values into batches that `boundary` closes.
+    ///
+    /// Every value that the boundary pushes closes the current batch and
+    /// pushes it, unless it is empty. When the current observable completes,
+    /// the partial batch is pushed before completion; when it fails, the
+    /// partial batch is dropped. Completion or failure of the boundary is
+    /// ignored, so the rest of the values end up in a single batch.
+    fn buffer_with<Boundary>(self, boundary: Boundary) -> BufferWithObservable<Self, Boundary>
+        where Self: Sized, Boundary: Observable {
+        BufferWithObservable::new(self, boundary)
+    }
+
+    /// Splits the values into windows that are open for `duration` each.
+    ///
+    /// The observer receives an observable for every window: the first one
+    /// upon subscription, and a new one every `duration` after that, when the
+    /// previous window completes. Windows are hot, so subscribe to a window
+    /// while handling it to receive all of its values. Completion and
+    /// failure of the current observable are pushed to the current window
+    /// and then to the observer.
+    fn window_time<Sch>(self, duration: Duration, scheduler: Sch)
+                        -> WindowTimeObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        WindowTimeObservable::new(self, duration, scheduler)
+    }
+
+    /// Pushes the latest `size` values, every `step` values.
+    ///
+    /// The first window is pushed once `size` values arrived, and the next
+    /// ones every `step` values after that, so with a step smaller than the
+    /// size the windows overlap. Every window is a copy, for aggregates like
+    /// rolling medians that need all of its values. Only full windows are
+    /// pushed.
+    fn sliding_window(self, size: usize, step: usize) -> SlidingWindowObservable<Self>
+        where Self: Sized, Self::Item: Clone {
+        SlidingWindowObservable::new(self, size, step)
+    }
+
     /// Joins two observables sequentially.
     ///
     /// After the current observable completes, an observer will start to
     /// receive values from `next` until that observable completes or fails.
     /// The `next` observable is only subscribed to after the current observable
-    /// completes.
-    fn continue_with<'s, ObNext>(&'s mut self, next: &'s mut ObNext) -> ContinueWithObservable<'s, Self, ObNext>
-        where ObNext: Observable<Item = Self::Item, Error = Self::Error> {
+    /// completes. To join more than two observables, use the `concat!` macro.
+    fn continue_with<ObNext>(self, next: ObNext) -> ContinueWithObservable<Self, ObNext>
+        where Self: Sized, ObNext: Observable<Item = Self::Item, Error = Self::Error> {
         ContinueWithObservable::new(self, next)
     }
+
+    /// Subscribes to the current observable again whenever a signal says so.
+    ///
+    /// The function receives an observable that pushes `()` whenever the
+    /// current observable completes, and it returns the signal. Whenever the
+    /// signal pushes a value after a completion, the current observable is
+    /// subscribed to again, so a delay on the completions polls with a
+    /// backoff. The result completes when the current observable completes
+    /// after the signal completed, and a failure of either is pushed.
+    fn repeat_when<Signal, F>(self, f: F) -> RepeatWhenObservable<Self, F>
+        where Self: Sized,
+              Signal: Observable<Error = Self::Error>,
+              F: FnMut(SubjectObservable<(), Self::Error>) -> Signal {
+        RepeatWhenObservable::new(self, f)
+    }
+
+    /// Pushes the values of the current observable until `trigger` pushes a value.
+    ///
+    /// When the trigger pushes its first value, the observer completes, and
+    /// the subscriptions to the current observable and to the trigger are
+    /// dropped. Completion or failure of the trigger is ignored. A subject is
+    /// a convenient trigger to shut down a pipeline:
+    /// `source.take_until(shutdown.observable())`.
+    fn take_until<Trigger>(self, trigger: Trigger) -> TakeUntilObservable<Self, Trigger>
+        where Self: Sized, Trigger: Observable {
+        TakeUntilObservable::new(self, trigger)
+    }
+
+    /// Discards the values of the current observable until `trigger` pushes a value.
+    ///
+    /// After the trigger pushed its first value, all values of the current
+    /// observable are pushed, and the subscription to the trigger is dropped.
+    /// Completion and failure of the current observable are always pushed.
+    /// Completion or failure of the trigger is ignored.
+    fn skip_until<Trigger>(self, trigger: Trigger) -> SkipUntilObservable<Self, Trigger>
+        where Self: Sized, Trigger: Observable {
+        SkipUntilObservable::new(self, trigger)
+    }
+
+    /// Pushes the latest value of the current observable whenever `sampler` pushes.
+    ///
+    /// When the sampler pushes, the latest value that arrived since the
+    /// previous sample is pushed; if no value arrived, nothing is pushed.
+    /// Completion and failure of the current observable are pushed right
+    /// away, and a value that was not sampled yet is dropped. Completion or
+    /// failure of the sampler is ignored. With a periodic sampler, this
+    /// takes snapshots of a value that changes faster than it is needed.
+    fn sample<Sampler>(self, sampler: Sampler) -> SampleObservable<Self, Sampler>
+        where Self: Sized, Sampler: Observable {
+        SampleObservable::new(self, sampler)
+    }
+
+    /// Pushes whether the current observable and `other` push the same values.
+    ///
+    /// The values are compared pairwise, in order. On the first mismatch, or
+    /// as soon as one observable completes while the other pushed more
+    /// values, `false` is pushed; when both complete with equal values,
+    /// `true` is pushed. Either way, completion follows and the subscriptions
+    /// are dropped. A failure of either observable is pushed instead.
+    fn sequence_equal<Other>(self, other: Other) -> SequenceEqualObservable<Self, Other>
+        where Self: Sized, Other: Observable<Item = Self::Item, Error = Self::Error> {
+        SequenceEqualObservable::new(self, other)
+    }
+
+    /// Pushes the values of the current observable and `other` as they arrive.
+    ///
+    /// Both observables are subscribed to right away. The result completes
+    /// when both complete, and a failure of either is pushed right away. To
+    /// merge more than two observables, use the `merge!` macro.
+    fn merge<Other>(self, other: Other) -> MergeObservable<Self, Other>
+        where Self: Sized, Other: Observable<Item = Self::Item, Error = Self::Error> {
+        MergeObservable::new(self, other)
+    }
+
+    /// Pushes the values of the current observable and `other` as they arrive.
+    ///
+    /// Values of the current observable are wrapped in `Either::Left`, and
+    /// values of `other` in `Either::Right`, so observers can tell where every
+    /// value came from. The result completes when both complete, and a
+    /// failure of either is pushed right away. To keep using `other`, pass
+    /// `&mut other`.
+    fn merge_either<Other>(self, other: Other) -> MergeEitherObservable<Self, Other>
+        where Self: Sized, Other: Observable<Error = Self::Error> {
+        MergeEitherObservable::new(self, other)
+    }
+
+    /// Pushes the values of the latest inner observable, for an observable of observables.
+    ///
+    /// Every inner observable is subscribed to when the current observable
+    /// pushes it, and the subscription to the previous one is dropped, so
+    /// only the latest one is mirrored. The result completes when the current
+    /// observable and the latest inner observable complete. A failure of the
+    /// current observable or of the latest inner observable is pushed.
+    fn switch(self) -> SwitchObservable<Self>
+        where Self: Sized, Self::Item: Observable<Error = Self::Error> {
+        SwitchObservable::new(self)
+    }
+
+    /// Pushes the values of the observable that f returns, unless one is active.
+    ///
+    /// For every value of the current observable, f returns an inner
+    /// observable, which is subscribed to. Values that arrive while the
+    /// previous inner observable did not complete yet are ignored, so f is
+    /// not even called for them. This suits actions that should not be
+    /// repeated while they are in progress. The result completes when the
+    /// current observable and the active inner observable complete.
+    fn exhaust_map<Inner, F>(self, f: F) -> ExhaustMapObservable<Self, F>
+        where Self: Sized, Inner: Observable<Error = Self::Error>, F: Fn(Self::Item) -> Inner {
+        ExhaustMapObservable::new(self, f)
+    }
+
+    /// Pushes every value, and feeds it back through f to get more values.
+    ///
+    /// Every value of the current observable is pushed, and f returns an
+    /// observable for it, whose values are pushed and expanded in turn. The
+    /// values are expanded breadth-first, one observable at a time, which
+    /// suits walking a tree or fetching page after page. The result completes
+    /// when the current observable completed, and every value has been
+    /// expanded into an observable that completed. If f never returns an
+    /// empty observable, the result never completes.
+    fn expand<Inner, F>(self, f: F) -> ExpandObservable<Self, F>
+        where Self: Sized,
+              Inner: Observable<Item = Self::Item, Error = Self::Error>,
+              F: Fn(Self::Item) -> Inner {
+        ExpandObservable::new(self, f)
+    }
+
+    /// Pushes `f(left, right)` for every pair of values whose lifetimes overlap.
+    ///
+    /// Every value of the current observable and of `other` is open from the
+    /// moment it is pushed until the observable that `left_duration` or
+    /// `right_duration` returns for it pushes a value or ends. When a value
+    /// arrives, it is paired with all open values of the other side. The
+    /// result completes once either side completed and has no open values
+    /// left, and a failure of either side is pushed right away.
+    fn join<Other, FL, FR, F, DL, DR, U>(self, other: Other, left_duration: FL,
+                                         right_duration: FR, f: F)
+                                         -> JoinObservable<Self, Other, FL, FR, F>
+        where Self: Sized,
+              Other: Observable<Error = Self::Error>,
+              FL: Fn(&Self::Item) -> DL,
+              FR: Fn(&Other::Item) -> DR,
+              DL: Observable,
+              DR: Observable,
+              F: Fn(&Self::Item, &Other::Item) -> U {
+        JoinObservable::new(self, other, left_duration, right_duration, f)
+    }
+
+    /// Pushes `f(left, window)` for every value of the current observable.
+    ///
+    /// The window is an observable of the values of `other` whose lifetimes
+    /// overlap with the lifetime of `left`, as for `join()`. It completes when
+    /// the lifetime of `left` ends. The result completes once the current
+    /// observable completed and all windows closed; the completion of `other`
+    /// only stops the windows from receiving values.
+    fn group_join<Other, FL, FR, F, DL, DR, U>(self, other: Other, left_duration: FL,
+                                               right_duration: FR, f: F)
+                                               -> GroupJoinObservable<Self, Other, FL, FR, F>
+        where Self: Sized,
+              Other: Observable<Error = Self::Error>,
+              FL: Fn(&Self::Item) -> DL,
+              FR: Fn(&Other::Item) -> DR,
+              DL: Observable,
+              DR: Observable,
+              F: Fn(Self::Item, SubjectObservable<Other::Item, Self::Error>) -> U {
+        GroupJoinObservable::new(self, other, left_duration, right_duration, f)
+    }
+
+    /// Pushes the only value of the current observable, and then completes.
+    ///
+    /// Fails with `SingleError::Empty` if the current observable completes
+    /// without a value, and with `SingleError::Multiple` as soon as it pushes
+    /// a second value. Use this where exactly one result is expected, rather
+    /// than silently taking the first.
+    fn single(self) -> SingleObservable<Self, Self::Item>
+        where Self: Sized {
+        SingleObservable::new(self, None)
+    }
+
+    /// Like `single()`, but pushes `default` if there is no value.
+    ///
+    /// More than one value still fails with `SingleError::Multiple`.
+    fn single_or_default(self, default: Self::Item) -> SingleObservable<Self, Self::Item>
+        where Self: Sized {
+        SingleObservable::new(self, Some(default))
+    }
+
+    /// Pushes the value for which `f` returns the smallest key, upon completion.
+    ///
+    /// Only the best value so far is kept. If several values have the
+    /// smallest key, the first one is pushed. If the current observable
+    /// completes without values, nothing is pushed before completion.
+    fn min_by_key<K, F>(self, f: F) -> MinMaxByKeyObservable<Self, F>
+        where Self: Sized, F: Fn(&Self::Item) -> K, K: Ord {
+        MinMaxByKeyObservable::new(self, f, false)
+    }
+
+    /// Pushes the value for which `f` returns the largest key, upon completion.
+    ///
+    /// Only the best value so far is kept. If several values have the
+    /// largest key, the last one is pushed. If the current observable
+    /// completes without values, nothing is pushed before completion.
+    fn max_by_key<K, F>(self, f: F) -> MinMaxByKeyObservable<Self, F>
+        where Self: Sized, F: Fn(&Self::Item) -> K, K: Ord {
+        MinMaxByKeyObservable::new(self, f, true)
+    }
+
+    /// Pushes running statistics of the values so far, for every value.
+    ///
+    /// The statistics are updated with Welford's algorithm, so the values
+    /// need not be kept in memory. See `stats_final()` to push only the
+    /// statistics of all values, upon completion.
+    fn stats(self) -> StatsObservable<Self>
+        where Self: Sized, Self::Item: Into<f64> {
+        StatsObservable::new(self, false)
+    }
+
+    /// Pushes the statistics of all values upon completion.
+    ///
+    /// If the current observable completes without values, nothing is
+    /// pushed before completion.
+    fn stats_final(self) -> StatsObservable<Self>
+        where Self: Sized, Self::Item: Into<f64> {
+        StatsObservable::new(self, true)
+    }
+
+    /// Counts the values per bucket, and pushes the histogram upon completion.
+    ///
+    /// `bounds` are the upper bounds of the buckets; see `Histogram`. Call
+    /// `snapshot_on()` on the result to also push the histogram so far
+    /// whenever another observable pushes, for example a periodic one.
+    fn histogram(self, bounds: Vec<f64>) -> SketchObservable<Self, Histogram, Never<(), NoError>>
+        where Self: Sized, Self::Item: Into<f64> {
+        SketchObservable::new(self, Histogram::new(bounds))
+    }
+
+    /// Estimates the given quantiles of the values, and pushes them upon completion.
+    ///
+    /// The quantiles are between 0 and 1, so `&[0.5, 0.95, 0.99]` gives the
+    /// median and the 95th and 99th percentiles. The estimates are within 1%
+    /// of the actual values, using memory that grows only with the
+    /// logarithm of the range of the values; see `Quantiles`. Call
+    /// `snapshot_on()` on the result to also push the estimates so far
+    /// whenever another observable pushes.
+    fn percentiles(self, quantiles: &[f64]) -> SketchObservable<Self, Quantiles, Never<(), NoError>>
+        where Self: Sized, Self::Item: Into<f64> {
+        SketchObservable::new(self, Quantiles::new(quantiles))
+    }
+
+    /// Pushes the exponential moving average of the values, for every value.
+    ///
+    /// Every value moves the average by `alpha` times its difference from
+    /// the average, so `alpha` between 0 and 1 determines how fast older
+    /// values are forgotten. The first value is the initial average. Call
+    /// `warm_up()` on the result to hold back the first averages.
+    fn ema(self, alpha: f64) -> EmaObservable<Self>
+        where Self: Sized, Self::Item: Into<f64> {
+        EmaObservable::new(self, alpha)
+    }
+
+    /// Holds back the values of the current observable while `control` says so.
+    ///
+    /// Values pass while the last value of the control observable is `true`,
+    /// or before it pushed anything. While it is `false`, values are dropped;
+    /// call `buffered()` on the result to push them when delivery resumes
+    /// instead. Completion waits for the buffered values; failure is pushed
+    /// right away. Completion or failure of the control is ignored.
+    fn pausable<Control>(self, control: Control) -> PausableObservable<Self, Control>
+        where Self: Sized, Control: Observable<Item = bool> {
+        PausableObservable::new(self, control)
+    }
+
+    /// Builds a custom operator from a function that transforms observers.
+    ///
+    /// Upon subscription, `f` is called with the downstream observer, and the
+    /// observer that it returns is subscribed to the current observable. This
+    /// makes it possible to write an operator as an observer that wraps the
+    /// downstream observer, without implementing `Observable` for it. The
+    /// downstream observer is boxed, because `f` cannot be generic over it.
+    fn lift<U, F, G, Up>(self, f: G) -> LiftObservable<Self, G, U, F>
+        where Self: Sized,
+              U: Clone,
+              F: Clone,
+              G: FnMut(Box<dyn BoxedObserver<U, F>>) -> Up,
+              Up: Observer<Self::Item, Self::Error> + 'static {
+        LiftObservable::new(self, f)
+    }
+
+    /// Pushes values, completion and failure to the observer through the scheduler.
+    ///
+    /// The notifications of the current observable are queued, and an action
+    /// on the scheduler pushes them to the observer in order. The queue is
+    /// unbounded; call `bounded()` on the result to limit its capacity, and
+    /// to configure what happens when it is full.
+    fn observe_on<Sch>(self, scheduler: Sch) -> ObserveOnObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        ObserveOnObservable::new(self, scheduler)
+    }
+
+    /// Pushes values, completion and failure to the observer on a thread of the scheduler.
+    ///
+    /// This is like `observe_on()`, but for a `SendScheduler`: the observer
+    /// runs on the threads of the scheduler, one notification at a time and
+    /// in order, while the current observable keeps pushing on its own
+    /// thread. The observer must be `Send`, so subscribe with the
+    /// `subscribe()` method of the result, rather than `Observable::subscribe()`.
+    fn observe_on_thread<Sch>(self, scheduler: Sch) -> ObserveOnThreadObservable<Self, Sch>
+        where Self: Sized, Sch: SendScheduler {
+        ObserveOnThreadObservable::new(self, scheduler)
+    }
+
+    /// Converts the observable into a pull observable.
+    ///
+    /// Values of the current observable are queued until the observer
+    /// requests them. The queue holds at most `capacity` values beyond the
+    /// values that were requested, and `policy` determines what happens when
+    /// it is full. Completion and failure are pushed once the queued values
+    /// have been pushed.
+    fn into_pull(self, capacity: usize, policy: OverflowPolicy<Self::Error>)
+                 -> IntoPullObservable<Self, fn(Self::Item)>
+        where Self: Sized {
+        IntoPullObservable::new(self, capacity, policy, pull::ignore)
+    }
+
+    /// Buffers values that the observer of the pull observable did not request.
+    ///
+    /// This is `into_pull()`: up to `capacity` values are buffered, and
+    /// `policy` determines what happens when the buffer is full. A large
+    /// buffer trades memory and latency for fewer lost values.
+    fn on_backpressure_buffer(self, capacity: usize, policy: OverflowPolicy<Self::Error>)
+                              -> IntoPullObservable<Self, fn(Self::Item)>
+        where Self: Sized {
+        self.into_pull(capacity, policy)
+    }
+
+    /// Drops values that the observer of the pull observable did not request.
+    ///
+    /// Values that arrive while there is no demand are passed to `f` and
+    /// dropped, so the observer only receives values that arrived after it
+    /// requested them.
+    fn on_backpressure_drop<F>(self, f: F) -> IntoPullObservable<Self, F>
+        where Self: Sized, F: Fn(Self::Item) {
+        IntoPullObservable::new(self, 0, OverflowPolicy::DropNewest, f)
+    }
+
+    /// Keeps only the latest value that the observer of the pull observable
+    /// did not request.
+    ///
+    /// While there is no demand, every new value replaces the previous one,
+    /// so the next request receives the most recent value. This suits values
+    /// that represent a state, such as a position or a reading.
+    fn on_backpressure_latest(self) -> IntoPullObservable<Self, fn(Self::Item)>
+        where Self: Sized {
+        IntoPullObservable::new(self, 1, OverflowPolicy::DropOldest, pull::ignore)
+    }
+
+    /// Limits the rate of values with a token bucket.
+    ///
+    /// The bucket starts with `burst` tokens, and an action on the scheduler
+    /// adds a token every `interval`, as long as the bucket is not full.
+    /// Every value takes a token, so at most `burst` values pass at once, and
+    /// after that, one per interval. Values that find the bucket empty are
+    /// queued until a token arrives; call `drop_excess()` on the result to
+    /// drop them instead. Completion waits for the queued values.
+    fn rate_limit<Sch>(self, interval: Duration, burst: usize, scheduler: Sch)
+                       -> RateLimitObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        RateLimitObservable::new(self, interval, burst, scheduler)
+    }
+
+    /// Stops subscribing to the observable after it failed repeatedly.
+    ///
+    /// The circuit is shared by all subscriptions to the result. After
+    /// `policy.failure_threshold` failures in a row, the circuit opens, and
+    /// subscriptions fail right away with `CircuitError::Open`, without
+    /// subscribing to the current observable. Once `policy.cooldown` has
+    /// passed on the scheduler's clock, a single subscription probes the
+    /// current observable again. A value or completion closes the circuit,
+    /// and a failure of the probe opens it for another cooldown.
+    fn circuit_breaker<Sch>(self, policy: CircuitBreakerPolicy, scheduler: Sch)
+                            -> CircuitBreakerObservable<Self, Sch>
+        where Self: Sized, Sch: Scheduler {
+        CircuitBreakerObservable::new(self, policy, scheduler)
+    }
+
+    /// Moves the rest of the pipeline onto a new thread.
+    ///
+    /// The new thread calls `downstream` with an observable of the values of
+    /// the current observable, which are sent through a channel with the given
+    /// capacity. When the channel is full, the current observable is blocked
+    /// until the downstream thread catches up. The result of `downstream` can
+    /// be retrieved with `join()` on the returned subscription.
+    ///
+    /// This method is only available with the `crossbeam` feature.
+    #[cfg(feature = "crossbeam")]
+    fn pipe_between_threads<F, R>(self,
+                                  capacity: usize,
+                                  downstream: F)
+                                  -> PipeSubscription<Self::Subscription, R>
+        where Self: Sized,
+              Self::Item: Send + 'static,
+              Self::Error: Send + 'static,
+              F: FnOnce(PipeObservable<Self::Item, Self::Error>) -> R + Send + 'static,
+              R: Send + 'static {
+        crossbeam::pipe_between_threads(self, capacity, downstream)
+    }
+
+    /// Shares a single subscription to the observable among multiple observers.
+    ///
+    /// The returned observable subscribes to the current observable only when
+    /// `connect()` is called on it. Until then, observers can subscribe, but
+    /// they do not receive any values. After the current observable completed
+    /// or failed, connecting again pushes its values through a new subject,
+    /// which observers that subscribe from then on receive.
+    fn publish(self) -> ConnectableObservable<Self, Subject<Self::Item, Self::Error>>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        ConnectableObservable::with_factory(self, Subject::new)
+    }
+
+    /// Shares a single subscription to the observable through the subject.
+    ///
+    /// This is like `publish()`, but the values are pushed through the given
+    /// subject, so the kind of subject determines what observers receive upon
+    /// subscription. For instance, with a `BehaviorSubject`, observers receive
+    /// the latest value, and with a `ReplaySubject`, they receive the buffered
+    /// values.
+    fn multicast<S>(self, subject: S) -> ConnectableObservable<Self, S>
+        where Self: Sized, S: Multicast<Self::Item, Self::Error> {
+        ConnectableObservable::new(self, subject)
+    }
+
+    /// Shares a single subscription to the observable while it has observers.
+    ///
+    /// The returned observable subscribes to the current observable when the
+    /// first observer subscribes, and it drops the subscription when the last
+    /// observer's subscription is dropped. This is `publish().ref_count()`, so
+    /// after the current observable completed or failed, the next observer to
+    /// subscribe once all previous subscriptions are dropped subscribes to it
+    /// again, rather than receiving only the terminal event.
+    fn share(self) -> RefCountObservable<Self, Subject<Self::Item, Self::Error>>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        self.publish().ref_count()
+    }
+
+    /// Shares a single subscription to the observable while it has observers,
+    /// and keeps it for the linger duration after the last observer leaves.
+    ///
+    /// This is `publish().ref_count_linger(linger, scheduler)`. The scheduler
+    /// runs the disconnect once the linger duration has passed.
+    fn share_linger<Sch>(self,
+                         linger: Duration,
+                         scheduler: Sch)
+                         -> RefCountObservable<Self, Subject<Self::Item, Self::Error>>
+        where Self: Sized,
+              Self::Item: 'static,
+              Self::Error: 'static,
+              Sch: Scheduler + 'static,
+              Sch::Subscription: 'static,
+              Self::Subscription: 'static {
+        self.publish().ref_count_linger(linger, scheduler)
+    }
+
+    /// Shares a single subscription to the observable while it has observers,
+    /// and replays the last `n` values to new observers.
+    ///
+    /// This is like `share()`, but the values are pushed through a
+    /// `ReplaySubject` with capacity `n`. The buffer is kept when the last
+    /// observer unsubscribes, so a later observer still receives the last
+    /// values, followed by the values pushed after reconnecting.
+    fn share_replay(self, n: usize) -> RefCountObservable<Self, ReplaySubject<Self::Item, Self::Error>>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        self.multicast(ReplaySubject::with_capacity(n)).ref_count()
+    }
+
+    /// Subscribes to the observable once, and replays everything it pushed.
+    ///
+    /// The current observable is subscribed to when the first observer
+    /// subscribes. All of its values, and its completion or failure, are
+    /// recorded and pushed to every observer that subscribes later. Unlike
+    /// `share_replay()`, the subscription is never dropped, so the current
+    /// observable is not subscribed to again, and the values are kept as
+    /// long as the returned observable lives.
+    fn cache(self) -> CacheObservable<Self>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        CacheObservable::new(self)
+    }
+
+    /// Converts an observable that cannot fail into one with any error type.
+    ///
+    /// This makes it possible to combine the observable with observables
+    /// that can fail, for instance with `continue_with()`.
+    fn infallible<E>(self) -> MapErrorObservable<Self, fn(NoError) -> E>
+        where Self: Sized + Observable<Error = NoError>, E: Clone {
+        MapErrorObservable::new(self, NoError::unreachable::<E> as fn(NoError) -> E)
+    }
+
+    /// Calls the function every time an observer subscribes.
+    ///
+    /// The function is called before the current observable is subscribed
+    /// to, so also before any values that it pushes upon subscription.
+    /// Together with `do_on_unsubscribe()`, this can count subscribers, or
+    /// acquire a resource for as long as they are subscribed.
+    fn do_on_subscribe<F>(self, f: F) -> DoOnSubscribeObservable<Self, F>
+        where Self: Sized, F: FnMut() {
+        DoOnSubscribeObservable::new(self, f)
+    }
+
+    /// Calls the function when a subscription is unsubscribed or dropped.
+    ///
+    /// The function is called once per subscription, also if the observable
+    /// completed or failed before the subscription was dropped, so every
+    /// subscription that was made is also ended. The function is called
+    /// before the subscription to the current observable is dropped.
+    fn do_on_unsubscribe<F>(self, f: F) -> DoOnUnsubscribeObservable<Self, F>
+        where Self: Sized, F: Fn() {
+        DoOnUnsubscribeObservable::new(self, f)
+    }
+
+    /// Reports every event of the observable, tagged with the name of the stage.
+    ///
+    /// Subscription, every value, completion, failure, and unsubscription
+    /// are reported with the tag and the time, to the sink that was set with
+    /// `rx::set_log_sink()`. By default, they are printed on standard error.
+    /// Inserting `log()` between stages shows where a pipeline stalls.
+    fn log<S: Into<String>>(self, tag: S) -> LogObservable<Self>
+        where Self: Sized, Self::Item: Debug, Self::Error: Debug {
+        LogObservable::new(self, tag.into())
+    }
+
+    /// Reports metrics of the observable under the name.
+    ///
+    /// The `Items`, `Errors` and `Completions` counters are incremented for
+    /// every value, failure and completion, and about once per second the
+    /// `Throughput` gauge is set to the number of values per second. They
+    /// are reported to the sink that was set with `rx::set_metrics_sink()`.
+    /// To report the depth of the queue of `observe_on()`, call
+    /// `metered_queue()` on its result.
+    fn metered<S: Into<String>>(self, name: S) -> MeteredObservable<Self> where Self: Sized {
+        MeteredObservable::new(self, name.into())
+    }
+
+    /// Reports every subscription to the observable as a `tracing` span.
+    ///
+    /// Every subscription gets a span named `subscription`, with target `rx`
+    /// and a `name` field that holds the name. The span is entered while the
+    /// source is subscribed to and while it pushes, so events of the source
+    /// and of downstream observers are nested in it. Values are recorded as
+    /// trace events with an `item` field, completion and unsubscription as
+    /// debug events, and failure as a warning with an `error` field.
+    ///
+    /// This method is only available with the `tracing` feature.
+    #[cfg(feature = "tracing")]
+    fn instrument<S: Into<String>>(self, name: S) -> InstrumentObservable<Self>
+        where Self: Sized, Self::Item: Debug, Self::Error: Debug {
+        InstrumentObservable::new(self, name.into())
+    }
+
+    /// Shields observers from a source that does not follow the observer contract.
+    ///
+    /// Observers of the returned observable receive nothing after their
+    /// subscription was unsubscribed or dropped, and nothing after completion
+    /// or failure, even if the source keeps pushing.
+    fn safe(self) -> SafeObservable<Self> where Self: Sized {
+        SafeObservable::new(self)
+    }
+
+    /// Converts the observable into an iterator that blocks until values arrive.
+    ///
+    /// This subscribes to the observable. The iterator yields `Ok(item)` for
+    /// every value, and ends when the observable completes. If it fails, the
+    /// last element is `Err(error)`. See `BlockingIter` for how values are
+    /// buffered.
+    fn into_blocking_iter(mut self) -> BlockingIter<Self::Item, Self::Error, Self::Subscription>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        blocking::blocking_iter(|observer| self.subscribe(observer))
+    }
+
+    /// Converts the observable into a futures stream.
+    ///
+    /// The stream subscribes to the observable when it is first polled. It
+    /// yields `Ok(item)` for every value, and ends when the observable
+    /// completes. If it fails, the last item is `Err(error)`. Values are
+    /// queued until the stream is polled. The queue is unbounded; call
+    /// `bounded()` on the result to limit its capacity, and to configure
+    /// what happens when it is full.
+    ///
+    /// This method is only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    fn into_stream(self) -> ObservableStream<Self> where Self: Sized {
+        ObservableStream::new(self)
+    }
+
+    /// Converts the observable into a future of its first value.
+    ///
+    /// The future subscribes to the observable when it is first polled. It
+    /// resolves with `Ok(Some(item))` for the first value, `Ok(None)` if the
+    /// observable completes without values, or `Err(error)` if it fails.
+    ///
+    /// This method is only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    fn first_into_future(self) -> FirstFuture<Self> where Self: Sized {
+        FirstFuture::new(self)
+    }
+
+    /// Provides methods that block until the observable produces a result.
+    ///
+    /// For instance, `observable.blocking().last()` waits until the observable
+    /// completes, and returns its last value.
+    fn blocking(self) -> BlockingObservable<Self>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        BlockingObservable::new(self)
+    }
+
+    /// Subscribes to the observable, and returns all of its values once it
+    /// completes, or the error if it fails.
+    ///
+    /// **This panics if the observable has not completed or failed by the
+    /// time that `subscribe()` returns.** Observables that push later, for
+    /// instance through an `EventLoop`, cannot make progress while the current
+    /// thread waits, so this would block forever. To wait for a value that is
+    /// pushed from a different thread, use `blocking()` with a timeout instead.
+    fn collect_blocking(self) -> Result<Vec<Self::Item>, Self::Error>
+        where Self: Sized, Self::Item: 'static, Self::Error: 'static {
+        blocking::collect(self)
+    }
+
+    /// Erases the type of the observable.
+    ///
+    /// The operators return nested types that spell out the whole chain. The
+    /// boxed observable only names the item and error type, so observables
+    /// built in different ways can be stored in the same place.
+    fn boxed(self) -> BoxedObservable<Self::Item, Self::Error>
+        where Self: Sized + 'static,
+              Self::Item: 'static,
+              Self::Error: 'static,
+              Self::Subscription: 'static {
+        BoxedObservable::new(self)
+    }
+}
+
+impl<Source: Observable + ?Sized> ObservableExt for Source {}
+
+/// Observable implementation for mutable references to observables.
+///
+/// This makes it possible to apply an operator to an observable without giving
+/// it up. Subscribing subscribes to the referenced observable.
+impl<Source: Observable + ?Sized> Observable for &mut Source {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Source::Subscription
+        where O: Observer<Source::Item, Source::Error> + 'static {
+        (**self).subscribe(observer)
+    }
+
+    fn describe(&self) -> Graph {
+        (**self).describe()
+    }
 }
@@ -0,0 +1,707 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `observe_on()` and `observe_on_thread()` operators, which push values
+//! through a scheduler.
+
+use Notification;
+use describe::Graph;
+use metrics::{self, Gauge};
+use observable::Observable;
+use observer::{BoxedObserver, Observer};
+use scheduler::{Scheduler, SendScheduler};
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::{self, Rc};
+use std::sync::{self, Arc, Condvar, Mutex, MutexGuard};
+use subscription::Subscription;
+
+/// What `observe_on()`, `observe_on_thread()`, `into_stream()`, `into_pull()`
+/// and `pausable()` do with a value when their queue is full.
+///
+/// The type parameter is the error type of the observable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy<E> {
+    /// Blocks the thread that pushes the new value until the queue has room.
+    ///
+    /// Only `observe_on_thread()` can block. The other operators make room on
+    /// the thread that pushes, so waiting would never end; configuring them
+    /// to block panics.
+    Block,
+
+    /// Drops the new value.
+    DropNewest,
+
+    /// Drops the oldest value in the queue, to make room for the new value.
+    DropOldest,
+
+    /// Drops the new value, and fails with the error, as if the observable
+    /// failed at that point. Values after that are ignored.
+    Error(E),
+
+    /// Panics in the observer that pushes the new value.
+    Panic,
+}
+
+// Panics if the policy is to block, for operators that empty their queue on
+// the thread that fills it.
+pub fn assert_non_blocking<E>(policy: &OverflowPolicy<E>, operator: &str) {
+    if let OverflowPolicy::Block = *policy {
+        panic!("{}() cannot block, nothing would make room in its queue", operator);
+    }
+}
+
+/// The result of calling `observe_on()` on an observable.
+///
+/// Values, completion and failure of the source are queued, and they are
+/// pushed to the observer by an action on the scheduler. The queue is
+/// unbounded, unless `bounded()` limits the number of values it holds;
+/// completion and failure are always queued.
+///
+/// The action runs on the thread that the source pushes on, so a full queue
+/// cannot block the source until the observer catches up: the action could
+/// not run meanwhile. To slow down the source instead of losing values, use
+/// a pull observable, see `into_pull()`.
+pub struct ObserveOnObservable<Source: Observable, Sch> {
+    source: Source,
+    scheduler: Sch,
+    capacity: usize,
+    policy: OverflowPolicy<Source::Error>,
+    name: Option<Arc<String>>,
+}
+
+/// The result of calling `observe_on_thread()` on an observable.
+///
+/// This is like `observe_on()`, but the action that pushes to the observer
+/// runs on a thread of the scheduler, so the observer runs there, while the
+/// source keeps pushing on its own thread.
+///
+/// `Observable::subscribe()` does not require the observer to be `Send`, so
+/// this does not implement `Observable`. Subscribe with the
+/// [`subscribe()`](#method.subscribe) method instead.
+pub struct ObserveOnThreadObservable<Source: Observable, Sch> {
+    source: Source,
+    scheduler: Sch,
+    capacity: usize,
+    policy: OverflowPolicy<Source::Error>,
+    name: Option<Arc<String>>,
+}
+
+/// The result of subscribing to an `observe_on()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the notifications that have not been pushed yet.
+pub struct ObserveOnSubscription<Subs, T, E, SchSubs> {
+    subscription: Subs,
+    state: LocalState<T, E, SchSubs>,
+}
+
+/// The result of subscribing to an `observe_on_thread()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the notifications that have not been pushed yet. If the observer
+/// is being called on another thread meanwhile, it is dropped there when the
+/// call returns.
+pub struct ObserveOnThreadSubscription<Subs, T, E, SchSubs> {
+    subscription: Subs,
+    state: ThreadState<T, E, SchSubs>,
+}
+
+type LocalState<T, E, SchSubs> =
+    Rc<RefCell<ObserveOnState<T, E, Box<dyn BoxedObserver<T, E>>, SchSubs>>>;
+
+type ThreadState<T, E, SchSubs> = Arc<ThreadQueue<T, E, SchSubs>>;
+
+type SendObserveOnState<T, E, SchSubs> =
+    ObserveOnState<T, E, Box<dyn BoxedObserver<T, E> + Send>, SchSubs>;
+
+// The state of an `observe_on_thread()` subscription. The condition variable
+// is signalled whenever a value is taken out of the queue, or the
+// subscription is cancelled, to wake up a source that waits for room.
+struct ThreadQueue<T, E, SchSubs> {
+    state: Mutex<SendObserveOnState<T, E, SchSubs>>,
+    has_room: Condvar,
+}
+
+struct ObserveOnState<T, E, O, SchSubs> {
+    queue: VecDeque<Notification<T, E>>,
+
+    // The number of values in the queue, the most there have been, and the
+    // number of values that were dropped because it was full.
+    len: usize,
+    peak_len: usize,
+    dropped: usize,
+
+    // The name that the queue depth is reported under, if it is metered.
+    name: Option<Arc<String>>,
+
+    // The observer is taken out while it is being called, and it is gone for
+    // good after completion, failure, or cancellation.
+    observer: Option<O>,
+
+    is_terminated: bool,
+    is_cancelled: bool,
+
+    // Whether the observer received completion or failure.
+    is_done: bool,
+
+    // Whether a drain action is scheduled or running. Notifications that
+    // arrive meanwhile are picked up by that action.
+    is_draining: bool,
+    drain: Option<SchSubs>,
+}
+
+// What happened to a notification that was pushed into the queue.
+enum Enqueued<T, E> {
+    Queued { needs_drain: bool },
+
+    // The observer is gone, or the notification was dropped.
+    Ignored,
+
+    // The queue is full, and the policy is to wait for room.
+    Full(Notification<T, E>),
+}
+
+struct ObserveOnObserver<T, E, Sch: Scheduler> {
+    state: LocalState<T, E, Sch::Subscription>,
+    scheduler: Sch,
+    capacity: usize,
+    policy: OverflowPolicy<E>,
+}
+
+struct ObserveOnThreadObserver<T, E, Sch: SendScheduler> {
+    state: ThreadState<T, E, Sch::Subscription>,
+    scheduler: Sch,
+    capacity: usize,
+    policy: OverflowPolicy<E>,
+}
+
+impl<Source: Observable, Sch> ObserveOnObservable<Source, Sch> {
+    pub fn new(source: Source, scheduler: Sch) -> ObserveOnObservable<Source, Sch> {
+        ObserveOnObservable {
+            source: source,
+            scheduler: scheduler,
+            // The policy never applies to an unbounded queue.
+            capacity: usize::MAX,
+            policy: OverflowPolicy::DropNewest,
+            name: None,
+        }
+    }
+
+    /// Sets the capacity of the queue, and what happens when it is full.
+    ///
+    /// By default, the queue is unbounded. The observer is called on the
+    /// thread that fills the queue, so the policy cannot be to block; see
+    /// `observe_on_thread()` for that.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<Source::Error>)
+                   -> ObserveOnObservable<Source, Sch> {
+        assert_non_blocking(&policy, "observe_on");
+        self.capacity = capacity;
+        self.policy = policy;
+        self
+    }
+
+    /// Reports the length of the queue as the `QueueDepth` gauge under the name.
+    ///
+    /// The gauge is reported to the sink that was set with
+    /// `rx::set_metrics_sink()`, whenever a value is queued or pushed.
+    pub fn metered_queue<S: Into<String>>(mut self, name: S) -> ObserveOnObservable<Source, Sch> {
+        self.name = Some(Arc::new(name.into()));
+        self
+    }
+}
+
+impl<Source: Observable, Sch> ObserveOnThreadObservable<Source, Sch> {
+    pub fn new(source: Source, scheduler: Sch) -> ObserveOnThreadObservable<Source, Sch> {
+        ObserveOnThreadObservable {
+            source: source,
+            scheduler: scheduler,
+            // The policy never applies to an unbounded queue.
+            capacity: usize::MAX,
+            policy: OverflowPolicy::DropNewest,
+            name: None,
+        }
+    }
+
+    /// Sets the capacity of the queue, and what happens when it is full.
+    ///
+    /// By default, the queue is unbounded. With `OverflowPolicy::Block`, the
+    /// source waits until the observer has taken a value out of the queue.
+    /// The queue then holds at least one value, even with zero capacity.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<Source::Error>)
+                   -> ObserveOnThreadObservable<Source, Sch> {
+        self.capacity = capacity;
+        self.policy = policy;
+        self
+    }
+
+    /// Reports the length of the queue as the `QueueDepth` gauge under the name.
+    ///
+    /// See `ObserveOnObservable::metered_queue()`.
+    pub fn metered_queue<S: Into<String>>(mut self, name: S)
+                                          -> ObserveOnThreadObservable<Source, Sch> {
+        self.name = Some(Arc::new(name.into()));
+        self
+    }
+
+    /// Subscribes an observer and returns the subscription.
+    ///
+    /// This is the equivalent of `Observable::subscribe()`, for observers that
+    /// can be sent to other threads.
+    pub fn subscribe<T, E, O>(&mut self, observer: O)
+                              -> ObserveOnThreadSubscription<Source::Subscription, T, E,
+                                                             Sch::Subscription>
+        where Source: Observable<Item = T, Error = E>,
+              Sch: SendScheduler + Clone + 'static,
+              Sch::Subscription: Send + 'static,
+              T: Send + 'static,
+              E: Clone + Send + 'static,
+              O: Observer<T, E> + Send + 'static {
+        let boxed: Box<dyn BoxedObserver<T, E> + Send> = Box::new(observer);
+        let state = Arc::new(ThreadQueue {
+            state: Mutex::new(ObserveOnState::new(boxed, self.name.clone())),
+            has_room: Condvar::new(),
+        });
+        let observe_on_observer = ObserveOnThreadObserver {
+            state: state.clone(),
+            scheduler: self.scheduler.clone(),
+            capacity: self.capacity,
+            policy: self.policy.clone(),
+        };
+        ObserveOnThreadSubscription {
+            subscription: self.source.subscribe(observe_on_observer),
+            state: state,
+        }
+    }
+}
+
+impl<Source, Sch, T, E> Observable for ObserveOnObservable<Source, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = ObserveOnSubscription<Source::Subscription, T, E, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let boxed: Box<dyn BoxedObserver<T, E>> = Box::new(observer);
+        let state = Rc::new(RefCell::new(ObserveOnState::new(boxed, self.name.clone())));
+        let observe_on_observer = ObserveOnObserver {
+            state: state.clone(),
+            scheduler: self.scheduler.clone(),
+            capacity: self.capacity,
+            policy: self.policy.clone(),
+        };
+        ObserveOnSubscription {
+            subscription: self.source.subscribe(observe_on_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("observe_on", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, SchSubs> ObserveOnState<T, E, O, SchSubs> {
+    fn new(observer: O, name: Option<Arc<String>>) -> ObserveOnState<T, E, O, SchSubs> {
+        ObserveOnState {
+            queue: VecDeque::new(),
+            len: 0,
+            peak_len: 0,
+            dropped: 0,
+            name: name,
+            observer: Some(observer),
+            is_terminated: false,
+            is_cancelled: false,
+            is_done: false,
+            is_draining: false,
+            drain: None,
+        }
+    }
+
+    // Queues the notification, unless the observer is gone or the queue is
+    // full and the policy is to drop it.
+    fn enqueue(&mut self,
+               notification: Notification<T, E>,
+               capacity: usize,
+               policy: &OverflowPolicy<E>)
+               -> Enqueued<T, E>
+        where E: Clone {
+        if self.is_terminated || self.is_cancelled {
+            return Enqueued::Ignored;
+        }
+        let is_full = match notification {
+            Notification::Next(..) => match *policy {
+                // A blocking queue holds at least one value, or it could never make room.
+                OverflowPolicy::Block => self.len >= cmp::max(capacity, 1),
+                _ => self.len >= capacity,
+            },
+            _ => false,
+        };
+        let notification = if !is_full {
+            notification
+        } else {
+            match *policy {
+                OverflowPolicy::Block => return Enqueued::Full(notification),
+                OverflowPolicy::DropNewest => {
+                    self.dropped += 1;
+                    return Enqueued::Ignored;
+                }
+                OverflowPolicy::DropOldest => {
+                    self.drop_oldest();
+                    // With zero capacity, dropping the oldest value makes no room.
+                    if self.len >= capacity {
+                        self.dropped += 1;
+                        return Enqueued::Ignored;
+                    }
+                    notification
+                }
+                OverflowPolicy::Error(ref error) => {
+                    self.dropped += 1;
+                    Notification::Error(error.clone())
+                }
+                OverflowPolicy::Panic => panic!("observe_on queue is full"),
+            }
+        };
+        match notification {
+            Notification::Next(..) => {
+                self.len += 1;
+                self.peak_len = cmp::max(self.peak_len, self.len);
+            }
+            _ => self.is_terminated = true,
+        }
+        self.queue.push_back(notification);
+        let needs_drain = !self.is_draining;
+        self.is_draining = true;
+        Enqueued::Queued { needs_drain: needs_drain }
+    }
+
+    // Takes out the next notification and the observer, so the observer can
+    // be called without holding on to the state. Returns `None` when the
+    // drain action is done.
+    fn next(&mut self) -> Option<(Notification<T, E>, O)> {
+        let notification = match self.queue.pop_front() {
+            Some(Notification::Next(item)) => {
+                self.len -= 1;
+                Notification::Next(item)
+            }
+            Some(notification) => {
+                self.is_done = true;
+                notification
+            }
+            None => {
+                self.is_draining = false;
+                return None;
+            }
+        };
+        self.observer.take().map(|observer| (notification, observer))
+    }
+
+    // Puts the observer back after a value was pushed to it. Returns the
+    // observer if the subscription was cancelled meanwhile, to be dropped.
+    fn restore(&mut self, observer: O) -> Option<O> {
+        if self.is_cancelled {
+            Some(observer)
+        } else {
+            self.observer = Some(observer);
+            None
+        }
+    }
+
+    // Returns the queue length to report, if the queue is metered.
+    fn depth(&self) -> Option<(Arc<String>, usize)> {
+        self.name.as_ref().map(|name| (name.clone(), self.len))
+    }
+
+    fn drop_oldest(&mut self) {
+        let oldest = self.queue.iter()
+            .position(|notification| matches!(*notification, Notification::Next(..)));
+        if let Some(index) = oldest {
+            self.queue.remove(index);
+            self.len -= 1;
+            self.dropped += 1;
+        }
+    }
+
+    // Stops pushing, and returns what has to be dropped, in that order.
+    fn cancel(&mut self) -> (Option<SchSubs>, VecDeque<Notification<T, E>>, Option<O>) {
+        self.is_cancelled = true;
+        self.len = 0;
+        let queue = mem::take(&mut self.queue);
+        (self.drain.take(), queue, self.observer.take())
+    }
+}
+
+fn report_depth(depth: Option<(Arc<String>, usize)>) {
+    if let Some((name, len)) = depth {
+        metrics::gauge(&name, Gauge::QueueDepth, len as f64);
+    }
+}
+
+// Pushes the notification to the observer. Returns the observer if it can
+// receive more notifications.
+fn deliver<T, E, O: Observer<T, E>>(notification: Notification<T, E>, observer: O) -> Option<O> {
+    match notification {
+        Notification::Next(item) => {
+            let mut observer = observer;
+            observer.on_next(item);
+            Some(observer)
+        }
+        Notification::Completed => {
+            observer.on_completed();
+            None
+        }
+        Notification::Error(error) => {
+            observer.on_error(error);
+            None
+        }
+    }
+}
+
+// Pushes the queued notifications to the observer, until the queue is empty.
+fn drain<T, E, O, SchSubs>(state: rc::Weak<RefCell<ObserveOnState<T, E, O, SchSubs>>>)
+    where O: Observer<T, E> {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    loop {
+        // The state is not borrowed while the observer is called, so the
+        // observer can push into the source, or drop its subscription.
+        let (notification, observer, depth) = {
+            let mut state = state.borrow_mut();
+            match state.next() {
+                Some((notification, observer)) => (notification, observer, state.depth()),
+                None => return,
+            }
+        };
+        report_depth(depth);
+        if let Some(observer) = deliver(notification, observer) {
+            let cancelled = state.borrow_mut().restore(observer);
+            drop(cancelled);
+        }
+    }
+}
+
+impl<T, E, SchSubs> ThreadQueue<T, E, SchSubs> {
+    fn lock<'s>(&'s self) -> MutexGuard<'s, SendObserveOnState<T, E, SchSubs>> {
+        // The state is consistent even if an observer panicked while it was
+        // called, so the poisoning can be ignored.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// Pushes the queued notifications to the observer on the current thread,
+// until the queue is empty.
+fn drain_thread<T, E, SchSubs>(state: sync::Weak<ThreadQueue<T, E, SchSubs>>) {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    loop {
+        // The lock is not held while the observer is called, so the source
+        // can keep queueing meanwhile.
+        let (notification, observer, depth) = {
+            let mut locked = state.lock();
+            match locked.next() {
+                Some((notification, observer)) => (notification, observer, locked.depth()),
+                None => return,
+            }
+        };
+        state.has_room.notify_all();
+        report_depth(depth);
+        if let Some(observer) = deliver(notification, observer) {
+            let cancelled = state.lock().restore(observer);
+            drop(cancelled);
+        }
+    }
+}
+
+impl<T, E, Sch> ObserveOnObserver<T, E, Sch>
+where Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: Clone + 'static {
+    fn push(&self, notification: Notification<T, E>) {
+        let (needs_drain, depth) = {
+            let mut state = self.state.borrow_mut();
+            match state.enqueue(notification, self.capacity, &self.policy) {
+                Enqueued::Queued { needs_drain } => (needs_drain, state.depth()),
+                Enqueued::Ignored => return,
+                Enqueued::Full(..) => unreachable!("observe_on() cannot block"),
+            }
+        };
+        report_depth(depth);
+        if needs_drain {
+            // The scheduler might run the action immediately,
+            // so the state must not be borrowed here.
+            let state = Rc::downgrade(&self.state);
+            let drain = self.scheduler.schedule(move || drain(state));
+            self.state.borrow_mut().drain = Some(drain);
+        }
+    }
+}
+
+impl<T, E, Sch> ObserveOnThreadObserver<T, E, Sch>
+where Sch: SendScheduler,
+      Sch::Subscription: Send + 'static,
+      T: Send + 'static,
+      E: Clone + Send + 'static {
+    fn push(&self, notification: Notification<T, E>) {
+        let (needs_drain, depth) = {
+            let mut state = self.state.lock();
+            let mut notification = notification;
+            loop {
+                match state.enqueue(notification, self.capacity, &self.policy) {
+                    Enqueued::Queued { needs_drain } => break (needs_drain, state.depth()),
+                    Enqueued::Ignored => return,
+                    Enqueued::Full(returned) => {
+                        notification = returned;
+                        state = self.state.has_room.wait(state)
+                                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    }
+                }
+            }
+        };
+        report_depth(depth);
+        if needs_drain {
+            // The action might start on another thread right away,
+            // so the state must not be locked here.
+            let state = Arc::downgrade(&self.state);
+            let drain = self.scheduler.schedule(move || drain_thread(state));
+            self.state.lock().drain = Some(drain);
+        }
+    }
+}
+
+impl<T, E, Sch> Observer<T, E> for ObserveOnObserver<T, E, Sch>
+where Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: Clone + 'static {
+    fn on_next(&mut self, item: T) {
+        self.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.push(Notification::Error(error));
+    }
+}
+
+impl<T, E, Sch> Observer<T, E> for ObserveOnThreadObserver<T, E, Sch>
+where Sch: SendScheduler,
+      Sch::Subscription: Send + 'static,
+      T: Send + 'static,
+      E: Clone + Send + 'static {
+    fn on_next(&mut self, item: T) {
+        self.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.push(Notification::Error(error));
+    }
+}
+
+impl<Subs, T, E, SchSubs> ObserveOnSubscription<Subs, T, E, SchSubs> {
+    /// Returns the number of values that are queued, but not pushed yet.
+    pub fn queue_len(&self) -> usize {
+        self.state.borrow().len
+    }
+
+    /// Returns the largest number of values that have been queued at once.
+    ///
+    /// A peak that gets close to the capacity means that the observer does
+    /// not keep up with the source.
+    pub fn peak_queue_len(&self) -> usize {
+        self.state.borrow().peak_len
+    }
+
+    /// Returns the number of values that were dropped because the queue was full.
+    pub fn dropped_count(&self) -> usize {
+        self.state.borrow().dropped
+    }
+
+    fn cancel(&mut self) {
+        let cancelled = self.state.borrow_mut().cancel();
+        drop(cancelled);
+    }
+}
+
+impl<Subs, T, E, SchSubs> ObserveOnThreadSubscription<Subs, T, E, SchSubs> {
+    /// Returns the number of values that are queued, but not pushed yet.
+    pub fn queue_len(&self) -> usize {
+        self.state.lock().len
+    }
+
+    /// Returns the largest number of values that have been queued at once.
+    pub fn peak_queue_len(&self) -> usize {
+        self.state.lock().peak_len
+    }
+
+    /// Returns the number of values that were dropped because the queue was full.
+    pub fn dropped_count(&self) -> usize {
+        self.state.lock().dropped
+    }
+
+    fn cancel(&mut self) {
+        let cancelled = self.state.lock().cancel();
+        // A source that waits for room has to stop waiting.
+        self.state.has_room.notify_all();
+        drop(cancelled);
+    }
+}
+
+impl<Subs: Subscription, T, E, SchSubs> Subscription for ObserveOnSubscription<Subs, T, E, SchSubs> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.cancel();
+    }
+
+    fn is_active(&self) -> bool {
+        let state = self.state.borrow();
+        !state.is_cancelled && !state.is_done
+    }
+}
+
+impl<Subs, T, E, SchSubs> Subscription for ObserveOnThreadSubscription<Subs, T, E, SchSubs>
+    where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.cancel();
+    }
+
+    fn is_active(&self) -> bool {
+        let state = self.state.lock();
+        !state.is_cancelled && !state.is_done
+    }
+}
+
+impl<Subs, T, E, SchSubs> Drop for ObserveOnSubscription<Subs, T, E, SchSubs> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl<Subs, T, E, SchSubs> Drop for ObserveOnThreadSubscription<Subs, T, E, SchSubs> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
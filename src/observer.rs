@@ -17,6 +17,15 @@ pub trait Observer<T, E> {
 
     /// Notifies the observer that the provider experienced an error condition.
     fn on_error(self, error: E);
+
+    /// Moves the observer into a box, to use it as a trait object.
+    ///
+    /// The boxed observer is an observer itself, so it can be passed where a
+    /// generic observer is expected, and observers of different types can be
+    /// stored together.
+    fn boxed(self) -> Box<dyn BoxedObserver<T, E>> where Self: Sized + 'static {
+        Box::new(self)
+    }
 }
 
 pub struct NextObserver<FnNext> {
@@ -46,7 +55,7 @@ impl<T, E, FnNext> Observer<T, E> for NextObserver<FnNext>
     where E: Debug, FnNext: FnMut(T) {
 
     fn on_next(&mut self, item: T) {
-        self.fn_next.call_mut((item,));
+        (self.fn_next)(item);
     }
 
     fn on_completed(self) {
@@ -62,11 +71,11 @@ impl<T, E, FnNext, FnCompleted> Observer<T, E> for CompletedObserver<FnNext, FnC
     where E: Debug, FnNext: FnMut(T), FnCompleted: FnOnce() {
 
     fn on_next(&mut self, item: T) {
-        self.fn_next.call_mut((item,));
+        (self.fn_next)(item);
     }
 
     fn on_completed(self) {
-        self.fn_completed.call_once(());
+        (self.fn_completed)();
     }
 
     fn on_error(self, error: E) {
@@ -79,15 +88,15 @@ impl<T, E, FnNext, FnCompleted, FnError>
     where FnNext: FnMut(T), FnCompleted: FnOnce(), FnError: FnOnce(E) {
 
     fn on_next(&mut self, item: T) {
-        self.fn_next.call_mut((item,));
+        (self.fn_next)(item);
     }
 
     fn on_completed(self) {
-        self.fn_completed.call_once(());
+        (self.fn_completed)();
     }
 
     fn on_error(self, error: E) {
-        self.fn_error.call_once((error,));
+        (self.fn_error)(error);
     }
 }
 
@@ -95,11 +104,11 @@ impl<T, E, FnOption> Observer<T, E> for OptionObserver<FnOption>
     where E: Debug, FnOption: FnMut(Option<T>) {
 
     fn on_next(&mut self, item: T) {
-        self.fn_option.call_mut((Some(item),));
+        (self.fn_option)(Some(item));
     }
 
     fn on_completed(mut self) {
-        self.fn_option.call_mut((None,));
+        (self.fn_option)(None);
     }
 
     fn on_error(self, error: E) {
@@ -111,15 +120,15 @@ impl<T, E, FnResult> Observer<T, E> for ResultObserver<FnResult>
     where FnResult: FnMut(Result<Option<T>, E>) {
 
     fn on_next(&mut self, item: T) {
-        self.fn_result.call_mut((Ok(Some(item)),));
+        (self.fn_result)(Ok(Some(item)));
     }
 
     fn on_completed(mut self) {
-        self.fn_result.call_mut((Ok(None),));
+        (self.fn_result)(Ok(None));
     }
 
     fn on_error(mut self, error: E) {
-        self.fn_result.call_mut((Err(error),));
+        (self.fn_result)(Err(error));
     }
 }
 
@@ -147,3 +156,31 @@ impl<O, T, E> BoxedObserver<T, E> for O where O: Observer<T, E> {
         self.on_error(error);
     }
 }
+
+impl<'a, T, E> Observer<T, E> for Box<dyn BoxedObserver<T, E> + 'a> {
+    fn on_next(&mut self, item: T) {
+        (**self).on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.on_completed_box();
+    }
+
+    fn on_error(self, error: E) {
+        self.on_error_box(error);
+    }
+}
+
+impl<'a, T, E> Observer<T, E> for Box<dyn BoxedObserver<T, E> + Send + 'a> {
+    fn on_next(&mut self, item: T) {
+        (**self).on_next(item);
+    }
+
+    fn on_completed(self) {
+        self.on_completed_box();
+    }
+
+    fn on_error(self, error: E) {
+        self.on_error_box(error);
+    }
+}
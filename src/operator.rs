@@ -0,0 +1,244 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The state of operators that combine sources, scheduled actions, or queues.
+//!
+//! Such an operator keeps its state in an `Rc<RefCell<..>>`, which the
+//! observers of its sources, its scheduled actions, and its subscription
+//! share. The state must not be borrowed while user code runs, because that
+//! code can push into a source of the operator, or drop its subscription,
+//! which borrows the state again. Calling the observer runs user code, and
+//! so does dropping a subscription or a value.
+//!
+//! Therefore the observer is taken out of the state while it is called, and
+//! put back afterwards. Values that arrive while the observer is out, because
+//! it pushed into a source of the operator, are queued and pushed once it
+//! returns, like `Subject` does. When the operator completes or fails while
+//! the observer is out, the observer receives that after the queued values.
+//! Everything the operator lets go of when it is done is dropped after the
+//! borrow ends. The operator state implements `OperatorState`, and the
+//! functions in this module take care of the rest.
+
+use leak::Tracker;
+use observer::BoxedObserver;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
+
+/// The observer of an operator, which is taken out while it is called.
+pub struct ObserverSlot<T, E> {
+    // The observer is gone for good after completion, failure, or cancellation.
+    observer: Option<Box<dyn BoxedObserver<T, E>>>,
+
+    // Whether the observer received, or is about to receive, completion or
+    // failure, or the subscription was dropped. No values are pushed after that.
+    is_done: bool,
+
+    // Values that arrived while the observer was out, in order.
+    queue: VecDeque<T>,
+
+    // Set when the operator completed or failed while the observer was out.
+    pending: Option<Terminal<T, E>>,
+
+    // Lists the subscription in the leak registry until it is cancelled.
+    tracker: Tracker,
+}
+
+// How the observer ends.
+enum Terminal<T, E> {
+    // Completion, after a last value if there is one.
+    Completed(Option<T>),
+    Failed(E),
+}
+
+// The observer, unless it is out, and the queued values of a cancelled operator.
+type Cancelled<T, E> = (Option<Box<dyn BoxedObserver<T, E>>>, VecDeque<T>);
+
+/// An observer that is about to complete or fail.
+///
+/// Call `deliver()` once the state is no longer borrowed.
+pub struct Ending<T, E> {
+    observer: Box<dyn BoxedObserver<T, E>>,
+    terminal: Terminal<T, E>,
+}
+
+/// The state of an operator, with its observer in an `ObserverSlot`.
+pub trait OperatorState {
+    type Item;
+    type Error;
+
+    /// What the operator lets go of when it is done, like subscriptions,
+    /// scheduled actions, and queued values.
+    type Released;
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<Self::Item, Self::Error>;
+
+    /// Takes out what the operator lets go of when it is done.
+    fn release(&mut self) -> Self::Released;
+}
+
+impl<T, E> ObserverSlot<T, E> {
+    /// Creates the slot, and records the subscription of the given kind in
+    /// the leak registry, see the `debug` module.
+    pub fn new(kind: &'static str, observer: Box<dyn BoxedObserver<T, E>>) -> ObserverSlot<T, E> {
+        ObserverSlot {
+            observer: Some(observer),
+            is_done: false,
+            queue: VecDeque::new(),
+            pending: None,
+            tracker: Tracker::new(kind),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.is_done
+    }
+
+    /// Marks the operator as done. The observer completes after it receives
+    /// `last`, if that is a value.
+    pub fn complete(&mut self, last: Option<T>) -> Option<Ending<T, E>> {
+        self.end(Terminal::Completed(last))
+    }
+
+    /// Marks the operator as done. The observer fails with the error.
+    pub fn fail(&mut self, error: E) -> Option<Ending<T, E>> {
+        self.end(Terminal::Failed(error))
+    }
+
+    // Returns the ending, or holds on to it until the observer returns.
+    fn end(&mut self, terminal: Terminal<T, E>) -> Option<Ending<T, E>> {
+        if self.is_done {
+            return None;
+        }
+        self.is_done = true;
+        self.tracker.terminate();
+        match self.observer.take() {
+            Some(observer) => Some(Ending { observer: observer, terminal: terminal }),
+            None => {
+                self.pending = Some(terminal);
+                None
+            }
+        }
+    }
+
+    // Marks the operator as done, and returns the observer, if it is not
+    // out, and the queued values to drop.
+    fn cancel(&mut self) -> Cancelled<T, E> {
+        self.is_done = true;
+        self.pending = None;
+        self.tracker.untrack();
+        (self.observer.take(), mem::take(&mut self.queue))
+    }
+}
+
+impl<T, E> Ending<T, E> {
+    pub fn deliver(self) {
+        let mut observer = self.observer;
+        match self.terminal {
+            Terminal::Completed(last) => {
+                if let Some(item) = last {
+                    observer.on_next(item);
+                }
+                observer.on_completed_box();
+            }
+            Terminal::Failed(error) => observer.on_error_box(error),
+        }
+    }
+}
+
+// Puts back the observer after it was called, once it received the values
+// that were queued meanwhile. If the operator ended in the meantime, the
+// observer completes or fails now instead.
+fn restore<S>(state: &RefCell<S>, observer: Box<dyn BoxedObserver<S::Item, S::Error>>)
+    where S: OperatorState {
+    let mut observer = observer;
+    let pending = loop {
+        let item = {
+            let mut state = state.borrow_mut();
+            let slot = state.observer_mut();
+            match slot.queue.pop_front() {
+                Some(item) => item,
+                None if !slot.is_done => {
+                    slot.observer = Some(observer);
+                    return;
+                }
+                None => break slot.pending.take(),
+            }
+        };
+        observer.on_next(item);
+    };
+    if let Some(terminal) = pending {
+        Ending { observer: observer, terminal: terminal }.deliver();
+    }
+}
+
+/// Pushes the value to the observer, unless the operator is done. If the
+/// observer is out, the value is pushed once it returns.
+pub fn push<S: OperatorState>(state: &RefCell<S>, item: S::Item) {
+    let mut observer = {
+        let mut state = state.borrow_mut();
+        let slot = state.observer_mut();
+        if slot.is_done {
+            return;
+        }
+        match slot.observer.take() {
+            Some(observer) => observer,
+            None => {
+                slot.queue.push_back(item);
+                return;
+            }
+        }
+    };
+    observer.on_next(item);
+    restore(state, observer);
+}
+
+/// Completes the observer, unless the operator is done.
+pub fn complete<S: OperatorState>(state: &RefCell<S>) {
+    end(state, |slot| slot.complete(None));
+}
+
+/// Pushes a last value to the observer and completes it, unless the operator
+/// is done.
+pub fn complete_with<S: OperatorState>(state: &RefCell<S>, last: S::Item) {
+    end(state, |slot| slot.complete(Some(last)));
+}
+
+/// Fails the observer with the error, unless the operator is done.
+pub fn fail<S: OperatorState>(state: &RefCell<S>, error: S::Error) {
+    end(state, |slot| slot.fail(error));
+}
+
+fn end<S, F>(state: &RefCell<S>, f: F)
+    where S: OperatorState,
+          F: FnOnce(&mut ObserverSlot<S::Item, S::Error>) -> Option<Ending<S::Item, S::Error>> {
+    let (ending, released) = {
+        let mut state = state.borrow_mut();
+        if state.observer_mut().is_done {
+            return;
+        }
+        let ending = f(state.observer_mut());
+        (ending, state.release())
+    };
+    drop(released);
+    if let Some(ending) = ending {
+        ending.deliver();
+    }
+}
+
+/// Marks the operator as done, and drops the observer and what the operator
+/// lets go of.
+pub fn cancel<S: OperatorState>(state: &RefCell<S>) {
+    let ((observer, queue), released) = {
+        let mut state = state.borrow_mut();
+        let cancelled = state.observer_mut().cancel();
+        (cancelled, state.release())
+    };
+    drop(released);
+    drop(queue);
+    drop(observer);
+}
@@ -0,0 +1,141 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Observables of operating system events.
+//!
+//! This module is only available with the `signals` feature, on Unix.
+
+use Notification;
+use channel::{self, FromReceiverSubscription, TryReceive};
+use io::Error;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::TryRecvError;
+
+/// A signal that `signals()` can observe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Signal {
+    /// `SIGINT`, sent by Ctrl-C in a terminal.
+    Interrupt,
+
+    /// `SIGTERM`, the polite request to terminate.
+    Terminate,
+
+    /// `SIGHUP`, sent when the terminal closes, and often used to reload
+    /// configuration.
+    Hangup,
+
+    /// `SIGQUIT`, sent by Ctrl-\ in a terminal.
+    Quit,
+}
+
+impl Signal {
+    fn number(self) -> i32 {
+        match self {
+            Signal::Interrupt => SIGINT,
+            Signal::Terminate => SIGTERM,
+            Signal::Hangup => SIGHUP,
+            Signal::Quit => SIGQUIT,
+        }
+    }
+
+    fn from_number(number: i32) -> Option<Signal> {
+        match number {
+            SIGINT => Some(Signal::Interrupt),
+            SIGTERM => Some(Signal::Terminate),
+            SIGHUP => Some(Signal::Hangup),
+            SIGQUIT => Some(Signal::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// The result of calling `signals()`.
+pub struct SignalsObservable<Sch> {
+    signals: Vec<Signal>,
+    scheduler: Sch,
+}
+
+/// The signal handlers of a subscription, which are polled for signals.
+///
+/// Dropping this unregisters the handlers.
+pub struct SignalReceiver {
+    signals: RefCell<Option<Signals>>,
+
+    // Set when registering failed, until the error has been received.
+    error: RefCell<Option<io::Error>>,
+
+    // Signals that have arrived, but that have not been received yet.
+    pending: RefCell<VecDeque<Signal>>,
+}
+
+/// Creates an observable of the signals that the process receives.
+///
+/// Every subscription registers handlers for the signals, and an action on
+/// the scheduler pushes the signals as they arrive. Signals that arrive in
+/// quick succession may be pushed only once. The observable fails if the
+/// handlers cannot be registered. It does not complete.
+///
+/// While a handler is registered, the signal no longer has its default
+/// effect, so for instance Ctrl-C does not terminate the process. After the
+/// subscription is dropped, the signal is ignored, rather than having its
+/// default effect again.
+pub fn signals<Sch: Scheduler>(signals: &[Signal], scheduler: Sch) -> SignalsObservable<Sch> {
+    SignalsObservable {
+        signals: signals.to_vec(),
+        scheduler: scheduler,
+    }
+}
+
+impl TryReceive for SignalReceiver {
+    type Item = Notification<Signal, Error>;
+
+    fn try_receive(&self) -> Result<Self::Item, TryRecvError> {
+        if let Some(error) = self.error.borrow_mut().take() {
+            return Ok(Notification::Error(Arc::new(error)));
+        }
+        let mut pending = self.pending.borrow_mut();
+        if pending.is_empty() {
+            if let Some(ref mut signals) = *self.signals.borrow_mut() {
+                pending.extend(signals.pending().filter_map(Signal::from_number));
+            }
+        }
+        match pending.pop_front() {
+            Some(signal) => Ok(Notification::Next(signal)),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<Sch> Observable for SignalsObservable<Sch>
+where Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static {
+    type Item = Signal;
+    type Error = Error;
+    type Subscription = FromReceiverSubscription<SignalReceiver, Sch, Signal, Error>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Signal, Error> + 'static {
+        let (signals, error) = match Signals::new(self.signals.iter().map(|s| s.number())) {
+            Ok(signals) => (Some(signals), None),
+            Err(error) => (None, Some(error)),
+        };
+        let receiver = SignalReceiver {
+            signals: RefCell::new(signals),
+            error: RefCell::new(error),
+            pending: RefCell::new(VecDeque::new()),
+        };
+        channel::from_notification_receiver(receiver, self.scheduler.clone()).subscribe(observer)
+    }
+}
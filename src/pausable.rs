@@ -0,0 +1,268 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `pausable()` operator, which lets a control observable hold values back.
+
+use describe::Graph;
+use observable::Observable;
+use observe_on::{OverflowPolicy, assert_non_blocking};
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `pausable()` on an observable.
+///
+/// By default, values that the source pushes while paused are dropped. Call
+/// `buffered()` to keep them until delivery resumes instead.
+pub struct PausableObservable<Source: Observable, Control> {
+    source: Source,
+    control: Control,
+
+    // The buffer is used while paused, if there is one.
+    buffer: Option<(usize, OverflowPolicy<Source::Error>)>,
+}
+
+/// The result of subscribing to a `pausable()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the control observable, and it discards the buffered values.
+pub struct PausableSubscription<T, E, Subs, CtrlSubs> {
+    state: Rc<RefCell<PausableState<T, E, Subs, CtrlSubs>>>,
+}
+
+struct PausableState<T, E, Subs, CtrlSubs> {
+    observer: ObserverSlot<T, E>,
+
+    is_paused: bool,
+    queue: VecDeque<T>,
+
+    // Whether the source completed. The observer completes once the queue
+    // has been pushed.
+    is_completed: bool,
+
+    // Whether values are being pushed. Values and resumptions that arrive
+    // meanwhile are picked up by the loop that pushes.
+    is_draining: bool,
+
+    source: Option<Subs>,
+    control: Option<CtrlSubs>,
+}
+
+type PausableShared<T, E, Subs, CtrlSubs> = Rc<RefCell<PausableState<T, E, Subs, CtrlSubs>>>;
+
+struct PausableObserver<T, E, Subs, CtrlSubs> {
+    state: PausableShared<T, E, Subs, CtrlSubs>,
+    buffer: Option<(usize, OverflowPolicy<E>)>,
+}
+
+struct ControlObserver<T, E, Subs, CtrlSubs> {
+    state: PausableShared<T, E, Subs, CtrlSubs>,
+}
+
+impl<Source: Observable, Control> PausableObservable<Source, Control> {
+    pub fn new(source: Source, control: Control) -> PausableObservable<Source, Control> {
+        PausableObservable {
+            source: source,
+            control: control,
+            buffer: None,
+        }
+    }
+
+    /// Buffers the values that the source pushes while paused.
+    ///
+    /// The buffer holds at most `capacity` values, and `policy` determines
+    /// what happens when it is full. The buffered values are pushed when
+    /// delivery resumes. Nothing makes room while paused, so the policy
+    /// cannot be `Block`.
+    pub fn buffered(mut self, capacity: usize, policy: OverflowPolicy<Source::Error>)
+                    -> PausableObservable<Source, Control> {
+        assert_non_blocking(&policy, "pausable");
+        self.buffer = Some((capacity, policy));
+        self
+    }
+}
+
+impl<Source, Control, T, E> Observable for PausableObservable<Source, Control>
+where Source: Observable<Item = T, Error = E>,
+      Source::Subscription: 'static,
+      Control: Observable<Item = bool>,
+      Control::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = PausableSubscription<T, E, Source::Subscription, Control::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(PausableState {
+            observer: ObserverSlot::new("pausable subscription", observer.boxed()),
+            is_paused: false,
+            queue: VecDeque::new(),
+            is_completed: false,
+            is_draining: false,
+            source: None,
+            control: None,
+        }));
+
+        // The control observable is subscribed to first, so a control that
+        // pushes upon subscription determines whether the first values pass.
+        let control = self.control.subscribe(ControlObserver { state: state.clone() });
+        state.borrow_mut().control = Some(control);
+
+        let pausable_observer = PausableObserver {
+            state: state.clone(),
+            buffer: self.buffer.clone(),
+        };
+        let source = self.source.subscribe(pausable_observer);
+        let (source, control) = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                (Some(source), state.control.take())
+            } else {
+                state.source = Some(source);
+                (None, None)
+            }
+        };
+        drop(source);
+        drop(control);
+        PausableSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.control.describe()];
+        Graph::operator::<Self>("pausable", sources)
+    }
+}
+
+impl<T, E, Subs, CtrlSubs> OperatorState for PausableState<T, E, Subs, CtrlSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (VecDeque<T>, Option<Subs>, Option<CtrlSubs>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (VecDeque<T>, Option<Subs>, Option<CtrlSubs>) {
+        (mem::take(&mut self.queue), self.source.take(), self.control.take())
+    }
+}
+
+// Pushes the queued values to the observer, until the queue is empty or
+// delivery is paused.
+fn drain<T, E, Subs, CtrlSubs>(state: &RefCell<PausableState<T, E, Subs, CtrlSubs>>) {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_draining || state.observer.is_done() {
+            return;
+        }
+        state.is_draining = true;
+    }
+    loop {
+        let item = {
+            let mut state = state.borrow_mut();
+            if state.is_paused || state.observer.is_done() {
+                state.is_draining = false;
+                return;
+            }
+            match state.queue.pop_front() {
+                Some(item) => item,
+                None if state.is_completed => break,
+                None => {
+                    state.is_draining = false;
+                    return;
+                }
+            }
+        };
+        operator::push(state, item);
+    }
+    operator::complete(state);
+}
+
+impl<T, E: Clone, Subs, CtrlSubs> Observer<T, E> for PausableObserver<T, E, Subs, CtrlSubs> {
+    fn on_next(&mut self, item: T) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if state.is_paused {
+                let (capacity, policy) = match self.buffer {
+                    Some((capacity, ref policy)) => (capacity, policy),
+                    None => return,
+                };
+                if state.queue.len() >= capacity {
+                    match *policy {
+                        OverflowPolicy::DropNewest => return,
+                        OverflowPolicy::DropOldest => {
+                            // With zero capacity, dropping the oldest value makes no room.
+                            if state.queue.pop_front().is_none() {
+                                return;
+                            }
+                        }
+                        OverflowPolicy::Error(ref error) => {
+                            let error = error.clone();
+                            drop(state);
+                            drop(item);
+                            operator::fail(&self.state, error);
+                            return;
+                        }
+                        OverflowPolicy::Panic => panic!("pausable buffer is full"),
+                        OverflowPolicy::Block => unreachable!(),
+                    }
+                }
+            }
+            state.queue.push_back(item);
+        }
+        drain(&self.state);
+    }
+
+    fn on_completed(self) {
+        self.state.borrow_mut().is_completed = true;
+        drain(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Subs, CtrlSubs, F> Observer<bool, F> for ControlObserver<T, E, Subs, CtrlSubs> {
+    fn on_next(&mut self, is_running: bool) {
+        self.state.borrow_mut().is_paused = !is_running;
+        drain(&self.state);
+    }
+
+    fn on_completed(self) {
+        // Delivery stays paused or running, as the last value determined.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the control is ignored, like its completion.
+    }
+}
+
+impl<T, E, Subs, CtrlSubs> Subscription for PausableSubscription<T, E, Subs, CtrlSubs> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<T, E, Subs, CtrlSubs> Drop for PausableSubscription<T, E, Subs, CtrlSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
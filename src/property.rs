@@ -0,0 +1,364 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Properties, values that can be observed as they change.
+
+use NoError;
+use observable::ObservableExt;
+use observer::Observer;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::mem;
+use std::rc::{Rc, Weak};
+use subject::{BehaviorSubject, BehaviorSubjectObservable};
+use subscription::Subscription;
+
+/// A value that can be observed as it changes.
+///
+/// A property is a cell for application state: it always has a value, and
+/// `observe()` returns an observable that pushes the current value upon
+/// subscription, and every new value afterwards. Setting a property to a
+/// value that equals the current one does not push anything, so observers
+/// only see actual changes.
+pub struct Property<T> {
+    subject: BehaviorSubject<T, NoError>,
+}
+
+impl<T: Clone + PartialEq> Property<T> {
+    /// Creates a property with the initial value.
+    pub fn new(initial: T) -> Property<T> {
+        Property {
+            subject: BehaviorSubject::new(initial),
+        }
+    }
+
+    /// Returns the current value.
+    pub fn get(&self) -> T {
+        self.subject.value().clone()
+    }
+
+    /// Sets the value, and pushes it to observers if it differs from the current value.
+    ///
+    /// Computed properties that depend on this property are recomputed
+    /// before this returns, or at the end of the batch, see `batch()`.
+    pub fn set(&mut self, value: T) {
+        if *self.subject.value() != value {
+            batch(|| self.subject.on_next(value));
+        }
+    }
+
+    /// Changes the value in place, and pushes it to observers if it changed.
+    ///
+    /// The function receives a copy of the current value, so observers
+    /// never see the value halfway through a change.
+    pub fn modify<F: FnOnce(&mut T)>(&mut self, f: F) {
+        let mut value = self.get();
+        f(&mut value);
+        self.set(value);
+    }
+
+    /// Returns an observable of the value.
+    ///
+    /// Upon subscription, an observer receives the current value, followed
+    /// by every change. The observable never completes.
+    pub fn observe(&self) -> BehaviorSubjectObservable<T, NoError> {
+        self.subject.observable()
+    }
+}
+
+/// A property that can be read and observed, but not set.
+///
+/// This is implemented by `Property` and `Computed`, so both can be inputs
+/// of a computed property, see `computed()`.
+pub trait ReadProperty {
+    /// The type of the value.
+    type Value: Clone + PartialEq + 'static;
+
+    /// Returns the current value.
+    fn get(&self) -> Self::Value;
+
+    /// Returns an observable of the current value and every change.
+    fn observe(&self) -> BehaviorSubjectObservable<Self::Value, NoError>;
+
+    /// Returns the number of computed properties in the longest chain of
+    /// properties that this property depends on, including itself.
+    ///
+    /// This is 0 for a property that does not depend on other properties.
+    /// Pending computed properties are recomputed in order of rank, so a
+    /// computed property is recomputed after all of its inputs.
+    fn rank(&self) -> usize;
+}
+
+impl<T: Clone + PartialEq + 'static> ReadProperty for Property<T> {
+    type Value = T;
+
+    fn get(&self) -> T {
+        Property::get(self)
+    }
+
+    fn observe(&self) -> BehaviorSubjectObservable<T, NoError> {
+        Property::observe(self)
+    }
+
+    fn rank(&self) -> usize {
+        0
+    }
+}
+
+/// A read-only property whose value is computed from other properties.
+///
+/// See `computed()`. A computed property stops updating when one of its
+/// inputs is dropped.
+pub struct Computed<T> {
+    core: Rc<ComputedCore<T>>,
+}
+
+struct ComputedCore<T> {
+    subject: BehaviorSubject<T, NoError>,
+    compute: Box<dyn Fn() -> T>,
+    rank: usize,
+
+    // Whether the property is queued for recomputation in the current batch.
+    is_pending: Cell<bool>,
+
+    // The subscriptions to the inputs, which update the latest input values.
+    subscriptions: RefCell<Vec<Box<dyn Subscription>>>,
+}
+
+// A computed property that can be queued for recomputation.
+trait Recompute {
+    fn recompute(&self);
+
+    // Called instead of `recompute()` when the batch is abandoned after a panic.
+    fn abandon(&self);
+}
+
+struct Batch {
+    depth: usize,
+
+    // The computed properties to recompute at the end of the batch, in order
+    // of rank, and then in the order in which they were queued.
+    pending: BTreeMap<(usize, u64), Rc<dyn Recompute>>,
+    next_sequence: u64,
+}
+
+thread_local! {
+    static BATCH: RefCell<Batch> = RefCell::new(Batch {
+        depth: 0,
+        pending: BTreeMap::new(),
+        next_sequence: 0,
+    })
+}
+
+// Ends the batch when dropped, also when the closure or an observer panics.
+struct Batching;
+
+impl Drop for Batching {
+    fn drop(&mut self) {
+        let abandoned = BATCH.with(|batch| {
+            let mut batch = batch.borrow_mut();
+            batch.depth -= 1;
+            // After a panic in the outermost batch, the pending recomputations
+            // are abandoned. Otherwise, there are none left at this point.
+            if batch.depth == 0 {
+                mem::take(&mut batch.pending)
+            } else {
+                BTreeMap::new()
+            }
+        });
+        for job in abandoned.values() {
+            job.abandon();
+        }
+    }
+}
+
+/// Runs the closure, and recomputes the affected computed properties once at the end.
+///
+/// Every `Property::set()` is a batch of its own. When several properties
+/// are set inside `batch()`, the computed properties that depend on them
+/// are recomputed only once, when the outermost batch ends, and their
+/// observers never see a value that was computed from some of the new
+/// inputs but not others. Until then, computed properties return their
+/// old value.
+pub fn batch<R, F: FnOnce() -> R>(f: F) -> R {
+    let is_outermost = BATCH.with(|batch| {
+        let mut batch = batch.borrow_mut();
+        batch.depth += 1;
+        batch.depth == 1
+    });
+    let _batching = Batching;
+    let result = f();
+    if is_outermost {
+        // The batch stays open while recomputing, so the changes that a
+        // recomputation pushes queue their dependents, rather than
+        // recomputing them right away.
+        loop {
+            let next = BATCH.with(|batch| batch.borrow_mut().pending.pop_first());
+            match next {
+                Some((_, job)) => job.recompute(),
+                None => break,
+            }
+        }
+    }
+    result
+}
+
+// Queues the computed property for recomputation, unless it is queued already.
+fn invalidate<T: Clone + PartialEq + 'static>(core: &Weak<ComputedCore<T>>) {
+    let core = match core.upgrade() {
+        Some(core) => core,
+        None => return,
+    };
+    if core.is_pending.get() {
+        return;
+    }
+    core.is_pending.set(true);
+    // If the change was not pushed in a batch, this batch recomputes right away.
+    batch(|| BATCH.with(|batch| {
+        let mut batch = batch.borrow_mut();
+        let sequence = batch.next_sequence;
+        batch.next_sequence += 1;
+        batch.pending.insert((core.rank, sequence), core.clone());
+    }));
+}
+
+impl<T: Clone + PartialEq> Recompute for ComputedCore<T> {
+    fn recompute(&self) {
+        self.is_pending.set(false);
+        let value = (self.compute)();
+        if *self.subject.value() != value {
+            // The subject is not borrowed while it pushes, so observers can
+            // read the property.
+            self.subject.sink().on_next(value);
+        }
+    }
+
+    fn abandon(&self) {
+        self.is_pending.set(false);
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> Computed<T> {
+    // Creates a computed property of the given rank.
+    //
+    // The subscriptions to the inputs must be added to the property afterwards.
+    // They push the current values upon subscription, which must not queue
+    // a recomputation, so the property is pending until `start()` is called.
+    fn new(rank: usize, compute: Box<dyn Fn() -> T>) -> Computed<T> {
+        let initial = compute();
+        Computed {
+            core: Rc::new(ComputedCore {
+                subject: BehaviorSubject::new(initial),
+                compute: compute,
+                rank: rank,
+                is_pending: Cell::new(true),
+                subscriptions: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    fn start(self, subscriptions: Vec<Box<dyn Subscription>>) -> Computed<T> {
+        *self.core.subscriptions.borrow_mut() = subscriptions;
+        self.core.is_pending.set(false);
+        self
+    }
+
+    /// Returns the current value.
+    pub fn get(&self) -> T {
+        self.core.subject.value().clone()
+    }
+
+    /// Returns an observable of the value.
+    ///
+    /// Upon subscription, an observer receives the current value, followed
+    /// by every change. A recomputation that results in the same value as
+    /// before is not pushed.
+    pub fn observe(&self) -> BehaviorSubjectObservable<T, NoError> {
+        self.core.subject.observable()
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> ReadProperty for Computed<T> {
+    type Value = T;
+
+    fn get(&self) -> T {
+        Computed::get(self)
+    }
+
+    fn observe(&self) -> BehaviorSubjectObservable<T, NoError> {
+        Computed::observe(self)
+    }
+
+    fn rank(&self) -> usize {
+        self.core.rank
+    }
+}
+
+/// The inputs of a computed property: a tuple of references to properties.
+pub trait ComputedInputs<F, T> {
+    /// Returns a property that is computed from the inputs with the function.
+    fn computed(self, f: F) -> Computed<T>;
+}
+
+/// Returns a read-only property that is computed from other properties.
+///
+/// The inputs are a tuple of references to properties, and the function
+/// receives a reference to the value of every input:
+///
+/// ```
+/// use rx::{Property, computed};
+/// let mut width = Property::new(2);
+/// let mut height = Property::new(3);
+/// let area = computed((&width, &height), |w, h| w * h);
+/// assert_eq!(6, area.get());
+/// width.set(5);
+/// assert_eq!(15, area.get());
+/// ```
+///
+/// The property is recomputed when an input changes. When several inputs
+/// change in one batch, see `batch()`, it is recomputed only once. Computed
+/// properties can be inputs of computed properties too. If an input changes,
+/// every computed property that depends on it, directly or indirectly, is
+/// recomputed once, after all of its own inputs have been recomputed.
+pub fn computed<I: ComputedInputs<F, T>, F, T>(inputs: I, f: F) -> Computed<T> {
+    inputs.computed(f)
+}
+
+macro_rules! impl_computed_inputs {
+    ($($input:ident . $index:tt),+) => {
+        impl<'a, T, F, $($input),+> ComputedInputs<F, T> for ($(&'a $input,)+)
+        where T: Clone + PartialEq + 'static,
+              F: Fn($(&$input::Value),+) -> T + 'static,
+              $($input: ReadProperty),+ {
+            fn computed(self, f: F) -> Computed<T> {
+                let rank = 1 + [$(self.$index.rank()),+].iter().cloned().max().unwrap();
+                let latest = Rc::new(RefCell::new(($(self.$index.get(),)+)));
+                let latest_compute = latest.clone();
+                let computed = Computed::new(rank, Box::new(move || {
+                    let latest = latest_compute.borrow();
+                    f($(&latest.$index),+)
+                }));
+                let mut subscriptions: Vec<Box<dyn Subscription>> = Vec::new();
+                $(
+                    let latest_input = latest.clone();
+                    let core = Rc::downgrade(&computed.core);
+                    let subscription = self.$index.observe().subscribe_next(move |value| {
+                        latest_input.borrow_mut().$index = value;
+                        invalidate(&core);
+                    });
+                    subscriptions.push(Box::new(subscription));
+                )+
+                computed.start(subscriptions)
+            }
+        }
+    }
+}
+
+impl_computed_inputs!(A.0);
+impl_computed_inputs!(A.0, B.1);
+impl_computed_inputs!(A.0, B.1, C.2);
+impl_computed_inputs!(A.0, B.1, C.2, D.3);
@@ -0,0 +1,863 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Observables that push values only when the observer requests them.
+//!
+//! A plain observable pushes as fast as its source produces, so a slow
+//! observer behind a queue makes the queue grow. A pull observable pushes
+//! only as many values as the observer requested, and bridges to and from
+//! plain observables make the point where values can be lost explicit.
+
+use {NoError, Notification};
+use describe::{self, Graph, Node};
+use observable::Observable;
+use observe_on::{OverflowPolicy, assert_non_blocking};
+use observer::{BoxedObserver, Observer};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::{Rc, Weak};
+use subscription::Subscription;
+
+/// A subscription through which the observer requests values.
+pub trait PullSubscription: Subscription {
+    /// Requests `n` more values.
+    ///
+    /// The source pushes at most as many values as have been requested in
+    /// total. Completion and failure are pushed without a request. When this
+    /// is called from within the observer, the values are pushed after the
+    /// observer returns.
+    fn request(&self, n: usize);
+}
+
+/// A stream of values that are pushed only on request.
+///
+/// This is like `Observable`, but after subscription, nothing is pushed
+/// until the observer requests values through `request()` on the
+/// subscription. A slow observer can request a value at a time, so a fast
+/// source cannot flood it.
+pub trait PullObservable {
+    /// The value produced by the observable.
+    type Item: Clone;
+
+    /// The error produced if the observable fails.
+    type Error: Clone;
+
+    /// The result of subscribing an observer.
+    type Subscription: PullSubscription;
+
+    /// Subscribes an observer and returns the subscription.
+    ///
+    /// The observer receives no values until they are requested through the
+    /// subscription. Dropping the subscription prevents further calls on the
+    /// observer.
+    fn subscribe_pull<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static;
+
+    /// Returns the graph of the pipeline that ends in this pull observable.
+    ///
+    /// This is the counterpart of `Observable::describe()`.
+    fn describe(&self) -> Graph {
+        let node = Node::new::<Self::Item, Self::Error>(&describe::type_name::<Self>());
+        Graph::new(node, Vec::new())
+    }
+
+    /// Converts the pull observable into an observable that requests values itself.
+    ///
+    /// Every subscription requests `batch` values upon subscription, and
+    /// `batch` more values every time that the previous batch has been pushed.
+    /// With `usize::MAX`, everything is requested at once.
+    fn into_push(self, batch: usize) -> PushObservable<Self> where Self: Sized {
+        PushObservable::new(self, batch)
+    }
+
+    /// Merges two pull observables that are sorted into one that is sorted.
+    ///
+    /// Both observables must push their values in the order of `compare`.
+    /// Every subscription requests a value at a time from both, and pushes
+    /// the smaller one on request, so at most one value per side is buffered.
+    /// Of equal values, the one of the current observable goes first. The
+    /// result completes when both complete, and a failure of either is
+    /// pushed right away. To keep using `other`, pass `&mut other`.
+    fn merge_sorted<Other, F>(self, other: Other, compare: F)
+                              -> MergeSortedObservable<Self, Other, F>
+        where Self: Sized,
+              Other: PullObservable<Item = Self::Item, Error = Self::Error>,
+              F: Fn(&Self::Item, &Self::Item) -> Ordering {
+        MergeSortedObservable::new(self, other, compare)
+    }
+}
+
+/// Pull observable implementation for mutable references to pull observables.
+///
+/// This makes it possible to apply an operator to a pull observable without
+/// giving it up. Subscribing subscribes to the referenced pull observable.
+impl<Source: PullObservable + ?Sized> PullObservable for &mut Source {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe_pull<O>(&mut self, observer: O) -> Source::Subscription
+        where O: Observer<Source::Item, Source::Error> + 'static {
+        (**self).subscribe_pull(observer)
+    }
+
+    fn describe(&self) -> Graph {
+        (**self).describe()
+    }
+}
+
+/// The result of calling `pull_iter()`.
+pub struct PullIter<I> {
+    iter: I,
+}
+
+/// The result of subscribing to a `pull_iter()` observable.
+pub struct PullIterSubscription<I: Iterator> {
+    state: DemandShared<I::Item, NoError, IterProducer<I>>,
+}
+
+/// The result of calling `into_pull()` or an `on_backpressure_*()` operator on
+/// an observable.
+///
+/// Values that the observer did not request yet are queued. The queue holds
+/// at most `capacity` values beyond the demand, and values that are dropped
+/// because it is full are passed to `F`.
+pub struct IntoPullObservable<Source: Observable, F> {
+    source: Source,
+    capacity: usize,
+    policy: OverflowPolicy<Source::Error>,
+
+    // Every subscription shares the function.
+    on_drop: Rc<F>,
+}
+
+/// The result of subscribing to an `into_pull()` or `on_backpressure_*()`
+/// observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the values that have not been requested yet.
+pub struct IntoPullSubscription<Subs, T, E> {
+    subscription: Subs,
+    state: DemandShared<T, E, QueueProducer<T, E>>,
+}
+
+/// The result of calling `into_push()` on a pull observable.
+pub struct PushObservable<Source> {
+    source: Source,
+    batch: usize,
+}
+
+/// The result of subscribing to an `into_push()` observable.
+pub struct PushSubscription<Subs> {
+    // The observer requests through the subscription, so it is shared.
+    subscription: Rc<RefCell<Option<Subs>>>,
+}
+
+/// The result of calling `merge_sorted()` on a pull observable.
+pub struct MergeSortedObservable<Source, Other, F> {
+    source: Source,
+    other: Other,
+
+    // Every subscription shares the function.
+    compare: Rc<F>,
+}
+
+/// The result of subscribing to a `merge_sorted()` observable.
+///
+/// Dropping this subscription drops the subscriptions to both pull
+/// observables, and it discards the values that have not been pushed yet.
+pub struct MergeSortedSubscription<Subs, OtherSubs, T, E, F> {
+    state: DemandShared<T, E, MergeProducer<T, E, F>>,
+    sources: Rc<MergeSources<Subs, OtherSubs>>,
+}
+
+/// Creates a pull observable of the values of an iterator.
+///
+/// Every subscription iterates over a clone of the iterator, and takes the
+/// next value only when it was requested. The observable completes when the
+/// iterator is exhausted, and it does not fail.
+pub fn pull_iter<I>(iter: I) -> PullIter<I::IntoIter>
+    where I: IntoIterator, I::IntoIter: Clone, I::Item: Clone {
+    PullIter {
+        iter: iter.into_iter(),
+    }
+}
+
+// Produces the notifications of a pull subscription.
+trait Produce<T, E> {
+    // Returns the next notification, or `None` if there is nothing to push
+    // for now. Values are only returned when there is demand.
+    fn produce(&mut self, has_demand: bool) -> Option<Notification<T, E>>;
+}
+
+struct IterProducer<I> {
+    iter: I,
+}
+
+struct QueueProducer<T, E> {
+    values: VecDeque<T>,
+
+    // Completion or failure, once the source pushed it.
+    terminal: Option<Notification<T, E>>,
+}
+
+struct DemandState<T, E, P> {
+    producer: P,
+
+    // The observer is taken out while it is being called, and it is gone for
+    // good after completion, failure, or cancellation.
+    observer: Option<Box<dyn BoxedObserver<T, E>>>,
+
+    // The number of values that were requested but not pushed yet.
+    requested: usize,
+
+    is_done: bool,
+
+    // Whether notifications are being pushed. Requests that arrive meanwhile
+    // are picked up by the loop that pushes.
+    is_draining: bool,
+}
+
+type DemandShared<T, E, P> = Rc<RefCell<DemandState<T, E, P>>>;
+
+impl<I: Iterator> Produce<I::Item, NoError> for IterProducer<I> {
+    fn produce(&mut self, has_demand: bool) -> Option<Notification<I::Item, NoError>> {
+        if !has_demand {
+            return None;
+        }
+        match self.iter.next() {
+            Some(item) => Some(Notification::Next(item)),
+            None => Some(Notification::Completed),
+        }
+    }
+}
+
+// One of the sorted observables of `merge_sorted()`.
+struct MergeSide<T> {
+    // The next value of this side, once it arrived.
+    head: Option<T>,
+
+    is_completed: bool,
+
+    // Whether a value was requested that did not arrive yet.
+    is_requested: bool,
+}
+
+struct MergeProducer<T, E, F> {
+    left: MergeSide<T>,
+    right: MergeSide<T>,
+    compare: Rc<F>,
+
+    // Failure of either side, once it was pushed.
+    error: Option<E>,
+}
+
+// The subscriptions are set once `subscribe_pull()` returns. They are shared
+// so a request can be made without borrowing, because a source that pushes
+// right away may cause another request.
+struct MergeSources<Subs, OtherSubs> {
+    source: RefCell<Option<Rc<Subs>>>,
+    other: RefCell<Option<Rc<OtherSubs>>>,
+}
+
+struct MergeSortedObserver<T, E, F, Subs, OtherSubs> {
+    state: DemandShared<T, E, MergeProducer<T, E, F>>,
+
+    // The subscriptions own the observers, so these are not owned in turn.
+    sources: Weak<MergeSources<Subs, OtherSubs>>,
+
+    is_left: bool,
+}
+
+impl<T, E> Produce<T, E> for QueueProducer<T, E> {
+    fn produce(&mut self, has_demand: bool) -> Option<Notification<T, E>> {
+        if has_demand {
+            if let Some(item) = self.values.pop_front() {
+                return Some(Notification::Next(item));
+            }
+        }
+        if self.values.is_empty() {
+            self.terminal.take()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> MergeSide<T> {
+    fn new() -> MergeSide<T> {
+        MergeSide {
+            head: None,
+            is_completed: false,
+            is_requested: false,
+        }
+    }
+
+    // Returns whether a value should be requested, and records that it was.
+    fn claim_request(&mut self) -> bool {
+        let needs_request = self.head.is_none() && !self.is_completed && !self.is_requested;
+        if needs_request {
+            self.is_requested = true;
+        }
+        needs_request
+    }
+}
+
+impl<T, E, F> MergeProducer<T, E, F> {
+    fn side(&mut self, is_left: bool) -> &mut MergeSide<T> {
+        if is_left { &mut self.left } else { &mut self.right }
+    }
+}
+
+impl<T, E, F> Produce<T, E> for MergeProducer<T, E, F> where F: Fn(&T, &T) -> Ordering {
+    fn produce(&mut self, has_demand: bool) -> Option<Notification<T, E>> {
+        if let Some(error) = self.error.take() {
+            return Some(Notification::Error(error));
+        }
+        // A side without a value holds up the other, until it completes.
+        let is_left = match (&self.left.head, &self.right.head) {
+            (Some(left), Some(right)) => (self.compare)(left, right) != Ordering::Greater,
+            (Some(_), None) if self.right.is_completed => true,
+            (None, Some(_)) if self.left.is_completed => false,
+            (None, None) if self.left.is_completed && self.right.is_completed => {
+                return Some(Notification::Completed);
+            }
+            _ => return None,
+        };
+        if !has_demand {
+            return None;
+        }
+        self.side(is_left).head.take().map(Notification::Next)
+    }
+}
+
+fn new_state<T, E, P>(producer: P, observer: Box<dyn BoxedObserver<T, E>>)
+                      -> DemandShared<T, E, P> {
+    Rc::new(RefCell::new(DemandState {
+        producer: producer,
+        observer: Some(observer),
+        requested: 0,
+        is_done: false,
+        is_draining: false,
+    }))
+}
+
+fn request<T, E, P>(state: &RefCell<DemandState<T, E, P>>, n: usize) where P: Produce<T, E> {
+    {
+        let mut state = state.borrow_mut();
+        state.requested = state.requested.saturating_add(n);
+    }
+    drain(state);
+}
+
+// Pushes notifications to the observer, as long as there is demand.
+fn drain<T, E, P>(state: &RefCell<DemandState<T, E, P>>) where P: Produce<T, E> {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_draining || state.is_done {
+            return;
+        }
+        state.is_draining = true;
+    }
+    loop {
+        // The state is not borrowed while the observer is called, so the
+        // observer can request more values, or drop its subscription.
+        let (notification, observer) = {
+            let mut state = state.borrow_mut();
+            let has_demand = state.requested > 0;
+            let notification = match state.producer.produce(has_demand) {
+                Some(notification) => notification,
+                None => {
+                    state.is_draining = false;
+                    return;
+                }
+            };
+            if let Notification::Next(..) = notification {
+                state.requested -= 1;
+            }
+            match state.observer.take() {
+                Some(observer) => (notification, observer),
+                None => return,
+            }
+        };
+        match notification {
+            Notification::Next(item) => {
+                let mut observer = observer;
+                observer.on_next(item);
+                let mut state = state.borrow_mut();
+                if !state.is_done {
+                    state.observer = Some(observer);
+                }
+            }
+            Notification::Completed => {
+                state.borrow_mut().is_done = true;
+                observer.on_completed_box();
+            }
+            Notification::Error(error) => {
+                state.borrow_mut().is_done = true;
+                observer.on_error_box(error);
+            }
+        }
+    }
+}
+
+fn cancel<T, E, P>(state: &RefCell<DemandState<T, E, P>>) {
+    let observer = {
+        let mut state = state.borrow_mut();
+        state.is_done = true;
+        state.observer.take()
+    };
+    drop(observer);
+}
+
+impl<I> PullObservable for PullIter<I>
+where I: Iterator + Clone + 'static,
+      I::Item: Clone + 'static {
+    type Item = I::Item;
+    type Error = NoError;
+    type Subscription = PullIterSubscription<I>;
+
+    fn subscribe_pull<O>(&mut self, observer: O) -> PullIterSubscription<I>
+        where O: Observer<I::Item, NoError> + 'static {
+        let producer = IterProducer { iter: self.iter.clone() };
+        PullIterSubscription {
+            state: new_state(producer, observer.boxed()),
+        }
+    }
+}
+
+impl<I: Iterator> Subscription for PullIterSubscription<I> {
+    fn unsubscribe(&mut self) {
+        cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().is_done
+    }
+}
+
+impl<I: Iterator> PullSubscription for PullIterSubscription<I> {
+    fn request(&self, n: usize) {
+        request(&self.state, n);
+    }
+}
+
+impl<I: Iterator> Drop for PullIterSubscription<I> {
+    fn drop(&mut self) {
+        cancel(&self.state);
+    }
+}
+
+impl<Source: Observable, F> IntoPullObservable<Source, F> {
+    pub fn new(source: Source, capacity: usize, policy: OverflowPolicy<Source::Error>, on_drop: F)
+               -> IntoPullObservable<Source, F> {
+        // The values are requested on the thread that fills the queue.
+        assert_non_blocking(&policy, "into_pull");
+        IntoPullObservable {
+            source: source,
+            capacity: capacity,
+            policy: policy,
+            on_drop: Rc::new(on_drop),
+        }
+    }
+}
+
+/// Discards a value that was dropped because the queue was full.
+pub fn ignore<T>(_item: T) {}
+
+struct IntoPullObserver<T, E, F> {
+    state: DemandShared<T, E, QueueProducer<T, E>>,
+    capacity: usize,
+    policy: OverflowPolicy<E>,
+    on_drop: Rc<F>,
+}
+
+impl<T, E: Clone, F> Observer<T, E> for IntoPullObserver<T, E, F> where F: Fn(T) {
+    fn on_next(&mut self, item: T) {
+        let dropped = {
+            let mut state = self.state.borrow_mut();
+            if state.is_done || state.producer.terminal.is_some() {
+                return;
+            }
+            // Values up to the demand are pushed right away, so only the
+            // values beyond it count against the capacity.
+            let limit = state.requested.saturating_add(self.capacity);
+            let values = &mut state.producer.values;
+            if values.len() < limit {
+                values.push_back(item);
+                None
+            } else {
+                match self.policy {
+                    OverflowPolicy::DropNewest => Some(item),
+                    OverflowPolicy::DropOldest => {
+                        values.push_back(item);
+                        values.pop_front()
+                    }
+                    OverflowPolicy::Error(ref error) => {
+                        // The failure is pushed after the queued values, as if the source failed.
+                        state.producer.terminal = Some(Notification::Error(error.clone()));
+                        Some(item)
+                    }
+                    OverflowPolicy::Block => unreachable!("into_pull() cannot block"),
+                    OverflowPolicy::Panic => panic!("into_pull queue is full"),
+                }
+            }
+        };
+        // The function is user code, so it is called outside of the borrow.
+        if let Some(item) = dropped {
+            (self.on_drop)(item);
+        }
+        drain(&self.state);
+    }
+
+    fn on_completed(self) {
+        self.state.borrow_mut().producer.terminal = Some(Notification::Completed);
+        drain(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        self.state.borrow_mut().producer.terminal = Some(Notification::Error(error));
+        drain(&self.state);
+    }
+}
+
+impl<Source, F> PullObservable for IntoPullObservable<Source, F>
+where Source: Observable,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      F: Fn(Source::Item) + 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = IntoPullSubscription<Source::Subscription, Source::Item, Source::Error>;
+
+    fn subscribe_pull<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let producer = QueueProducer {
+            values: VecDeque::new(),
+            terminal: None,
+        };
+        let state = new_state(producer, observer.boxed());
+        let into_pull_observer = IntoPullObserver {
+            state: state.clone(),
+            capacity: self.capacity,
+            policy: self.policy.clone(),
+            on_drop: self.on_drop.clone(),
+        };
+        IntoPullSubscription {
+            subscription: self.source.subscribe(into_pull_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let node = Node::new::<Self::Item, Self::Error>("into_pull");
+        Graph::new(node, vec![self.source.describe()])
+    }
+}
+
+impl<Subs, T, E> IntoPullSubscription<Subs, T, E> {
+    fn discard(&mut self) {
+        cancel(&self.state);
+        let values = mem::take(&mut self.state.borrow_mut().producer.values);
+        drop(values);
+    }
+}
+
+impl<Subs: Subscription, T, E> Subscription for IntoPullSubscription<Subs, T, E> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.discard();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().is_done
+    }
+}
+
+impl<Subs: Subscription, T, E> PullSubscription for IntoPullSubscription<Subs, T, E> {
+    fn request(&self, n: usize) {
+        request(&self.state, n);
+    }
+}
+
+impl<Subs, T, E> Drop for IntoPullSubscription<Subs, T, E> {
+    fn drop(&mut self) {
+        self.discard();
+    }
+}
+
+impl<Source, Other, F> MergeSortedObservable<Source, Other, F> {
+    pub fn new(source: Source, other: Other, compare: F)
+               -> MergeSortedObservable<Source, Other, F> {
+        MergeSortedObservable {
+            source: source,
+            other: other,
+            compare: Rc::new(compare),
+        }
+    }
+}
+
+// Requests a value from every side that has none, unless one is on its way.
+fn refill<T, E, F, Subs, OtherSubs>(state: &RefCell<DemandState<T, E, MergeProducer<T, E, F>>>,
+                                    sources: &MergeSources<Subs, OtherSubs>)
+    where Subs: PullSubscription, OtherSubs: PullSubscription {
+    let (needs_source, needs_other) = {
+        let mut state = state.borrow_mut();
+        if state.is_done {
+            return;
+        }
+        (state.producer.left.claim_request(), state.producer.right.claim_request())
+    };
+    if needs_source {
+        let source = sources.source.borrow().clone();
+        if let Some(source) = source {
+            source.request(1);
+        }
+    }
+    if needs_other {
+        let other = sources.other.borrow().clone();
+        if let Some(other) = other {
+            other.request(1);
+        }
+    }
+}
+
+impl<T, E, F, Subs, OtherSubs> Observer<T, E> for MergeSortedObserver<T, E, F, Subs, OtherSubs>
+where F: Fn(&T, &T) -> Ordering,
+      Subs: PullSubscription,
+      OtherSubs: PullSubscription {
+    fn on_next(&mut self, item: T) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.is_done {
+                return;
+            }
+            let side = state.producer.side(self.is_left);
+            side.head = Some(item);
+            side.is_requested = false;
+        }
+        drain(&self.state);
+        if let Some(sources) = self.sources.upgrade() {
+            refill(&self.state, &sources);
+        }
+    }
+
+    fn on_completed(self) {
+        {
+            let mut state = self.state.borrow_mut();
+            let side = state.producer.side(self.is_left);
+            side.is_completed = true;
+            side.is_requested = false;
+        }
+        // Values of the other side that were held up may have been pushed.
+        drain(&self.state);
+        if let Some(sources) = self.sources.upgrade() {
+            refill(&self.state, &sources);
+        }
+    }
+
+    fn on_error(self, error: E) {
+        self.state.borrow_mut().producer.error = Some(error);
+        drain(&self.state);
+    }
+}
+
+impl<Source, Other, F> PullObservable for MergeSortedObservable<Source, Other, F>
+where Source: PullObservable,
+      Source::Subscription: 'static,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      Other: PullObservable<Item = Source::Item, Error = Source::Error>,
+      Other::Subscription: 'static,
+      F: Fn(&Source::Item, &Source::Item) -> Ordering + 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = MergeSortedSubscription<Source::Subscription, Other::Subscription,
+                                                Source::Item, Source::Error, F>;
+
+    fn subscribe_pull<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let producer = MergeProducer {
+            left: MergeSide::new(),
+            right: MergeSide::new(),
+            compare: self.compare.clone(),
+            error: None,
+        };
+        let state = new_state(producer, observer.boxed());
+        let sources = Rc::new(MergeSources {
+            source: RefCell::new(None),
+            other: RefCell::new(None),
+        });
+        let source = self.source.subscribe_pull(MergeSortedObserver {
+            state: state.clone(),
+            sources: Rc::downgrade(&sources),
+            is_left: true,
+        });
+        *sources.source.borrow_mut() = Some(Rc::new(source));
+        let other = self.other.subscribe_pull(MergeSortedObserver {
+            state: state.clone(),
+            sources: Rc::downgrade(&sources),
+            is_left: false,
+        });
+        *sources.other.borrow_mut() = Some(Rc::new(other));
+
+        // The first values are requested before there is demand, so the
+        // smaller one is known when the first request arrives.
+        refill(&state, &sources);
+        MergeSortedSubscription {
+            state: state,
+            sources: sources,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let node = Node::new::<Self::Item, Self::Error>("merge_sorted");
+        Graph::new(node, vec![self.source.describe(), self.other.describe()])
+    }
+}
+
+impl<Subs, OtherSubs, T, E, F> MergeSortedSubscription<Subs, OtherSubs, T, E, F> {
+    fn discard(&mut self) {
+        cancel(&self.state);
+        let (left, right) = {
+            let mut state = self.state.borrow_mut();
+            (state.producer.left.head.take(), state.producer.right.head.take())
+        };
+        let source = self.sources.source.borrow_mut().take();
+        let other = self.sources.other.borrow_mut().take();
+        drop(source);
+        drop(other);
+        drop(left);
+        drop(right);
+    }
+}
+
+impl<Subs, OtherSubs, T, E, F> Subscription
+    for MergeSortedSubscription<Subs, OtherSubs, T, E, F> {
+    fn unsubscribe(&mut self) {
+        self.discard();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().is_done
+    }
+}
+
+impl<Subs, OtherSubs, T, E, F> PullSubscription
+    for MergeSortedSubscription<Subs, OtherSubs, T, E, F>
+where Subs: PullSubscription,
+      OtherSubs: PullSubscription,
+      F: Fn(&T, &T) -> Ordering {
+    fn request(&self, n: usize) {
+        request(&self.state, n);
+        refill(&self.state, &self.sources);
+    }
+}
+
+impl<Subs, OtherSubs, T, E, F> Drop for MergeSortedSubscription<Subs, OtherSubs, T, E, F> {
+    fn drop(&mut self) {
+        self.discard();
+    }
+}
+
+impl<Source> PushObservable<Source> {
+    pub fn new(source: Source, batch: usize) -> PushObservable<Source> {
+        PushObservable {
+            source: source,
+            batch: batch,
+        }
+    }
+}
+
+struct PushObserver<O, Subs> {
+    observer: O,
+    batch: usize,
+
+    // The number of values that are left of the current batch.
+    remaining: usize,
+    subscription: Rc<RefCell<Option<Subs>>>,
+}
+
+impl<T, E, O, Subs> Observer<T, E> for PushObserver<O, Subs>
+where O: Observer<T, E>,
+      Subs: PullSubscription {
+    fn on_next(&mut self, item: T) {
+        self.observer.on_next(item);
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.remaining = self.batch;
+            if let Some(ref subscription) = *self.subscription.borrow() {
+                subscription.request(self.batch);
+            }
+        }
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+impl<Source> Observable for PushObservable<Source>
+where Source: PullObservable,
+      Source::Subscription: 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = PushSubscription<Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let shared = Rc::new(RefCell::new(None));
+        let push_observer = PushObserver {
+            observer: observer,
+            batch: self.batch,
+            remaining: self.batch,
+            subscription: shared.clone(),
+        };
+        let subscription = self.source.subscribe_pull(push_observer);
+        *shared.borrow_mut() = Some(subscription);
+        // The values are pushed while the subscription is borrowed, so the
+        // observer can only borrow it too.
+        if let Some(ref subscription) = *shared.borrow() {
+            subscription.request(self.batch);
+        }
+        PushSubscription {
+            subscription: shared,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("into_push", vec![self.source.describe()])
+    }
+}
+
+impl<Subs: Subscription> Subscription for PushSubscription<Subs> {
+    fn unsubscribe(&mut self) {
+        let subscription = self.subscription.borrow_mut().take();
+        if let Some(mut subscription) = subscription {
+            subscription.unsubscribe();
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.borrow().as_ref().is_some_and(|subscription| subscription.is_active())
+    }
+}
+
+impl<Subs> Drop for PushSubscription<Subs> {
+    fn drop(&mut self) {
+        // The observer holds on to the subscription, until the subscription
+        // is dropped here.
+        let subscription = self.subscription.borrow_mut().take();
+        drop(subscription);
+    }
+}
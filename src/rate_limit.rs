@@ -0,0 +1,265 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `rate_limit()` operator, which caps the rate of values with a token bucket.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use subscription::Subscription;
+
+/// The result of calling `rate_limit()` on an observable.
+///
+/// Every value takes a token from a bucket that holds at most `burst`
+/// tokens, and that gains a token every interval. By default, values that
+/// find the bucket empty are queued until a token arrives. Call
+/// `drop_excess()` to drop them instead.
+pub struct RateLimitObservable<Source, Sch> {
+    source: Source,
+    scheduler: Sch,
+    interval: Duration,
+    burst: usize,
+    drops_excess: bool,
+}
+
+/// The result of subscribing to a `rate_limit()` observable.
+///
+/// Dropping this subscription drops the subscription to the source, and it
+/// discards the values that have not been pushed yet.
+pub struct RateLimitSubscription<Subs, T, E, SchSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<RateLimitState<T, E, SchSubs>>>,
+}
+
+struct RateLimitState<T, E, SchSubs> {
+    observer: ObserverSlot<T, E>,
+
+    tokens: usize,
+    queue: VecDeque<T>,
+
+    // Whether the source completed. The observer completes once the queue
+    // has been pushed.
+    is_completed: bool,
+
+    // Whether values are being pushed. Values and tokens that arrive
+    // meanwhile are picked up by the loop that pushes.
+    is_draining: bool,
+
+    // The periodic action that adds tokens, while the bucket is not full.
+    refill: Option<SchSubs>,
+}
+
+struct RateLimitObserver<T, E, Sch: Scheduler> {
+    state: Rc<RefCell<RateLimitState<T, E, Sch::Subscription>>>,
+    scheduler: Sch,
+    interval: Duration,
+    burst: usize,
+    drops_excess: bool,
+}
+
+impl<Source, Sch> RateLimitObservable<Source, Sch> {
+    pub fn new(source: Source, interval: Duration, burst: usize, scheduler: Sch)
+               -> RateLimitObservable<Source, Sch> {
+        RateLimitObservable {
+            source: source,
+            scheduler: scheduler,
+            interval: interval,
+            // A bucket without room for a token would never let a value pass.
+            burst: cmp::max(burst, 1),
+            drops_excess: false,
+        }
+    }
+
+    /// Drops the values that exceed the rate, instead of delaying them.
+    pub fn drop_excess(mut self) -> RateLimitObservable<Source, Sch> {
+        self.drops_excess = true;
+        self
+    }
+}
+
+impl<Source, Sch, T, E> Observable for RateLimitObservable<Source, Sch>
+where Source: Observable<Item = T, Error = E>,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = RateLimitSubscription<Source::Subscription, T, E, Sch::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(RateLimitState {
+            observer: ObserverSlot::new("rate_limit subscription", observer.boxed()),
+            tokens: self.burst,
+            queue: VecDeque::new(),
+            is_completed: false,
+            is_draining: false,
+            refill: None,
+        }));
+        let rate_limit_observer = RateLimitObserver {
+            state: state.clone(),
+            scheduler: self.scheduler.clone(),
+            interval: self.interval,
+            burst: self.burst,
+            drops_excess: self.drops_excess,
+        };
+        RateLimitSubscription {
+            subscription: self.source.subscribe(rate_limit_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("rate_limit", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, SchSubs> OperatorState for RateLimitState<T, E, SchSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<SchSubs>, VecDeque<T>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<SchSubs>, VecDeque<T>) {
+        (self.refill.take(), mem::take(&mut self.queue))
+    }
+}
+
+// Pushes the queued values to the observer, as long as there are tokens.
+fn drain<T, E, SchSubs>(state: &RefCell<RateLimitState<T, E, SchSubs>>) {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_draining || state.observer.is_done() {
+            return;
+        }
+        state.is_draining = true;
+    }
+    loop {
+        let item = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if state.queue.is_empty() && state.is_completed {
+                break;
+            }
+            if state.queue.is_empty() || state.tokens == 0 {
+                state.is_draining = false;
+                return;
+            }
+            state.tokens -= 1;
+            state.queue.pop_front().unwrap()
+        };
+        operator::push(state, item);
+    }
+    operator::complete(state);
+}
+
+// Adds a token, and stops adding tokens once the bucket is full.
+fn refill<T, E, SchSubs>(state: &Weak<RefCell<RateLimitState<T, E, SchSubs>>>, burst: usize) {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    {
+        let mut state = state.borrow_mut();
+        state.tokens = cmp::min(state.tokens + 1, burst);
+    }
+    drain(&state);
+    let refill = {
+        let mut state = state.borrow_mut();
+        if state.tokens == burst && state.queue.is_empty() {
+            state.refill.take()
+        } else {
+            None
+        }
+    };
+    drop(refill);
+}
+
+impl<T, E, Sch> RateLimitObserver<T, E, Sch>
+where Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static {
+    // Starts adding tokens, if the bucket is not full and nothing adds them yet.
+    fn ensure_refill(&self) {
+        let needs_refill = {
+            let state = self.state.borrow();
+            !state.observer.is_done() && state.refill.is_none() && state.tokens < self.burst
+        };
+        if needs_refill {
+            let state = Rc::downgrade(&self.state);
+            let burst = self.burst;
+            let refill = self.scheduler.schedule_periodic(self.interval, move || {
+                refill(&state, burst)
+            });
+            self.state.borrow_mut().refill = Some(refill);
+        }
+    }
+}
+
+impl<T, E, Sch> Observer<T, E> for RateLimitObserver<T, E, Sch>
+where Sch: Scheduler,
+      Sch::Subscription: 'static,
+      T: 'static,
+      E: 'static {
+    fn on_next(&mut self, item: T) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            let is_excess = !state.queue.is_empty() || state.tokens == 0;
+            if is_excess && self.drops_excess {
+                return;
+            }
+            state.queue.push_back(item);
+        }
+        drain(&self.state);
+        self.ensure_refill();
+    }
+
+    fn on_completed(self) {
+        self.state.borrow_mut().is_completed = true;
+        drain(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Subs, T, E, SchSubs> Subscription for RateLimitSubscription<Subs, T, E, SchSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, SchSubs> Drop for RateLimitSubscription<Subs, T, E, SchSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
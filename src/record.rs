@@ -0,0 +1,203 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Recording notifications with their timing, and replaying them.
+
+use Notification;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use subscription::{CompositeSubscription, Subscription};
+
+/// The notifications of an observable, with the time at which they arrived.
+///
+/// The times are relative to the moment of subscription. A recording is
+/// made with `ObservableExt::record()`, and `replay()` turns it back into an
+/// observable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Recording<T, E> {
+    notifications: Vec<(Duration, Notification<T, E>)>,
+}
+
+/// The result of calling `record()` on an observable.
+///
+/// Dropping this subscription drops the subscription to the observable, and
+/// stops the recording.
+pub struct RecordingSubscription<Subs, T, E> {
+    subscription: Subs,
+    notifications: SharedRecording<T, E>,
+}
+
+/// The result of calling `replay()` on a recording.
+pub struct ReplayObservable<T, E, Sch> {
+    notifications: Rc<Vec<(Duration, Notification<T, E>)>>,
+    scheduler: Sch,
+}
+
+// The notifications with the time since the subscription, shared with the observer.
+type SharedRecording<T, E> = Rc<RefCell<Vec<(Duration, Notification<T, E>)>>>;
+
+struct RecordObserver<T, E, Sch> {
+    notifications: SharedRecording<T, E>,
+    scheduler: Sch,
+    start: Instant,
+}
+
+impl<T, E> Recording<T, E> {
+    /// Creates a recording from notifications and their time since subscription.
+    ///
+    /// The times should not decrease; notifications after a terminal event
+    /// are never replayed.
+    pub fn new(notifications: Vec<(Duration, Notification<T, E>)>) -> Recording<T, E> {
+        Recording {
+            notifications: notifications,
+        }
+    }
+
+    /// Returns the notifications and their time since subscription.
+    pub fn notifications(&self) -> &[(Duration, Notification<T, E>)] {
+        &self.notifications[..]
+    }
+
+    /// Returns whether the recorded observable completed or failed.
+    pub fn is_terminated(&self) -> bool {
+        self.notifications.iter().any(|(_, notification)| match *notification {
+            Notification::Next(..) => false,
+            Notification::Completed | Notification::Error(..) => true,
+        })
+    }
+
+    /// Multiplies the times of all notifications by the factor.
+    ///
+    /// A factor of 0.5 replays the recording twice as fast, and a factor of
+    /// 0 replays every notification immediately.
+    pub fn scaled(self, factor: f64) -> Recording<T, E> {
+        assert!(factor >= 0.0, "cannot scale a recording by a negative factor");
+        let notifications = self.notifications.into_iter()
+            .map(|(time, notification)| (time.mul_f64(factor), notification))
+            .collect();
+        Recording::new(notifications)
+    }
+
+    /// Returns an observable that pushes the recorded notifications.
+    ///
+    /// Every observer receives all notifications, at their recorded time
+    /// relative to the moment it subscribed, as the scheduler reads time.
+    /// With a `TestScheduler`, the replay is deterministic.
+    pub fn replay<Sch: Scheduler + Clone>(&self, scheduler: &Sch) -> ReplayObservable<T, E, Sch>
+        where T: Clone, E: Clone {
+        ReplayObservable {
+            notifications: Rc::new(self.notifications.clone()),
+            scheduler: scheduler.clone(),
+        }
+    }
+}
+
+pub fn record<Source, Sch>(source: &mut Source, scheduler: Sch)
+                           -> RecordingSubscription<Source::Subscription, Source::Item,
+                                                    Source::Error>
+    where Source: Observable + ?Sized,
+          Source::Item: 'static,
+          Source::Error: 'static,
+          Sch: Scheduler + 'static {
+    let notifications = Rc::new(RefCell::new(Vec::new()));
+    let record_observer = RecordObserver {
+        notifications: notifications.clone(),
+        start: scheduler.now_instant(),
+        scheduler: scheduler,
+    };
+    RecordingSubscription {
+        subscription: source.subscribe(record_observer),
+        notifications: notifications,
+    }
+}
+
+impl<T, E, Sch: Scheduler> RecordObserver<T, E, Sch> {
+    fn record(&self, notification: Notification<T, E>) {
+        let time = self.scheduler.now_instant() - self.start;
+        self.notifications.borrow_mut().push((time, notification));
+    }
+}
+
+impl<T, E, Sch: Scheduler> Observer<T, E> for RecordObserver<T, E, Sch> {
+    fn on_next(&mut self, item: T) {
+        self.record(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.record(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.record(Notification::Error(error));
+    }
+}
+
+impl<Subs, T: Clone, E: Clone> RecordingSubscription<Subs, T, E> {
+    /// Returns the notifications that were recorded so far.
+    pub fn recording(&self) -> Recording<T, E> {
+        Recording::new(self.notifications.borrow().clone())
+    }
+}
+
+impl<Subs: Subscription, T, E> Subscription for RecordingSubscription<Subs, T, E> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<T, E, Sch> Observable for ReplayObservable<T, E, Sch>
+where T: Clone + 'static,
+      E: Clone + 'static,
+      Sch: Scheduler,
+      Sch::Subscription: 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = CompositeSubscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> CompositeSubscription
+        where O: Observer<T, E> + 'static {
+        // The scheduled actions share the observer. A terminal event takes it.
+        let observer = Rc::new(RefCell::new(Some(observer)));
+        let mut subscription = CompositeSubscription::new();
+        for &(delay, ref notification) in self.notifications.iter() {
+            let observer = observer.clone();
+            let notification = notification.clone();
+            let action = self.scheduler.schedule_after(delay, move || {
+                match notification {
+                    Notification::Next(item) => {
+                        if let Some(ref mut observer) = *observer.borrow_mut() {
+                            observer.on_next(item);
+                        }
+                    }
+                    Notification::Completed => {
+                        let observer = observer.borrow_mut().take();
+                        if let Some(observer) = observer {
+                            observer.on_completed();
+                        }
+                    }
+                    Notification::Error(error) => {
+                        let observer = observer.borrow_mut().take();
+                        if let Some(observer) = observer {
+                            observer.on_error(error);
+                        }
+                    }
+                }
+            });
+            subscription.add(action);
+        }
+        subscription
+    }
+}
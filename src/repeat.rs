@@ -0,0 +1,262 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that subscribe to the source again after it completes.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::rc::Rc;
+use subject::{Subject, SubjectObservable};
+use subscription::Subscription;
+
+/// The result of calling `repeat_when()` on an observable.
+pub struct RepeatWhenObservable<Source, F> {
+    // The source is shared with the subscriptions, which subscribe to it again.
+    source: Rc<RefCell<Source>>,
+    f: F,
+}
+
+/// The result of subscribing to a `repeat_when()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the signal.
+pub struct RepeatWhenSubscription<Source: Observable> {
+    state: Rc<RefCell<RepeatState<Source>>>,
+}
+
+struct RepeatState<Source: Observable> {
+    observer: ObserverSlot<Source::Item, Source::Error>,
+    source: Rc<RefCell<Source>>,
+
+    // Pushes a value to the signal function's observable whenever the source completes.
+    completions: Rc<Subject<(), Source::Error>>,
+
+    // The subscriptions are stored once `subscribe()` returns, unless the
+    // source or signal ended by then.
+    subscription: Option<Source::Subscription>,
+    signal: Option<Box<dyn Subscription>>,
+
+    // Whether the source is subscribed to, or about to be.
+    is_source_active: bool,
+
+    // Whether the signal asked to subscribe again, and it did not happen yet.
+    is_pending: bool,
+
+    // Whether the source is being subscribed to. A repeat that the signal
+    // asks for meanwhile is picked up by the loop that subscribes.
+    is_subscribing: bool,
+
+    // Once the signal completes, the result completes with the source.
+    is_signal_completed: bool,
+}
+
+struct RepeatObserver<Source: Observable> {
+    state: Rc<RefCell<RepeatState<Source>>>,
+}
+
+struct SignalObserver<Source: Observable> {
+    state: Rc<RefCell<RepeatState<Source>>>,
+}
+
+impl<Source, F> RepeatWhenObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> RepeatWhenObservable<Source, F> {
+        RepeatWhenObservable {
+            source: Rc::new(RefCell::new(source)),
+            f: f,
+        }
+    }
+}
+
+impl<Source: Observable> OperatorState for RepeatState<Source> {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Released = (Option<Source::Subscription>, Option<Box<dyn Subscription>>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<Source::Item, Source::Error> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<Source::Subscription>, Option<Box<dyn Subscription>>) {
+        (self.subscription.take(), self.signal.take())
+    }
+}
+
+impl<Source, Signal, F> Observable for RepeatWhenObservable<Source, F>
+where Source: Observable + 'static,
+      Source::Item: 'static,
+      Source::Error: Clone + 'static,
+      Signal: Observable<Error = Source::Error>,
+      Signal::Subscription: 'static,
+      F: FnMut(SubjectObservable<(), Source::Error>) -> Signal {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = RepeatWhenSubscription<Source>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let completions = Rc::new(Subject::new());
+        let state = Rc::new(RefCell::new(RepeatState {
+            observer: ObserverSlot::new("repeat_when subscription", observer.boxed()),
+            source: self.source.clone(),
+            completions: completions.clone(),
+            subscription: None,
+            signal: None,
+            is_source_active: true,
+            is_pending: true,
+            is_subscribing: false,
+            is_signal_completed: false,
+        }));
+
+        // The signal is subscribed to first, so it sees the completion of a
+        // source that completes right away.
+        let mut signal = (self.f)(completions.observable());
+        let signal_observer = SignalObserver {
+            state: state.clone(),
+        };
+        let subscription = signal.subscribe(signal_observer);
+        let subscription = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() || state.is_signal_completed {
+                Some(subscription)
+            } else {
+                state.signal = Some(Box::new(subscription));
+                None
+            }
+        };
+        drop(subscription);
+
+        subscribe_source(&state);
+        RepeatWhenSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("repeat_when", vec![self.source.borrow().describe()])
+    }
+}
+
+// Subscribes to the source for every repeat that the signal asked for.
+fn subscribe_source<Source>(state: &Rc<RefCell<RepeatState<Source>>>)
+    where Source: Observable + 'static,
+          Source::Item: 'static,
+          Source::Error: Clone + 'static {
+    {
+        let mut state = state.borrow_mut();
+        if state.is_subscribing || state.observer.is_done() {
+            return;
+        }
+        state.is_subscribing = true;
+    }
+    loop {
+        let source = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if !state.is_pending {
+                state.is_subscribing = false;
+                return;
+            }
+            state.is_pending = false;
+            state.is_source_active = true;
+            state.source.clone()
+        };
+        let repeat_observer = RepeatObserver {
+            state: state.clone(),
+        };
+        let subscription = source.borrow_mut().subscribe(repeat_observer);
+        let subscription = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() || !state.is_source_active {
+                Some(subscription)
+            } else {
+                state.subscription = Some(subscription);
+                None
+            }
+        };
+        drop(subscription);
+    }
+}
+
+impl<Source> Observer<Source::Item, Source::Error> for RepeatObserver<Source>
+where Source: Observable,
+      Source::Error: Clone {
+    fn on_next(&mut self, item: Source::Item) {
+        operator::push(&self.state, item);
+    }
+
+    fn on_completed(self) {
+        let (subscription, completions, is_signal_completed) = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            state.is_source_active = false;
+            (state.subscription.take(), state.completions.clone(), state.is_signal_completed)
+        };
+        drop(subscription);
+        if is_signal_completed {
+            operator::complete(&self.state);
+        } else {
+            (&*completions).on_next(());
+        }
+    }
+
+    fn on_error(self, error: Source::Error) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Source, T> Observer<T, Source::Error> for SignalObserver<Source>
+where Source: Observable + 'static,
+      Source::Item: 'static,
+      Source::Error: Clone + 'static {
+    fn on_next(&mut self, _item: T) {
+        {
+            let mut state = self.state.borrow_mut();
+            // Values of the signal while the source is active are ignored.
+            if state.observer.is_done() || state.is_source_active {
+                return;
+            }
+            state.is_pending = true;
+        }
+        subscribe_source(&self.state);
+    }
+
+    fn on_completed(self) {
+        let is_source_active = {
+            let mut state = self.state.borrow_mut();
+            state.is_signal_completed = true;
+            state.is_source_active
+        };
+        if !is_source_active {
+            operator::complete(&self.state);
+        }
+    }
+
+    fn on_error(self, error: Source::Error) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<Source: Observable> Subscription for RepeatWhenSubscription<Source> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Source: Observable> Drop for RepeatWhenSubscription<Source> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
@@ -0,0 +1,135 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators for observables whose values are results.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::rc::Rc;
+
+/// The result of calling `flatten_results()` on an observable.
+pub struct FlattenResultsObservable<Source> {
+    source: Source,
+}
+
+/// The result of calling `ok_or_else()` on an observable.
+pub struct OkOrElseObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+}
+
+struct FlattenResultsObserver<O> {
+    // The observer is gone once it failed.
+    observer: Option<O>,
+}
+
+struct OkOrElseObserver<O, F> {
+    observer: O,
+    f: Rc<F>,
+}
+
+impl<Source> FlattenResultsObservable<Source> {
+    pub fn new(source: Source) -> FlattenResultsObservable<Source> {
+        FlattenResultsObservable {
+            source: source,
+        }
+    }
+}
+
+impl<Source, T> Observable for FlattenResultsObservable<Source>
+where Source: Observable<Item = Result<T, <Source as Observable>::Error>>,
+      T: Clone {
+    type Item = T;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, Source::Error> + 'static {
+        self.source.subscribe(FlattenResultsObserver { observer: Some(observer) })
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("flatten_results", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<Result<T, E>, E> for FlattenResultsObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: Result<T, E>) {
+        match item {
+            Ok(value) => if let Some(ref mut observer) = self.observer {
+                observer.on_next(value);
+            },
+            // Values after the first error are ignored.
+            Err(error) => if let Some(observer) = self.observer.take() {
+                observer.on_error(error);
+            },
+        }
+    }
+
+    fn on_completed(self) {
+        if let Some(observer) = self.observer {
+            observer.on_completed();
+        }
+    }
+
+    fn on_error(self, error: E) {
+        if let Some(observer) = self.observer {
+            observer.on_error(error);
+        }
+    }
+}
+
+impl<Source, F> OkOrElseObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> OkOrElseObservable<Source, F> {
+        OkOrElseObservable {
+            source: source,
+            f: Rc::new(f),
+        }
+    }
+}
+
+impl<Source, T, U, F> Observable for OkOrElseObservable<Source, F>
+where Source: Observable<Item = Option<T>>,
+      T: Clone,
+      U: Clone,
+      F: Fn() -> U + 'static {
+    type Item = Result<T, U>;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Result<T, U>, Source::Error> + 'static {
+        let ok_or_else_observer = OkOrElseObserver {
+            observer: observer,
+            f: self.f.clone(),
+        };
+        self.source.subscribe(ok_or_else_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("ok_or_else", vec![self.source.describe()])
+    }
+}
+
+impl<T, U, E, O, F> Observer<Option<T>, E> for OkOrElseObserver<O, F>
+where O: Observer<Result<T, U>, E>,
+      F: Fn() -> U {
+    fn on_next(&mut self, item: Option<T>) {
+        self.observer.on_next(item.ok_or_else(&*self.f));
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
@@ -0,0 +1,115 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `safe()` operator, which shields observers from misbehaving sources.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::cell::Cell;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// An observer that ignores notifications once it is no longer active.
+///
+/// Completion and failure take the observer by value, so there can be at most
+/// one terminal event per observer. What the type system cannot prevent, is a
+/// source that keeps calling `on_next()` after the subscription was dropped.
+struct SafeObserver<O> {
+    observer: O,
+    is_active: Rc<Cell<bool>>,
+}
+
+impl<T, E, O> Observer<T, E> for SafeObserver<O> where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        if self.is_active.get() {
+            self.observer.on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        if self.is_active.get() {
+            self.is_active.set(false);
+            self.observer.on_completed();
+        }
+    }
+
+    fn on_error(self, error: E) {
+        if self.is_active.get() {
+            self.is_active.set(false);
+            self.observer.on_error(error);
+        }
+    }
+}
+
+/// The result of calling `safe()` on an observable.
+pub struct SafeObservable<Source> {
+    source: Source,
+}
+
+/// The result of subscribing to a `safe()` observable.
+///
+/// Unsubscribing deactivates the observer before it unsubscribes from the
+/// source, so the observer does not receive notifications that the source
+/// pushes during or after unsubscription.
+pub struct SafeSubscription<Subs> {
+    subscription: Subs,
+    is_active: Rc<Cell<bool>>,
+}
+
+impl<Source> SafeObservable<Source> {
+    pub fn new(source: Source) -> SafeObservable<Source> {
+        SafeObservable {
+            source: source,
+        }
+    }
+}
+
+impl<Source: Observable> Observable for SafeObservable<Source> {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = SafeSubscription<Source::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let is_active = Rc::new(Cell::new(true));
+        let safe_observer = SafeObserver {
+            observer: observer,
+            is_active: is_active.clone(),
+        };
+        SafeSubscription {
+            subscription: self.source.subscribe(safe_observer),
+            is_active: is_active,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("safe", vec![self.source.describe()])
+    }
+}
+
+impl<Subs: Subscription> Subscription for SafeSubscription<Subs> {
+    fn unsubscribe(&mut self) {
+        self.is_active.set(false);
+        self.subscription.unsubscribe();
+    }
+
+    /// Returns whether the observer can still receive notifications.
+    ///
+    /// This is false once the observer completed or failed, even if the
+    /// source subscription claims to be active.
+    fn is_active(&self) -> bool {
+        self.is_active.get()
+    }
+}
+
+impl<Subs> Drop for SafeSubscription<Subs> {
+    fn drop(&mut self) {
+        // The source subscription is dropped after this.
+        self.is_active.set(false);
+    }
+}
@@ -0,0 +1,165 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `sample()` operator, which pushes the latest value when a sampler fires.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `sample()` on an observable.
+pub struct SampleObservable<Source, Sampler> {
+    source: Source,
+    sampler: Sampler,
+}
+
+/// The result of subscribing to a `sample()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the sampler.
+pub struct SampleSubscription<Subs, T, E, SmSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<SampleState<T, E, SmSubs>>>,
+}
+
+struct SampleState<T, E, SmSubs> {
+    observer: ObserverSlot<T, E>,
+
+    // The latest value of the source, until the sampler takes it.
+    latest: Option<T>,
+
+    sampler: Option<SmSubs>,
+}
+
+struct SampleObserver<T, E, SmSubs> {
+    state: Rc<RefCell<SampleState<T, E, SmSubs>>>,
+}
+
+struct SamplerObserver<T, E, SmSubs> {
+    state: Rc<RefCell<SampleState<T, E, SmSubs>>>,
+}
+
+impl<Source, Sampler> SampleObservable<Source, Sampler> {
+    pub fn new(source: Source, sampler: Sampler) -> SampleObservable<Source, Sampler> {
+        SampleObservable {
+            source: source,
+            sampler: sampler,
+        }
+    }
+}
+
+impl<Source, Sampler, T, E> Observable for SampleObservable<Source, Sampler>
+where Source: Observable<Item = T, Error = E>,
+      Sampler: Observable,
+      Sampler::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = SampleSubscription<Source::Subscription, T, E, Sampler::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(SampleState {
+            observer: ObserverSlot::new("sample subscription", observer.boxed()),
+            latest: None,
+            sampler: None,
+        }));
+
+        // The sampler is subscribed to first, so it is in place for the
+        // values that the source pushes upon subscription.
+        let sampler = self.sampler.subscribe(SamplerObserver { state: state.clone() });
+        state.borrow_mut().sampler = Some(sampler);
+
+        let sample_observer = SampleObserver { state: state.clone() };
+        SampleSubscription {
+            subscription: self.source.subscribe(sample_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.sampler.describe()];
+        Graph::operator::<Self>("sample", sources)
+    }
+}
+
+impl<T, E, SmSubs> OperatorState for SampleState<T, E, SmSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<SmSubs>, Option<T>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<SmSubs>, Option<T>) {
+        (self.sampler.take(), self.latest.take())
+    }
+}
+
+impl<T, E, SmSubs> Observer<T, E> for SampleObserver<T, E, SmSubs> {
+    fn on_next(&mut self, item: T) {
+        let previous = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            state.latest.replace(item)
+        };
+        drop(previous);
+    }
+
+    fn on_completed(self) {
+        // A value that was not sampled yet is dropped.
+        operator::complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, SmSubs, U, F> Observer<U, F> for SamplerObserver<T, E, SmSubs> {
+    fn on_next(&mut self, _item: U) {
+        let latest = self.state.borrow_mut().latest.take();
+        if let Some(item) = latest {
+            operator::push(&self.state, item);
+        }
+    }
+
+    fn on_completed(self) {
+        // Without a sampler, no more values are pushed, but the source can
+        // still complete or fail.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the sampler is ignored, like its completion.
+    }
+}
+
+impl<Subs, T, E, SmSubs> Subscription for SampleSubscription<Subs, T, E, SmSubs>
+where Subs: Subscription {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, T, E, SmSubs> Drop for SampleSubscription<Subs, T, E, SmSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
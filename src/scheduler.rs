@@ -0,0 +1,865 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Schedulers control when and where actions run.
+//!
+//! Operators that work with time take a scheduler as a parameter, instead of
+//! deciding for themselves how to wait. This makes it possible to choose where
+//! the work happens, and to test time-based operators without waiting.
+
+use leak::Tracker;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::ops::Add;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::thread;
+use std::time::{Duration, Instant};
+use subscription::Subscription;
+use UncancellableSubscription;
+
+/// Runs actions, now or in the future.
+pub trait Scheduler {
+    /// The result of scheduling an action.
+    ///
+    /// Dropping the subscription cancels the action if it has not run yet,
+    /// and it stops a periodic action.
+    type Subscription: Subscription;
+
+    /// Schedules the action to run as soon as possible.
+    fn schedule<F>(&self, action: F) -> Self::Subscription
+        where F: FnOnce() + 'static {
+        self.schedule_after(Duration::from_secs(0), action)
+    }
+
+    /// Schedules the action to run once the delay has passed.
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> Self::Subscription
+        where F: FnOnce() + 'static;
+
+    /// Schedules the action to run every period, starting one period from now.
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> Self::Subscription
+        where F: FnMut() + 'static;
+
+    /// Returns the current time, as the clock of the scheduler reads it.
+    ///
+    /// By default this is the system clock. Schedulers with their own notion
+    /// of time, like `TestScheduler`, override it, so operators that read the
+    /// time follow that clock.
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Runs actions on other threads, now or in the future.
+///
+/// This is like `Scheduler`, but the actions must be `Send`, because they run
+/// on a different thread than the one that scheduled them. Pipelines built on
+/// `Rc` cannot be scheduled this way.
+pub trait SendScheduler {
+    /// The result of scheduling an action.
+    ///
+    /// Dropping the subscription cancels the action if it has not run yet,
+    /// and it stops a periodic action.
+    type Subscription: Subscription;
+
+    /// Schedules the action to run as soon as possible.
+    fn schedule<F>(&self, action: F) -> Self::Subscription
+        where F: FnOnce() + Send + 'static {
+        self.schedule_after(Duration::from_secs(0), action)
+    }
+
+    /// Schedules the action to run once the delay has passed.
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> Self::Subscription
+        where F: FnOnce() + Send + 'static;
+
+    /// Schedules the action to run every period, starting one period from now.
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> Self::Subscription
+        where F: FnMut() + Send + 'static;
+}
+
+/// A scheduler that runs actions on the current thread, before returning.
+///
+/// An action with a delay blocks the current thread until the delay has
+/// passed. Because the action has run by the time the scheduler returns, the
+/// subscription is not cancellable. A periodic action runs forever, so
+/// scheduling one never returns.
+#[derive(Clone, Copy)]
+pub struct ImmediateScheduler;
+
+impl Scheduler for ImmediateScheduler {
+    type Subscription = UncancellableSubscription;
+
+    fn schedule<F>(&self, action: F) -> UncancellableSubscription
+        where F: FnOnce() + 'static {
+        action();
+        UncancellableSubscription
+    }
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> UncancellableSubscription
+        where F: FnOnce() + 'static {
+        thread::sleep(delay);
+        action();
+        UncancellableSubscription
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, mut action: F) -> UncancellableSubscription
+        where F: FnMut() + 'static {
+        loop {
+            thread::sleep(period);
+            action();
+        }
+    }
+}
+
+/// A scheduler that queues actions and runs them on the current thread.
+///
+/// If no action of this scheduler is running on the current thread, the
+/// scheduled action runs before the scheduler returns. Actions scheduled from
+/// within a running action are queued instead, and they run in order when the
+/// running action returns. This prevents unbounded recursion when an action
+/// schedules further actions.
+///
+/// Actions with a delay run in order of their due time. While waiting for the
+/// next action, the current thread is blocked.
+///
+/// Because the queue runs until it is empty, scheduling a periodic action
+/// when no action is running never returns, unless the periodic action is
+/// cancelled from within another action, and the returned subscription cannot
+/// be used to cancel it. Schedule periodic actions from within an action, so
+/// the subscription is returned right away.
+#[derive(Clone, Copy)]
+pub struct CurrentThreadScheduler;
+
+/// A scheduler that runs actions in a message loop.
+///
+/// Scheduling an action adds it to the queue of the event loop. The actions
+/// run on the thread that runs the loop, with `run()` or `run_until_idle()`.
+/// The event loop is a handle: clones of it share the same queue.
+pub struct EventLoop {
+    queue: Rc<RefCell<ActionQueue<Instant>>>,
+}
+
+/// A scheduler with virtual time, for testing.
+///
+/// Time does not pass by itself: actions run only when the test advances the
+/// clock with `advance_by()` or `advance_to()`, so time-based operators can be
+/// tested deterministically and without waiting. Actions run in order of
+/// their due time, on the thread that advances the clock. The scheduler is a
+/// handle: clones of it share the same clock.
+pub struct TestScheduler {
+    clock: Rc<TestClock>,
+}
+
+/// A scheduler that runs every action on a new thread.
+///
+/// An action with a delay waits on its own thread, so scheduling never blocks.
+/// A periodic action runs on a single thread, for as long as it is not
+/// cancelled.
+#[derive(Clone, Copy)]
+pub struct NewThreadScheduler;
+
+/// A scheduler that runs actions on a fixed number of worker threads.
+///
+/// Actions are queued in order of their due time, and the first worker that is
+/// idle runs the next due action. The scheduler is a handle: clones of it share
+/// the same workers. When the last handle is dropped, the workers stop after
+/// their current action, and actions that have not run yet are dropped.
+///
+/// A panic in an action does not take down its worker. The panic hook reports
+/// the panic as usual, then the panic is discarded, the action is cancelled,
+/// also if it is periodic, and the worker moves on to the next action.
+pub struct ThreadPoolScheduler {
+    handle: Arc<PoolHandle>,
+}
+
+/// The result of scheduling an action on a queueing scheduler.
+///
+/// Dropping this subscription cancels the action if it has not run yet,
+/// and it stops a periodic action.
+pub struct ScheduledSubscription {
+    cancelled: Rc<Cell<bool>>,
+    tracker: Tracker,
+}
+
+/// The result of scheduling an action on a thread scheduler.
+///
+/// Dropping this subscription cancels the action if it has not started yet,
+/// and it stops a periodic action. An action that is running is not
+/// interrupted.
+pub struct ThreadSubscription {
+    cancelled: Arc<AtomicBool>,
+}
+
+// A flag that marks a scheduled action as cancelled. The flag is also set
+// once a one-off action has run, so the subscription knows it is done.
+trait CancelFlag: Clone {
+    fn new() -> Self;
+    fn set(&self);
+    fn is_set(&self) -> bool;
+}
+
+// An action in a queue of scheduled actions.
+struct ScheduledAction<Time, Action, Flag> {
+    due: Time,
+
+    // Actions that are due at the same time run in the order of this number,
+    // which is the order in which they were scheduled.
+    sequence: u64,
+
+    period: Option<Duration>,
+    action: Action,
+    cancelled: Flag,
+}
+
+// A queue of scheduled actions, ordered by due time.
+struct ActionQueue<Time, Action = Box<dyn FnMut()>, Flag = Rc<Cell<bool>>> {
+    actions: BinaryHeap<ScheduledAction<Time, Action, Flag>>,
+    next_sequence: u64,
+}
+
+struct TestClock {
+    // The virtual time that has passed since the scheduler was created.
+    now: Cell<Duration>,
+
+    // The instant that virtual time zero maps to, for `now_instant()`.
+    epoch: Instant,
+    queue: RefCell<ActionQueue<Duration>>,
+}
+
+type SendActionQueue = ActionQueue<Instant, Box<dyn FnMut() + Send>, Arc<AtomicBool>>;
+
+struct Pool {
+    state: Mutex<PoolState>,
+
+    // Signalled when an action is queued, and when the pool shuts down.
+    changed: Condvar,
+}
+
+struct PoolState {
+    queue: SendActionQueue,
+    is_shut_down: bool,
+}
+
+// Shuts down the pool when the last scheduler handle is dropped. The workers
+// own the pool, but not the handle.
+struct PoolHandle {
+    pool: Arc<Pool>,
+}
+
+struct Trampoline {
+    queue: ActionQueue<Instant>,
+    is_running: bool,
+}
+
+thread_local! {
+    static TRAMPOLINE: RefCell<Trampoline> = RefCell::new(Trampoline {
+        queue: ActionQueue::new(),
+        is_running: false,
+    })
+}
+
+// Stops the trampoline when dropped, also when an action panics.
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        TRAMPOLINE.with(|trampoline| {
+            let mut trampoline = trampoline.borrow_mut();
+            trampoline.is_running = false;
+            // After a panic, the remaining actions are abandoned.
+            trampoline.queue = ActionQueue::new();
+        });
+    }
+}
+
+// Turns a one-off action into an action that can be stored in a queue.
+fn once<F: FnOnce() + 'static>(action: F) -> Box<dyn FnMut()> {
+    let mut action = Some(action);
+    Box::new(move || {
+        if let Some(action) = action.take() {
+            action();
+        }
+    })
+}
+
+// Like `once`, and records in the leak registry when the action ran.
+fn once_tracked<F: FnOnce() + 'static>(tracker: &Tracker, action: F) -> Box<dyn FnMut()> {
+    let termination = tracker.termination();
+    once(move || {
+        termination.terminate();
+        action();
+    })
+}
+
+// Like `once`, for actions that run on other threads.
+fn once_send<F: FnOnce() + Send + 'static>(action: F) -> Box<dyn FnMut() + Send> {
+    let mut action = Some(action);
+    Box::new(move || {
+        if let Some(action) = action.take() {
+            action();
+        }
+    })
+}
+
+impl CancelFlag for Rc<Cell<bool>> {
+    fn new() -> Rc<Cell<bool>> {
+        Rc::new(Cell::new(false))
+    }
+
+    fn set(&self) {
+        Cell::set(self, true);
+    }
+
+    fn is_set(&self) -> bool {
+        self.get()
+    }
+}
+
+impl CancelFlag for Arc<AtomicBool> {
+    fn new() -> Arc<AtomicBool> {
+        Arc::new(AtomicBool::new(false))
+    }
+
+    fn set(&self) {
+        self.store(true, AtomicOrdering::SeqCst);
+    }
+
+    fn is_set(&self) -> bool {
+        self.load(AtomicOrdering::SeqCst)
+    }
+}
+
+impl<Time: Ord, Action, Flag> PartialEq for ScheduledAction<Time, Action, Flag> {
+    fn eq(&self, other: &ScheduledAction<Time, Action, Flag>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Time: Ord, Action, Flag> Eq for ScheduledAction<Time, Action, Flag> {}
+
+impl<Time: Ord, Action, Flag> PartialOrd for ScheduledAction<Time, Action, Flag> {
+    fn partial_cmp(&self, other: &ScheduledAction<Time, Action, Flag>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Time: Ord, Action, Flag> Ord for ScheduledAction<Time, Action, Flag> {
+    fn cmp(&self, other: &ScheduledAction<Time, Action, Flag>) -> Ordering {
+        // The binary heap is a max-heap, but the earliest action should be on top.
+        (&other.due, other.sequence).cmp(&(&self.due, self.sequence))
+    }
+}
+
+impl<Time, Action, Flag> ActionQueue<Time, Action, Flag>
+where Time: Ord + Copy + Add<Duration, Output = Time>,
+      Flag: CancelFlag {
+    fn new() -> ActionQueue<Time, Action, Flag> {
+        ActionQueue {
+            actions: BinaryHeap::new(),
+            next_sequence: 0,
+        }
+    }
+
+    // Queues the action, and returns the flag that cancels it.
+    fn push(&mut self, due: Time, period: Option<Duration>, action: Action) -> Flag {
+        let cancelled = Flag::new();
+        self.push_action(ScheduledAction {
+            due: due,
+            sequence: 0,
+            period: period,
+            action: action,
+            cancelled: cancelled.clone(),
+        });
+        cancelled
+    }
+
+    fn push_action(&mut self, mut action: ScheduledAction<Time, Action, Flag>) {
+        action.sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.actions.push(action);
+    }
+
+    // Drops cancelled actions from the top of the queue.
+    fn drop_cancelled(&mut self) {
+        while self.actions.peek().is_some_and(|action| action.cancelled.is_set()) {
+            self.actions.pop();
+        }
+    }
+
+    // Returns the due time of the earliest action that was not cancelled.
+    fn next_due(&mut self) -> Option<Time> {
+        self.drop_cancelled();
+        self.actions.peek().map(|action| action.due)
+    }
+
+    // Removes the earliest action that was not cancelled, if it is due.
+    fn pop_due(&mut self, now: Time) -> Option<ScheduledAction<Time, Action, Flag>> {
+        match self.next_due() {
+            Some(due) if due <= now => self.actions.pop(),
+            _ => None,
+        }
+    }
+
+    // Removes the earliest action that was not cancelled.
+    fn pop(&mut self) -> Option<ScheduledAction<Time, Action, Flag>> {
+        self.drop_cancelled();
+        self.actions.pop()
+    }
+
+    // Puts a periodic action back into the queue, if it was not cancelled.
+    // A one-off action is done after it has run.
+    fn reschedule(&mut self, mut action: ScheduledAction<Time, Action, Flag>) {
+        match action.period {
+            Some(period) => {
+                if !action.cancelled.is_set() {
+                    action.due = action.due + period;
+                    self.push_action(action);
+                }
+            }
+            None => action.cancelled.set(),
+        }
+    }
+}
+
+impl CurrentThreadScheduler {
+    fn enqueue(&self,
+               delay: Duration,
+               period: Option<Duration>,
+               action: Box<dyn FnMut()>,
+               tracker: Tracker)
+               -> ScheduledSubscription {
+        let (cancelled, is_running) = TRAMPOLINE.with(|trampoline| {
+            let mut trampoline = trampoline.borrow_mut();
+            let cancelled = trampoline.queue.push(Instant::now() + delay, period, action);
+            (cancelled, trampoline.is_running)
+        });
+        let subscription = ScheduledSubscription { cancelled: cancelled, tracker: tracker };
+        if !is_running {
+            CurrentThreadScheduler::run();
+        }
+        subscription
+    }
+
+    // Runs queued actions until the queue is empty.
+    fn run() {
+        TRAMPOLINE.with(|trampoline| trampoline.borrow_mut().is_running = true);
+        let _running = Running;
+        loop {
+            // The trampoline is not borrowed while an action runs,
+            // so the action can schedule new actions.
+            let next = TRAMPOLINE.with(|trampoline| trampoline.borrow_mut().queue.pop());
+            let mut action = match next {
+                Some(action) => action,
+                None => break,
+            };
+            let now = Instant::now();
+            if action.due > now {
+                thread::sleep(action.due - now);
+            }
+            (action.action)();
+            TRAMPOLINE.with(|trampoline| trampoline.borrow_mut().queue.reschedule(action));
+        }
+    }
+}
+
+impl Scheduler for CurrentThreadScheduler {
+    type Subscription = ScheduledSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> ScheduledSubscription
+        where F: FnOnce() + 'static {
+        let tracker = Tracker::new("scheduled action");
+        let action = once_tracked(&tracker, action);
+        self.enqueue(delay, None, action, tracker)
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> ScheduledSubscription
+        where F: FnMut() + 'static {
+        let tracker = Tracker::new("scheduled action");
+        self.enqueue(period, Some(period), Box::new(action), tracker)
+    }
+}
+
+impl EventLoop {
+    /// Creates a new event loop without actions.
+    pub fn new() -> EventLoop {
+        EventLoop {
+            queue: Rc::new(RefCell::new(ActionQueue::new())),
+        }
+    }
+
+    /// Runs actions until no actions remain.
+    ///
+    /// When no action is due, this blocks the current thread until the next
+    /// action is due. While a periodic action has not been cancelled, this
+    /// does not return.
+    pub fn run(&self) {
+        loop {
+            let next_due = self.queue.borrow_mut().next_due();
+            let due = match next_due {
+                Some(due) => due,
+                None => break,
+            };
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            self.run_until_idle();
+        }
+    }
+
+    /// Runs the actions that are due, until no action is due.
+    ///
+    /// This does not wait for actions that are scheduled in the future.
+    pub fn run_until_idle(&self) {
+        loop {
+            // The queue is not borrowed while an action runs,
+            // so the action can schedule new actions.
+            let next = self.queue.borrow_mut().pop_due(Instant::now());
+            let mut action = match next {
+                Some(action) => action,
+                None => break,
+            };
+            (action.action)();
+            self.queue.borrow_mut().reschedule(action);
+        }
+    }
+}
+
+impl Default for EventLoop {
+    fn default() -> EventLoop {
+        EventLoop::new()
+    }
+}
+
+impl Clone for EventLoop {
+    fn clone(&self) -> EventLoop {
+        EventLoop {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl Scheduler for EventLoop {
+    type Subscription = ScheduledSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> ScheduledSubscription
+        where F: FnOnce() + 'static {
+        let tracker = Tracker::new("scheduled action");
+        let action = once_tracked(&tracker, action);
+        let cancelled = self.queue.borrow_mut().push(Instant::now() + delay, None, action);
+        ScheduledSubscription { cancelled: cancelled, tracker: tracker }
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> ScheduledSubscription
+        where F: FnMut() + 'static {
+        let due = Instant::now() + period;
+        let cancelled = self.queue.borrow_mut().push(due, Some(period), Box::new(action));
+        let tracker = Tracker::new("scheduled action");
+        ScheduledSubscription { cancelled: cancelled, tracker: tracker }
+    }
+}
+
+impl TestScheduler {
+    /// Creates a new test scheduler at virtual time zero.
+    pub fn new() -> TestScheduler {
+        TestScheduler {
+            clock: Rc::new(TestClock {
+                now: Cell::new(Duration::from_secs(0)),
+                epoch: Instant::now(),
+                queue: RefCell::new(ActionQueue::new()),
+            }),
+        }
+    }
+
+    /// Returns the virtual time that has passed since the scheduler was created.
+    pub fn now(&self) -> Duration {
+        self.clock.now.get()
+    }
+
+    /// Advances the clock by the duration, running the actions that become due.
+    pub fn advance_by(&self, duration: Duration) {
+        let time = self.now() + duration;
+        self.advance_to(time);
+    }
+
+    /// Advances the clock to the given time, running the actions that become due.
+    ///
+    /// While an action runs, the clock reads the due time of that action.
+    /// Actions that are scheduled by a running action run in this call too, if
+    /// they become due before the given time. Panics if the time is earlier
+    /// than the current time.
+    pub fn advance_to(&self, time: Duration) {
+        assert!(time >= self.now(), "virtual time cannot go backwards");
+        loop {
+            // The queue is not borrowed while an action runs,
+            // so the action can schedule new actions.
+            let next = self.clock.queue.borrow_mut().pop_due(time);
+            let mut action = match next {
+                Some(action) => action,
+                None => break,
+            };
+            self.clock.now.set(action.due);
+            (action.action)();
+            self.clock.queue.borrow_mut().reschedule(action);
+        }
+        self.clock.now.set(time);
+    }
+}
+
+impl Default for TestScheduler {
+    fn default() -> TestScheduler {
+        TestScheduler::new()
+    }
+}
+
+impl Clone for TestScheduler {
+    fn clone(&self) -> TestScheduler {
+        TestScheduler {
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl Scheduler for TestScheduler {
+    type Subscription = ScheduledSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> ScheduledSubscription
+        where F: FnOnce() + 'static {
+        let due = self.now() + delay;
+        let tracker = Tracker::new("scheduled action");
+        let action = once_tracked(&tracker, action);
+        let cancelled = self.clock.queue.borrow_mut().push(due, None, action);
+        ScheduledSubscription { cancelled: cancelled, tracker: tracker }
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> ScheduledSubscription
+        where F: FnMut() + 'static {
+        let due = self.now() + period;
+        let cancelled = self.clock.queue.borrow_mut().push(due, Some(period), Box::new(action));
+        let tracker = Tracker::new("scheduled action");
+        ScheduledSubscription { cancelled: cancelled, tracker: tracker }
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.clock.epoch + self.now()
+    }
+}
+
+impl SendScheduler for NewThreadScheduler {
+    type Subscription = ThreadSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> ThreadSubscription
+        where F: FnOnce() + Send + 'static {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let is_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            if !is_cancelled.is_set() {
+                action();
+                is_cancelled.set();
+            }
+        });
+        ThreadSubscription { cancelled: cancelled }
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, mut action: F) -> ThreadSubscription
+        where F: FnMut() + Send + 'static {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let is_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let mut due = Instant::now() + period;
+            loop {
+                let now = Instant::now();
+                if due > now {
+                    thread::sleep(due - now);
+                }
+                if is_cancelled.is_set() {
+                    break;
+                }
+                action();
+                due += period;
+            }
+        });
+        ThreadSubscription { cancelled: cancelled }
+    }
+}
+
+impl ThreadPoolScheduler {
+    /// Creates a pool with `n` worker threads.
+    ///
+    /// Panics if `n` is zero.
+    pub fn new(n: usize) -> ThreadPoolScheduler {
+        assert!(n > 0, "a thread pool needs at least one worker thread");
+        let pool = Arc::new(Pool {
+            state: Mutex::new(PoolState {
+                queue: ActionQueue::new(),
+                is_shut_down: false,
+            }),
+            changed: Condvar::new(),
+        });
+        for _ in 0..n {
+            let pool = pool.clone();
+            thread::spawn(move || pool.work());
+        }
+        ThreadPoolScheduler {
+            handle: Arc::new(PoolHandle { pool: pool }),
+        }
+    }
+
+    fn enqueue(&self,
+               delay: Duration,
+               period: Option<Duration>,
+               action: Box<dyn FnMut() + Send>)
+               -> ThreadSubscription {
+        let pool = &self.handle.pool;
+        let cancelled = pool.state.lock().unwrap().queue.push(Instant::now() + delay, period, action);
+        pool.changed.notify_one();
+        ThreadSubscription { cancelled: cancelled }
+    }
+}
+
+impl Clone for ThreadPoolScheduler {
+    fn clone(&self) -> ThreadPoolScheduler {
+        ThreadPoolScheduler {
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl SendScheduler for ThreadPoolScheduler {
+    type Subscription = ThreadSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> ThreadSubscription
+        where F: FnOnce() + Send + 'static {
+        self.enqueue(delay, None, once_send(action))
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> ThreadSubscription
+        where F: FnMut() + Send + 'static {
+        self.enqueue(period, Some(period), Box::new(action))
+    }
+}
+
+impl Pool {
+    // Runs due actions until the pool shuts down.
+    fn work(&self) {
+        loop {
+            let mut action = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if state.is_shut_down {
+                        return;
+                    }
+                    let now = Instant::now();
+                    if let Some(action) = state.queue.pop_due(now) {
+                        break action;
+                    }
+                    state = match state.queue.next_due() {
+                        Some(due) => self.changed.wait_timeout(state, due - now).unwrap().0,
+                        None => self.changed.wait(state).unwrap(),
+                    };
+                }
+            };
+            // The lock is not held while an action runs,
+            // so other workers can run actions meanwhile.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| (action.action)()));
+            if result.is_ok() {
+                self.state.lock().unwrap().queue.reschedule(action);
+            } else {
+                // The panic hook reported the panic already.
+                action.cancelled.set();
+            }
+        }
+    }
+}
+
+impl Drop for PoolHandle {
+    fn drop(&mut self) {
+        let mut state = self.pool.state.lock().unwrap();
+        state.is_shut_down = true;
+        state.queue = ActionQueue::new();
+        self.pool.changed.notify_all();
+    }
+}
+
+impl Subscription for ThreadSubscription {
+    fn unsubscribe(&mut self) {
+        self.cancelled.set();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.cancelled.is_set()
+    }
+}
+
+impl Drop for ThreadSubscription {
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
+
+impl Subscription for ScheduledSubscription {
+    fn unsubscribe(&mut self) {
+        self.cancelled.set();
+        self.tracker.untrack();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.cancelled.is_set()
+    }
+}
+
+impl Drop for ScheduledSubscription {
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
+
+impl<S: Scheduler> Scheduler for &S {
+    type Subscription = S::Subscription;
+
+    fn schedule<F>(&self, action: F) -> S::Subscription
+        where F: FnOnce() + 'static {
+        (**self).schedule(action)
+    }
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> S::Subscription
+        where F: FnOnce() + 'static {
+        (**self).schedule_after(delay, action)
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> S::Subscription
+        where F: FnMut() + 'static {
+        (**self).schedule_periodic(period, action)
+    }
+
+    fn now_instant(&self) -> Instant {
+        (**self).now_instant()
+    }
+}
+
+impl<S: SendScheduler> SendScheduler for &S {
+    type Subscription = S::Subscription;
+
+    fn schedule<F>(&self, action: F) -> S::Subscription
+        where F: FnOnce() + Send + 'static {
+        (**self).schedule(action)
+    }
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> S::Subscription
+        where F: FnOnce() + Send + 'static {
+        (**self).schedule_after(delay, action)
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> S::Subscription
+        where F: FnMut() + Send + 'static {
+        (**self).schedule_periodic(period, action)
+    }
+}
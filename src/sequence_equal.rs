@@ -0,0 +1,214 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `sequence_equal()` operator, which compares two observables value by value.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `sequence_equal()` on an observable.
+pub struct SequenceEqualObservable<Source, Other> {
+    source: Source,
+    other: Other,
+}
+
+/// The result of subscribing to a `sequence_equal()` observable.
+///
+/// Dropping this subscription drops the subscriptions to both observables.
+pub struct SequenceEqualSubscription<T, E, Subs, OtherSubs> {
+    state: Rc<RefCell<SequenceEqualState<T, E, Subs, OtherSubs>>>,
+}
+
+struct SequenceEqualState<T, E, Subs, OtherSubs> {
+    observer: ObserverSlot<bool, E>,
+
+    // The values of the observable that is ahead, which the other one has
+    // not pushed counterparts for yet. Only one side can be ahead at a time.
+    queue: VecDeque<T>,
+    is_source_ahead: bool,
+
+    is_source_completed: bool,
+    is_other_completed: bool,
+
+    // The subscriptions are stored once `subscribe()` returns, unless the
+    // result was known by then.
+    source: Option<Subs>,
+    other: Option<OtherSubs>,
+}
+
+type SequenceEqualShared<T, E, Subs, OtherSubs> =
+    Rc<RefCell<SequenceEqualState<T, E, Subs, OtherSubs>>>;
+
+struct SequenceEqualObserver<T, E, Subs, OtherSubs> {
+    state: SequenceEqualShared<T, E, Subs, OtherSubs>,
+    is_source: bool,
+}
+
+impl<Source, Other> SequenceEqualObservable<Source, Other> {
+    pub fn new(source: Source, other: Other) -> SequenceEqualObservable<Source, Other> {
+        SequenceEqualObservable {
+            source: source,
+            other: other,
+        }
+    }
+}
+
+impl<Source, Other, T, E> Observable for SequenceEqualObservable<Source, Other>
+where Source: Observable<Item = T, Error = E>,
+      Source::Subscription: 'static,
+      Other: Observable<Item = T, Error = E>,
+      Other::Subscription: 'static,
+      T: Clone + PartialEq + 'static,
+      E: Clone + 'static {
+    type Item = bool;
+    type Error = E;
+    type Subscription = SequenceEqualSubscription<T, E, Source::Subscription, Other::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<bool, E> + 'static {
+        let state = Rc::new(RefCell::new(SequenceEqualState {
+            observer: ObserverSlot::new("sequence_equal subscription", observer.boxed()),
+            queue: VecDeque::new(),
+            is_source_ahead: false,
+            is_source_completed: false,
+            is_other_completed: false,
+            source: None,
+            other: None,
+        }));
+
+        let source = self.source.subscribe(SequenceEqualObserver {
+            state: state.clone(),
+            is_source: true,
+        });
+        if state.borrow().observer.is_done() {
+            drop(source);
+            return SequenceEqualSubscription { state: state };
+        }
+        state.borrow_mut().source = Some(source);
+
+        let other = self.other.subscribe(SequenceEqualObserver {
+            state: state.clone(),
+            is_source: false,
+        });
+        let other = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                Some(other)
+            } else {
+                state.other = Some(other);
+                None
+            }
+        };
+        drop(other);
+        SequenceEqualSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.other.describe()];
+        Graph::operator::<Self>("sequence_equal", sources)
+    }
+}
+
+impl<T, E, Subs, OtherSubs> OperatorState for SequenceEqualState<T, E, Subs, OtherSubs> {
+    type Item = bool;
+    type Error = E;
+    type Released = (Option<Subs>, Option<OtherSubs>, VecDeque<T>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<bool, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<Subs>, Option<OtherSubs>, VecDeque<T>) {
+        let queue = mem::take(&mut self.queue);
+        (self.source.take(), self.other.take(), queue)
+    }
+}
+
+impl<T, E, Subs, OtherSubs> Observer<T, E> for SequenceEqualObserver<T, E, Subs, OtherSubs>
+    where T: PartialEq {
+    fn on_next(&mut self, item: T) {
+        let counterpart = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if state.queue.is_empty() || state.is_source_ahead == self.is_source {
+                let is_other_completed = if self.is_source {
+                    state.is_other_completed
+                } else {
+                    state.is_source_completed
+                };
+                if !is_other_completed {
+                    state.is_source_ahead = self.is_source;
+                    state.queue.push_back(item);
+                    return;
+                }
+                // The other observable completed with fewer values.
+                None
+            } else {
+                state.queue.pop_front()
+            }
+        };
+        // Comparing runs user code, so it happens outside of the borrow.
+        match counterpart {
+            Some(ref counterpart) if *counterpart == item => {}
+            _ => operator::complete_with(&self.state, false),
+        }
+    }
+
+    fn on_completed(self) {
+        let result = {
+            let mut state = self.state.borrow_mut();
+            if state.observer.is_done() {
+                return;
+            }
+            if self.is_source {
+                state.is_source_completed = true;
+            } else {
+                state.is_other_completed = true;
+            }
+            if !state.queue.is_empty() && state.is_source_ahead != self.is_source {
+                // The other observable pushed values that this one lacks.
+                Some(false)
+            } else if state.is_source_completed && state.is_other_completed {
+                Some(state.queue.is_empty())
+            } else {
+                None
+            }
+        };
+        if let Some(is_equal) = result {
+            operator::complete_with(&self.state, is_equal);
+        }
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Subs, OtherSubs> Subscription for SequenceEqualSubscription<T, E, Subs, OtherSubs> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<T, E, Subs, OtherSubs> Drop for SequenceEqualSubscription<T, E, Subs, OtherSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
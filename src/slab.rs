@@ -0,0 +1,88 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! A module for storing values under stable keys.
+//!
+//! A slab is like a vector where removing an element leaves a vacant entry
+//! instead of shifting the elements after it. The key of an element therefore
+//! remains valid until the element is removed, and both insertion and removal
+//! are O(1). Vacant entries are reused by later insertions.
+
+use std::iter::{Enumerate, FilterMap};
+use std::slice;
+use std::vec;
+
+/// A collection of values with stable keys.
+pub struct Slab<T> {
+    entries: Vec<Option<T>>,
+    vacant: Vec<usize>,
+}
+
+/// Iterator over the keys and values in a slab.
+pub type Iter<'a, T> = FilterMap<Enumerate<slice::Iter<'a, Option<T>>>,
+                                 fn((usize, &'a Option<T>)) -> Option<(usize, &'a T)>>;
+
+/// Iterator that removes all values from a slab.
+pub type Drain<'a, T> = FilterMap<vec::Drain<'a, Option<T>>, fn(Option<T>) -> Option<T>>;
+
+impl<T> Slab<T> {
+    /// Creates a new empty slab.
+    pub fn new() -> Slab<T> {
+        Slab {
+            entries: Vec::new(),
+            vacant: Vec::new(),
+        }
+    }
+
+    /// Stores the value and returns its key.
+    pub fn insert(&mut self, value: T) -> usize {
+        match self.vacant.pop() {
+            Some(key) => {
+                self.entries[key] = Some(value);
+                key
+            }
+            None => {
+                self.entries.push(Some(value));
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    /// Removes the value with the given key, if there is one.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        let value = match self.entries.get_mut(key) {
+            Some(entry) => entry.take(),
+            None => None,
+        };
+        if value.is_some() {
+            self.vacant.push(key);
+        }
+        value
+    }
+
+    /// Returns the value with the given key, if there is one.
+    pub fn get(&self, key: usize) -> Option<&T> {
+        self.entries.get(key).and_then(|entry| entry.as_ref())
+    }
+
+    /// Returns an iterator over the keys and values in the slab.
+    pub fn iter<'a>(&'a self) -> Iter<'a, T> {
+        fn occupied<T>((key, entry): (usize, &Option<T>)) -> Option<(usize, &T)> {
+            entry.as_ref().map(|value| (key, value))
+        }
+        self.entries.iter().enumerate().filter_map(occupied as fn(_) -> _)
+    }
+
+    /// Removes all values from the slab and returns an iterator over them.
+    pub fn drain<'a>(&'a mut self) -> Drain<'a, T> {
+        fn occupied<T>(entry: Option<T>) -> Option<T> {
+            entry
+        }
+        self.vacant.clear();
+        self.entries.drain(..).filter_map(occupied as fn(_) -> _)
+    }
+}
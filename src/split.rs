@@ -0,0 +1,175 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that divide the values of an observable between two observables.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use subject::{Subject, SubjectSubscription};
+use subscription::Subscription;
+
+/// One of the observables that `split_results()` or `route()` returns.
+///
+/// The two observables share a single subscription to the source. The
+/// source is subscribed to when the first observer subscribes to either of
+/// them, and the subscription is dropped when the last subscription to
+/// either of them is dropped.
+pub struct SplitObservable<Source: Observable, U> {
+    shared: Rc<SplitShared<Source>>,
+
+    // Picks the values of this observable out of the values of the source.
+    select: fn(Source::Item) -> Option<U>,
+}
+
+/// The result of subscribing to an observable that `split_results()` or
+/// `route()` returns.
+///
+/// Dropping this subscription will prevent further calls on the observer.
+/// Dropping the last subscription also drops the subscription to the source.
+pub struct SplitSubscription<Source: Observable> {
+    subscription: SubjectSubscription<Source::Item, Source::Error>,
+
+    // The share of the connection is given up only once.
+    shared: Option<Rc<SplitShared<Source>>>,
+}
+
+struct SplitShared<Source: Observable> {
+    subject: Subject<Source::Item, Source::Error>,
+    source: RefCell<Source>,
+    connection: RefCell<Option<Source::Subscription>>,
+
+    // The number of subscriptions to either observable.
+    ref_count: Cell<usize>,
+}
+
+struct SelectObserver<O, T, U> {
+    observer: O,
+    select: fn(T) -> Option<U>,
+}
+
+/// Splits an observable into two that share a single subscription to it.
+///
+/// The values of the source for which `left` returns a value go to the first
+/// observable, and those for which `right` returns a value go to the second.
+pub fn split<Source, L, R>(source: Source,
+                           left: fn(Source::Item) -> Option<L>,
+                           right: fn(Source::Item) -> Option<R>)
+                           -> (SplitObservable<Source, L>, SplitObservable<Source, R>)
+    where Source: Observable {
+    let shared = Rc::new(SplitShared {
+        subject: Subject::new(),
+        source: RefCell::new(source),
+        connection: RefCell::new(None),
+        ref_count: Cell::new(0),
+    });
+    let left = SplitObservable {
+        shared: shared.clone(),
+        select: left,
+    };
+    let right = SplitObservable {
+        shared: shared,
+        select: right,
+    };
+    (left, right)
+}
+
+impl<T, U, E, O> Observer<T, E> for SelectObserver<O, T, U> where O: Observer<U, E> {
+    fn on_next(&mut self, item: T) {
+        if let Some(selected) = (self.select)(item) {
+            self.observer.on_next(selected);
+        }
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+impl<Source, U> Observable for SplitObservable<Source, U>
+where Source: Observable,
+      Source::Item: 'static,
+      Source::Error: 'static,
+      U: Clone + 'static {
+    type Item = U;
+    type Error = Source::Error;
+    type Subscription = SplitSubscription<Source>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<U, Source::Error> + 'static {
+        let select_observer = SelectObserver {
+            observer: observer,
+            select: self.select,
+        };
+        // Subscribe before connecting, so the first observer receives
+        // the values that the source pushes upon subscription.
+        let subscription = self.shared.subject.observable().subscribe(select_observer);
+        let ref_count = self.shared.ref_count.get() + 1;
+        self.shared.ref_count.set(ref_count);
+
+        // The count is raised before connecting, so an observer that
+        // subscribes to the other observable meanwhile does not connect again.
+        if ref_count == 1 {
+            let sink = self.shared.subject.sink();
+            let connection = self.shared.source.borrow_mut().subscribe(sink);
+            // If every subscription was dropped meanwhile, the connection is
+            // dropped right away.
+            if self.shared.ref_count.get() > 0 {
+                *self.shared.connection.borrow_mut() = Some(connection);
+            }
+        }
+        SplitSubscription {
+            subscription: subscription,
+            shared: Some(self.shared.clone()),
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("split", vec![self.shared.source.borrow().describe()])
+    }
+}
+
+impl<Source: Observable> SplitSubscription<Source> {
+    // Gives up this subscription's share of the connection. This only has an
+    // effect the first time it is called.
+    fn release(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            let ref_count = shared.ref_count.get() - 1;
+            shared.ref_count.set(ref_count);
+            if ref_count == 0 {
+                let connection = shared.connection.borrow_mut().take();
+                // The source subscription is dropped outside of the borrow,
+                // in case dropping it ends up in this observable again.
+                drop(connection);
+            }
+        }
+    }
+}
+
+impl<Source: Observable> Subscription for SplitSubscription<Source> {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        self.release();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<Source: Observable> Drop for SplitSubscription<Source> {
+    fn drop(&mut self) {
+        // The observer subscription is dropped after this, with the fields.
+        self.release();
+    }
+}
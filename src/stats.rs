@@ -0,0 +1,574 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Operators that summarize numeric observables.
+
+use NoError;
+use describe::Graph;
+use generate::Never;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::f64;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// Summary statistics of the values of an observable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    /// The number of values.
+    pub count: u64,
+
+    /// The arithmetic mean of the values.
+    pub mean: f64,
+
+    /// The population variance of the values.
+    pub variance: f64,
+
+    /// The smallest value.
+    pub min: f64,
+
+    /// The largest value.
+    pub max: f64,
+}
+
+impl Stats {
+    /// Returns the population standard deviation of the values.
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+// Accumulates statistics with Welford's algorithm, which is numerically
+// stable and needs constant memory.
+struct Welford {
+    count: u64,
+    mean: f64,
+
+    // The sum of squared differences from the mean.
+    m2: f64,
+
+    min: f64,
+    max: f64,
+}
+
+impl Welford {
+    fn new() -> Welford {
+        Welford {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            count: self.count,
+            mean: self.mean,
+            variance: self.m2 / self.count as f64,
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// The result of calling `stats()` or `stats_final()` on an observable.
+pub struct StatsObservable<Source> {
+    source: Source,
+    is_final: bool,
+}
+
+struct StatsObserver<O> {
+    observer: O,
+    is_final: bool,
+    welford: Welford,
+}
+
+impl<Source> StatsObservable<Source> {
+    pub fn new(source: Source, is_final: bool) -> StatsObservable<Source> {
+        StatsObservable {
+            source: source,
+            is_final: is_final,
+        }
+    }
+}
+
+impl<Source> Observable for StatsObservable<Source>
+where Source: Observable,
+      Source::Item: Into<f64> {
+    type Item = Stats;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Stats, Self::Error> + 'static {
+        let stats_observer = StatsObserver {
+            observer: observer,
+            is_final: self.is_final,
+            welford: Welford::new(),
+        };
+        self.source.subscribe(stats_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("stats", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<T, E> for StatsObserver<O>
+where T: Into<f64>,
+      O: Observer<Stats, E> {
+    fn on_next(&mut self, item: T) {
+        self.welford.add(item.into());
+        if !self.is_final {
+            self.observer.on_next(self.welford.stats());
+        }
+    }
+
+    fn on_completed(mut self) {
+        if self.is_final && self.welford.count > 0 {
+            self.observer.on_next(self.welford.stats());
+        }
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+/// The result of calling `ema()` on an observable.
+///
+/// By default, the average is pushed for every value. Call `warm_up()` to
+/// hold back the first averages, which are based on few values.
+pub struct EmaObservable<Source> {
+    source: Source,
+    alpha: f64,
+    warm_up: usize,
+}
+
+struct EmaObserver<O> {
+    observer: O,
+    alpha: f64,
+
+    // The number of values that are not pushed yet.
+    warm_up: usize,
+
+    average: Option<f64>,
+}
+
+impl<Source> EmaObservable<Source> {
+    pub fn new(source: Source, alpha: f64) -> EmaObservable<Source> {
+        EmaObservable {
+            source: source,
+            alpha: alpha.clamp(0.0, 1.0),
+            warm_up: 0,
+        }
+    }
+
+    /// Pushes the average only after the first `count` values.
+    ///
+    /// The values still count towards the average.
+    pub fn warm_up(mut self, count: usize) -> EmaObservable<Source> {
+        self.warm_up = count;
+        self
+    }
+}
+
+impl<Source> Observable for EmaObservable<Source>
+where Source: Observable,
+      Source::Item: Into<f64> {
+    type Item = f64;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<f64, Self::Error> + 'static {
+        let ema_observer = EmaObserver {
+            observer: observer,
+            alpha: self.alpha,
+            warm_up: self.warm_up,
+            average: None,
+        };
+        self.source.subscribe(ema_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("ema", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O> Observer<T, E> for EmaObserver<O>
+where T: Into<f64>,
+      O: Observer<f64, E> {
+    fn on_next(&mut self, item: T) {
+        let value = item.into();
+        // The first value is the initial average.
+        let average = match self.average {
+            Some(average) => average + self.alpha * (value - average),
+            None => value,
+        };
+        self.average = Some(average);
+        if self.warm_up > 0 {
+            self.warm_up -= 1;
+        } else {
+            self.observer.on_next(average);
+        }
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+/// A compact summary of numeric values, for `histogram()` and `percentiles()`.
+pub trait Sketch {
+    /// What the sketch reports about the values so far.
+    type Snapshot: Clone;
+
+    /// Adds a value to the sketch.
+    fn add(&mut self, value: f64);
+
+    /// Returns what the sketch reports about the values so far.
+    fn snapshot(&self) -> Self::Snapshot;
+}
+
+/// Counts of values per bucket.
+///
+/// Bucket `i` counts the values that are at most `bounds()[i]` and larger
+/// than the bound before it. The last bucket counts the values that are
+/// larger than all bounds, so there is one more count than there are bounds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Creates an empty histogram with the given upper bounds of the buckets.
+    ///
+    /// The bounds are sorted, so they can be passed in any order.
+    pub fn new(mut bounds: Vec<f64>) -> Histogram {
+        bounds.sort_by(|a, b| a.partial_cmp(b).expect("histogram bounds must not be NaN"));
+        let counts = vec![0; bounds.len() + 1];
+        Histogram {
+            bounds: bounds,
+            counts: counts,
+        }
+    }
+
+    /// Returns the upper bounds of the buckets, in ascending order.
+    pub fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    /// Returns the number of values per bucket.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the number of values in all buckets.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+impl Sketch for Histogram {
+    type Snapshot = Histogram;
+
+    fn add(&mut self, value: f64) {
+        let bucket = self.bounds.iter().position(|&bound| value <= bound);
+        let bucket = bucket.unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+    }
+
+    fn snapshot(&self) -> Histogram {
+        self.clone()
+    }
+}
+
+/// Estimates quantiles of values with a relative error of at most 1%.
+///
+/// Values are counted in buckets whose width grows exponentially, so the
+/// memory use grows with the logarithm of the range of the values, rather
+/// than with their number. The snapshot contains an estimate per quantile,
+/// in the order in which the quantiles were given, or NaN if there are no
+/// values yet.
+#[derive(Clone, Debug)]
+pub struct Quantiles {
+    quantiles: Vec<f64>,
+
+    // The ratio between the bounds of consecutive buckets.
+    gamma: f64,
+
+    // Counts per bucket index, for the magnitudes of positive and negative values.
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+
+    // Values that are too close to zero to have a bucket.
+    zeros: u64,
+
+    count: u64,
+}
+
+// Values with a smaller magnitude are counted as zero.
+const MIN_MAGNITUDE: f64 = 1e-9;
+
+// The relative error of the estimates.
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+impl Quantiles {
+    /// Creates an empty sketch for the given quantiles, between 0 and 1.
+    pub fn new(quantiles: &[f64]) -> Quantiles {
+        Quantiles {
+            quantiles: quantiles.iter().map(|q| q.clamp(0.0, 1.0)).collect(),
+            gamma: (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zeros: 0,
+            count: 0,
+        }
+    }
+
+    fn index(&self, magnitude: f64) -> i32 {
+        (magnitude.ln() / self.gamma.ln()).ceil() as i32
+    }
+
+    // Returns the value that represents the bucket, which is within the
+    // relative accuracy of all values in the bucket.
+    fn value(&self, index: i32) -> f64 {
+        2.0 * self.gamma.powi(index) / (self.gamma + 1.0)
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        let rank = (q * (self.count - 1) as f64) as u64;
+        let mut seen = 0;
+        // From the smallest value to the largest.
+        for (&index, &count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return -self.value(index);
+            }
+        }
+        seen += self.zeros;
+        if seen > rank {
+            return 0.0;
+        }
+        for (&index, &count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return self.value(index);
+            }
+        }
+        // Not reached, because the counts add up to more than the rank.
+        f64::NAN
+    }
+}
+
+impl Sketch for Quantiles {
+    type Snapshot = Vec<f64>;
+
+    fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.count += 1;
+        if value.abs() < MIN_MAGNITUDE {
+            self.zeros += 1;
+            return;
+        }
+        let index = self.index(value.abs());
+        let buckets = if value > 0.0 { &mut self.positive } else { &mut self.negative };
+        *buckets.entry(index).or_insert(0) += 1;
+    }
+
+    fn snapshot(&self) -> Vec<f64> {
+        self.quantiles.iter().map(|&q| self.quantile(q)).collect()
+    }
+}
+
+/// The result of calling `histogram()` or `percentiles()` on an observable.
+///
+/// By default, the snapshot of the sketch is pushed when the source
+/// completes. Call `snapshot_on()` to also push snapshots while the source
+/// is running.
+pub struct SketchObservable<Source, S, Trigger> {
+    source: Source,
+    sketch: S,
+    trigger: Trigger,
+}
+
+/// The result of subscribing to a `histogram()` or `percentiles()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the trigger.
+pub struct SketchSubscription<Subs, S: Sketch, E, TrSubs> {
+    subscription: Subs,
+    state: Rc<RefCell<SketchState<S, E, TrSubs>>>,
+}
+
+struct SketchState<S: Sketch, E, TrSubs> {
+    observer: ObserverSlot<S::Snapshot, E>,
+    sketch: S,
+    trigger: Option<TrSubs>,
+}
+
+struct SketchObserver<S: Sketch, E, TrSubs> {
+    state: Rc<RefCell<SketchState<S, E, TrSubs>>>,
+}
+
+struct SnapshotObserver<S: Sketch, E, TrSubs> {
+    state: Rc<RefCell<SketchState<S, E, TrSubs>>>,
+}
+
+impl<Source, S> SketchObservable<Source, S, Never<(), NoError>> {
+    pub fn new(source: Source, sketch: S) -> SketchObservable<Source, S, Never<(), NoError>> {
+        SketchObservable {
+            source: source,
+            sketch: sketch,
+            trigger: Never::new(),
+        }
+    }
+}
+
+impl<Source, S, Trigger> SketchObservable<Source, S, Trigger> {
+    /// Also pushes a snapshot every time `trigger` pushes a value.
+    ///
+    /// The snapshots cover all values so far; the sketch is not reset.
+    pub fn snapshot_on<Tr>(self, trigger: Tr) -> SketchObservable<Source, S, Tr>
+        where Tr: Observable {
+        SketchObservable {
+            source: self.source,
+            sketch: self.sketch,
+            trigger: trigger,
+        }
+    }
+}
+
+impl<Source, S, Trigger> Observable for SketchObservable<Source, S, Trigger>
+where Source: Observable,
+      Source::Item: Into<f64>,
+      Source::Error: 'static,
+      S: Sketch + Clone + 'static,
+      Trigger: Observable,
+      Trigger::Subscription: 'static {
+    type Item = S::Snapshot;
+    type Error = Source::Error;
+    type Subscription = SketchSubscription<Source::Subscription, S, Source::Error,
+                                           Trigger::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<S::Snapshot, Source::Error> + 'static {
+        let state = Rc::new(RefCell::new(SketchState {
+            observer: ObserverSlot::new("sketch subscription", observer.boxed()),
+            sketch: self.sketch.clone(),
+            trigger: None,
+        }));
+
+        let trigger = self.trigger.subscribe(SnapshotObserver { state: state.clone() });
+        state.borrow_mut().trigger = Some(trigger);
+
+        let sketch_observer = SketchObserver { state: state.clone() };
+        SketchSubscription {
+            subscription: self.source.subscribe(sketch_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.trigger.describe()];
+        Graph::operator::<Self>("sketch", sources)
+    }
+}
+
+impl<S: Sketch, E, TrSubs> OperatorState for SketchState<S, E, TrSubs> {
+    type Item = S::Snapshot;
+    type Error = E;
+    type Released = Option<TrSubs>;
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<S::Snapshot, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> Option<TrSubs> {
+        self.trigger.take()
+    }
+}
+
+impl<T, E, S, TrSubs> Observer<T, E> for SketchObserver<S, E, TrSubs>
+where T: Into<f64>,
+      S: Sketch {
+    fn on_next(&mut self, item: T) {
+        let mut state = self.state.borrow_mut();
+        if !state.observer.is_done() {
+            state.sketch.add(item.into());
+        }
+    }
+
+    fn on_completed(self) {
+        let snapshot = self.state.borrow().sketch.snapshot();
+        operator::complete_with(&self.state, snapshot);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<S: Sketch, E, TrSubs, U, F> Observer<U, F> for SnapshotObserver<S, E, TrSubs> {
+    fn on_next(&mut self, _item: U) {
+        let snapshot = self.state.borrow().sketch.snapshot();
+        operator::push(&self.state, snapshot);
+    }
+
+    fn on_completed(self) {
+        // Without a trigger, the snapshot is still pushed upon completion.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the trigger is ignored, like its completion.
+    }
+}
+
+impl<Subs, S, E, TrSubs> Subscription for SketchSubscription<Subs, S, E, TrSubs>
+where Subs: Subscription,
+      S: Sketch {
+    fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<Subs, S: Sketch, E, TrSubs> Drop for SketchSubscription<Subs, S, E, TrSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
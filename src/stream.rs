@@ -0,0 +1,565 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Bridges between observables and futures.
+//!
+//! This module is only available with the `futures` feature.
+
+use futures::future::{self, AbortHandle, Future, FutureExt};
+use futures::stream::{self, Once, Stream, StreamExt, TryStream};
+use futures::task::{self, ArcWake, Context, LocalSpawn, LocalSpawnExt, Poll, SpawnError, Waker};
+use observable::Observable;
+use observe_on::{OverflowPolicy, assert_non_blocking};
+use observer::{BoxedObserver, Observer};
+use scheduler::Scheduler;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::mem;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use subscription::Subscription;
+
+/// How long `from_stream()` waits before it checks whether a pending stream
+/// has been woken, unless configured otherwise.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The result of calling `from_stream()`.
+pub struct FromStream<S, Sch> {
+    // The stream is gone once it has ended.
+    stream: Rc<RefCell<Option<Pin<Box<S>>>>>,
+    scheduler: Sch,
+    poll_interval: Duration,
+}
+
+/// The result of subscribing to a `from_stream()` observable.
+///
+/// Dropping this subscription stops polling the stream.
+pub struct FromStreamSubscription<S: TryStream, Sch: Scheduler> {
+    state: Rc<RefCell<StreamState<S, Sch>>>,
+}
+
+struct StreamState<S: TryStream, Sch: Scheduler> {
+    stream: Rc<RefCell<Option<Pin<Box<S>>>>>,
+    scheduler: Sch,
+    poll_interval: Duration,
+    woken: Arc<Woken>,
+
+    // The observer is taken out while it is being called, and it is gone for
+    // good after termination or cancellation.
+    observer: Option<Box<dyn BoxedObserver<S::Ok, S::Error>>>,
+    is_cancelled: bool,
+
+    // Whether the observer received completion or failure.
+    is_done: bool,
+
+    // The action that polls the stream next.
+    poll: Option<Sch::Subscription>,
+}
+
+// The waker of the stream. The scheduler need not be thread-safe, so waking
+// cannot schedule an action. It only raises a flag instead, which the next
+// action checks.
+struct Woken {
+    flag: AtomicBool,
+}
+
+impl ArcWake for Woken {
+    fn wake_by_ref(arc_self: &Arc<Woken>) {
+        arc_self.flag.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Creates an observable that pushes the items of a stream of results.
+///
+/// Upon subscription, an action on the scheduler polls the stream, and pushes
+/// `Ok` items as values. The observable fails with the first `Err` item, and
+/// it completes when the stream ends. When the stream is pending, the action
+/// checks every poll interval whether the stream has been woken, and polls
+/// it again if so, see `FromStream::poll_interval()`.
+///
+/// For a stream that cannot fail, use `stream.map(Ok::<_, NoError>)`.
+///
+/// An item can be taken from the stream only once. If multiple observers
+/// subscribe, every item is pushed to one of them. To push every item to all
+/// observers, use `share()`.
+///
+/// This function is only available with the `futures` feature.
+pub fn from_stream<S, Sch>(stream: S, scheduler: Sch) -> FromStream<S, Sch>
+    where S: TryStream, Sch: Scheduler {
+    FromStream {
+        stream: Rc::new(RefCell::new(Some(Box::pin(stream)))),
+        scheduler: scheduler,
+        poll_interval: DEFAULT_POLL_INTERVAL,
+    }
+}
+
+/// The result of calling `from_future()`.
+pub type FromFuture<F, Sch> = FromStream<Once<F>, Sch>;
+
+/// Creates an observable that pushes the result of a future.
+///
+/// Upon subscription, an action on the scheduler polls the future. When it
+/// resolves with `Ok(item)`, the observable pushes the value and completes.
+/// When it resolves with `Err(error)`, the observable fails. A pending future
+/// is polled like a pending stream, see `from_stream()`.
+///
+/// The future runs only once. If multiple observers subscribe, only one of
+/// them receives the value, the others receive completion. To push the value
+/// to all observers, use `share_replay()`.
+///
+/// This function is only available with the `futures` feature.
+pub fn from_future<F, T, E, Sch>(future: F, scheduler: Sch) -> FromFuture<F, Sch>
+    where F: Future<Output = Result<T, E>>, Sch: Scheduler {
+    from_stream(stream::once(future), scheduler)
+}
+
+impl<S, Sch> FromStream<S, Sch> {
+    /// Sets how long to wait before checking a pending stream again.
+    ///
+    /// By default, this is one millisecond.
+    pub fn poll_interval(mut self, interval: Duration) -> FromStream<S, Sch> {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl<S, Sch> Observable for FromStream<S, Sch>
+where S: TryStream + 'static,
+      S::Ok: Clone + 'static,
+      S::Error: Clone + 'static,
+      Sch: Scheduler + Clone + 'static,
+      Sch::Subscription: 'static {
+    type Item = S::Ok;
+    type Error = S::Error;
+    type Subscription = FromStreamSubscription<S, Sch>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<S::Ok, S::Error> + 'static {
+        let state = Rc::new(RefCell::new(StreamState {
+            stream: self.stream.clone(),
+            scheduler: self.scheduler.clone(),
+            poll_interval: self.poll_interval,
+            // The stream must be polled once before it can be woken.
+            woken: Arc::new(Woken { flag: AtomicBool::new(true) }),
+            observer: Some(observer.boxed()),
+            is_cancelled: false,
+            is_done: false,
+            poll: None,
+        }));
+        schedule_poll(&state, Duration::from_secs(0));
+        FromStreamSubscription {
+            state: state,
+        }
+    }
+}
+
+// Schedules an action that polls the stream if it has been woken.
+fn schedule_poll<S, Sch>(state: &Rc<RefCell<StreamState<S, Sch>>>, delay: Duration)
+    where S: TryStream + 'static, Sch: Scheduler + Clone + 'static, Sch::Subscription: 'static {
+    // The scheduler might run the action immediately,
+    // so the state must not be borrowed here.
+    let scheduler = state.borrow().scheduler.clone();
+    let weak_state = Rc::downgrade(state);
+    let poll_action = scheduler.schedule_after(delay, move || poll(weak_state));
+    let previous = {
+        let mut state = state.borrow_mut();
+        if state.is_cancelled {
+            None
+        } else {
+            state.poll.replace(poll_action)
+        }
+    };
+    drop(previous);
+}
+
+// Pushes the items of the stream to the observer, until the stream is pending.
+fn poll<S, Sch>(state: Weak<RefCell<StreamState<S, Sch>>>)
+    where S: TryStream + 'static, Sch: Scheduler + Clone + 'static, Sch::Subscription: 'static {
+    let state = match state.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    let (stream, woken, poll_interval) = {
+        let state = state.borrow();
+        (state.stream.clone(), state.woken.clone(), state.poll_interval)
+    };
+    // Clear the flag before polling, so a wake during the poll is not lost.
+    if !woken.flag.swap(false, Ordering::SeqCst) {
+        schedule_poll(&state, poll_interval);
+        return;
+    }
+    let waker = task::waker(woken);
+    let mut context = Context::from_waker(&waker);
+    loop {
+        // The state is not borrowed while the observer is called, so the
+        // observer can drop its subscription.
+        let observer = match state.borrow_mut().observer.take() {
+            Some(observer) => observer,
+            None => return,
+        };
+        let item = {
+            let mut stream = stream.borrow_mut();
+            let item = match *stream {
+                Some(ref mut stream) => stream.as_mut().try_poll_next(&mut context),
+                None => Poll::Ready(None),
+            };
+            if let Poll::Ready(None) | Poll::Ready(Some(Err(..))) = item {
+                *stream = None;
+            }
+            item
+        };
+        match item {
+            Poll::Ready(Some(Ok(item))) => {
+                let mut observer = observer;
+                observer.on_next(item);
+                let mut state = state.borrow_mut();
+                if !state.is_cancelled {
+                    state.observer = Some(observer);
+                }
+            }
+            Poll::Ready(Some(Err(error))) => {
+                state.borrow_mut().is_done = true;
+                observer.on_error_box(error);
+                return;
+            }
+            Poll::Ready(None) => {
+                state.borrow_mut().is_done = true;
+                observer.on_completed_box();
+                return;
+            }
+            Poll::Pending => {
+                state.borrow_mut().observer = Some(observer);
+                schedule_poll(&state, poll_interval);
+                return;
+            }
+        }
+    }
+}
+
+impl<S: TryStream, Sch: Scheduler> FromStreamSubscription<S, Sch> {
+    fn cancel(&mut self) {
+        let (observer, poll) = {
+            let mut state = self.state.borrow_mut();
+            state.is_cancelled = true;
+            (state.observer.take(), state.poll.take())
+        };
+        drop(poll);
+        drop(observer);
+    }
+}
+
+impl<S: TryStream, Sch: Scheduler> Subscription for FromStreamSubscription<S, Sch> {
+    fn unsubscribe(&mut self) {
+        self.cancel();
+    }
+
+    fn is_active(&self) -> bool {
+        let state = self.state.borrow();
+        !state.is_cancelled && !state.is_done
+    }
+}
+
+impl<S: TryStream, Sch: Scheduler> Drop for FromStreamSubscription<S, Sch> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The result of calling `into_stream()` on an observable.
+///
+/// The stream subscribes to the observable when it is polled for the first
+/// time. It yields `Ok(item)` for every value, and it ends after the
+/// observable completes. If the observable fails, the last item is
+/// `Err(error)`. Dropping the stream drops the subscription.
+///
+/// Values are queued until the stream is polled. The queue is unbounded;
+/// call `bounded()` to limit its capacity, and to configure what happens
+/// when it is full.
+pub struct ObservableStream<Source: Observable> {
+    source: Source,
+    capacity: usize,
+
+    // The policy is moved into the observer upon subscription.
+    policy: Option<OverflowPolicy<Source::Error>>,
+    subscribed: Option<Subscribed<Source::Item, Source::Error, Source::Subscription>>,
+}
+
+// The queue that the observer shares with the stream, and the subscription.
+type Subscribed<T, E, Subs> = (Arc<Mutex<StreamQueue<T, E>>>, Subs);
+
+struct StreamQueue<T, E> {
+    queue: VecDeque<T>,
+    error: Option<E>,
+
+    // Set upon completion or failure.
+    terminated: bool,
+
+    // The task to wake when the queue changes.
+    waker: Option<Waker>,
+}
+
+fn lock<'s, T, E>(queue: &'s Mutex<StreamQueue<T, E>>) -> MutexGuard<'s, StreamQueue<T, E>> {
+    // The queue is consistent even if a thread panicked while holding the
+    // lock, so the poisoning can be ignored.
+    queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<T, E> StreamQueue<T, E> {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+// An observer that pushes into the queue of an observable stream.
+struct StreamObserver<T, E> {
+    queue: Arc<Mutex<StreamQueue<T, E>>>,
+    capacity: usize,
+    policy: OverflowPolicy<E>,
+}
+
+impl<T, E> Observer<T, E> for StreamObserver<T, E> {
+    fn on_next(&mut self, item: T) {
+        let mut queue = lock(&self.queue);
+        if queue.terminated {
+            return;
+        }
+        if queue.queue.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropNewest => return,
+                OverflowPolicy::DropOldest => { queue.queue.pop_front(); }
+                OverflowPolicy::Error(..) => {
+                    // The stream fails only once, so the error can be moved out.
+                    let policy = mem::replace(&mut self.policy, OverflowPolicy::DropNewest);
+                    if let OverflowPolicy::Error(error) = policy {
+                        queue.error = Some(error);
+                    }
+                    queue.terminated = true;
+                    queue.wake();
+                    return;
+                }
+                OverflowPolicy::Panic => panic!("into_stream queue is full"),
+                OverflowPolicy::Block => unreachable!(),
+            }
+        }
+        queue.queue.push_back(item);
+        queue.wake();
+    }
+
+    fn on_completed(self) {
+        let mut queue = lock(&self.queue);
+        queue.terminated = true;
+        queue.wake();
+    }
+
+    fn on_error(self, error: E) {
+        let mut queue = lock(&self.queue);
+        queue.error = Some(error);
+        queue.terminated = true;
+        queue.wake();
+    }
+}
+
+impl<Source: Observable> ObservableStream<Source> {
+    /// Creates a stream of the values of the observable.
+    ///
+    /// Prefer `ObservableExt::into_stream()`.
+    pub fn new(source: Source) -> ObservableStream<Source> {
+        ObservableStream {
+            source: source,
+            // The policy never applies to an unbounded queue.
+            capacity: usize::MAX,
+            policy: Some(OverflowPolicy::DropNewest),
+            subscribed: None,
+        }
+    }
+
+    /// Sets the capacity of the queue, and what happens when it is full.
+    ///
+    /// By default, the queue is unbounded. Only polling makes room in the
+    /// queue, so the policy cannot be `Block`.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<Source::Error>)
+                   -> ObservableStream<Source> {
+        assert_non_blocking(&policy, "into_stream");
+        self.capacity = capacity;
+        self.policy = Some(policy);
+        self
+    }
+}
+
+// The source is never pinned, it is only subscribed to.
+impl<Source: Observable> Unpin for ObservableStream<Source> {}
+
+impl<Source> Stream for ObservableStream<Source>
+where Source: Observable, Source::Item: 'static, Source::Error: 'static {
+    type Item = Result<Source::Item, Source::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.subscribed.is_none() {
+            let queue = Arc::new(Mutex::new(StreamQueue {
+                queue: VecDeque::new(),
+                error: None,
+                terminated: false,
+                waker: None,
+            }));
+            let observer = StreamObserver {
+                queue: queue.clone(),
+                capacity: this.capacity,
+                policy: this.policy.take().unwrap_or(OverflowPolicy::DropNewest),
+            };
+            let subscription = this.source.subscribe(observer);
+            this.subscribed = Some((queue, subscription));
+        }
+        let queue = match this.subscribed {
+            Some((ref queue, _)) => queue,
+            None => unreachable!(),
+        };
+        let mut queue = lock(queue);
+        if let Some(item) = queue.queue.pop_front() {
+            return Poll::Ready(Some(Ok(item)));
+        }
+        if let Some(error) = queue.error.take() {
+            return Poll::Ready(Some(Err(error)));
+        }
+        if queue.terminated {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(context.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The result of calling `first_into_future()` on an observable.
+///
+/// The future subscribes to the observable when it is first polled. It
+/// resolves with `Ok(Some(item))` for the first value, with `Ok(None)` if the
+/// observable completes without producing a value, or with `Err(error)` if it
+/// fails first. The subscription is dropped once the future has resolved.
+pub struct FirstFuture<Source: Observable> {
+    stream: Option<ObservableStream<Source>>,
+}
+
+impl<Source: Observable> FirstFuture<Source> {
+    /// Creates a future of the first value of the observable.
+    ///
+    /// Prefer `ObservableExt::first_into_future()`.
+    pub fn new(source: Source) -> FirstFuture<Source> {
+        FirstFuture {
+            // Only the first value is of interest, later values can be dropped.
+            stream: Some(ObservableStream::new(source).bounded(1, OverflowPolicy::DropNewest)),
+        }
+    }
+}
+
+impl<Source: Observable> Unpin for FirstFuture<Source> {}
+
+impl<Source> Future for FirstFuture<Source>
+where Source: Observable, Source::Item: 'static, Source::Error: 'static {
+    type Output = Result<Option<Source::Item>, Source::Error>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let result = match this.stream {
+            Some(ref mut stream) => match Pin::new(stream).poll_next(context) {
+                Poll::Ready(Some(Ok(item))) => Ok(Some(item)),
+                Poll::Ready(Some(Err(error))) => Err(error),
+                Poll::Ready(None) => Ok(None),
+                Poll::Pending => return Poll::Pending,
+            },
+            None => panic!("FirstFuture polled after it resolved"),
+        };
+        this.stream = None;
+        Poll::Ready(result)
+    }
+}
+
+/// The result of calling `subscribe_async()` on an observable.
+///
+/// Dropping this subscription stops the task that handles the values. A
+/// handler that is running is dropped at its next await point.
+pub struct AsyncSubscription<E> {
+    handle: AbortHandle,
+
+    // Set when the task has handled all values.
+    is_finished: Rc<Cell<bool>>,
+
+    // Set when the observable failed, which stops the task.
+    error: Rc<RefCell<Option<E>>>,
+}
+
+/// Spawns a task that calls `handler` with every value, and awaits the result.
+///
+/// At most `limit` handlers run at the same time. See
+/// `ObservableExt::subscribe_async()`.
+pub fn subscribe_async<Source, Sp, F, Fut>(source: Source,
+                                           spawner: &Sp,
+                                           limit: usize,
+                                           handler: F)
+                                           -> Result<AsyncSubscription<Source::Error>, SpawnError>
+    where Source: Observable + 'static,
+          Source::Item: 'static,
+          Source::Error: 'static,
+          Sp: LocalSpawn,
+          F: FnMut(Source::Item) -> Fut + 'static,
+          Fut: Future<Output = ()> + 'static {
+    // For `for_each_concurrent()`, a limit of zero means no limit at all.
+    assert!(limit > 0, "subscribe_async needs to run at least one handler at a time");
+    let is_finished = Rc::new(Cell::new(false));
+    let task_is_finished = is_finished.clone();
+    let error = Rc::new(RefCell::new(None));
+    let task_error = error.clone();
+    // The values after the error are not handled, the stream ends there.
+    let values = ObservableStream::new(source)
+        .take_while(move |item| future::ready(match *item {
+            Ok(..) => true,
+            Err(ref error) => {
+                *task_error.borrow_mut() = Some(error.clone());
+                false
+            }
+        }))
+        .filter_map(|item| future::ready(item.ok()));
+    let task = values.for_each_concurrent(limit, handler)
+                     .map(move |()| task_is_finished.set(true));
+    let (task, handle) = future::abortable(task);
+    spawner.spawn_local(task.map(|_| ()))?;
+    Ok(AsyncSubscription {
+        handle: handle,
+        is_finished: is_finished,
+        error: error,
+    })
+}
+
+impl<E: Clone> AsyncSubscription<E> {
+    /// Returns the error of the observable, if it failed.
+    ///
+    /// The task stops when the observable fails, after the handlers of the
+    /// earlier values have finished, so the subscription is no longer active.
+    pub fn error(&self) -> Option<E> {
+        self.error.borrow().clone()
+    }
+}
+
+impl<E> Subscription for AsyncSubscription<E> {
+    fn unsubscribe(&mut self) {
+        self.handle.abort();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.is_finished.get() && !self.handle.is_aborted()
+    }
+}
+
+impl<E> Drop for AsyncSubscription<E> {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -5,34 +5,253 @@
 // you may not use this file except in compliance with the License.
 // A copy of the License has been included in the root of the repository.
 
+use Notification;
+use blocking::{self, BlockingIter};
+use leak::Tracker;
 use lifeline;
 use observable::Observable;
 use observer::{Observer, BoxedObserver};
+use slab::Slab;
+use std::any::Any;
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex, MutexGuard};
+use subscription::Subscription;
+
+type ObserverSlab<T, E> = Slab<lifeline::Owner<Box<dyn BoxedObserver<T, E>>>>;
 
 /// Both an observer and observable.
 ///
 /// A subject is a low-level primitive for creating observables.
 ///
+/// A subject remembers whether it completed or failed. Subscribers that arrive
+/// after the terminal event receive it immediately, and values pushed after the
+/// terminal event are ignored. Completing a subject by value drops it, so to
+/// subscribe after completion, complete the subject through a reference
+/// instead: `(&subject).on_completed()`.
+///
+/// Observers can push values into the subject they are subscribed to, through
+/// a shared reference to the subject. Such values are pushed after the current
+/// value has been pushed to all observers. Observers can also subscribe to the
+/// subject or drop subscriptions while a value is being pushed. A new observer
+/// does not receive the value that is being pushed, and an observer whose
+/// subscription is dropped does not receive it if it has not done so already.
+///
+/// By default, a panic in an observer propagates to the code that pushed into
+/// the subject, and the observers after it do not receive the event. See
+/// `isolate_panics()` to deliver the event to the other observers regardless.
+///
 /// TODO: Add example.
 pub struct Subject<T, E> {
-    observers: Vec<lifeline::Owner<Box<BoxedObserver<T, E>>>>,
+    core: Rc<SubjectCore<T, E>>,
+}
+
+// The core is shared with the subscriptions, so a subscription can remove its
+// observer in O(1) when it is dropped. The observers are never called while the
+// slab is borrowed, so the subscription can always remove its entry.
+struct SubjectCore<T, E> {
+    observers: RefCell<ObserverSlab<T, E>>,
+    terminal: RefCell<Option<Result<(), E>>>,
+
+    // While the subject is pushing to its observers, this contains the events
+    // that the observers pushed into the subject in the mean time.
+    queue: RefCell<Option<VecDeque<Notification<T, E>>>>,
+
+    panic_mode: RefCell<PanicMode>,
+
+    // Records the subject in the leak registry, see `debug::set_leak_tracking()`.
+    tracker: Tracker,
+}
+
+enum PanicMode {
+    Propagate,
+    Resume,
+    Report(Box<dyn FnMut(Box<dyn Any + Send>)>),
+}
+
+// Ends the push when dropped, also when an observer panics,
+// so the subject keeps working afterwards.
+struct Pushing<'a, T: 'a, E: 'a> {
+    queue: &'a RefCell<Option<VecDeque<Notification<T, E>>>>,
+}
+
+impl<'a, T, E> Drop for Pushing<'a, T, E> {
+    fn drop(&mut self) {
+        *self.queue.borrow_mut() = None;
+    }
 }
 
 /// Proxy object that exposes the observable part of a subject.
-pub struct SubjectObservable<'s, T: 's, E: 's> {
-    subject: &'s mut Subject<T, E>,
+pub struct SubjectObservable<T, E> {
+    core: Rc<SubjectCore<T, E>>,
+}
+
+/// The observer half of a subject, see `Subject::split()`.
+pub struct SubjectSink<T, E> {
+    core: Rc<SubjectCore<T, E>>,
+}
+
+/// A subject that stores the latest value and pushes it to new subscribers.
+///
+/// Upon subscription, an observer immediately receives the current value,
+/// followed by all values pushed to the subject afterwards. The current value
+/// can also be read synchronously with `value()`.
+pub struct BehaviorSubject<T, E> {
+    subject: Subject<T, E>,
+
+    // The value is shared with the observable and observer halves.
+    value: Rc<RefCell<T>>,
+}
+
+/// Proxy object that exposes the observable part of a behavior subject.
+pub struct BehaviorSubjectObservable<T, E> {
+    observable: SubjectObservable<T, E>,
+    value: Rc<RefCell<T>>,
+}
+
+/// The observer half of a behavior subject, see `BehaviorSubject::sink()`.
+pub struct BehaviorSubjectSink<T, E> {
+    sink: SubjectSink<T, E>,
+    value: Rc<RefCell<T>>,
+}
+
+/// A subject that replays buffered values to new subscribers.
+///
+/// The subject buffers the values pushed to it, up to an optional capacity.
+/// Upon subscription, an observer first receives the buffered values, followed
+/// by all values pushed to the subject afterwards. If the subject has completed
+/// or failed, new subscribers receive the terminal event after the buffered
+/// values.
+///
+/// Complete the subject through a mutable reference to replay the terminal
+/// event to late subscribers: `(&mut subject).on_completed()`.
+pub struct ReplaySubject<T, E> {
+    subject: Subject<T, E>,
+
+    // The buffer is shared with the observable and observer halves.
+    buffer: Rc<RefCell<ReplayBuffer<T>>>,
+}
+
+struct ReplayBuffer<T> {
+    values: VecDeque<T>,
+    capacity: Option<usize>,
+}
+
+/// Proxy object that exposes the observable part of a replay subject.
+pub struct ReplaySubjectObservable<T, E> {
+    observable: SubjectObservable<T, E>,
+    buffer: Rc<RefCell<ReplayBuffer<T>>>,
+}
+
+/// The observer half of a replay subject, see `ReplaySubject::sink()`.
+pub struct ReplaySubjectSink<T, E> {
+    sink: SubjectSink<T, E>,
+    buffer: Rc<RefCell<ReplayBuffer<T>>>,
+}
+
+/// A subject that pushes only its last value, upon completion.
+///
+/// The subject caches the latest value pushed to it, but it does not push the
+/// value to observers until the subject completes. Upon completion, every
+/// observer receives the last value (if there was one), followed by completion.
+/// Subscribers that arrive after completion receive the cached value and
+/// completion immediately. If the subject fails, observers receive only the
+/// error.
+///
+/// Complete the subject through a mutable reference to keep the result around
+/// for late subscribers: `(&mut subject).on_completed()`.
+pub struct AsyncSubject<T, E> {
+    subject: Subject<T, E>,
+
+    // The last value is shared with the observable and observer halves.
+    last: Rc<RefCell<Option<T>>>,
 }
 
+/// Proxy object that exposes the observable part of an async subject.
+pub struct AsyncSubjectObservable<T, E> {
+    observable: SubjectObservable<T, E>,
+    last: Rc<RefCell<Option<T>>>,
+}
+
+/// The observer half of an async subject, see `AsyncSubject::sink()`.
+pub struct AsyncSubjectSink<T, E> {
+    sink: SubjectSink<T, E>,
+    last: Rc<RefCell<Option<T>>>,
+}
+
+/// A subject that pushes every value to its observers without cloning it.
+///
+/// A plain subject clones a value once per observer. A shared subject wraps
+/// the value in an `Arc` once, and observers receive clones of the `Arc`
+/// instead, so the value itself is never cloned, and it does not need to
+/// implement `Clone`.
+pub struct SharedSubject<T, E> {
+    subject: Subject<Arc<T>, E>,
+}
+
+/// A subject that can be shared between threads.
+///
+/// A sync subject is a handle that can be cloned and sent to other threads.
+/// All clones share the same observers: a value pushed through any handle is
+/// pushed to every observer, on the thread that pushed it. Therefore observers
+/// must be `Send`. Calls to observers are serialized: a value is pushed to all
+/// observers before the next value is pushed. Pushing a value into a sync
+/// subject from within one of its own observers deadlocks.
+///
+/// When the subject completes or fails through one handle, values pushed
+/// through other handles are ignored, and new subscribers receive the terminal
+/// event immediately.
+///
+/// `Observable::subscribe()` does not require the observer to be `Send`, so a
+/// sync subject does not implement `Observable`. Subscribe with the
+/// [`subscribe()`](#method.subscribe) method on the subject instead.
+pub struct SyncSubject<T, E> {
+    state: Arc<Mutex<SyncSubjectState<T, E>>>,
+}
+
+struct SyncSubjectState<T, E> {
+    observers: Vec<lifeline::SyncOwner<Box<dyn BoxedObserver<T, E> + Send>>>,
+    terminal: Option<Result<(), E>>,
+}
+
+/// The result of subscribing to a sync subject.
+///
+/// Dropping this subscription will prevent further calls on the observer.
+/// The subscription can be dropped on any thread.
+pub struct SyncSubjectSubscription<T, E> {
+    alive: Option<lifeline::SyncLifeline<Box<dyn BoxedObserver<T, E> + Send>>>,
+}
+
+/// The result of subscribing to a subject.
+///
+/// Dropping this subscription will prevent further calls on the observer.
 pub struct SubjectSubscription<T, E> {
-    #[allow(dead_code)] // This code is not dead, it keeps the observer alive.
-    alive: lifeline::Lifeline<Box<BoxedObserver<T, E>>>,
+    registration: Option<SubjectRegistration<T, E>>,
+
+    // Only held to remove the subscription from the leak registry upon drop.
+    _tracker: Tracker,
+}
+
+struct SubjectRegistration<T, E> {
+    alive: lifeline::Lifeline<Box<dyn BoxedObserver<T, E>>>,
+    core: Weak<SubjectCore<T, E>>,
+    key: usize,
 }
 
 impl<T, E> Subject<T, E> {
     /// Creates a new subject.
     pub fn new() -> Subject<T, E> {
+        let core = SubjectCore {
+            observers: RefCell::new(Slab::new()),
+            terminal: RefCell::new(None),
+            queue: RefCell::new(None),
+            panic_mode: RefCell::new(PanicMode::Propagate),
+            tracker: Tracker::new("subject"),
+        };
         Subject {
-            observers: Vec::new(),
+            core: Rc::new(core),
         }
     }
 
@@ -41,18 +260,800 @@ impl<T, E> Subject<T, E> {
     /// This can be used to avoid exposing the observer methods while still
     /// allowing subscription. When a subject is used internally as the source
     /// of an observable, a getter can expose the `observable()` of the subject.
-    pub fn observable<'s>(&'s mut self) -> SubjectObservable<'s, T, E> {
+    pub fn observable(&self) -> SubjectObservable<T, E> {
         SubjectObservable {
-            subject: self,
+            core: self.core.clone(),
+        }
+    }
+
+    /// Returns an observer that pushes into the subject.
+    ///
+    /// Unlike `split()`, this does not consume the subject.
+    pub fn sink(&self) -> SubjectSink<T, E> {
+        SubjectSink {
+            core: self.core.clone(),
+        }
+    }
+
+    /// Splits the subject into an observer half and an observable half.
+    ///
+    /// The sink can be moved to the code that produces the values, and the
+    /// observable can be handed to the consumers, so the producer cannot
+    /// subscribe by accident, and the consumers cannot push into the subject.
+    /// Once the sink completes or fails, late subscribers to the observable
+    /// receive the terminal event.
+    pub fn split(self) -> (SubjectSink<T, E>, SubjectObservable<T, E>) {
+        (self.sink(), self.observable())
+    }
+
+    /// Returns the number of observers subscribed to the subject.
+    ///
+    /// Observers that completed, failed, or whose subscription was dropped are
+    /// not counted.
+    pub fn observer_count(&self) -> usize {
+        self.core.observers.borrow().iter()
+            .filter(|&(_key, observer_owner)| observer_owner.is_alive())
+            .count()
+    }
+
+    /// Returns whether any observers are subscribed to the subject.
+    pub fn has_observers(&self) -> bool {
+        self.core.observers.borrow().iter()
+            .any(|(_key, observer_owner)| observer_owner.is_alive())
+    }
+
+    /// Isolates the observers of the subject from panics in other observers.
+    ///
+    /// If an observer panics, the panic is caught and the observer is dropped,
+    /// as if its subscription had been dropped. The other observers still
+    /// receive the event. When the subject is done pushing, the first panic is
+    /// resumed.
+    pub fn isolate_panics(&mut self) {
+        *self.core.panic_mode.borrow_mut() = PanicMode::Resume;
+    }
+
+    /// Isolates the observers of the subject from panics in other observers,
+    /// and reports the panics to the hook instead of resuming them.
+    ///
+    /// The hook receives the payload of every panic, after the subject is done
+    /// pushing. See also `isolate_panics()`.
+    pub fn isolate_panics_with<F>(&mut self, hook: F)
+        where F: FnMut(Box<dyn Any + Send>) + 'static {
+        *self.core.panic_mode.borrow_mut() = PanicMode::Report(Box::new(hook));
+    }
+
+    /// Returns whether the subject completed or failed.
+    ///
+    /// A subject that terminated ignores the values pushed into it, and new
+    /// observers receive only the terminal event.
+    pub fn is_terminated(&self) -> bool {
+        self.core.is_terminated()
+    }
+
+}
+
+impl<T, E> Default for Subject<T, E> {
+    fn default() -> Subject<T, E> {
+        Subject::new()
+    }
+}
+
+impl<T: Clone, E: Clone> Subject<T, E> {
+    /// Pushes the value produced by `f` to the observers, but only calls `f`
+    /// if there are observers to push to.
+    ///
+    /// This avoids constructing values that nobody would receive.
+    pub fn on_next_lazy<F: FnOnce() -> T>(&mut self, f: F) {
+        if self.has_observers() && !self.is_terminated() {
+            self.on_next(f());
+        }
+    }
+}
+
+impl<T, E> SubjectCore<T, E> {
+    fn is_terminated(&self) -> bool {
+        self.terminal.borrow().is_some()
+    }
+
+    fn is_completed(&self) -> bool {
+        matches!(*self.terminal.borrow(), Some(Ok(())))
+    }
+}
+
+impl<T: Clone, E: Clone> SubjectCore<T, E> {
+    fn push(&self, notification: Notification<T, E>) {
+        // The terminal state is latched immediately, so values pushed after the
+        // terminal event are ignored, even if the terminal event is queued.
+        {
+            let mut terminal = self.terminal.borrow_mut();
+            if terminal.is_some() {
+                return;
+            }
+            match notification {
+                Notification::Next(..) => {}
+                Notification::Completed => *terminal = Some(Ok(())),
+                Notification::Error(ref error) => *terminal = Some(Err(error.clone())),
+            }
+            if terminal.is_some() {
+                self.tracker.terminate();
+            }
+        }
+
+        {
+            let mut queue = self.queue.borrow_mut();
+            if let Some(ref mut queue) = *queue {
+                // An observer pushed into the subject, push it after the current event.
+                queue.push_back(notification);
+                return;
+            }
+            *queue = Some(VecDeque::new());
+        }
+
+        let mut panics = Vec::new();
+        {
+            let _pushing = Pushing { queue: &self.queue };
+            let mut next = Some(notification);
+            while let Some(notification) = next {
+                self.dispatch(notification, &mut panics);
+                next = self.queue.borrow_mut().as_mut().and_then(|queue| queue.pop_front());
+            }
+        }
+
+        if panics.is_empty() {
+            return;
+        }
+        match *self.panic_mode.borrow_mut() {
+            PanicMode::Propagate => unreachable!("panics are only caught when isolated"),
+            PanicMode::Resume => panic::resume_unwind(panics.swap_remove(0)),
+            PanicMode::Report(ref mut hook) => {
+                for payload in panics {
+                    hook(payload);
+                }
+            }
+        }
+    }
+
+    fn dispatch(&self, notification: Notification<T, E>, panics: &mut Vec<Box<dyn Any + Send>>) {
+        match notification {
+            Notification::Next(item) => {
+                let observers: Vec<_> = self.observers.borrow().iter()
+                    .map(|(_key, observer_owner)| observer_owner.clone())
+                    .collect();
+                for mut observer_owner in observers {
+                    // If the subscription was dropped in the mean time, this is a no-op.
+                    let mut result = Ok(());
+                    observer_owner.with_mut_value(|observer| {
+                        result = self.invoke(|| observer.on_next(item.clone()));
+                    });
+                    if let Err(payload) = result {
+                        // Drop the observer, so it does not receive further events.
+                        drop(observer_owner.take());
+                        panics.push(payload);
+                    }
+                }
+            }
+            Notification::Completed => {
+                let observers: Vec<_> = self.observers.borrow_mut().drain().collect();
+                for observer_owner in observers {
+                    if let Some(observer) = observer_owner.take() {
+                        // The subscription was not dropped, invoke the method.
+                        if let Err(payload) = self.invoke(|| observer.on_completed_box()) {
+                            panics.push(payload);
+                        }
+                    }
+                }
+            }
+            Notification::Error(error) => {
+                let observers: Vec<_> = self.observers.borrow_mut().drain().collect();
+                for observer_owner in observers {
+                    if let Some(observer) = observer_owner.take() {
+                        // The subscription was not dropped, invoke the method.
+                        if let Err(payload) = self.invoke(|| observer.on_error_box(error.clone())) {
+                            panics.push(payload);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Calls the observer, catching a panic if panics are isolated.
+    fn invoke<F: FnOnce()>(&self, action: F) -> Result<(), Box<dyn Any + Send>> {
+        let isolated = !matches!(*self.panic_mode.borrow(), PanicMode::Propagate);
+        if isolated {
+            // The observer that panicked is dropped, so a broken invariant
+            // cannot be observed afterwards.
+            panic::catch_unwind(AssertUnwindSafe(action))
+        } else {
+            action();
+            Ok(())
+        }
+    }
+}
+
+impl<T, E> BehaviorSubject<T, E> {
+    /// Creates a new behavior subject with the given initial value.
+    pub fn new(initial: T) -> BehaviorSubject<T, E> {
+        BehaviorSubject {
+            subject: Subject::new(),
+            value: Rc::new(RefCell::new(initial)),
+        }
+    }
+
+    /// Returns the latest value pushed to the subject,
+    /// or the initial value if no value has been pushed yet.
+    pub fn value<'s>(&'s self) -> Ref<'s, T> {
+        self.value.borrow()
+    }
+
+    /// Returns a proxy object that exposes the observable part of the subject.
+    ///
+    /// See also [`Subject::observable()`](struct.Subject.html#method.observable).
+    pub fn observable(&self) -> BehaviorSubjectObservable<T, E> {
+        BehaviorSubjectObservable {
+            observable: self.subject.observable(),
+            value: self.value.clone(),
+        }
+    }
+
+    /// Returns an observer that pushes into the subject.
+    ///
+    /// See also [`Subject::sink()`](struct.Subject.html#method.sink).
+    pub fn sink(&self) -> BehaviorSubjectSink<T, E> {
+        BehaviorSubjectSink {
+            sink: self.subject.sink(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T, E> ReplaySubject<T, E> {
+    /// Creates a new replay subject that buffers all values.
+    pub fn new() -> ReplaySubject<T, E> {
+        ReplaySubject::with_buffer(VecDeque::new(), None)
+    }
+
+    /// Creates a new replay subject that buffers at most the last `capacity` values.
+    pub fn with_capacity(capacity: usize) -> ReplaySubject<T, E> {
+        ReplaySubject::with_buffer(VecDeque::with_capacity(capacity), Some(capacity))
+    }
+
+    fn with_buffer(values: VecDeque<T>, capacity: Option<usize>) -> ReplaySubject<T, E> {
+        let buffer = ReplayBuffer {
+            values: values,
+            capacity: capacity,
+        };
+        ReplaySubject {
+            subject: Subject::new(),
+            buffer: Rc::new(RefCell::new(buffer)),
+        }
+    }
+
+    /// Returns a proxy object that exposes the observable part of the subject.
+    ///
+    /// See also [`Subject::observable()`](struct.Subject.html#method.observable).
+    pub fn observable(&self) -> ReplaySubjectObservable<T, E> {
+        ReplaySubjectObservable {
+            observable: self.subject.observable(),
+            buffer: self.buffer.clone(),
+        }
+    }
+
+    /// Returns an observer that pushes into the subject.
+    ///
+    /// See also [`Subject::sink()`](struct.Subject.html#method.sink).
+    pub fn sink(&self) -> ReplaySubjectSink<T, E> {
+        ReplaySubjectSink {
+            sink: self.subject.sink(),
+            buffer: self.buffer.clone(),
+        }
+    }
+}
+
+impl<T, E> Default for ReplaySubject<T, E> {
+    fn default() -> ReplaySubject<T, E> {
+        ReplaySubject::new()
+    }
+}
+
+impl<T> ReplayBuffer<T> {
+    fn push(&mut self, item: T) {
+        if self.capacity != Some(0) {
+            // Make room in the buffer by dropping the oldest value if it is full.
+            if Some(self.values.len()) == self.capacity {
+                self.values.pop_front();
+            }
+            self.values.push_back(item);
+        }
+    }
+}
+
+impl<T, E> AsyncSubject<T, E> {
+    /// Creates a new async subject.
+    pub fn new() -> AsyncSubject<T, E> {
+        AsyncSubject {
+            subject: Subject::new(),
+            last: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Returns a proxy object that exposes the observable part of the subject.
+    ///
+    /// See also [`Subject::observable()`](struct.Subject.html#method.observable).
+    pub fn observable(&self) -> AsyncSubjectObservable<T, E> {
+        AsyncSubjectObservable {
+            observable: self.subject.observable(),
+            last: self.last.clone(),
+        }
+    }
+
+    /// Returns an observer that pushes into the subject.
+    ///
+    /// See also [`Subject::sink()`](struct.Subject.html#method.sink).
+    pub fn sink(&self) -> AsyncSubjectSink<T, E> {
+        AsyncSubjectSink {
+            sink: self.subject.sink(),
+            last: self.last.clone(),
+        }
+    }
+}
+
+impl<T, E> Default for AsyncSubject<T, E> {
+    fn default() -> AsyncSubject<T, E> {
+        AsyncSubject::new()
+    }
+}
+
+impl<T, E> SharedSubject<T, E> {
+    /// Creates a new shared subject.
+    pub fn new() -> SharedSubject<T, E> {
+        SharedSubject {
+            subject: Subject::new(),
+        }
+    }
+
+    /// Returns a proxy object that exposes the observable part of the subject.
+    ///
+    /// See also [`Subject::observable()`](struct.Subject.html#method.observable).
+    pub fn observable(&self) -> SubjectObservable<Arc<T>, E> {
+        self.subject.observable()
+    }
+}
+
+impl<T, E> Default for SharedSubject<T, E> {
+    fn default() -> SharedSubject<T, E> {
+        SharedSubject::new()
+    }
+}
+
+impl<T, E> SyncSubject<T, E> {
+    /// Creates a new sync subject.
+    pub fn new() -> SyncSubject<T, E> {
+        let state = SyncSubjectState {
+            observers: Vec::new(),
+            terminal: None,
+        };
+        SyncSubject {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Subscribes an observer and returns the subscription.
+    ///
+    /// This is the equivalent of `Observable::subscribe()`, for observers that
+    /// can be sent to other threads.
+    pub fn subscribe<O>(&self, observer: O) -> SyncSubjectSubscription<T, E>
+        where T: Clone, E: Clone, O: Observer<T, E> + Send + 'static {
+        let mut state = self.lock();
+        match state.terminal {
+            Some(Ok(())) => observer.on_completed(),
+            Some(Err(ref error)) => observer.on_error(error.clone()),
+            None => {
+                let boxed: Box<dyn BoxedObserver<T, E> + Send> = Box::new(observer);
+                let (alive, owner) = lifeline::new_sync(boxed);
+                state.observers.push(owner);
+                return SyncSubjectSubscription {
+                    alive: Some(alive),
+                };
+            }
+        }
+        // The observer received the terminal event already, it is not kept alive.
+        SyncSubjectSubscription {
+            alive: None,
+        }
+    }
+
+    /// Subscribes an iterator that blocks until values arrive.
+    ///
+    /// This is the equivalent of `ObservableExt::into_blocking_iter()`. Values
+    /// can be pushed into the subject from other threads while the current
+    /// thread iterates; when the buffer of the iterator is full, those threads
+    /// block until it has room again.
+    pub fn blocking_iter(&self) -> BlockingIter<T, E, SyncSubjectSubscription<T, E>>
+        where T: Clone + Send + 'static, E: Clone + Send + 'static {
+        blocking::blocking_iter(|observer| self.subscribe(observer))
+    }
+
+    fn lock<'s>(&'s self) -> MutexGuard<'s, SyncSubjectState<T, E>> {
+        // If an observer panicked, it is considered dead, but the state of the
+        // subject itself is still consistent, so the poisoning can be ignored.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T, E> Default for SyncSubject<T, E> {
+    fn default() -> SyncSubject<T, E> {
+        SyncSubject::new()
+    }
+}
+
+impl<T, E> Clone for SyncSubject<T, E> {
+    fn clone(&self) -> SyncSubject<T, E> {
+        SyncSubject {
+            state: self.state.clone(),
         }
     }
 }
 
 impl<T: Clone, E: Clone> Observer<T, E> for Subject<T, E> {
     fn on_next(&mut self, item: T) {
+        self.core.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.core.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.core.push(Notification::Error(error));
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for &Subject<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.core.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.core.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.core.push(Notification::Error(error));
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for &mut Subject<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.core.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.core.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.core.push(Notification::Error(error));
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for SubjectSink<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.core.push(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.core.push(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.core.push(Notification::Error(error));
+    }
+}
+
+impl<T, E> Clone for SubjectObservable<T, E> {
+    fn clone(&self) -> SubjectObservable<T, E> {
+        SubjectObservable {
+            core: self.core.clone(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> Observable for SubjectObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let terminal = self.core.terminal.borrow().clone();
+        match terminal {
+            Some(Ok(())) => observer.on_completed(),
+            Some(Err(error)) => observer.on_error(error),
+            None => {
+                let tracker = Tracker::new("subject subscription");
+                let boxed: Box<dyn BoxedObserver<T, E>> = Box::new(tracker.observer(observer));
+                let (alive, owner) = lifeline::new(boxed);
+                let key = self.core.observers.borrow_mut().insert(owner);
+                let registration = SubjectRegistration {
+                    alive: alive,
+                    core: Rc::downgrade(&self.core),
+                    key: key,
+                };
+                return SubjectSubscription {
+                    registration: Some(registration),
+                    _tracker: tracker,
+                };
+            }
+        }
+        // The observer received the terminal event already, it is not kept alive.
+        let tracker = Tracker::new("subject subscription");
+        tracker.terminate();
+        SubjectSubscription {
+            registration: None,
+            _tracker: tracker,
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for BehaviorSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.subject.is_terminated() {
+            *self.value.borrow_mut() = item.clone();
+            self.subject.on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        self.subject.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.subject.on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for BehaviorSubjectSink<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.sink.core.is_terminated() {
+            *self.value.borrow_mut() = item.clone();
+            self.sink.on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        self.sink.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.sink.on_error(error);
+    }
+}
+
+impl<T, E> Clone for BehaviorSubjectObservable<T, E> {
+    fn clone(&self) -> BehaviorSubjectObservable<T, E> {
+        BehaviorSubjectObservable {
+            observable: self.observable.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> Observable for BehaviorSubjectObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, mut observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let value = self.value.borrow().clone();
+        observer.on_next(value);
+        self.observable.subscribe(observer)
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for &mut ReplaySubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        (**self).on_next(item);
+    }
+
+    fn on_completed(self) {
+        (&mut self.subject).on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        (&mut self.subject).on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for ReplaySubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.subject.is_terminated() {
+            self.buffer.borrow_mut().push(item.clone());
+            self.subject.on_next(item);
+        }
+    }
+
+    fn on_completed(mut self) {
+        (&mut self).on_completed();
+    }
+
+    fn on_error(mut self, error: E) {
+        (&mut self).on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for ReplaySubjectSink<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.sink.core.is_terminated() {
+            self.buffer.borrow_mut().push(item.clone());
+            self.sink.on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        self.sink.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.sink.on_error(error);
+    }
+}
+
+impl<T, E> Clone for ReplaySubjectObservable<T, E> {
+    fn clone(&self) -> ReplaySubjectObservable<T, E> {
+        ReplaySubjectObservable {
+            observable: self.observable.clone(),
+            buffer: self.buffer.clone(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> Observable for ReplaySubjectObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, mut observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        // Copy the values, so the observer can push into the subject.
+        let values: Vec<T> = self.buffer.borrow().values.iter().cloned().collect();
+        for item in values {
+            observer.on_next(item);
+        }
+        // If the subject has terminated, the inner subject replays the terminal event.
+        self.observable.subscribe(observer)
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for &mut AsyncSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        (**self).on_next(item);
+    }
+
+    fn on_completed(self) {
+        if !self.subject.is_terminated() {
+            let last = self.last.borrow().clone();
+            if let Some(item) = last {
+                self.subject.on_next(item);
+            }
+            (&mut self.subject).on_completed();
+        }
+    }
+
+    fn on_error(self, error: E) {
+        (&mut self.subject).on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for AsyncSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.subject.is_terminated() {
+            *self.last.borrow_mut() = Some(item);
+        }
+    }
+
+    fn on_completed(mut self) {
+        (&mut self).on_completed();
+    }
+
+    fn on_error(mut self, error: E) {
+        (&mut self).on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for AsyncSubjectSink<T, E> {
+    fn on_next(&mut self, item: T) {
+        // Values after a terminal event are ignored.
+        if !self.sink.core.is_terminated() {
+            *self.last.borrow_mut() = Some(item);
+        }
+    }
+
+    fn on_completed(mut self) {
+        if !self.sink.core.is_terminated() {
+            let last = self.last.borrow().clone();
+            if let Some(item) = last {
+                self.sink.on_next(item);
+            }
+            self.sink.on_completed();
+        }
+    }
+
+    fn on_error(self, error: E) {
+        self.sink.on_error(error);
+    }
+}
+
+impl<T, E> Clone for AsyncSubjectObservable<T, E> {
+    fn clone(&self) -> AsyncSubjectObservable<T, E> {
+        AsyncSubjectObservable {
+            observable: self.observable.clone(),
+            last: self.last.clone(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> Observable for AsyncSubjectObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = SubjectSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, mut observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        if self.observable.core.is_completed() {
+            let last = self.last.borrow().clone();
+            if let Some(item) = last {
+                observer.on_next(item);
+            }
+        }
+        // If the subject has terminated, the inner subject replays the terminal event.
+        self.observable.subscribe(observer)
+    }
+}
+
+impl<T, E: Clone> Observer<T, E> for SharedSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.subject.on_next(Arc::new(item));
+    }
+
+    fn on_completed(self) {
+        self.subject.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.subject.on_error(error);
+    }
+}
+
+impl<T, E: Clone> Observer<T, E> for &mut SharedSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.subject.on_next(Arc::new(item));
+    }
+
+    fn on_completed(self) {
+        (&mut self.subject).on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        (&mut self.subject).on_error(error);
+    }
+}
+
+impl<T: Clone, E: Clone> Observer<T, E> for SyncSubject<T, E> {
+    fn on_next(&mut self, item: T) {
+        let mut state = self.lock();
+        if state.terminal.is_some() {
+            // Values after a terminal event are ignored.
+            return;
+        }
         let mut remove_indices = Vec::new();
-        let mut i = 0;
-        for observer_owner in &mut self.observers {
+        for (i, observer_owner) in state.observers.iter_mut().enumerate() {
             observer_owner.with_mut_value_or(|observer| {
                 // The subscription was not dropped, invoke the method.
                 observer.on_next(item.clone());
@@ -60,51 +1061,79 @@ impl<T: Clone, E: Clone> Observer<T, E> for Subject<T, E> {
                 // The subscription was dropped, ignore the observer next time.
                 remove_indices.push(i);
             });
-            i += 1;
         }
 
         for &rm_i in remove_indices.iter().rev() {
-            self.observers.remove(rm_i);
+            state.observers.remove(rm_i);
         }
     }
 
-    fn on_completed(mut self) {
-        for observer_owner in self.observers.drain(..) {
-            if let Some(observer) = observer_owner.take() {
-                // The subscription was not dropped, invoke the method.
-                observer.on_completed_box();
+    fn on_completed(self) {
+        let mut state = self.lock();
+        if state.terminal.is_none() {
+            state.terminal = Some(Ok(()));
+            for observer_owner in state.observers.drain(..) {
+                if let Some(observer) = observer_owner.take() {
+                    // The subscription was not dropped, invoke the method.
+                    observer.on_completed_box();
+                }
             }
         }
     }
 
-    fn on_error(mut self, error: E) {
-        for observer_owner in self.observers.drain(..) {
-            if let Some(observer) = observer_owner.take() {
-                // The subscription was not dropped, invoke the method.
-                observer.on_error_box(error.clone());
+    fn on_error(self, error: E) {
+        let mut state = self.lock();
+        if state.terminal.is_none() {
+            state.terminal = Some(Err(error.clone()));
+            for observer_owner in state.observers.drain(..) {
+                if let Some(observer) = observer_owner.take() {
+                    // The subscription was not dropped, invoke the method.
+                    observer.on_error_box(error.clone());
+                }
             }
         }
     }
 }
 
-impl<'s, T: Clone, E: Clone> Observable for SubjectObservable<'s, T, E> {
-    type Item = T;
-    type Error = E;
-    type Subscription = SubjectSubscription<T, E>;
+impl<T, E> Subscription for SyncSubjectSubscription<T, E> {
+    fn unsubscribe(&mut self) {
+        // Dropping the lifeline prevents further calls on the observer.
+        self.alive = None;
+    }
 
-    fn subscribe<O: 'static>(&mut self, observer: O) -> Self::Subscription
-        where O: Observer<Self::Item, Self::Error> {
-        let boxed: Box<BoxedObserver<T, E>> = Box::new(observer);
-        let (alive, owner) = lifeline::new(boxed);
-        self.subject.observers.push(owner);
-        SubjectSubscription {
-            alive: alive,
+    fn is_active(&self) -> bool {
+        self.alive.as_ref().is_some_and(|alive| alive.is_alive())
+    }
+}
+
+impl<T, E> Drop for SyncSubjectSubscription<T, E> {
+    fn drop(&mut self) {
+        // Nothing to do, the Arc already does the right thing.
+    }
+}
+
+impl<T, E> Subscription for SubjectSubscription<T, E> {
+    fn unsubscribe(&mut self) {
+        // Dropping the lifeline prevents further calls on the observer, and
+        // the entry in the subject can be removed right away. Entries are only
+        // removed here or when the subject terminates, and no observers can be
+        // added after that, so the key cannot have been reused by a different
+        // observer.
+        if let Some(registration) = self.registration.take() {
+            if let Some(core) = registration.core.upgrade() {
+                let observer = core.observers.borrow_mut().remove(registration.key);
+                drop(observer);
+            }
         }
     }
+
+    fn is_active(&self) -> bool {
+        self.registration.as_ref().is_some_and(|registration| registration.alive.is_alive())
+    }
 }
 
 impl<T, E> Drop for SubjectSubscription<T, E> {
     fn drop(&mut self) {
-        // Nothing to do, the Rc already does the right thing.
+        self.unsubscribe();
     }
 }
@@ -0,0 +1,184 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Subscriptions, and containers that manage their lifetime.
+
+use slab::Slab;
+
+/// A handle to an observer that is subscribed to an observable,
+/// or to an action that is scheduled.
+///
+/// Dropping a subscription cancels it, like `unsubscribe()` does. Calling
+/// `unsubscribe()` cancels it without giving up the subscription value, so
+/// it can still be asked whether it is active.
+pub trait Subscription {
+    /// Prevents further calls on the observer, or cancels the action.
+    ///
+    /// Calling this more than once has no effect.
+    fn unsubscribe(&mut self);
+
+    /// Returns whether the observer can still receive notifications,
+    /// or whether the action can still run.
+    ///
+    /// This returns false after `unsubscribe()`, and after the observable
+    /// has completed or failed.
+    fn is_active(&self) -> bool;
+}
+
+/// A group of subscriptions that are dropped together.
+///
+/// The subscriptions in the group can be of different types. They are
+/// dropped when the composite subscription is dropped, or when `clear()` is
+/// called. After `clear()`, new subscriptions can be added.
+pub struct CompositeSubscription {
+    // Every subscription is stored with the generation of its key.
+    subscriptions: Slab<(u64, Box<dyn Subscription>)>,
+    next_generation: u64,
+}
+
+/// A slot that holds at most one subscription.
+///
+/// Setting a new subscription drops the previous one, after the new one has
+/// been stored. The subscription in the slot is dropped when the serial
+/// subscription is dropped, or when `clear()` is called.
+pub struct SerialSubscription {
+    current: Option<Box<dyn Subscription>>,
+}
+
+/// Identifies a subscription in a composite subscription.
+///
+/// A key never identifies a different subscription than the one it was
+/// returned for, also after that subscription was removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompositeKey {
+    index: usize,
+
+    // The slots of removed subscriptions are reused, the generation tells
+    // the subscriptions in the same slot apart.
+    generation: u64,
+}
+
+impl CompositeSubscription {
+    /// Creates a new empty composite subscription.
+    pub fn new() -> CompositeSubscription {
+        CompositeSubscription {
+            subscriptions: Slab::new(),
+            next_generation: 0,
+        }
+    }
+
+    /// Adds the subscription to the group, and returns its key.
+    pub fn add<S: 'static + Subscription>(&mut self, subscription: S) -> CompositeKey {
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        CompositeKey {
+            index: self.subscriptions.insert((generation, Box::new(subscription))),
+            generation: generation,
+        }
+    }
+
+    /// Drops the subscription with the given key.
+    ///
+    /// Returns whether the group still held the subscription.
+    pub fn remove(&mut self, key: CompositeKey) -> bool {
+        let is_held = match self.subscriptions.get(key.index) {
+            Some(&(generation, _)) => generation == key.generation,
+            None => false,
+        };
+        if is_held {
+            drop(self.subscriptions.remove(key.index));
+        }
+        is_held
+    }
+
+    /// Drops all subscriptions in the group.
+    pub fn clear(&mut self) {
+        for subscription in self.subscriptions.drain() {
+            drop(subscription);
+        }
+    }
+
+    /// Returns the number of subscriptions in the group.
+    pub fn len(&self) -> usize {
+        self.subscriptions.iter().count()
+    }
+
+    /// Returns whether the group holds no subscriptions.
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.iter().next().is_none()
+    }
+}
+
+impl Default for CompositeSubscription {
+    fn default() -> CompositeSubscription {
+        CompositeSubscription::new()
+    }
+}
+
+impl SerialSubscription {
+    /// Creates a new empty serial subscription.
+    pub fn new() -> SerialSubscription {
+        SerialSubscription {
+            current: None,
+        }
+    }
+
+    /// Stores the subscription, and drops the previous one.
+    pub fn set<S: 'static + Subscription>(&mut self, subscription: S) {
+        let previous = self.current.replace(Box::new(subscription));
+        drop(previous);
+    }
+
+    /// Drops the subscription in the slot, if there is one.
+    pub fn clear(&mut self) {
+        let previous = self.current.take();
+        drop(previous);
+    }
+
+    /// Returns whether the slot holds no subscription.
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none()
+    }
+}
+
+impl Default for SerialSubscription {
+    fn default() -> SerialSubscription {
+        SerialSubscription::new()
+    }
+}
+
+impl Subscription for CompositeSubscription {
+    fn unsubscribe(&mut self) {
+        self.clear();
+    }
+
+    /// Returns whether any subscription in the group is active.
+    fn is_active(&self) -> bool {
+        self.subscriptions.iter().any(|(_key, (_, subscription))| subscription.is_active())
+    }
+}
+
+impl Subscription for SerialSubscription {
+    fn unsubscribe(&mut self) {
+        self.clear();
+    }
+
+    /// Returns whether the subscription in the slot is active.
+    fn is_active(&self) -> bool {
+        self.current.as_ref().is_some_and(|subscription| subscription.is_active())
+    }
+}
+
+impl<S: Subscription + ?Sized> Subscription for Box<S> {
+    fn unsubscribe(&mut self) {
+        (**self).unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        (**self).is_active()
+    }
+}
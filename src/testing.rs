@@ -0,0 +1,487 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! Helpers for testing observables and operators.
+//!
+//! A `TestObserver` records the notifications it receives, so a test can
+//! subscribe it and make assertions about what was pushed afterwards:
+//!
+//! ```
+//! use rx::{Observable, ObservableExt};
+//! use rx::testing::TestObserver;
+//! let observer = TestObserver::new();
+//! (&[2u8, 3]).map(|&x| x * 2).subscribe(observer.clone());
+//! observer.assert_items(&[4, 6]);
+//! observer.assert_completed();
+//! ```
+//!
+//! # Marble diagrams
+//!
+//! Timing-sensitive operators are easier to specify with marble diagrams.
+//! A marble diagram is a string in which every character is one frame of
+//! virtual time on a `TestScheduler`, see `frames()`:
+//!
+//!  * `-` is a frame in which nothing happens.
+//!  * A letter or digit is a value, looked up in the values that are passed
+//!    along with the diagram.
+//!  * `|` is completion.
+//!  * `#` is failure, with the default value of the error type.
+//!  * `(` and `)` group notifications that happen in the same frame. The
+//!    group starts at the frame of `(`.
+//!  * `^` marks the subscription point of a hot observable, which is the
+//!    time at which it is created. Notifications before it are never pushed.
+//!  * Spaces are ignored, they can be used to align diagrams.
+//!
+//! `cold()` and `hot()` build observables from a diagram, and
+//! `expect_marbles()` compares what a test observer received to a diagram:
+//!
+//! ```
+//! use rx::{Observable, ObservableExt, TestScheduler};
+//! use rx::testing::{TestObserver, cold, expect_marbles};
+//! let scheduler = TestScheduler::new();
+//! let values = &[('a', 1), ('b', 2), ('x', 10), ('y', 20)];
+//! let mut source = cold::<u8, ()>(&scheduler, "-a--b-|", values);
+//! let observer = TestObserver::with_scheduler(&scheduler);
+//! let _subscription = source.map(|x| x * 10).subscribe(observer.clone());
+//! scheduler.advance_by(rx::testing::frames(10));
+//! expect_marbles(&observer, "-x--y-|", values);
+//! ```
+
+use Notification;
+use observable::Observable;
+use observer::Observer;
+use scheduler::{Scheduler, TestScheduler};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::time::Duration;
+use subject::{Subject, SubjectObservable, SubjectSubscription};
+use subscription::{CompositeSubscription, Subscription};
+
+/// An observer that records every notification it receives.
+///
+/// The test observer is a handle: subscribe a clone of it, and inspect the
+/// recorded notifications through the original. If it was created with a
+/// `TestScheduler`, every notification is recorded with the virtual time at
+/// which it was received. Otherwise, the time is always zero.
+pub struct TestObserver<T, E> {
+    notifications: SharedNotifications<T, E>,
+    scheduler: Option<TestScheduler>,
+}
+
+type SharedNotifications<T, E> = Rc<RefCell<Vec<(Duration, Notification<T, E>)>>>;
+
+impl<T, E> TestObserver<T, E> {
+    /// Creates a test observer that has not received anything.
+    pub fn new() -> TestObserver<T, E> {
+        TestObserver {
+            notifications: Rc::new(RefCell::new(Vec::new())),
+            scheduler: None,
+        }
+    }
+
+    /// Creates a test observer that records the virtual time of the scheduler.
+    pub fn with_scheduler(scheduler: &TestScheduler) -> TestObserver<T, E> {
+        TestObserver {
+            notifications: Rc::new(RefCell::new(Vec::new())),
+            scheduler: Some(scheduler.clone()),
+        }
+    }
+
+    fn record(&self, notification: Notification<T, E>) {
+        let now = self.scheduler.as_ref().map_or(Duration::from_secs(0), |s| s.now());
+        self.notifications.borrow_mut().push((now, notification));
+    }
+
+    /// Returns whether the observer received `on_completed()`.
+    pub fn is_completed(&self) -> bool {
+        self.notifications.borrow().iter().any(|(_, n)| match *n {
+            Notification::Completed => true,
+            Notification::Next(..) | Notification::Error(..) => false,
+        })
+    }
+
+    /// Returns whether the observer received `on_completed()` or `on_error()`.
+    pub fn is_terminated(&self) -> bool {
+        self.notifications.borrow().iter().any(|(_, n)| match *n {
+            Notification::Next(..) => false,
+            Notification::Completed | Notification::Error(..) => true,
+        })
+    }
+
+    /// Panics unless the observer received `on_completed()`.
+    pub fn assert_completed(&self) {
+        assert!(self.is_completed(), "observer did not complete");
+    }
+
+    /// Panics if the observer received `on_completed()` or `on_error()`.
+    pub fn assert_not_terminated(&self) {
+        assert!(!self.is_terminated(), "observer completed or failed");
+    }
+
+    /// Panics unless the observer failed with an error for which `f` returns true.
+    pub fn assert_error_matches<F: FnOnce(&E) -> bool>(&self, f: F) {
+        let notifications = self.notifications.borrow();
+        let error = notifications.iter().filter_map(|(_, n)| match *n {
+            Notification::Error(ref error) => Some(error),
+            _ => None,
+        }).next();
+        match error {
+            Some(error) => assert!(f(error), "observer failed with an unexpected error"),
+            None => panic!("observer did not fail"),
+        }
+    }
+}
+
+impl<T, E> Default for TestObserver<T, E> {
+    fn default() -> TestObserver<T, E> {
+        TestObserver::new()
+    }
+}
+
+impl<T: Clone, E: Clone> TestObserver<T, E> {
+    /// Returns the recorded notifications with the time at which they were received.
+    pub fn notifications(&self) -> Vec<(Duration, Notification<T, E>)> {
+        self.notifications.borrow().clone()
+    }
+
+    /// Returns the values that the observer received, in order.
+    pub fn items(&self) -> Vec<T> {
+        self.notifications.borrow().iter().filter_map(|(_, n)| match *n {
+            Notification::Next(ref item) => Some(item.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the error that the observer received, if it failed.
+    pub fn error(&self) -> Option<E> {
+        self.notifications.borrow().iter().filter_map(|(_, n)| match *n {
+            Notification::Error(ref error) => Some(error.clone()),
+            _ => None,
+        }).next()
+    }
+}
+
+impl<T: Clone + Debug + PartialEq, E: Clone> TestObserver<T, E> {
+    /// Panics unless the observer received exactly these values, in order.
+    pub fn assert_items(&self, expected: &[T]) {
+        assert_eq!(expected, &self.items()[..]);
+    }
+}
+
+impl<T: Clone + Debug + PartialEq, E: Clone + Debug + PartialEq> TestObserver<T, E> {
+    /// Panics unless the observer failed with the error.
+    pub fn assert_error(&self, expected: E) {
+        assert_eq!(Some(expected), self.error());
+    }
+}
+
+impl<T, E> Clone for TestObserver<T, E> {
+    fn clone(&self) -> TestObserver<T, E> {
+        TestObserver {
+            notifications: self.notifications.clone(),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+}
+
+impl<T, E> Observer<T, E> for TestObserver<T, E> {
+    fn on_next(&mut self, item: T) {
+        self.record(Notification::Next(item));
+    }
+
+    fn on_completed(self) {
+        self.record(Notification::Completed);
+    }
+
+    fn on_error(self, error: E) {
+        self.record(Notification::Error(error));
+    }
+}
+
+/// Returns the virtual time that `n` frames of a marble diagram take.
+///
+/// A frame is one millisecond.
+pub fn frames(n: u32) -> Duration {
+    Duration::from_millis(n as u64)
+}
+
+type FrameNotifications<T, E> = Vec<(u32, Notification<T, E>)>;
+
+/// Parses a marble diagram into notifications and their frame.
+///
+/// Returns the frame of the subscription point, and the notifications with
+/// frames relative to the start of the diagram.
+fn parse_marbles<T: Clone, E: Default>(marbles: &str,
+                                       values: &[(char, T)])
+                                       -> (u32, FrameNotifications<T, E>) {
+    let mut notifications = Vec::new();
+    let mut subscribed = None;
+    let mut group = None;
+    let mut frame = 0;
+    for c in marbles.chars() {
+        let at = group.unwrap_or(frame);
+        match c {
+            ' ' => continue,
+            '-' => {}
+            '(' => {
+                assert!(group.is_none(), "nested group in marble diagram '{}'", marbles);
+                group = Some(frame);
+            }
+            ')' => {
+                assert!(group.is_some(), "unopened group in marble diagram '{}'", marbles);
+                group = None;
+            }
+            '^' => {
+                assert!(subscribed.is_none(), "multiple '^' in marble diagram '{}'", marbles);
+                subscribed = Some(frame);
+            }
+            '|' => notifications.push((at, Notification::Completed)),
+            '#' => notifications.push((at, Notification::Error(E::default()))),
+            _ => {
+                let item = values.iter().find(|&&(name, _)| name == c).map(|(_, x)| x.clone());
+                match item {
+                    Some(item) => notifications.push((at, Notification::Next(item))),
+                    None => panic!("no value for '{}' in marble diagram '{}'", c, marbles),
+                }
+            }
+        }
+        frame += 1;
+    }
+    assert!(group.is_none(), "unclosed group in marble diagram '{}'", marbles);
+    (subscribed.unwrap_or(0), notifications)
+}
+
+/// The result of calling `cold()`.
+pub struct ColdObservable<T, E> {
+    scheduler: TestScheduler,
+    notifications: Rc<Vec<(Duration, Notification<T, E>)>>,
+}
+
+/// Creates an observable that pushes the notifications of a marble diagram.
+///
+/// Every observer receives all notifications in the diagram, at their frame
+/// relative to the time at which it subscribed. Panics if the diagram
+/// contains `^`, or a value that is not in `values`.
+pub fn cold<T: Clone, E: Clone + Default>(scheduler: &TestScheduler,
+                                          marbles: &str,
+                                          values: &[(char, T)])
+                                          -> ColdObservable<T, E> {
+    let (_, notifications) = parse_marbles(marbles, values);
+    assert!(!marbles.contains('^'),
+            "cold marble diagram '{}' cannot have a subscription point", marbles);
+    ColdObservable {
+        scheduler: scheduler.clone(),
+        notifications: Rc::new(notifications.into_iter().map(|(f, n)| (frames(f), n)).collect()),
+    }
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> Observable for ColdObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = CompositeSubscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> CompositeSubscription
+        where O: Observer<T, E> + 'static {
+        // The scheduled actions share the observer. A terminal event takes it.
+        let observer = Rc::new(RefCell::new(Some(observer)));
+        let mut subscription = CompositeSubscription::new();
+        for &(delay, ref notification) in self.notifications.iter() {
+            let observer = observer.clone();
+            let notification = notification.clone();
+            let action = self.scheduler.schedule_after(delay, move || {
+                match notification {
+                    Notification::Next(item) => {
+                        if let Some(ref mut observer) = *observer.borrow_mut() {
+                            observer.on_next(item);
+                        }
+                    }
+                    Notification::Completed => {
+                        let observer = observer.borrow_mut().take();
+                        if let Some(observer) = observer {
+                            observer.on_completed();
+                        }
+                    }
+                    Notification::Error(error) => {
+                        let observer = observer.borrow_mut().take();
+                        if let Some(observer) = observer {
+                            observer.on_error(error);
+                        }
+                    }
+                }
+            });
+            subscription.add(action);
+        }
+        subscription
+    }
+}
+
+// When every observer subscribed, and when it unsubscribed, if it did.
+type SubscriptionTimes = Rc<RefCell<Vec<(Duration, Option<Duration>)>>>;
+
+/// An observable that pushes scripted notifications at given virtual times.
+///
+/// The notifications are pushed to the observers that are subscribed at that
+/// time, regardless of when they subscribed, like a hot observable. The
+/// observable records at what virtual time every observer subscribed, and at
+/// what time its subscription was unsubscribed or dropped. Dropping the
+/// scheduled observable cancels the notifications that have not been pushed
+/// yet.
+pub struct ScheduledObservable<T, E> {
+    scheduler: TestScheduler,
+    observable: SubjectObservable<T, E>,
+    subscriptions: SubscriptionTimes,
+    _actions: CompositeSubscription,
+}
+
+/// The result of subscribing to a scheduled observable.
+///
+/// Unsubscribing or dropping the subscription records the virtual time in the
+/// scheduled observable.
+pub struct RecordedSubscription<T, E> {
+    subscription: SubjectSubscription<T, E>,
+    scheduler: TestScheduler,
+    subscriptions: SubscriptionTimes,
+    index: usize,
+}
+
+impl<T: Clone + 'static, E: Clone + 'static> ScheduledObservable<T, E> {
+    /// Creates an observable that pushes every notification at its virtual time.
+    ///
+    /// Panics if a notification is due before the current time of the scheduler.
+    pub fn new(scheduler: &TestScheduler,
+               notifications: Vec<(Duration, Notification<T, E>)>)
+               -> ScheduledObservable<T, E> {
+        let subject = Subject::new();
+        let mut actions = CompositeSubscription::new();
+        let sink = Rc::new(RefCell::new(Some(subject.sink())));
+        for (time, notification) in notifications {
+            assert!(time >= scheduler.now(), "notification is due before the current time");
+            let sink = sink.clone();
+            let action = scheduler.schedule_after(time - scheduler.now(), move || {
+                match notification {
+                    Notification::Next(item) => {
+                        if let Some(ref mut sink) = *sink.borrow_mut() {
+                            sink.on_next(item);
+                        }
+                    }
+                    Notification::Completed => {
+                        let sink = sink.borrow_mut().take();
+                        if let Some(sink) = sink {
+                            sink.on_completed();
+                        }
+                    }
+                    Notification::Error(error) => {
+                        let sink = sink.borrow_mut().take();
+                        if let Some(sink) = sink {
+                            sink.on_error(error);
+                        }
+                    }
+                }
+            });
+            actions.add(action);
+        }
+        ScheduledObservable {
+            scheduler: scheduler.clone(),
+            observable: subject.observable(),
+            subscriptions: Rc::new(RefCell::new(Vec::new())),
+            _actions: actions,
+        }
+    }
+}
+
+impl<T, E> ScheduledObservable<T, E> {
+    /// Returns the virtual times at which observers subscribed and unsubscribed.
+    ///
+    /// The unsubscription time is `None` if the subscription is still held.
+    /// Completion or failure does not end a subscription.
+    pub fn subscriptions(&self) -> Vec<(Duration, Option<Duration>)> {
+        self.subscriptions.borrow().clone()
+    }
+}
+
+/// Creates an observable that pushes the notifications of a marble diagram to
+/// its current observers.
+///
+/// The `^` in the diagram marks the current time of the scheduler. The
+/// notifications after it are pushed at their frame relative to that time,
+/// regardless of when observers subscribe. Notifications before it are
+/// ignored. Panics if the diagram contains a value that is not in `values`.
+pub fn hot<T: Clone + 'static, E: Clone + Default + 'static>(scheduler: &TestScheduler,
+                                                             marbles: &str,
+                                                             values: &[(char, T)])
+                                                             -> ScheduledObservable<T, E> {
+    let (subscribed, notifications) = parse_marbles(marbles, values);
+    let now = scheduler.now();
+    let notifications = notifications.into_iter()
+        .filter(|&(frame, _)| frame >= subscribed)
+        .map(|(frame, n)| (now + frames(frame - subscribed), n))
+        .collect();
+    ScheduledObservable::new(scheduler, notifications)
+}
+
+impl<T: Clone, E: Clone> Observable for ScheduledObservable<T, E> {
+    type Item = T;
+    type Error = E;
+    type Subscription = RecordedSubscription<T, E>;
+
+    fn subscribe<O>(&mut self, observer: O) -> RecordedSubscription<T, E>
+        where O: Observer<T, E> + 'static {
+        let index = {
+            let mut subscriptions = self.subscriptions.borrow_mut();
+            subscriptions.push((self.scheduler.now(), None));
+            subscriptions.len() - 1
+        };
+        RecordedSubscription {
+            subscription: self.observable.subscribe(observer),
+            scheduler: self.scheduler.clone(),
+            subscriptions: self.subscriptions.clone(),
+            index: index,
+        }
+    }
+}
+
+impl<T, E> RecordedSubscription<T, E> {
+    // Records the unsubscription, only the first time this is called.
+    fn record(&mut self) {
+        let mut subscriptions = self.subscriptions.borrow_mut();
+        let entry = &mut subscriptions[self.index];
+        if entry.1.is_none() {
+            entry.1 = Some(self.scheduler.now());
+        }
+    }
+}
+
+impl<T, E> Subscription for RecordedSubscription<T, E> {
+    fn unsubscribe(&mut self) {
+        self.record();
+        self.subscription.unsubscribe();
+    }
+
+    fn is_active(&self) -> bool {
+        self.subscription.is_active()
+    }
+}
+
+impl<T, E> Drop for RecordedSubscription<T, E> {
+    fn drop(&mut self) {
+        // The subject subscription is dropped after this.
+        self.record();
+    }
+}
+
+/// Panics unless the observer received exactly the notifications of the
+/// marble diagram.
+///
+/// The frames in the diagram are relative to virtual time zero of the
+/// scheduler that the observer was created with.
+pub fn expect_marbles<T, E>(observer: &TestObserver<T, E>, marbles: &str, values: &[(char, T)])
+    where T: Clone + Debug + PartialEq, E: Clone + Debug + Default + PartialEq {
+    let (_, expected) = parse_marbles(marbles, values);
+    let expected: Vec<_> = expected.into_iter().map(|(f, n)| (frames(f), n)).collect();
+    assert_eq!(expected, observer.notifications(), "notifications do not match '{}'", marbles);
+}
@@ -0,0 +1,138 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `timestamp()` and `time_interval()` operators, which pair values with
+//! the time of the scheduler.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use scheduler::Scheduler;
+use std::time::{Duration, Instant};
+
+/// The result of calling `timestamp()` on an observable.
+pub struct TimestampObservable<Source, Sch> {
+    source: Source,
+    scheduler: Sch,
+}
+
+struct TimestampObserver<O, Sch> {
+    observer: O,
+    scheduler: Sch,
+}
+
+impl<Source, Sch> TimestampObservable<Source, Sch> {
+    pub fn new(source: Source, scheduler: Sch) -> TimestampObservable<Source, Sch> {
+        TimestampObservable {
+            source: source,
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, Sch> Observable for TimestampObservable<Source, Sch>
+where Source: Observable,
+      Sch: Scheduler + Clone + 'static {
+    type Item = (Instant, Source::Item);
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let timestamp_observer = TimestampObserver {
+            observer: observer,
+            scheduler: self.scheduler.clone(),
+        };
+        self.source.subscribe(timestamp_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("timestamp", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, Sch> Observer<T, E> for TimestampObserver<O, Sch>
+where O: Observer<(Instant, T), E>,
+      Sch: Scheduler {
+    fn on_next(&mut self, item: T) {
+        self.observer.on_next((self.scheduler.now_instant(), item));
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+/// The result of calling `time_interval()` on an observable.
+pub struct TimeIntervalObservable<Source, Sch> {
+    source: Source,
+    scheduler: Sch,
+}
+
+struct TimeIntervalObserver<O, Sch> {
+    observer: O,
+    scheduler: Sch,
+
+    // The time of the previous value, or of the subscription before the
+    // first value.
+    previous: Instant,
+}
+
+impl<Source, Sch> TimeIntervalObservable<Source, Sch> {
+    pub fn new(source: Source, scheduler: Sch) -> TimeIntervalObservable<Source, Sch> {
+        TimeIntervalObservable {
+            source: source,
+            scheduler: scheduler,
+        }
+    }
+}
+
+impl<Source, Sch> Observable for TimeIntervalObservable<Source, Sch>
+where Source: Observable,
+      Sch: Scheduler + Clone + 'static {
+    type Item = (Duration, Source::Item);
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let interval_observer = TimeIntervalObserver {
+            observer: observer,
+            scheduler: self.scheduler.clone(),
+            previous: self.scheduler.now_instant(),
+        };
+        self.source.subscribe(interval_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("time_interval", vec![self.source.describe()])
+    }
+}
+
+impl<T, E, O, Sch> Observer<T, E> for TimeIntervalObserver<O, Sch>
+where O: Observer<(Duration, T), E>,
+      Sch: Scheduler {
+    fn on_next(&mut self, item: T) {
+        let now = self.scheduler.now_instant();
+        // A clock that goes backwards counts as no time passing.
+        let interval = now.checked_duration_since(self.previous).unwrap_or(Duration::from_secs(0));
+        self.previous = now;
+        self.observer.on_next((interval, item));
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
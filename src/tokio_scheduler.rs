@@ -0,0 +1,183 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! A scheduler that runs actions as tasks on a tokio runtime.
+//!
+//! This module is only available with the `tokio` feature.
+
+use scheduler::{Scheduler, SendScheduler};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{self as std_time, Duration};
+use subscription::Subscription;
+use tokio::runtime::Handle;
+use tokio::task::{self, JoinHandle};
+use tokio::time::{self, Instant, Interval, Sleep};
+
+/// A scheduler that runs actions as tasks on a tokio runtime.
+///
+/// Delays use the timer of the runtime, so the runtime must have the time
+/// driver enabled. As a `SendScheduler`, actions are spawned onto the runtime
+/// of the handle. As a `Scheduler`, actions need not be `Send`, and they are
+/// spawned onto the `LocalSet` of the current thread instead; scheduling an
+/// action outside of a `LocalSet` panics.
+#[derive(Clone)]
+pub struct TokioScheduler {
+    handle: Handle,
+}
+
+/// The result of scheduling an action on a `TokioScheduler`.
+///
+/// Dropping this subscription aborts the task of the action, which cancels
+/// the action if it has not run yet, and stops a periodic action.
+pub struct TaskSubscription {
+    task: JoinHandle<()>,
+
+    // An aborted task finishes only when the runtime gets to it.
+    is_cancelled: bool,
+}
+
+// Runs the action once the delay has passed.
+struct Delayed<F> {
+    sleep: Pin<Box<Sleep>>,
+    action: Option<F>,
+}
+
+// Runs the action on every tick of the interval.
+struct Periodic<F> {
+    interval: Interval,
+    action: F,
+}
+
+// Neither future pins the action, so they can be moved freely.
+impl<F> Unpin for Delayed<F> {}
+impl<F> Unpin for Periodic<F> {}
+
+impl<F: FnOnce()> Future for Delayed<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        match this.sleep.as_mut().poll(context) {
+            Poll::Ready(()) => {
+                if let Some(action) = this.action.take() {
+                    action();
+                }
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F: FnMut()> Future for Periodic<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        while this.interval.poll_tick(context).is_ready() {
+            (this.action)();
+        }
+        Poll::Pending
+    }
+}
+
+fn delayed<F>(delay: Duration, action: F) -> Delayed<F> {
+    Delayed {
+        sleep: Box::pin(time::sleep(delay)),
+        action: Some(action),
+    }
+}
+
+fn periodic<F>(period: Duration, action: F) -> Periodic<F> {
+    Periodic {
+        interval: time::interval_at(Instant::now() + period, period),
+        action: action,
+    }
+}
+
+impl TokioScheduler {
+    /// Creates a scheduler that spawns onto the runtime of the handle.
+    pub fn new(handle: Handle) -> TokioScheduler {
+        TokioScheduler {
+            handle: handle,
+        }
+    }
+
+    /// Creates a scheduler that spawns onto the current runtime.
+    ///
+    /// Panics if it is not called from within a tokio runtime.
+    pub fn current() -> TokioScheduler {
+        TokioScheduler::new(Handle::current())
+    }
+}
+
+impl Scheduler for TokioScheduler {
+    type Subscription = TaskSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> TaskSubscription
+        where F: FnOnce() + 'static {
+        // The timer belongs to the runtime of the handle, which must be
+        // entered to create it.
+        let _guard = self.handle.enter();
+        TaskSubscription::new(task::spawn_local(delayed(delay, action)))
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> TaskSubscription
+        where F: FnMut() + 'static {
+        let _guard = self.handle.enter();
+        TaskSubscription::new(task::spawn_local(periodic(period, action)))
+    }
+
+    fn now_instant(&self) -> std_time::Instant {
+        // The clock of tokio can be paused and advanced in tests.
+        Instant::now().into_std()
+    }
+}
+
+impl SendScheduler for TokioScheduler {
+    type Subscription = TaskSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> TaskSubscription
+        where F: FnOnce() + Send + 'static {
+        let _guard = self.handle.enter();
+        TaskSubscription::new(self.handle.spawn(delayed(delay, action)))
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> TaskSubscription
+        where F: FnMut() + Send + 'static {
+        let _guard = self.handle.enter();
+        TaskSubscription::new(self.handle.spawn(periodic(period, action)))
+    }
+}
+
+impl TaskSubscription {
+    fn new(task: JoinHandle<()>) -> TaskSubscription {
+        TaskSubscription {
+            task: task,
+            is_cancelled: false,
+        }
+    }
+}
+
+impl Subscription for TaskSubscription {
+    fn unsubscribe(&mut self) {
+        self.task.abort();
+        self.is_cancelled = true;
+    }
+
+    fn is_active(&self) -> bool {
+        !self.is_cancelled && !self.task.is_finished()
+    }
+}
+
+impl Drop for TaskSubscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -5,29 +5,28 @@
 // you may not use this file except in compliance with the License.
 // A copy of the License has been included in the root of the repository.
 
+use describe::Graph;
 use lifeline;
 use observable::Observable;
-use observer::Observer;
+use observer::{BoxedObserver, Observer};
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::rc::Rc;
+use subscription::Subscription;
 
-struct MapObserver<T, U, E, O, F>
-where O: Observer<U, E>,
-      F: Fn(T) -> U {
+struct MapObserver<O, F> {
     observer: O,
-    f: F,
-    _phantom_t: PhantomData<*mut T>,
-    _phantom_u: PhantomData<*mut U>,
-    _phantom_e: PhantomData<*mut E>,
+    f: Rc<F>,
 }
 
-impl<T, U, E, O, F> Observer<T, E> for MapObserver<T, U, E, O, F>
+impl<T, U, E, O, F> Observer<T, E> for MapObserver<O, F>
 where T: Clone,
       U: Clone,
       E: Clone,
       O: Observer<U, E>,
       F: Fn(T) -> U {
     fn on_next(&mut self, item: T) {
-        self.observer.on_next(self.f.call((item,)));
+        self.observer.on_next((self.f)(item));
     }
 
     fn on_completed(self) {
@@ -40,52 +39,50 @@ where T: Clone,
 }
 
 /// The result of calling `map()` on an observable.
-pub struct MapObservable<'a, Source: 'a + ?Sized, F> {
-    source: &'a mut Source,
-    f: F
+pub struct MapObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
 }
 
-impl<'a, Source: 'a + ?Sized, F> MapObservable<'a, Source, F> {
-    pub fn new(source: &'a mut Source, f: F) -> MapObservable<'a, Source, F> {
+impl<Source, F> MapObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> MapObservable<Source, F> {
         MapObservable {
             source: source,
-            f: f,
+            f: Rc::new(f),
         }
     }
 }
 
-impl<'a, Source, U, F> Observable for MapObservable<'a, Source, F>
+impl<Source, U, F> Observable for MapObservable<Source, F>
 where Source: Observable,
       U: Clone,
-      F: Fn(<Source as Observable>::Item) -> U {
+      F: Fn(<Source as Observable>::Item) -> U + 'static {
     type Item = U;
     type Error = <Source as Observable>::Error;
     type Subscription = <Source as Observable>::Subscription;
 
     fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
-        where O: Observer<Self::Item, Self::Error> {
+        where O: Observer<Self::Item, Self::Error> + 'static {
         let mapped_observer = MapObserver {
             observer: observer,
-            f: &self.f,
-            _phantom_t: PhantomData,
-            _phantom_u: PhantomData,
-            _phantom_e: PhantomData,
+            f: self.f.clone(),
         };
         self.source.subscribe(mapped_observer)
     }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("map", vec![self.source.describe()])
+    }
 }
 
-struct MapErrorObserver<T, E, F, O, G>
-where O: Observer<T, F>,
-      G: Fn(E) -> F {
+struct MapErrorObserver<O, G> {
     observer: O,
-    f: G,
-    _phantom_t: PhantomData<*mut T>,
-    _phantom_e: PhantomData<*mut E>,
-    _phantom_f: PhantomData<*mut F>,
+    f: Rc<G>,
 }
 
-impl<T, E, F, O, G> Observer<T, E> for MapErrorObserver<T, E, F, O, G>
+impl<T, E, F, O, G> Observer<T, E> for MapErrorObserver<O, G>
 where T: Clone,
       E: Clone,
       F: Clone,
@@ -100,35 +97,35 @@ where T: Clone,
     }
 
     fn on_error(self, error: E) {
-        self.observer.on_error(self.f.call((error,)));
+        self.observer.on_error((self.f)(error));
     }
 }
 
 /// The result of calling `map_error()` on an observable.
-pub struct MapErrorObservable<'a, Source: 'a + ?Sized, G> {
-    source: &'a mut Source,
-    f: G
+pub struct MapErrorObservable<Source, G> {
+    source: Source,
+    f: Rc<G>,
 }
 
-impl<'a, Source: 'a + ?Sized, G> MapErrorObservable<'a, Source, G> {
-    pub fn new(source: &'a mut Source, f: G) -> MapErrorObservable<'a, Source, G> {
+impl<Source, G> MapErrorObservable<Source, G> {
+    pub fn new(source: Source, f: G) -> MapErrorObservable<Source, G> {
         MapErrorObservable {
             source: source,
-            f: f,
+            f: Rc::new(f),
         }
     }
 }
 
-impl<'a, Source, F, G> Observable for MapErrorObservable<'a, Source, G>
+impl<Source, F, G> Observable for MapErrorObservable<Source, G>
 where Source: Observable,
       F: Clone,
-      G: Fn(<Source as Observable>::Error) -> F {
+      G: Fn(<Source as Observable>::Error) -> F + 'static {
     type Item = <Source as Observable>::Item;
     type Error = F;
     type Subscription = <Source as Observable>::Subscription;
 
     fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
-        where O: Observer<Self::Item, Self::Error> {
+        where O: Observer<Self::Item, Self::Error> + 'static {
         // Note that the function `G` cannot be `FnOnce` because every observer
         // receives a copy of it. Alternatively, `map_error` could be
         // implemented with a subject to only call the mapping function once,
@@ -136,21 +133,106 @@ where Source: Observable,
         // function call.
         let mapped_observer = MapErrorObserver {
             observer: observer,
-            f: &self.f,
-            _phantom_t: PhantomData,
-            _phantom_e: PhantomData,
-            _phantom_f: PhantomData,
+            f: self.f.clone(),
         };
         self.source.subscribe(mapped_observer)
     }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("map_error", vec![self.source.describe()])
+    }
+}
+
+struct ConcatMapIterObserver<O, F> {
+    observer: O,
+    f: Rc<F>,
+}
+
+impl<T, E, I, O, F> Observer<T, E> for ConcatMapIterObserver<O, F>
+where I: IntoIterator,
+      O: Observer<I::Item, E>,
+      F: Fn(T) -> I {
+    fn on_next(&mut self, item: T) {
+        for element in (self.f)(item) {
+            self.observer.on_next(element);
+        }
+    }
+
+    fn on_completed(self) {
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        self.observer.on_error(error);
+    }
+}
+
+/// The result of calling `concat_map_iter()` or `flatten_iter()` on an observable.
+pub struct ConcatMapIterObservable<Source, F> {
+    source: Source,
+
+    // Every observer shares the function.
+    f: Rc<F>,
+}
+
+impl<Source, F> ConcatMapIterObservable<Source, F> {
+    pub fn new(source: Source, f: F) -> ConcatMapIterObservable<Source, F> {
+        ConcatMapIterObservable {
+            source: source,
+            f: Rc::new(f),
+        }
+    }
+}
+
+/// Returns the value as it is, for `flatten_iter()`.
+pub fn identity<T>(item: T) -> T {
+    item
+}
+
+impl<Source, I, F> Observable for ConcatMapIterObservable<Source, F>
+where Source: Observable,
+      I: IntoIterator,
+      I::Item: Clone,
+      F: Fn(Source::Item) -> I + 'static {
+    type Item = I::Item;
+    type Error = Source::Error;
+    type Subscription = Source::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let concat_observer = ConcatMapIterObserver {
+            observer: observer,
+            f: self.f.clone(),
+        };
+        self.source.subscribe(concat_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("concat_map_iter", vec![self.source.describe()])
+    }
 }
 
+/// The result of subscribing to a `continue_with()` observable.
 pub struct ContinueWithSubscription<Source: Observable, ObNext: Observable> {
-    #[allow(dead_code)] // This code is not dead, it keeps the subscription alive.
     subs_source: Source::Subscription,
+    subs_next: Option<lifeline::Lifeline<Option<ObNext::Subscription>>>,
+}
 
-    #[allow(dead_code)] // Same here.
-    subs_next: lifeline::Lifeline<Option<ObNext::Subscription>>,
+impl<Source: Observable, ObNext: Observable> Subscription for ContinueWithSubscription<Source, ObNext> {
+    fn unsubscribe(&mut self) {
+        self.subs_source.unsubscribe();
+        self.subs_next = None;
+    }
+
+    fn is_active(&self) -> bool {
+        let next_is_active = match self.subs_next {
+            Some(ref subs_next) => subs_next.with_value_or(|subs| {
+                subs.as_ref().is_some_and(|subs| subs.is_active())
+            }, false),
+            None => false,
+        };
+        self.subs_source.is_active() || next_is_active
+    }
 }
 
 impl<Source: Observable, ObNext: Observable> Drop for ContinueWithSubscription<Source, ObNext> {
@@ -159,29 +241,24 @@ impl<Source: Observable, ObNext: Observable> Drop for ContinueWithSubscription<S
     }
 }
 
-struct ContinueWithObserver<'a, T: Clone, E: Clone, ObNext: 'a, O>
-where ObNext: Observable<Item = T, Error = E>,
-      O: Observer<T, E> {
+struct ContinueWithObserver<ObNext: Observable, O> {
     observer: O,
-    next: &'a mut ObNext,
+    next: Rc<RefCell<ObNext>>,
     subscription: lifeline::Owner<Option<ObNext::Subscription>>,
 }
 
-impl<'a, T, E, ObNext, O> Observer<T, E> for ContinueWithObserver<'a, T, E, ObNext, O>
+impl<T, E, ObNext, O> Observer<T, E> for ContinueWithObserver<ObNext, O>
 where T: Clone,
       E: Clone,
       ObNext: Observable<Item = T, Error = E>,
-      O: Observer<T, E> {
+      O: Observer<T, E> + 'static {
     fn on_next(&mut self, item: T) {
         self.observer.on_next(item);
     }
 
     fn on_completed(mut self) {
-        use std::mem;
-        let subs_next = self.next.subscribe(self.observer);
-        self.subscription.with_mut_value(|subs| {
-            mem::replace(subs, Some(subs_next));
-        });
+        let subs_next = self.next.borrow_mut().subscribe(self.observer);
+        self.subscription.with_mut_value(|subs| *subs = Some(subs_next));
     }
 
     fn on_error(self, error: E) {
@@ -190,39 +267,89 @@ where T: Clone,
 }
 
 /// The result of calling `continue_with()` on an observable.
-pub struct ContinueWithObservable<'a, Source: 'a + ?Sized, ObNext: 'a + ?Sized> {
-    source: &'a mut Source,
-    next: &'a mut ObNext,
+pub struct ContinueWithObservable<Source, ObNext> {
+    source: Source,
+
+    // Every observer subscribes to the next observable when the source
+    // completes, so the observers share it.
+    next: Rc<RefCell<ObNext>>,
 }
 
-impl<'a, Source: 'a + ?Sized, ObNext: 'a + ?Sized> ContinueWithObservable<'a, Source, ObNext> {
-    pub fn new(source: &'a mut Source, next: &'a mut ObNext) -> ContinueWithObservable<'a, Source, ObNext> {
+impl<Source, ObNext> ContinueWithObservable<Source, ObNext> {
+    pub fn new(source: Source, next: ObNext) -> ContinueWithObservable<Source, ObNext> {
         ContinueWithObservable {
             source: source,
-            next: next,
+            next: Rc::new(RefCell::new(next)),
         }
     }
 }
 
-impl<'a, T: Clone, E: Clone, Source, ObNext> Observable for ContinueWithObservable<'a, Source, ObNext>
+impl<T: Clone, E: Clone, Source, ObNext> Observable for ContinueWithObservable<Source, ObNext>
 where Source: Observable<Item = T, Error = E>,
-      ObNext: Observable<Item = T, Error = E> {
+      ObNext: Observable<Item = T, Error = E> + 'static {
     type Item = <Source as Observable>::Item;
     type Error = <Source as Observable>::Error;
     type Subscription = ContinueWithSubscription<Source, ObNext>;
 
     fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
-        where O: Observer<Self::Item, Self::Error> {
+        where O: Observer<Self::Item, Self::Error> + 'static {
         let (life, owner) = lifeline::new(None);
         let continued_observer = ContinueWithObserver {
             observer: observer,
-            next: self.next,
+            next: self.next.clone(),
             subscription: owner,
         };
         let subs_source = self.source.subscribe(continued_observer);
         ContinueWithSubscription {
             subs_source: subs_source,
-            subs_next: life,
+            subs_next: Some(life),
         }
     }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.next.borrow().describe()];
+        Graph::operator::<Self>("continue_with", sources)
+    }
+}
+
+/// The result of calling `lift()` on an observable.
+pub struct LiftObservable<Source, G, U, F> {
+    source: Source,
+    f: G,
+    _phantom_u: PhantomData<U>,
+    _phantom_f: PhantomData<F>,
+}
+
+impl<Source, G, U, F> LiftObservable<Source, G, U, F> {
+    pub fn new(source: Source, f: G) -> LiftObservable<Source, G, U, F> {
+        LiftObservable {
+            source: source,
+            f: f,
+            _phantom_u: PhantomData,
+            _phantom_f: PhantomData,
+        }
+    }
+}
+
+impl<Source, G, U, F, Up> Observable for LiftObservable<Source, G, U, F>
+where Source: Observable,
+      U: Clone + 'static,
+      F: Clone + 'static,
+      G: FnMut(Box<dyn BoxedObserver<U, F>>) -> Up,
+      Up: Observer<<Source as Observable>::Item, <Source as Observable>::Error> + 'static {
+    type Item = U;
+    type Error = F;
+    type Subscription = <Source as Observable>::Subscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        // The function cannot be generic over the observer, so the downstream
+        // observer is boxed before it is handed to the function.
+        let lifted_observer = (self.f)(observer.boxed());
+        self.source.subscribe(lifted_observer)
+    }
+
+    fn describe(&self) -> Graph {
+        Graph::operator::<Self>("lift", vec![self.source.describe()])
+    }
 }
@@ -0,0 +1,306 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! The `take_until()` and `skip_until()` operators, which let a second
+//! observable end or start the first.
+
+use describe::Graph;
+use observable::Observable;
+use observer::Observer;
+use operator::{self, ObserverSlot, OperatorState};
+use std::cell::RefCell;
+use std::rc::Rc;
+use subscription::Subscription;
+
+/// The result of calling `take_until()` on an observable.
+pub struct TakeUntilObservable<Source, Trigger> {
+    source: Source,
+    trigger: Trigger,
+}
+
+/// The result of subscribing to a `take_until()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the trigger.
+pub struct TakeUntilSubscription<T, E, Subs, TrSubs> {
+    state: Rc<RefCell<TakeUntilState<T, E, Subs, TrSubs>>>,
+}
+
+struct TakeUntilState<T, E, Subs, TrSubs> {
+    observer: ObserverSlot<T, E>,
+
+    // The subscriptions are stored once `subscribe()` on the source and the
+    // trigger return, unless the operator was done by then.
+    source: Option<Subs>,
+    trigger: Option<TrSubs>,
+}
+
+type TakeUntilShared<T, E, Subs, TrSubs> = Rc<RefCell<TakeUntilState<T, E, Subs, TrSubs>>>;
+
+struct TakeUntilObserver<T, E, Subs, TrSubs> {
+    state: TakeUntilShared<T, E, Subs, TrSubs>,
+}
+
+struct TakeUntilTriggerObserver<T, E, Subs, TrSubs> {
+    state: TakeUntilShared<T, E, Subs, TrSubs>,
+}
+
+impl<Source, Trigger> TakeUntilObservable<Source, Trigger> {
+    pub fn new(source: Source, trigger: Trigger) -> TakeUntilObservable<Source, Trigger> {
+        TakeUntilObservable {
+            source: source,
+            trigger: trigger,
+        }
+    }
+}
+
+impl<Source, Trigger, T, E> Observable for TakeUntilObservable<Source, Trigger>
+where Source: Observable<Item = T, Error = E>,
+      Source::Subscription: 'static,
+      Trigger: Observable,
+      Trigger::Subscription: 'static,
+      T: Clone + 'static,
+      E: Clone + 'static {
+    type Item = T;
+    type Error = E;
+    type Subscription = TakeUntilSubscription<T, E, Source::Subscription, Trigger::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<T, E> + 'static {
+        let state = Rc::new(RefCell::new(TakeUntilState {
+            observer: ObserverSlot::new("take_until subscription", observer.boxed()),
+            source: None,
+            trigger: None,
+        }));
+
+        // The trigger is subscribed to first, so a trigger that pushes a
+        // value upon subscription prevents the subscription to the source.
+        let trigger = self.trigger.subscribe(TakeUntilTriggerObserver { state: state.clone() });
+        if state.borrow().observer.is_done() {
+            drop(trigger);
+            return TakeUntilSubscription { state: state };
+        }
+        state.borrow_mut().trigger = Some(trigger);
+
+        let source = self.source.subscribe(TakeUntilObserver { state: state.clone() });
+        let source = {
+            let mut state = state.borrow_mut();
+            if state.observer.is_done() {
+                Some(source)
+            } else {
+                state.source = Some(source);
+                None
+            }
+        };
+        drop(source);
+        TakeUntilSubscription { state: state }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.trigger.describe()];
+        Graph::operator::<Self>("take_until", sources)
+    }
+}
+
+impl<T, E, Subs, TrSubs> OperatorState for TakeUntilState<T, E, Subs, TrSubs> {
+    type Item = T;
+    type Error = E;
+    type Released = (Option<Subs>, Option<TrSubs>);
+
+    fn observer_mut(&mut self) -> &mut ObserverSlot<T, E> {
+        &mut self.observer
+    }
+
+    fn release(&mut self) -> (Option<Subs>, Option<TrSubs>) {
+        (self.source.take(), self.trigger.take())
+    }
+}
+
+impl<T, E, Subs, TrSubs> Observer<T, E> for TakeUntilObserver<T, E, Subs, TrSubs> {
+    fn on_next(&mut self, item: T) {
+        operator::push(&self.state, item);
+    }
+
+    fn on_completed(self) {
+        operator::complete(&self.state);
+    }
+
+    fn on_error(self, error: E) {
+        operator::fail(&self.state, error);
+    }
+}
+
+impl<T, E, Subs, TrSubs, U, F> Observer<U, F> for TakeUntilTriggerObserver<T, E, Subs, TrSubs> {
+    fn on_next(&mut self, _item: U) {
+        operator::complete(&self.state);
+    }
+
+    fn on_completed(self) {
+        // A trigger that ends without a value never ends the source.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the trigger is ignored, like its completion.
+    }
+}
+
+impl<T, E, Subs, TrSubs> Subscription for TakeUntilSubscription<T, E, Subs, TrSubs> {
+    fn unsubscribe(&mut self) {
+        operator::cancel(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.state.borrow().observer.is_done()
+    }
+}
+
+impl<T, E, Subs, TrSubs> Drop for TakeUntilSubscription<T, E, Subs, TrSubs> {
+    fn drop(&mut self) {
+        operator::cancel(&self.state);
+    }
+}
+
+/// The result of calling `skip_until()` on an observable.
+pub struct SkipUntilObservable<Source, Trigger> {
+    source: Source,
+    trigger: Trigger,
+}
+
+/// The result of subscribing to a `skip_until()` observable.
+///
+/// Dropping this subscription drops the subscriptions to the source and to
+/// the trigger.
+pub struct SkipUntilSubscription<Subs, TrSubs> {
+    source: Subs,
+    state: Rc<RefCell<SkipUntilState<TrSubs>>>,
+}
+
+struct SkipUntilState<TrSubs> {
+    // Whether the trigger pushed a value, after which source values pass.
+    is_open: bool,
+
+    // The subscription to the trigger is dropped once it pushed a value, or
+    // once the source completed or failed.
+    trigger: Option<TrSubs>,
+}
+
+struct SkipUntilObserver<O, TrSubs> {
+    observer: O,
+    state: Rc<RefCell<SkipUntilState<TrSubs>>>,
+}
+
+struct SkipUntilTriggerObserver<TrSubs> {
+    state: Rc<RefCell<SkipUntilState<TrSubs>>>,
+}
+
+impl<Source, Trigger> SkipUntilObservable<Source, Trigger> {
+    pub fn new(source: Source, trigger: Trigger) -> SkipUntilObservable<Source, Trigger> {
+        SkipUntilObservable {
+            source: source,
+            trigger: trigger,
+        }
+    }
+}
+
+impl<Source, Trigger> Observable for SkipUntilObservable<Source, Trigger>
+where Source: Observable,
+      Trigger: Observable,
+      Trigger::Subscription: 'static {
+    type Item = Source::Item;
+    type Error = Source::Error;
+    type Subscription = SkipUntilSubscription<Source::Subscription, Trigger::Subscription>;
+
+    fn subscribe<O>(&mut self, observer: O) -> Self::Subscription
+        where O: Observer<Self::Item, Self::Error> + 'static {
+        let state = Rc::new(RefCell::new(SkipUntilState {
+            is_open: false,
+            trigger: None,
+        }));
+
+        // The trigger is subscribed to first, so a trigger that pushes a
+        // value upon subscription lets through the values that the source
+        // pushes upon subscription.
+        let trigger = self.trigger.subscribe(SkipUntilTriggerObserver { state: state.clone() });
+        if !state.borrow().is_open {
+            state.borrow_mut().trigger = Some(trigger);
+        }
+
+        let skip_until_observer = SkipUntilObserver {
+            observer: observer,
+            state: state.clone(),
+        };
+        SkipUntilSubscription {
+            source: self.source.subscribe(skip_until_observer),
+            state: state,
+        }
+    }
+
+    fn describe(&self) -> Graph {
+        let sources = vec![self.source.describe(), self.trigger.describe()];
+        Graph::operator::<Self>("skip_until", sources)
+    }
+}
+
+impl<TrSubs> SkipUntilState<TrSubs> {
+    fn drop_trigger(state: &RefCell<SkipUntilState<TrSubs>>) {
+        let trigger = state.borrow_mut().trigger.take();
+        drop(trigger);
+    }
+}
+
+impl<T, E, O, TrSubs> Observer<T, E> for SkipUntilObserver<O, TrSubs>
+    where O: Observer<T, E> {
+    fn on_next(&mut self, item: T) {
+        let is_open = self.state.borrow().is_open;
+        if is_open {
+            self.observer.on_next(item);
+        }
+    }
+
+    fn on_completed(self) {
+        SkipUntilState::drop_trigger(&self.state);
+        self.observer.on_completed();
+    }
+
+    fn on_error(self, error: E) {
+        SkipUntilState::drop_trigger(&self.state);
+        self.observer.on_error(error);
+    }
+}
+
+impl<TrSubs, U, F> Observer<U, F> for SkipUntilTriggerObserver<TrSubs> {
+    fn on_next(&mut self, _item: U) {
+        self.state.borrow_mut().is_open = true;
+        SkipUntilState::drop_trigger(&self.state);
+    }
+
+    fn on_completed(self) {
+        // A trigger that ends without a value keeps the source closed.
+    }
+
+    fn on_error(self, _error: F) {
+        // Failure of the trigger is ignored, like its completion.
+    }
+}
+
+impl<Subs: Subscription, TrSubs> Subscription for SkipUntilSubscription<Subs, TrSubs> {
+    fn unsubscribe(&mut self) {
+        self.source.unsubscribe();
+        SkipUntilState::drop_trigger(&self.state);
+    }
+
+    fn is_active(&self) -> bool {
+        self.source.is_active()
+    }
+}
+
+impl<Subs, TrSubs> Drop for SkipUntilSubscription<Subs, TrSubs> {
+    fn drop(&mut self) {
+        // The source subscription is dropped after this.
+        SkipUntilState::drop_trigger(&self.state);
+    }
+}
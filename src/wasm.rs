@@ -0,0 +1,122 @@
+// Rx -- Reactive programming for Rust
+// Copyright 2016 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+//! A scheduler that runs actions with the timers of a JavaScript host.
+//!
+//! This module is only available with the `wasm` feature.
+
+use scheduler::Scheduler;
+use std::cell::Cell;
+use std::cmp;
+use std::rc::Rc;
+use std::time::Duration;
+use subscription::Subscription;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &JsValue, timeout: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(handle: i32);
+
+    #[wasm_bindgen(js_name = setInterval)]
+    fn set_interval(handler: &JsValue, timeout: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearInterval)]
+    fn clear_interval(handle: i32);
+}
+
+/// A scheduler that runs actions with `setTimeout()` and `setInterval()`.
+///
+/// The actions run on the JavaScript event loop, after the current task, so
+/// scheduling never blocks. This works in browsers and web workers, where the
+/// thread must not block, and where time-based operators cannot use threads.
+/// Delays are rounded down to whole milliseconds.
+#[derive(Clone, Copy)]
+pub struct WasmScheduler;
+
+/// The result of scheduling an action on a `WasmScheduler`.
+///
+/// Dropping this subscription clears the timer, which cancels the action if
+/// it has not run yet, and stops a periodic action.
+pub struct TimerSubscription {
+    handle: i32,
+    is_periodic: bool,
+
+    // Set once a one-off action has run, or when the timer is cleared.
+    is_done: Rc<Cell<bool>>,
+
+    // The host calls into the closure, so it must live as long as the timer.
+    _closure: Closure<dyn FnMut()>,
+}
+
+fn to_millis(duration: Duration) -> i32 {
+    let millis = duration.as_secs()
+        .saturating_mul(1000)
+        .saturating_add(duration.subsec_nanos() as u64 / 1_000_000);
+    cmp::min(millis, i32::MAX as u64) as i32
+}
+
+impl Scheduler for WasmScheduler {
+    type Subscription = TimerSubscription;
+
+    fn schedule_after<F>(&self, delay: Duration, action: F) -> TimerSubscription
+        where F: FnOnce() + 'static {
+        let is_done = Rc::new(Cell::new(false));
+        let is_done_action = is_done.clone();
+        let mut action = Some(action);
+        let closure = Closure::wrap(Box::new(move || {
+            if let Some(action) = action.take() {
+                is_done_action.set(true);
+                action();
+            }
+        }) as Box<dyn FnMut()>);
+        TimerSubscription {
+            handle: set_timeout(closure.as_ref(), to_millis(delay)),
+            is_periodic: false,
+            is_done: is_done,
+            _closure: closure,
+        }
+    }
+
+    fn schedule_periodic<F>(&self, period: Duration, action: F) -> TimerSubscription
+        where F: FnMut() + 'static {
+        let closure = Closure::wrap(Box::new(action) as Box<dyn FnMut()>);
+        TimerSubscription {
+            handle: set_interval(closure.as_ref(), to_millis(period)),
+            is_periodic: true,
+            is_done: Rc::new(Cell::new(false)),
+            _closure: closure,
+        }
+    }
+}
+
+impl Subscription for TimerSubscription {
+    fn unsubscribe(&mut self) {
+        if self.is_done.get() {
+            return;
+        }
+        self.is_done.set(true);
+        if self.is_periodic {
+            clear_interval(self.handle);
+        } else {
+            clear_timeout(self.handle);
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        !self.is_done.get()
+    }
+}
+
+impl Drop for TimerSubscription {
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
@@ -5,11 +5,32 @@
 // you may not use this file except in compliance with the License.
 // A copy of the License has been included in the root of the repository.
 
+// The `field: field` style of struct initialization is used throughout.
+#![allow(clippy::redundant_field_names)]
+
 extern crate rx;
 
-use rx::{Never, Observable, Observer, Subject};
-use std::cell::RefCell;
+use rx::{AsyncSubject, BehaviorSubject, BlockingError, BoxedObservable, BoxedObserver,
+         CircuitBreakerPolicy, CircuitError, CombineLatest, CompositeSubscription, Counter,
+         CurrentThreadScheduler, Either, Emitter, EventLoop, Gauge, ImmediateScheduler, LogEvent,
+         LogRecord, LogSink, MetricsSink, Never, NewThreadScheduler, NoError, Notification,
+         Observable, ObservableExt, Observer, OverflowPolicy, Property, PullObservable,
+         PullSubscription, ReplaySubject, ScheduledSubscription, Scheduler, SendScheduler,
+         SerialSubscription, SharedSubject, SingleError, Stats, Subject, SubjectObservable,
+         Subscription, SyncSubject, TestScheduler, ThreadPoolScheduler, UncancellableSubscription,
+         batch, computed};
+use rx::testing::{ScheduledObservable, TestObserver, cold, expect_marbles, frames, hot};
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::io;
+use std::net;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
 
 // Generator tests
 
@@ -27,58 +48,157 @@ fn never() {
     // will.
 }
 
+#[test]
+fn create_pushes_to_emitter() {
+    let mut observable = rx::create(|mut emitter: Emitter<u8, ()>| {
+        emitter.next(2);
+        emitter.next(3);
+        emitter.completed();
+    });
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    observable.subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                                   move || completed_clone.set(true));
+    assert_eq!(&[2u8, 3], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn create_error() {
+    let mut observable = rx::create(|emitter: Emitter<u8, u8>| emitter.error(37));
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    observable.subscribe_error(
+        |_x| panic!("failing observable should not produce a value"),
+        || panic!("failing observable should not complete"),
+        move |err| error_clone.set(Some(err))
+    );
+    assert_eq!(Some(37), error.get());
+}
+
+#[test]
+fn create_emitter_is_unsubscribed_after_drop() {
+    let stored = Rc::new(RefCell::new(None));
+    let stored_in_create = stored.clone();
+    let mut observable = rx::create(move |emitter: Emitter<u8, ()>| {
+        *stored_in_create.borrow_mut() = Some(emitter);
+    });
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription = observable.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    assert!(!stored.borrow().as_ref().unwrap().is_unsubscribed());
+    stored.borrow_mut().as_mut().unwrap().next(2);
+
+    drop(subscription);
+
+    // After dropping the subscription, the emitter should be a no-op.
+    assert!(stored.borrow().as_ref().unwrap().is_unsubscribed());
+    stored.borrow_mut().as_mut().unwrap().next(3);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+}
+
+struct Resource {
+    is_released: Rc<Cell<bool>>,
+}
+
+impl Drop for Resource {
+    fn drop(&mut self) {
+        self.is_released.set(true);
+    }
+}
+
+#[test]
+fn using_releases_resource_on_unsubscribe() {
+    let is_released = Rc::new(Cell::new(false));
+    let is_released_clone = is_released.clone();
+    let subject = Subject::<u8, ()>::new();
+    let mut observable = rx::using(move || Resource { is_released: is_released_clone.clone() },
+                                   |_resource| subject.observable());
+    let observer = TestObserver::new();
+    let subscription = observable.subscribe(observer.clone());
+    assert!(!is_released.get());
+
+    drop(subscription);
+    assert!(is_released.get());
+}
+
+#[test]
+fn using_releases_resource_on_completed() {
+    let is_released = Rc::new(Cell::new(false));
+    let is_released_clone = is_released.clone();
+    let mut observable = rx::using(move || Resource { is_released: is_released_clone.clone() },
+                                   |resource| Some(resource.is_released.get()));
+    let observer = TestObserver::new();
+    let subscription = observable.subscribe(observer.clone());
+    observer.assert_items(&[false]);
+    observer.assert_completed();
+    assert!(is_released.get());
+    assert!(!subscription.is_active());
+}
+
 // Option tests
 
 #[test]
 fn option_subscribe_next() {
-    let mut received = None;
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
 
     // Subscribing to `Some` should push the value.
-    Some(19).subscribe_next(|x| received = Some(x));
-    assert_eq!(Some(19), received);
+    Some(19).subscribe_next(move |x| received_clone.set(Some(x)));
+    assert_eq!(Some(19), received.get());
 
     None.subscribe_next(|_x: u32| panic!("none should not push a value"));
 }
 
 #[test]
 fn option_subscribe_completed() {
-    let mut received = None;
-    let mut completed = false;
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
 
     // Subscribing to `Some` should complete after pushing the value.
-    Some(19).subscribe_completed(|x| received = Some(x), || completed = true);
-    assert_eq!(Some(19), received);
-    assert!(completed);
+    Some(19).subscribe_completed(move |x| received_clone.set(Some(x)),
+                                 move || completed_clone.set(true));
+    assert_eq!(Some(19), received.get());
+    assert!(completed.get());
 
     // Subscribing to `None` should complete without pushing a value.
-    completed = false;
+    completed.set(false);
+    let completed_clone = completed.clone();
     None.subscribe_completed(
         |_x: u32| panic!("none should not push a value"),
-        || completed = true
+        move || completed_clone.set(true)
     );
-    assert!(completed);
+    assert!(completed.get());
 }
 
 #[test]
 fn option_subscribe_error() {
-    let mut received = None;
-    let mut completed = false;
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
 
     Some(23).subscribe_error(
-        |x| received = Some(x),
-        || completed = true,
+        move |x| received_clone.set(Some(x)),
+        move || completed_clone.set(true),
         |_err| panic!("some observable should not fail")
     );
-    assert_eq!(Some(23), received);
-    assert!(completed);
+    assert_eq!(Some(23), received.get());
+    assert!(completed.get());
 
-    completed = false;
+    completed.set(false);
+    let completed_clone = completed.clone();
     None.subscribe_error(
         |_x: u32| panic!("none should not push a value"),
-        || completed = true,
+        move || completed_clone.set(true),
         |_err| panic!("none observable should not fail")
     );
-    assert!(completed);
+    assert!(completed.get());
 }
 
 // Result tests
@@ -86,55 +206,62 @@ fn option_subscribe_error() {
 #[test]
 fn result_subscribe_next_ok() {
     let mut result: Result<u32, ()> = Ok(13);
-    let mut received = None;
-    result.subscribe_next(|x| received = Some(x));
-    assert_eq!(Some(13), received);
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
+    result.subscribe_next(move |x| received_clone.set(Some(x)));
+    assert_eq!(Some(13), received.get());
 }
 
 #[test]
 #[should_panic]
 fn result_subscribe_next_err() {
     let mut result: Result<u32, ()> = Err(());
-    let mut received = None;
+    let received = Rc::new(Cell::new(None));
 
     // This should panic, because we did not provide an error handler.
-    result.subscribe_next(|x| received = Some(x));
+    result.subscribe_next(move |x| received.set(Some(x)));
 }
 
 #[test]
 fn result_subscribe_completed_ok() {
     let mut result: Result<u32, ()> = Ok(13);
-    let mut received = None;
-    let mut completed = false;
-    result.subscribe_completed(|x| received = Some(x), || completed = true);
-    assert_eq!(Some(13), received);
-    assert!(completed);
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    result.subscribe_completed(move |x| received_clone.set(Some(x)),
+                               move || completed_clone.set(true));
+    assert_eq!(Some(13), received.get());
+    assert!(completed.get());
 }
 
 #[test]
 fn result_subscribe_error_ok() {
     let mut result: Result<u32, ()> = Ok(13);
-    let mut received = None;
-    let mut completed = false;
+    let received = Rc::new(Cell::new(None));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
     result.subscribe_error(
-        |x| received = Some(x),
-        || completed = true,
+        move |x| received_clone.set(Some(x)),
+        move || completed_clone.set(true),
         |_err| panic!("ok result should not be a failing observable")
     );
-    assert_eq!(Some(13), received);
-    assert!(completed);
+    assert_eq!(Some(13), received.get());
+    assert!(completed.get());
 }
 
 #[test]
 fn result_subscribe_error_err() {
     let mut result: Result<(), u32> = Err(17);
-    let mut error = None;
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
     result.subscribe_error(
         |_x| panic!("err result should not push a value"),
         || panic!("err result should not complete"),
-        |err| error = Some(err)
+        move |err| error_clone.set(Some(err))
     );
-    assert_eq!(Some(17), error);
+    assert_eq!(Some(17), error.get());
 }
 
 // Slice tests
@@ -142,49 +269,60 @@ fn result_subscribe_error_err() {
 #[test]
 fn slice_subscribe_next() {
     let mut values = &[2u8, 3, 5, 7, 11, 13];
-    let mut received = Vec::new();
-    values.subscribe_next(|&x| received.push(x));
-    assert_eq!(&values[..], &received[..]);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    values.subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    assert_eq!(&values[..], &received.borrow()[..]);
 }
 
 #[test]
 fn slice_subscribe_completed() {
     let mut values = &[2u8, 3, 5, 7, 11, 13];
-    let mut received = Vec::new();
-    let mut completed = false;
-    values.subscribe_completed(|&x| received.push(x), || completed = true);
-    assert_eq!(&values[..], &received[..]);
-    assert!(completed);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    values.subscribe_completed(move |&x| received_clone.borrow_mut().push(x),
+                               move || completed_clone.set(true));
+    assert_eq!(&values[..], &received.borrow()[..]);
+    assert!(completed.get());
 }
 
 #[test]
 fn slice_subscribe_error() {
     let mut values = &[2u8, 3, 5, 7, 11, 13];
-    let mut received = Vec::new();
-    let mut completed = false;
-    let mut failed = false;
-    values.subscribe_error(|&x| received.push(x), || completed = true, |_err| failed = true);
-    assert_eq!(&values[..], &received[..]);
-    assert!(completed);
-    assert!(!failed);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let failed = Rc::new(Cell::new(false));
+    let failed_clone = failed.clone();
+    values.subscribe_error(move |&x| received_clone.borrow_mut().push(x),
+                           move || completed_clone.set(true),
+                           move |_err| failed_clone.set(true));
+    assert_eq!(&values[..], &received.borrow()[..]);
+    assert!(completed.get());
+    assert!(!failed.get());
 }
 
 #[test]
 fn slice_subscribe_option() {
     let mut values = &[2u8, 3, 5, 7, 11, 13];
     let expected = &[Some(2u8), Some(3), Some(5), Some(7), Some(11), Some(13), None];
-    let mut received = Vec::new();
-    values.subscribe_option(|x| received.push(x.cloned()));
-    assert_eq!(&received[..], &expected[..]);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    values.subscribe_option(move |x| received_clone.borrow_mut().push(x.cloned()));
+    assert_eq!(&received.borrow()[..], &expected[..]);
 }
 
 #[test]
 fn slice_subscribe_result() {
     let mut values = &[2u8, 3, 5, 7];
     let expected = &[Ok(Some(2u8)), Ok(Some(3)), Ok(Some(5)), Ok(Some(7)), Ok(None)];
-    let mut received = Vec::new();
-    values.subscribe_result(|x| received.push(x.map(|y| y.cloned())));
-    assert_eq!(&received[..], &expected[..]);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    values.subscribe_result(move |x| received_clone.borrow_mut().push(x.map(|y| y.cloned())));
+    assert_eq!(&received.borrow()[..], &expected[..]);
 }
 
 // Subject tests
@@ -192,53 +330,102 @@ fn slice_subscribe_result() {
 #[test]
 fn subject_on_next() {
     let mut subject = Subject::<u8, ()>::new();
-    let mut received = Vec::new();
-    let _subscription = subject.observable().subscribe_next(|x| received.push(x));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
 
     // Subject should not push anything upon subscription.
-    assert_eq!(0, received.len());
+    assert_eq!(0, received.borrow().len());
 
     let values = &[2u8, 3, 5, 7, 11, 13];
     for i in 0..values.len() {
         subject.on_next(values[i]);
-        assert_eq!(&values[..i + 1], &received[..]);
+        assert_eq!(&values[..i + 1], &received.borrow()[..]);
     }
 }
 
 #[test]
 fn subject_on_completed() {
-    let mut subject = Subject::<u8, ()>::new();
-    let mut completed = false;
+    let subject = Subject::<u8, ()>::new();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
     let _subscription = subject.observable().subscribe_completed(
         |_x| panic!("no value should be pushed"),
-        || completed = true
+        move || completed_clone.set(true)
     );
 
     // Subject should not push anything upon subscription.
-    assert!(!completed);
+    assert!(!completed.get());
 
     subject.on_completed();
-    assert!(completed);
+    assert!(completed.get());
 }
 
 #[test]
 fn subject_on_error() {
-    let mut subject = Subject::<u8, u8>::new();
-    let mut error = 0;
+    let subject = Subject::<u8, u8>::new();
+    let error = Rc::new(Cell::new(0));
+    let error_clone = error.clone();
     let _subscription = subject.observable().subscribe_error(
         |_x| panic!("no value should be pushed"),
         || panic!("subject should not complete"),
-        |err| error = err
+        move |err| error_clone.set(err)
     );
 
     // Subject should not fail upon subscription.
-    assert_eq!(0, error);
+    assert_eq!(0, error.get());
 
     subject.on_error(41);
-    assert_eq!(41, error);
+    assert_eq!(41, error.get());
 }
 
-/// Helper for the `subject_clones_once_per_observer()` test.
+#[test]
+fn subject_subscribe_after_completed() {
+    let mut subject = Subject::<u8, ()>::new();
+    (&mut subject).on_completed();
+
+    // Values pushed after completion should be ignored.
+    subject.on_next(2);
+
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = subject.observable().subscribe_completed(
+        |_x| panic!("no value should be pushed after completion"),
+        move || completed_clone.set(true)
+    );
+    assert!(completed.get());
+}
+
+#[test]
+fn subject_subscribe_after_error() {
+    let mut subject = Subject::<u8, u8>::new();
+    let first_error = Rc::new(Cell::new(None));
+    let first_error_clone = first_error.clone();
+    let _s1 = subject.observable().subscribe_error(
+        |_x| panic!("no value should be pushed after failure"),
+        || panic!("subject should not complete"),
+        move |err| first_error_clone.set(Some(err))
+    );
+    (&mut subject).on_error(53);
+    subject.on_next(2);
+
+    // Completing after failure should be ignored too.
+    (&mut subject).on_completed();
+
+    let second_error = Rc::new(Cell::new(None));
+    let second_error_clone = second_error.clone();
+    let _s2 = subject.observable().subscribe_error(
+        |_x| panic!("no value should be pushed after failure"),
+        || panic!("subject should not complete"),
+        move |err| second_error_clone.set(Some(err))
+    );
+    assert_eq!(Some(53), first_error.get());
+    assert_eq!(Some(53), second_error.get());
+}
+
+/// Helper for the `subject_clones_once_per_observer()` and
+/// `shared_subject_does_not_clone()` tests.
 struct CloneCounter {
     counter: Rc<RefCell<u32>>,
 }
@@ -256,15 +443,17 @@ impl Clone for CloneCounter {
 #[test]
 fn subject_clones_once_per_observer() {
     let mut subject = Subject::<CloneCounter, ()>::new();
-    let mut first_called = false;
-    let mut second_called = false;
+    let first_called = Rc::new(Cell::new(false));
+    let first_called_clone = first_called.clone();
+    let second_called = Rc::new(Cell::new(false));
+    let second_called_clone = second_called.clone();
     let counter = CloneCounter {
         counter: Rc::new(RefCell::new(0)),
     };
 
     // Subscribe twice.
-    let _s1 = subject.observable().subscribe_next(|_x| first_called = true);
-    let _s2 = subject.observable().subscribe_next(|_x| second_called = true);
+    let _s1 = subject.observable().subscribe_next(move |_x| first_called_clone.set(true));
+    let _s2 = subject.observable().subscribe_next(move |_x| second_called_clone.set(true));
 
     // Nothing should have been cloned yet.
     assert_eq!(0, *counter.counter.borrow());
@@ -273,21 +462,23 @@ fn subject_clones_once_per_observer() {
 
     // We cloned once, and the subject should have cloned once per subscription.
     assert_eq!(3, *counter.counter.borrow());
-    assert!(first_called);
-    assert!(second_called);
+    assert!(first_called.get());
+    assert!(second_called.get());
 }
 
 #[test]
 fn subject_drop_subscription() {
     let mut subject = Subject::<u8, ()>::new();
-    let mut received = Vec::new();
-    let subscription = subject.observable().subscribe_next(|x| received.push(x));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription = subject.observable()
+                              .subscribe_next(move |x| received_clone.borrow_mut().push(x));
 
     subject.on_next(2);
     subject.on_next(3);
     subject.on_next(5);
 
-    assert_eq!(&[2u8, 3, 5], &received[..]);
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
 
     drop(subscription);
 
@@ -295,14 +486,15 @@ fn subject_drop_subscription() {
     subject.on_next(11);
 
     // Values pushed after drop should not have been invoked.
-    assert_eq!(&[2u8, 3, 5], &received[..]);
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
 }
 
 #[test]
 fn subject_drop_subscription_multi() {
     let mut subject = Subject::<u8, ()>::new();
-    let mut received = Vec::new();
-    let _s1 = subject.observable().subscribe_next(|x| received.push(x));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _s1 = subject.observable().subscribe_next(move |x| received_clone.borrow_mut().push(x));
     let s2 = subject.observable().subscribe_error(
         |_x| panic!("no value should be pushed after dropping subscription"),
         || panic!("completion should not be signalled after dropping subscription"),
@@ -316,128 +508,4492 @@ fn subject_drop_subscription_multi() {
     subject.on_next(5);
     subject.on_completed();
 
-    assert_eq!(&[2u8, 3, 5], &received[..]);
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_drop_subscriptions_many() {
+    let mut subject = Subject::<u8, ()>::new();
+    let received: Vec<_> = (0..64).map(|_| Rc::new(RefCell::new(Vec::new()))).collect();
+    let mut subscriptions: Vec<_> = received.iter().map(|r| {
+        let r = r.clone();
+        Some(subject.observable().subscribe_next(move |x| r.borrow_mut().push(x)))
+    }).collect();
+
+    subject.on_next(2);
+
+    // Drop every third subscription, from the middle of the list as well as the ends.
+    for i in (0..64).filter(|i| i % 3 == 0) {
+        drop(subscriptions[i].take());
+    }
+
+    subject.on_next(3);
+
+    for (i, r) in received.iter().enumerate() {
+        if i % 3 == 0 {
+            assert_eq!(&[2u8], &r.borrow()[..]);
+        } else {
+            assert_eq!(&[2u8, 3], &r.borrow()[..]);
+        }
+    }
 }
 
-// TODO: Add a better test to test internal removal of observer from the list.
+#[test]
+fn subject_subscribe_after_drop_reuses_entry() {
+    let mut subject = Subject::<u8, ()>::new();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let third = Rc::new(RefCell::new(Vec::new()));
+    let third_clone = third.clone();
+    let _s1 = subject.observable().subscribe_next(move |x| first_clone.borrow_mut().push(x));
+    let s2 = subject.observable().subscribe_next(move |x| second_clone.borrow_mut().push(x));
+    subject.on_next(2);
+    drop(s2);
+
+    // The new subscription may take the place of the dropped one,
+    // but it should receive only values pushed after it subscribed.
+    let _s3 = subject.observable().subscribe_next(move |x| third_clone.borrow_mut().push(x));
+    subject.on_next(3);
+    drop(subject);
+
+    assert_eq!(&[2u8, 3], &first.borrow()[..]);
+    assert_eq!(&[2u8], &second.borrow()[..]);
+    assert_eq!(&[3u8], &third.borrow()[..]);
+}
 
 #[test]
 fn subject_drop_in_handler() {
     let mut subject = Subject::<u8, ()>::new();
-    let mut received = Vec::new();
-    let mut subscription_opt: Option<Box<Drop>> = None;
-    let subscription = subject.observable().subscribe_next(|x| {
-        received.push(x);
-        assert!(subscription_opt.is_some());
-        drop(subscription_opt.take().unwrap());
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription_opt: Rc<RefCell<Option<Box<dyn Subscription>>>> = Rc::new(RefCell::new(None));
+    let subscription_opt_clone = subscription_opt.clone();
+    let subscription = subject.observable().subscribe_next(move |x| {
+        received_clone.borrow_mut().push(x);
+        let subscription = subscription_opt_clone.borrow_mut().take();
+        assert!(subscription.is_some());
+        drop(subscription);
+    });
+    *subscription_opt.borrow_mut() = Some(Box::new(subscription));
+
+    subject.on_next(2);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+
+    subject.on_next(3);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_on_next_in_handler() {
+    let subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut sink = subject.sink();
+    let _s1 = subject.observable().subscribe_next(move |x| {
+        received_clone.borrow_mut().push((1, x));
+        if x < 10 {
+            sink.on_next(x * 10);
+        }
+    });
+    let received_clone = received.clone();
+    let _s2 = subject.observable()
+                     .subscribe_next(move |x| received_clone.borrow_mut().push((2, x)));
+
+    (&subject).on_next(1);
+
+    // The value pushed by the first observer should be pushed to all observers
+    // after the current value.
+    assert_eq!(&[(1, 1u8), (2, 1), (1, 10), (2, 10)], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_on_completed_in_handler() {
+    let subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(0));
+    let completed_clone = completed.clone();
+    let mut completing_sink = Some(subject.sink());
+    let mut sink = subject.sink();
+    let _s1 = subject.observable().subscribe_completed(move |x| {
+        received_clone.borrow_mut().push(x);
+        completing_sink.take().unwrap().on_completed();
+        // Values pushed after completion should be ignored, even if the
+        // completion has not been pushed yet.
+        sink.on_next(3);
+    }, move || completed_clone.set(completed_clone.get() + 1));
+    let received_clone = received.clone();
+    let completed_clone = completed.clone();
+    let _s2 = subject.observable().subscribe_completed(
+        move |x| received_clone.borrow_mut().push(x),
+        move || completed_clone.set(completed_clone.get() + 1)
+    );
+
+    (&subject).on_next(2);
+
+    assert_eq!(&[2u8, 2], &received.borrow()[..]);
+    assert_eq!(2, completed.get());
+}
+
+#[test]
+fn subject_subscribe_in_handler() {
+    let subject = Subject::<u8, ()>::new();
+    let late = Rc::new(RefCell::new(Vec::new()));
+    let late_clone = late.clone();
+    let mut observable = subject.observable();
+    let mut subscription_opt = None;
+    let _s1 = subject.observable().subscribe_next(move |_x| {
+        if subscription_opt.is_none() {
+            let late = late_clone.clone();
+            let subscription = observable.subscribe_next(move |x| late.borrow_mut().push(x));
+            subscription_opt = Some(subscription);
+        }
+    });
+
+    (&subject).on_next(2);
+
+    // The new observer should not receive the value that was being pushed.
+    assert_eq!(0, late.borrow().len());
+
+    (&subject).on_next(3);
+    assert_eq!(&[3u8], &late.borrow()[..]);
+}
+
+#[test]
+fn subject_drop_other_in_handler() {
+    let mut subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let s2_opt = Rc::new(RefCell::new(None));
+    let s2_opt_clone = s2_opt.clone();
+    let _s1 = subject.observable().subscribe_next(move |_x| {
+        drop(s2_opt_clone.borrow_mut().take());
     });
-    // TODO: this assignment is not unused, or this code doing something illegal.
-    // Either way, this is a bug in rustc.
-    subscription_opt = Some(Box::new(subscription));
+    let received_clone = received.clone();
+    let s2 = subject.observable().subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    *s2_opt.borrow_mut() = Some(s2);
 
+    // The first observer drops the subscription of the second one,
+    // before the second one receives the value.
     subject.on_next(2);
-    assert_eq!(&[2u8], &received[..]);
+    subject.on_next(3);
+
+    assert_eq!(0, received.borrow().len());
+}
+
+#[test]
+fn subject_observer_count() {
+    let mut subject = Subject::<u8, ()>::new();
+    assert_eq!(0, subject.observer_count());
+    assert!(!subject.has_observers());
+
+    let s1 = subject.observable().subscribe_next(|_x| {});
+    let _s2 = subject.observable().subscribe_next(|_x| {});
+    assert_eq!(2, subject.observer_count());
+    assert!(subject.has_observers());
+
+    drop(s1);
+    assert_eq!(1, subject.observer_count());
+
+    (&mut subject).on_completed();
+    assert_eq!(0, subject.observer_count());
+    assert!(!subject.has_observers());
+}
+
+#[test]
+fn subject_has_observers_while_pushing() {
+    let subject = Rc::new(Subject::<u8, ()>::new());
+    let subject_clone = subject.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable().subscribe_next(move |_x| {
+        // The observer that is handling the value counts as subscribed.
+        let counts = (subject_clone.has_observers(), subject_clone.observer_count());
+        received_clone.borrow_mut().push(counts);
+    });
+    (&*subject).on_next(2);
+    assert_eq!(&[(true, 1)], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_on_next_lazy() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut evaluated = 0;
+
+    // Without observers, the value should not be constructed.
+    subject.on_next_lazy(|| { evaluated += 1; 2 });
+    assert_eq!(0, evaluated);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription = subject.observable()
+                              .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    subject.on_next_lazy(|| { evaluated += 1; 3 });
+    assert_eq!(1, evaluated);
+
+    drop(subscription);
+    subject.on_next_lazy(|| { evaluated += 1; 5 });
+    assert_eq!(1, evaluated);
+    assert_eq!(&[3u8], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_split() {
+    let subject = Subject::<u8, ()>::new();
+    let (mut sink, mut observable) = subject.split();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = observable.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    sink.on_next(2);
+    sink.on_next(3);
+    sink.on_completed();
 
+    // A late subscriber should receive the completion, also after the sink is gone.
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _late = observable.subscribe_completed(
+        |_x| panic!("no value should be pushed after completion"),
+        move || completed_clone.set(true)
+    );
+    assert_eq!(&[2u8, 3], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn subject_panic_propagates() {
+    let mut subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _s1 = subject.observable().subscribe_next(|x| if x == 2 { panic!("observer failed") });
+    let _s2 = subject.observable().subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| subject.on_next(2)));
+    assert!(result.is_err());
+
+    // The subject should keep working after the panic.
+    subject.on_next(3);
+    assert_eq!(&[3u8], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_isolate_panics() {
+    let mut subject = Subject::<u8, ()>::new();
+    subject.isolate_panics();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let _s1 = subject.observable().subscribe_next(move |x| {
+        first_clone.borrow_mut().push(x);
+        if x == 2 {
+            panic!("observer failed");
+        }
+    });
+    let _s2 = subject.observable().subscribe_next(move |x| second_clone.borrow_mut().push(x));
+
+    // The panic should be resumed after all observers received the value.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| subject.on_next(2)));
+    assert!(result.is_err());
+
+    // The observer that panicked should have been dropped.
     subject.on_next(3);
-    assert_eq!(&[2u8], &received[..]);
+    assert_eq!(&[2u8], &first.borrow()[..]);
+    assert_eq!(&[2u8, 3], &second.borrow()[..]);
+}
+
+#[test]
+fn subject_isolate_panics_with_hook() {
+    let mut subject = Subject::<u8, ()>::new();
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let reported_clone = reported.clone();
+    subject.isolate_panics_with(move |payload| {
+        let message = payload.downcast_ref::<&str>().unwrap();
+        reported_clone.borrow_mut().push(message.to_string());
+    });
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _s1 = subject.observable().subscribe_completed(|_x| {}, || panic!("completion failed"));
+    let _s2 = subject.observable().subscribe_completed(|_x| {}, move || completed_clone.set(true));
+
+    subject.on_completed();
+
+    assert!(completed.get());
+    assert_eq!(&["completion failed".to_string()], &reported.borrow()[..]);
 }
 
 #[test]
 fn subject_continue_with() {
-    use std::mem;
     let mut first = Subject::<u8, ()>::new();
     let mut second = Subject::<u8, ()>::new();
-    let mut received = Vec::new();
-    let mut completed = false;
-    {
-        let subscription = first.observable()
-            .continue_with(&mut second.observable())
-            .subscribe_completed(|x| received.push(x), || completed = true);
-
-        // TODO: How can I keep this alive without the compiler complaining about borrows?
-        mem::forget(subscription);
-    }
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = first.observable()
+        .continue_with(second.observable())
+        .subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                             move || completed_clone.set(true));
 
     first.on_next(2);
-    assert_eq!(&[2u8][..], &received[..]);
+    assert_eq!(&[2u8][..], &received.borrow()[..]);
 
     // If `second` produces a value, it should not be pushed,
     // because `first` has not yet completed.
     second.on_next(3);
-    assert_eq!(&[2u8][..], &received[..]);
+    assert_eq!(&[2u8][..], &received.borrow()[..]);
 
     first.on_next(5);
-    assert_eq!(&[2u8, 5][..], &received[..]);
+    assert_eq!(&[2u8, 5][..], &received.borrow()[..]);
 
     // Completing `first` should not complete the continuation,
     // nor should it push a value.
     first.on_completed();
-    assert_eq!(&[2u8, 5][..], &received[..]);
-    assert!(!completed);
+    assert_eq!(&[2u8, 5][..], &received.borrow()[..]);
+    assert!(!completed.get());
 
     // Now pushing to `second` should have an effect, because `first` completed.
     second.on_next(7);
-    assert_eq!(&[2u8, 5, 7][..], &received[..]);
+    assert_eq!(&[2u8, 5, 7][..], &received.borrow()[..]);
 
     second.on_completed();
-    assert!(completed);
+    assert!(completed.get());
 }
 
 // TODO: Test multiple subscriptions and combinations of values and completed/error.
 // TODO: Add better tests for dropping the subject subscription.
 
-// Transform tests
+// Behavior subject tests
 
 #[test]
-fn map() {
-    let mut values = &[2u8, 3, 5, 7, 11, 13];
-    let expected = &[4u8, 6, 10, 14, 22, 26];
-    let mut received = Vec::new();
-    let mut mapped = values.map(|x| x * 2);
-    mapped.subscribe_next(|x| received.push(x));
-    assert_eq!(&expected[..], &received[..]);
+fn behavior_subject_pushes_current_value() {
+    let mut subject = BehaviorSubject::<u8, ()>::new(2);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    // Subscribing should push the initial value immediately.
+    assert_eq!(&[2u8], &received.borrow()[..]);
+
+    subject.on_next(3);
+    subject.on_next(5);
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
 }
 
 #[test]
-fn map_does_not_change_error() {
-    let mut error = None;
-    let mut mapped = Err(23_u32).map(|x: u32| format!("{}", x));
-    mapped.subscribe_error(
-        |_x: String| panic!("mapped error should not produce a value"),
-        || panic!("mapped error should not complete"),
-        |err| error = Some(err)
-    );
-    assert_eq!(Some(23), error);
+fn behavior_subject_late_subscriber_gets_latest_value() {
+    let mut subject = BehaviorSubject::<u8, ()>::new(2);
+    assert_eq!(2, *subject.value());
+
+    subject.on_next(3);
+    subject.on_next(5);
+    assert_eq!(5, *subject.value());
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    subject.on_next(7);
+    assert_eq!(&[5u8, 7], &received.borrow()[..]);
 }
 
 #[test]
-fn map_error() {
-    let mut error = None;
-    let mut observable = Err(23_u32);
-    let mut mapped = observable.map_error(|x| x * 2);
-    mapped.subscribe_error(
-        |_x: u32| panic!("mapped error should not produce a value"),
-        || panic!("mapped error should not complete"),
-        |err| error = Some(err)
+fn behavior_subject_ignores_values_after_completion() {
+    let mut subject = BehaviorSubject::<u8, ()>::new(2);
+    let mut sink = subject.sink();
+    subject.sink().on_completed();
+    subject.on_next(3);
+    sink.on_next(5);
+    assert_eq!(2, *subject.value());
+}
+
+// Property tests
+
+#[test]
+fn property_pushes_only_changes() {
+    let mut property = Property::new(2u8);
+    let observer = TestObserver::new();
+    let _subscription = property.observe().subscribe(observer.clone());
+    property.set(3);
+    property.set(3);
+    property.modify(|x| *x += 2);
+    property.modify(|_x| {});
+    assert_eq!(5, property.get());
+    observer.assert_items(&[2, 3, 5]);
+    observer.assert_not_terminated();
+}
+
+#[test]
+fn computed_recomputes_once_per_change_without_glitches() {
+    let mut a = Property::new(1u32);
+    let b = computed((&a,), |a| a * 10);
+    let count = Rc::new(Cell::new(0));
+    let count_clone = count.clone();
+    let c = computed((&a, &b), move |a, b| {
+        count_clone.set(count_clone.get() + 1);
+        a + b
+    });
+    let observer = TestObserver::new();
+    let _subscription = c.observe().subscribe(observer.clone());
+
+    count.set(0);
+    a.set(2);
+    assert_eq!(22, c.get());
+    assert_eq!(1, count.get());
+
+    // A value that is computed from the old b and the new a is never pushed.
+    observer.assert_items(&[11, 22]);
+}
+
+#[test]
+fn computed_recomputes_once_per_batch() {
+    let mut width = Property::new(2u32);
+    let mut height = Property::new(3u32);
+    let count = Rc::new(Cell::new(0));
+    let count_clone = count.clone();
+    let area = computed((&width, &height), move |w, h| {
+        count_clone.set(count_clone.get() + 1);
+        w * h
+    });
+    let observer = TestObserver::new();
+    let _subscription = area.observe().subscribe(observer.clone());
+
+    count.set(0);
+    batch(|| {
+        width.set(5);
+        height.set(7);
+        assert_eq!(6, area.get());
+    });
+    assert_eq!(35, area.get());
+    assert_eq!(1, count.get());
+    observer.assert_items(&[6, 35]);
+}
+
+// Replay subject tests
+
+#[test]
+fn replay_subject_replays_all_values() {
+    let mut subject = ReplaySubject::<u8, ()>::new();
+    subject.on_next(2);
+    subject.on_next(3);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert_eq!(&[2u8, 3], &received.borrow()[..]);
+
+    subject.on_next(5);
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn replay_subject_with_capacity_replays_last_values() {
+    let mut subject = ReplaySubject::<u8, ()>::with_capacity(2);
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(5);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert_eq!(&[3u8, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn replay_subject_replays_completion() {
+    let mut subject = ReplaySubject::<u8, ()>::with_capacity(1);
+    subject.on_next(2);
+    subject.on_next(3);
+    (&mut subject).on_completed();
+
+    // Values pushed after completion should be ignored.
+    subject.on_next(5);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = subject.observable()
+        .subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                             move || completed_clone.set(true));
+    assert_eq!(&[3u8], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn replay_subject_replays_error() {
+    let mut subject = ReplaySubject::<u8, u8>::new();
+    subject.on_next(2);
+    (&mut subject).on_error(43);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let _subscription = subject.observable().subscribe_error(
+        move |x| received_clone.borrow_mut().push(x),
+        || panic!("failed subject should not complete"),
+        move |err| error_clone.set(Some(err))
     );
-    assert_eq!(Some(46), error);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+    assert_eq!(Some(43), error.get());
 }
 
+// Async subject tests
+
 #[test]
-fn map_error_does_not_change_values() {
-    let mut values = &[2u8, 3, 5, 7, 11, 13];
-    let mut received = Vec::new();
-    let mut mapped = values.map_error(|_unit| 17u8);
-    mapped.subscribe_next(|&x| received.push(x));
-    assert_eq!(&values[..], &received[..]);
+fn async_subject_pushes_last_value_on_completion() {
+    let mut subject = AsyncSubject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = subject.observable()
+        .subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                             move || completed_clone.set(true));
+
+    subject.on_next(2);
+    subject.on_next(3);
+
+    // Nothing should be pushed before completion.
+    assert_eq!(0, received.borrow().len());
+
+    subject.on_completed();
+    assert_eq!(&[3u8], &received.borrow()[..]);
+    assert!(completed.get());
 }
 
 #[test]
-fn continue_with() {
-    let (mut first, mut second) = (&[2u8, 3, 5, 7], &[11u8, 13, 17, 19]);
-    let expected = &[2u8, 3, 5, 7, 11, 13, 17, 19];
-    let mut received = Vec::new();
-    let mut continued = first.continue_with(&mut second);
-    continued.subscribe_next(|&x| received.push(x));
-    assert_eq!(&expected[..], &received[..]);
+fn async_subject_late_subscriber_gets_cached_value() {
+    let mut subject = AsyncSubject::<u8, ()>::new();
+    subject.on_next(2);
+    subject.on_next(3);
+    (&mut subject).on_completed();
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = subject.observable()
+        .subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                             move || completed_clone.set(true));
+    assert_eq!(&[3u8], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn async_subject_error() {
+    let mut subject = AsyncSubject::<u8, u8>::new();
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let _subscription = subject.observable().subscribe_error(
+        |_x| panic!("failed async subject should not push a value"),
+        || panic!("failed async subject should not complete"),
+        move |err| error_clone.set(Some(err))
+    );
+    subject.on_next(2);
+    subject.on_error(47);
+    assert_eq!(Some(47), error.get());
+}
+
+// Shared subject tests
+
+#[test]
+fn shared_subject_does_not_clone() {
+    let mut subject = SharedSubject::<CloneCounter, ()>::new();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let counter = Rc::new(RefCell::new(0));
+    let _s1 = subject.observable().subscribe_next(move |x| first_clone.borrow_mut().push(x));
+    let _s2 = subject.observable().subscribe_next(move |x| second_clone.borrow_mut().push(x));
+
+    subject.on_next(CloneCounter { counter: counter.clone() });
+
+    // Both observers should have received the same value, without cloning it.
+    assert_eq!(0, *counter.borrow());
+    assert_eq!(1, first.borrow().len());
+    assert_eq!(1, second.borrow().len());
+    assert!(Arc::ptr_eq(&first.borrow()[0], &second.borrow()[0]));
+}
+
+// Sync subject tests
+
+/// Observer for the sync subject tests that records values in a shared vector.
+struct SharedVecObserver {
+    received: Arc<Mutex<Vec<u8>>>,
+    completed: Arc<Mutex<bool>>,
+}
+
+impl Observer<u8, ()> for SharedVecObserver {
+    fn on_next(&mut self, item: u8) {
+        self.received.lock().unwrap().push(item);
+    }
+
+    fn on_completed(self) {
+        *self.completed.lock().unwrap() = true;
+    }
+
+    fn on_error(self, _error: ()) {
+        panic!("sync subject should not fail");
+    }
+}
+
+#[test]
+fn sync_subject_on_next_from_other_threads() {
+    let subject = SyncSubject::<u8, ()>::new();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let completed = Arc::new(Mutex::new(false));
+    let _subscription = subject.subscribe(SharedVecObserver {
+        received: received.clone(),
+        completed: completed.clone(),
+    });
+
+    let threads: Vec<_> = (0..4u8).map(|i| {
+        let mut handle = subject.clone();
+        thread::spawn(move || handle.on_next(i))
+    }).collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let mut values = received.lock().unwrap().clone();
+    values.sort();
+    assert_eq!(&[0u8, 1, 2, 3], &values[..]);
+
+    subject.on_completed();
+    assert!(*completed.lock().unwrap());
+}
+
+#[test]
+fn sync_subject_drop_subscription_on_other_thread() {
+    let mut subject = SyncSubject::<u8, ()>::new();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let subscription = subject.subscribe(SharedVecObserver {
+        received: received.clone(),
+        completed: Arc::new(Mutex::new(false)),
+    });
+
+    subject.on_next(2);
+    thread::spawn(move || drop(subscription)).join().unwrap();
+    subject.on_next(3);
+
+    assert_eq!(&[2u8], &received.lock().unwrap()[..]);
+}
+
+#[test]
+fn sync_subject_late_subscriber_receives_completion() {
+    let subject = SyncSubject::<u8, ()>::new();
+    subject.clone().on_completed();
+
+    let completed = Arc::new(Mutex::new(false));
+    let _subscription = subject.subscribe(SharedVecObserver {
+        received: Arc::new(Mutex::new(Vec::new())),
+        completed: completed.clone(),
+    });
+    assert!(*completed.lock().unwrap());
+}
+
+// Scheduler tests
+
+#[test]
+fn immediate_scheduler_runs_before_returning() {
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    let ran_now = ran.clone();
+    let _s1 = ImmediateScheduler.schedule(move || ran_now.borrow_mut().push("now"));
+    let ran_later = ran.clone();
+    let _s2 = ImmediateScheduler.schedule_after(Duration::from_millis(1),
+                                                move || ran_later.borrow_mut().push("later"));
+    assert_eq!(&["now", "later"], &ran.borrow()[..]);
+}
+
+#[test]
+fn current_thread_scheduler_queues_nested_actions() {
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    let ran_outer = ran.clone();
+    let _subscription = CurrentThreadScheduler.schedule(move || {
+        let ran_first = ran_outer.clone();
+        let s1 = CurrentThreadScheduler.schedule(move || ran_first.borrow_mut().push("first"));
+        let ran_second = ran_outer.clone();
+        let s2 = CurrentThreadScheduler.schedule(move || ran_second.borrow_mut().push("second"));
+        let ran_cancelled = ran_outer.clone();
+        let s3 = CurrentThreadScheduler.schedule(move || ran_cancelled.borrow_mut().push("cancelled"));
+
+        // The nested actions should run after this action returns.
+        ran_outer.borrow_mut().push("outer");
+        drop(s3);
+        mem::forget(s1);
+        mem::forget(s2);
+    });
+    assert_eq!(&["outer", "first", "second"], &ran.borrow()[..]);
+}
+
+#[test]
+fn current_thread_scheduler_periodic_action_blocks_outside_of_actions() {
+    // The call only returns through the panic, the subscription is never returned.
+    let count = Rc::new(Cell::new(0));
+    let count_clone = count.clone();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        CurrentThreadScheduler.schedule_periodic(Duration::from_millis(1), move || {
+            count_clone.set(count_clone.get() + 1);
+            if count_clone.get() == 3 {
+                panic!("periodic action ran three times");
+            }
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(3, count.get());
+}
+
+#[test]
+fn current_thread_scheduler_periodic_action_within_action_is_cancellable() {
+    let count = Rc::new(Cell::new(0));
+    let count_outer = count.clone();
+    let _subscription = CurrentThreadScheduler.schedule(move || {
+        let count_periodic = count_outer.clone();
+        let periodic = CurrentThreadScheduler.schedule_periodic(Duration::from_millis(1), move || {
+            count_periodic.set(count_periodic.get() + 1);
+        });
+        // The periodic action has not run yet, it runs after this action returns.
+        assert_eq!(0, count_outer.get());
+        let cancel = CurrentThreadScheduler.schedule_after(Duration::from_millis(10), move || {
+            drop(periodic);
+        });
+        mem::forget(cancel);
+    });
+    let ran = count.get();
+    assert!(ran > 0);
+    assert!(ran <= 10);
+}
+
+/// Helper for the `current_thread_scheduler_does_not_recurse()` test.
+fn count_down(n: u32, count: Rc<Cell<u32>>, subscriptions: Rc<RefCell<Vec<ScheduledSubscription>>>) {
+    count.set(count.get() + 1);
+    if n > 0 {
+        let subscriptions_clone = subscriptions.clone();
+        let subscription = CurrentThreadScheduler.schedule(move || {
+            count_down(n - 1, count, subscriptions_clone)
+        });
+        subscriptions.borrow_mut().push(subscription);
+    }
+}
+
+#[test]
+fn current_thread_scheduler_does_not_recurse() {
+    // Without the trampoline, this many nested calls would overflow the stack.
+    let count = Rc::new(Cell::new(0));
+    let subscriptions = Rc::new(RefCell::new(Vec::new()));
+    count_down(100_000, count.clone(), subscriptions);
+    assert_eq!(100_001, count.get());
+}
+
+#[test]
+fn event_loop_runs_actions_in_order_of_due_time() {
+    let event_loop = EventLoop::new();
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    let ran_b = ran.clone();
+    let _s1 = event_loop.schedule_after(Duration::from_millis(2), move || ran_b.borrow_mut().push("b"));
+    let ran_a = ran.clone();
+    let _s2 = event_loop.schedule_after(Duration::from_millis(1), move || ran_a.borrow_mut().push("a"));
+    let ran_now = ran.clone();
+    let _s3 = event_loop.schedule(move || ran_now.borrow_mut().push("now"));
+
+    // Nothing should run before the loop runs.
+    assert_eq!(0, ran.borrow().len());
+
+    event_loop.run_until_idle();
+    assert_eq!(&["now"], &ran.borrow()[..]);
+
+    event_loop.run();
+    assert_eq!(&["now", "a", "b"], &ran.borrow()[..]);
+}
+
+#[test]
+fn event_loop_periodic_action_until_cancelled() {
+    let event_loop = EventLoop::new();
+    let count = Rc::new(Cell::new(0));
+    let subscription = Rc::new(RefCell::new(None));
+    let count_clone = count.clone();
+    let subscription_clone = subscription.clone();
+    let periodic = event_loop.schedule_periodic(Duration::from_millis(1), move || {
+        count_clone.set(count_clone.get() + 1);
+        if count_clone.get() == 3 {
+            // Dropping the subscription stops the action, so the loop returns.
+            subscription_clone.borrow_mut().take();
+        }
+    });
+    *subscription.borrow_mut() = Some(periodic);
+
+    event_loop.run();
+    assert_eq!(3, count.get());
+}
+
+#[test]
+fn test_scheduler_runs_actions_when_time_advances() {
+    let scheduler = TestScheduler::new();
+    let ran = Rc::new(RefCell::new(Vec::new()));
+    let ran_b = ran.clone();
+    let clock = scheduler.clone();
+    let _s1 = scheduler.schedule_after(Duration::from_secs(2), move || {
+        ran_b.borrow_mut().push(("b", clock.now()));
+    });
+    let ran_a = ran.clone();
+    let clock = scheduler.clone();
+    let _s2 = scheduler.schedule_after(Duration::from_secs(1), move || {
+        ran_a.borrow_mut().push(("a", clock.now()));
+    });
+
+    // Nothing should run before the clock advances.
+    assert_eq!(0, ran.borrow().len());
+
+    scheduler.advance_by(Duration::from_millis(1500));
+    assert_eq!(&[("a", Duration::from_secs(1))], &ran.borrow()[..]);
+    assert_eq!(Duration::from_millis(1500), scheduler.now());
+
+    scheduler.advance_to(Duration::from_secs(10));
+    assert_eq!(&[("a", Duration::from_secs(1)), ("b", Duration::from_secs(2))], &ran.borrow()[..]);
+    assert_eq!(Duration::from_secs(10), scheduler.now());
+}
+
+#[test]
+fn test_scheduler_periodic_action() {
+    let scheduler = TestScheduler::new();
+    let count = Rc::new(Cell::new(0));
+    let count_clone = count.clone();
+    let subscription = scheduler.schedule_periodic(Duration::from_secs(1), move || {
+        count_clone.set(count_clone.get() + 1);
+    });
+
+    scheduler.advance_by(Duration::from_millis(3500));
+    assert_eq!(3, count.get());
+
+    // After cancelling, the action should not run any more.
+    drop(subscription);
+    scheduler.advance_by(Duration::from_secs(5));
+    assert_eq!(3, count.get());
+}
+
+#[test]
+fn test_scheduler_runs_actions_scheduled_by_actions() {
+    let scheduler = TestScheduler::new();
+    let ran = Rc::new(Cell::new(false));
+    let ran_clone = ran.clone();
+    let inner_scheduler = scheduler.clone();
+    let inner = Rc::new(RefCell::new(None));
+    let inner_clone = inner.clone();
+    let _outer = scheduler.schedule_after(Duration::from_secs(1), move || {
+        let subscription = inner_scheduler.schedule_after(Duration::from_secs(1), move || ran_clone.set(true));
+        *inner_clone.borrow_mut() = Some(subscription);
+    });
+
+    scheduler.advance_by(Duration::from_secs(3));
+    assert!(ran.get());
+}
+
+#[test]
+fn new_thread_scheduler_runs_action_on_other_thread() {
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = NewThreadScheduler.schedule(move || {
+        sender.send(thread::current().id()).unwrap();
+    });
+    let action_thread = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(action_thread != thread::current().id());
+}
+
+#[test]
+fn new_thread_scheduler_cancel_delayed_action() {
+    let (sender, receiver) = mpsc::channel();
+    let subscription = NewThreadScheduler.schedule_after(Duration::from_millis(50), move || {
+        sender.send(()).unwrap();
+    });
+    drop(subscription);
+    // The sender is dropped without sending when the action is cancelled.
+    assert_eq!(Err(mpsc::RecvError), receiver.recv());
+}
+
+#[test]
+fn thread_pool_scheduler_runs_actions_on_workers() {
+    let pool = ThreadPoolScheduler::new(2);
+    let (sender, receiver) = mpsc::channel();
+    let mut subscriptions = Vec::new();
+    for i in 0..8 {
+        let sender = sender.clone();
+        subscriptions.push(pool.schedule(move || {
+            sender.send((i, thread::current().id())).unwrap();
+        }));
+    }
+    let mut received: Vec<u32> = (0..8).map(|_| {
+        let (i, action_thread) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(action_thread != thread::current().id());
+        i
+    }).collect();
+    received.sort();
+    assert_eq!(&[0, 1, 2, 3, 4, 5, 6, 7], &received[..]);
+}
+
+#[test]
+fn thread_pool_scheduler_runs_actions_in_order_of_due_time() {
+    let pool = ThreadPoolScheduler::new(1);
+    let (sender, receiver) = mpsc::channel();
+    let sender_b = sender.clone();
+    let _s1 = pool.schedule_after(Duration::from_millis(20), move || sender_b.send("b").unwrap());
+    let _s2 = pool.schedule_after(Duration::from_millis(10), move || sender.send("a").unwrap());
+    assert_eq!("a", receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+    assert_eq!("b", receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+}
+
+#[test]
+fn thread_pool_scheduler_survives_panicking_action() {
+    let pool = ThreadPoolScheduler::new(1);
+    let (sender, receiver) = mpsc::channel();
+    let panicking = pool.schedule_periodic(Duration::from_millis(1), || panic!("action panicked"));
+    let _s = pool.schedule_after(Duration::from_millis(10), move || sender.send(()).unwrap());
+    assert_eq!(Ok(()), receiver.recv_timeout(Duration::from_secs(5)));
+    // The periodic action is cancelled after its panic.
+    assert!(!panicking.is_active());
+}
+
+#[test]
+fn thread_pool_scheduler_periodic_action_until_cancelled() {
+    let pool = ThreadPoolScheduler::new(1);
+    let (sender, receiver) = mpsc::channel();
+    let subscription = pool.schedule_periodic(Duration::from_millis(1), move || {
+        // Sending fails once the receiver is gone, but the action keeps running until cancelled.
+        let _ = sender.send(());
+    });
+    for _ in 0..3 {
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    drop(subscription);
+    // Wait for the action to observe the cancellation and be dropped.
+    while receiver.recv_timeout(Duration::from_secs(5)).is_ok() {}
+}
+
+// Subscription tests
+
+#[test]
+fn subject_subscription_unsubscribe() {
+    let mut subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut subscription = subject.observable().subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert!(subscription.is_active());
+
+    subject.on_next(2);
+    subscription.unsubscribe();
+    assert!(!subscription.is_active());
+    assert!(!subject.has_observers());
+
+    // Unsubscribing again should have no effect.
+    subscription.unsubscribe();
+    subject.on_next(3);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+}
+
+#[test]
+fn subject_subscription_inactive_after_completion() {
+    let subject = Subject::<u8, ()>::new();
+    let subscription = subject.observable().subscribe_completed(|_x| {}, || {});
+    assert!(subscription.is_active());
+    subject.on_completed();
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn create_subscription_unsubscribe() {
+    let emitter = Rc::new(RefCell::new(None));
+    let emitter_clone = emitter.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut observable = rx::create(move |e: Emitter<u8, ()>| *emitter_clone.borrow_mut() = Some(e));
+    let mut subscription = observable.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert!(subscription.is_active());
+
+    emitter.borrow_mut().as_mut().unwrap().next(2);
+    subscription.unsubscribe();
+    assert!(!subscription.is_active());
+    emitter.borrow_mut().as_mut().unwrap().next(3);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+}
+
+#[test]
+fn scheduled_subscription_is_active_until_run() {
+    let event_loop = EventLoop::new();
+    let mut cancelled = event_loop.schedule(|| panic!("cancelled action should not run"));
+    let pending = event_loop.schedule(|| {});
+    cancelled.unsubscribe();
+    assert!(!cancelled.is_active());
+    assert!(pending.is_active());
+
+    event_loop.run_until_idle();
+    assert!(!pending.is_active());
+}
+
+#[test]
+fn composite_subscription_drops_all_on_drop() {
+    let mut subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let mut composite = CompositeSubscription::new();
+    for _ in 0..3 {
+        let received = received.clone();
+        composite.add(subject.observable().subscribe_next(move |x| received.borrow_mut().push(x)));
+    }
+    composite.add(UncancellableSubscription);
+    assert_eq!(4, composite.len());
+
+    subject.on_next(2);
+    drop(composite);
+    subject.on_next(3);
+    assert_eq!(&[2u8, 2, 2], &received.borrow()[..]);
+    assert!(!subject.has_observers());
+}
+
+#[test]
+fn composite_subscription_remove_and_clear() {
+    let subject = Subject::<u8, ()>::new();
+    let mut composite = CompositeSubscription::new();
+    let key = composite.add(subject.observable().subscribe_next(|_x| {}));
+    composite.add(subject.observable().subscribe_next(|_x| {}));
+    assert_eq!(2, subject.observer_count());
+
+    assert!(composite.remove(key));
+    assert!(!composite.remove(key));
+    assert_eq!(1, subject.observer_count());
+
+    assert!(composite.is_active());
+    composite.clear();
+    assert!(composite.is_empty());
+    assert!(!composite.is_active());
+    assert_eq!(0, subject.observer_count());
+
+    // The composite subscription can be reused after clearing.
+    composite.add(subject.observable().subscribe_next(|_x| {}));
+    assert_eq!(1, subject.observer_count());
+}
+
+#[test]
+fn composite_subscription_stale_key_removes_nothing() {
+    let subject = Subject::<u8, ()>::new();
+    let mut composite = CompositeSubscription::new();
+    let removed = composite.add(subject.observable().subscribe_next(|_x| {}));
+    assert!(composite.remove(removed));
+    composite.add(subject.observable().subscribe_next(|_x| {}));
+    assert!(!composite.remove(removed));
+
+    let cleared = composite.add(subject.observable().subscribe_next(|_x| {}));
+    composite.clear();
+    composite.add(subject.observable().subscribe_next(|_x| {}));
+    assert!(!composite.remove(cleared));
+    assert_eq!(1, subject.observer_count());
+}
+
+#[test]
+fn serial_subscription_drops_previous() {
+    let subject = Subject::<u8, ()>::new();
+    let mut serial = SerialSubscription::new();
+    assert!(serial.is_empty());
+
+    serial.set(subject.observable().subscribe_next(|_x| {}));
+    assert_eq!(1, subject.observer_count());
+
+    // Setting a new subscription should drop the previous one.
+    serial.set(subject.observable().subscribe_next(|_x| {}));
+    assert_eq!(1, subject.observer_count());
+    assert!(!serial.is_empty());
+
+    serial.clear();
+    assert!(serial.is_empty());
+    assert_eq!(0, subject.observer_count());
+
+    serial.set(subject.observable().subscribe_next(|_x| {}));
+    drop(serial);
+    assert_eq!(0, subject.observer_count());
+}
+
+// Observe on tests
+
+#[test]
+fn observe_on_pushes_through_scheduler() {
+    let values = &[2u8, 3, 5];
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = values.observe_on(event_loop.clone()).subscribe_completed(
+        move |&x| received_clone.borrow_mut().push(x),
+        move || completed_clone.set(true));
+
+    // Nothing should be pushed before the event loop runs.
+    assert_eq!(0, received.borrow().len());
+    assert!(!completed.get());
+
+    event_loop.run_until_idle();
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn observe_on_drop_discards_queued_values() {
+    let values = &[2u8, 3, 5];
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription = values.observe_on(event_loop.clone())
+                             .subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    drop(subscription);
+    event_loop.run_until_idle();
+    assert_eq!(0, received.borrow().len());
+}
+
+#[test]
+fn observe_on_queue_is_unbounded_by_default() {
+    let source = rx::create(|mut emitter: Emitter<u32, ()>| {
+        for x in 0..2000 {
+            emitter.next(x);
+        }
+        emitter.completed();
+    });
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let subscription = source.observe_on(event_loop.clone())
+                             .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert_eq!(2000, subscription.queue_len());
+    event_loop.run_until_idle();
+    assert_eq!((0..2000).collect::<Vec<_>>(), *received.borrow());
+}
+
+#[test]
+fn observe_on_overflow_drop_oldest() {
+    let values = &[1u8, 2, 3, 4, 5];
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = values.observe_on(event_loop.clone())
+                              .bounded(2, OverflowPolicy::DropOldest)
+                              .subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    event_loop.run_until_idle();
+    assert_eq!(&[4u8, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn observe_on_overflow_drop_newest() {
+    let values = &[1u8, 2, 3, 4, 5];
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = values.observe_on(event_loop.clone())
+                              .bounded(2, OverflowPolicy::DropNewest)
+                              .subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    event_loop.run_until_idle();
+    assert_eq!(&[1u8, 2], &received.borrow()[..]);
+}
+
+#[test]
+#[should_panic]
+fn observe_on_overflow_panic() {
+    let values = &[1u8, 2, 3];
+    let event_loop = EventLoop::new();
+    let _subscription = values.observe_on(event_loop.clone())
+                              .bounded(2, OverflowPolicy::Panic)
+                              .subscribe_next(|_x| {});
+}
+
+#[test]
+fn observe_on_overflow_error() {
+    let mut subject = Subject::<u8, u8>::new();
+    let event_loop = EventLoop::new();
+    let observer = TestObserver::new();
+    let _subscription = subject.observable()
+                               .observe_on(event_loop.clone())
+                               .bounded(2, OverflowPolicy::Error(7))
+                               .subscribe(observer.clone());
+    subject.on_next(1);
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(4);
+    event_loop.run_until_idle();
+    observer.assert_items(&[1, 2]);
+    observer.assert_error(7);
+}
+
+#[test]
+#[should_panic]
+fn observe_on_overflow_block_panics() {
+    let values = &[1u8, 2, 3];
+    let event_loop = EventLoop::new();
+    let _observable = values.observe_on(event_loop.clone()).bounded(2, OverflowPolicy::Block);
+}
+
+#[test]
+fn observe_on_queue_metrics() {
+    let values = &[1u8, 2, 3, 4, 5];
+    let event_loop = EventLoop::new();
+    let subscription = values.observe_on(event_loop.clone())
+                             .bounded(3, OverflowPolicy::DropNewest)
+                             .subscribe_next(|_x| {});
+    assert_eq!(3, subscription.queue_len());
+    assert_eq!(2, subscription.dropped_count());
+    event_loop.run_until_idle();
+    assert_eq!(0, subscription.queue_len());
+    assert_eq!(3, subscription.peak_queue_len());
+}
+
+// Sends every notification into a channel, with the thread that it arrived on.
+struct ThreadObserver {
+    sender: mpsc::Sender<(Option<u8>, thread::ThreadId)>,
+}
+
+impl Observer<u8, ()> for ThreadObserver {
+    fn on_next(&mut self, item: u8) {
+        self.sender.send((Some(item), thread::current().id())).unwrap();
+    }
+
+    fn on_completed(self) {
+        self.sender.send((None, thread::current().id())).unwrap();
+    }
+
+    fn on_error(self, _error: ()) {
+        panic!("observe_on_thread should not fail");
+    }
+}
+
+#[test]
+fn observe_on_thread_pushes_on_other_thread() {
+    let mut subject = Subject::<u8, ()>::new();
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = subject.observable()
+                               .observe_on_thread(NewThreadScheduler)
+                               .subscribe(ThreadObserver { sender: sender });
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(5);
+    subject.on_completed();
+
+    let received: Vec<_> = receiver.iter().take(4).collect();
+    let values: Vec<_> = received.iter().map(|&(x, _id)| x).collect();
+    assert_eq!(vec![Some(2), Some(3), Some(5), None], values);
+    assert!(received.iter().all(|&(_x, id)| id != thread::current().id()));
+}
+
+#[test]
+fn observe_on_thread_overflow_block() {
+    let mut subject = Subject::<u8, ()>::new();
+    let (sender, receiver) = mpsc::channel();
+    let subscription = subject.observable()
+                              .observe_on_thread(NewThreadScheduler)
+                              .bounded(1, OverflowPolicy::Block)
+                              .subscribe(ThreadObserver { sender: sender });
+    for x in 0..100 {
+        subject.on_next(x);
+    }
+    subject.on_completed();
+
+    let values: Vec<_> = receiver.iter().take(101).map(|(x, _id)| x).collect();
+    let mut expected: Vec<_> = (0..100).map(Some).collect();
+    expected.push(None);
+    assert_eq!(expected, values);
+    assert_eq!(0, subscription.dropped_count());
+    assert_eq!(1, subscription.peak_queue_len());
+}
+
+// Multicast tests
+
+#[test]
+fn publish_pushes_after_connect() {
+    let values = &[2u8, 3, 5];
+    let mut published = values.publish();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _s1 = published.subscribe_next(move |&x| first_clone.borrow_mut().push(x));
+    let _s2 = published.subscribe_completed(move |&x| second_clone.borrow_mut().push(x),
+                                            move || completed_clone.set(true));
+
+    // Nothing should be pushed before connecting.
+    assert_eq!(0, first.borrow().len());
+    assert!(!published.is_connected());
+
+    published.connect();
+    assert_eq!(&[2u8, 3, 5], &first.borrow()[..]);
+    assert_eq!(&[2u8, 3, 5], &second.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn publish_shares_subscription() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut published = source.observable().publish();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let _s1 = published.subscribe_next(move |x| first_clone.borrow_mut().push(x));
+    let _s2 = published.subscribe_next(move |x| second_clone.borrow_mut().push(x));
+    assert_eq!(0, source.observer_count());
+
+    published.connect();
+    published.connect();
+    assert_eq!(1, source.observer_count());
+    source.on_next(2);
+
+    published.disconnect();
+    assert_eq!(0, source.observer_count());
+    source.on_next(3);
+
+    assert_eq!(&[2u8], &first.borrow()[..]);
+    assert_eq!(&[2u8], &second.borrow()[..]);
+}
+
+#[test]
+fn share_connects_while_subscribed() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut shared = source.observable().share();
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+
+    let s1 = shared.subscribe_next(move |x| first_clone.borrow_mut().push(x));
+    assert_eq!(1, source.observer_count());
+    source.on_next(2);
+
+    let s2 = shared.subscribe_next(move |x| second_clone.borrow_mut().push(x));
+    assert_eq!(1, source.observer_count());
+    source.on_next(3);
+
+    drop(s1);
+    assert_eq!(1, source.observer_count());
+    source.on_next(5);
+
+    // Dropping the last subscription should disconnect from the source.
+    drop(s2);
+    assert_eq!(0, source.observer_count());
+
+    assert_eq!(&[2u8, 3], &first.borrow()[..]);
+    assert_eq!(&[3u8, 5], &second.borrow()[..]);
+}
+
+#[test]
+fn share_resubscribes_after_completion() {
+    let mut shared = Some(7u8).share();
+    let events = Rc::new(RefCell::new(Vec::new()));
+    for _ in 0..2 {
+        let next_events = events.clone();
+        let completed_events = events.clone();
+        let subscription = shared.subscribe_completed(
+            move |x| next_events.borrow_mut().push(x.to_string()),
+            move || completed_events.borrow_mut().push("done".to_string()));
+        drop(subscription);
+    }
+    assert_eq!(&["7", "done", "7", "done"], &events.borrow()[..]);
+}
+
+#[test]
+fn share_pushes_to_first_subscriber_upon_connect() {
+    let values = &[2u8, 3, 5];
+    let mut shared = values.share();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = shared.subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn share_linger_disconnects_after_linger() {
+    let source = Subject::<u8, ()>::new();
+    let mut shared = source.observable().share_linger(Duration::from_millis(1), ImmediateScheduler);
+    let subscription = shared.subscribe_next(|_x| {});
+    assert_eq!(1, source.observer_count());
+
+    // The immediate scheduler waits for the linger duration before returning.
+    drop(subscription);
+    assert_eq!(0, source.observer_count());
+}
+
+#[test]
+fn share_linger_reuses_connection_within_linger() {
+    let source = Subject::<u8, ()>::new();
+    let scheduler = TestScheduler::new();
+    let mut shared = source.observable().share_linger(Duration::from_secs(1), scheduler.clone());
+    let subscription = shared.subscribe_next(|_x| {});
+    drop(subscription);
+
+    // Within the linger duration, the connection should still be there.
+    scheduler.advance_by(Duration::from_millis(500));
+    assert_eq!(1, source.observer_count());
+
+    // A new subscriber cancels the pending disconnect.
+    let subscription = shared.subscribe_next(|_x| {});
+    scheduler.advance_by(Duration::from_secs(2));
+    assert_eq!(1, source.observer_count());
+
+    drop(subscription);
+    scheduler.advance_by(Duration::from_secs(1));
+    assert_eq!(0, source.observer_count());
+}
+
+#[test]
+fn share_replay_pushes_last_values_to_late_subscribers() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut shared = source.observable().share_replay(1);
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+
+    let _s1 = shared.subscribe_next(move |x| first_clone.borrow_mut().push(x));
+    source.on_next(2);
+    source.on_next(3);
+
+    // The late subscriber should receive the latest value, followed by live values.
+    let _s2 = shared.subscribe_next(move |x| second_clone.borrow_mut().push(x));
+    source.on_next(5);
+
+    assert_eq!(1, source.observer_count());
+    assert_eq!(&[2u8, 3, 5], &first.borrow()[..]);
+    assert_eq!(&[3u8, 5], &second.borrow()[..]);
+}
+
+#[test]
+fn cache_subscribes_once_and_replays_terminal_event() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut cached = source.observable().cache();
+    let first = TestObserver::new();
+    let subscription = cached.subscribe(first.clone());
+    source.on_next(2);
+    drop(subscription);
+
+    // The source stays subscribed, even without observers.
+    assert_eq!(1, source.observer_count());
+    source.on_next(3);
+    source.on_completed();
+
+    let second = TestObserver::new();
+    cached.subscribe(second.clone());
+    first.assert_items(&[2]);
+    second.assert_items(&[2, 3]);
+    second.assert_completed();
+}
+
+#[test]
+fn multicast_behavior_subject() {
+    let values = &[3u8, 5];
+    let mut connectable = values.multicast(BehaviorSubject::new(&2u8));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = connectable.subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+
+    // The observer should receive the initial value before connecting.
+    assert_eq!(&[2u8], &received.borrow()[..]);
+
+    connectable.connect();
+    assert_eq!(&[2u8, 3, 5], &received.borrow()[..]);
+}
+
+#[test]
+fn multicast_async_subject() {
+    let values = &[2u8, 3, 5];
+    let mut connectable = values.multicast(AsyncSubject::new());
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let first_clone = first.clone();
+    let _s1 = connectable.subscribe_next(move |&x| first_clone.borrow_mut().push(x));
+    connectable.connect();
+
+    // A late observer should receive the cached last value too.
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let second_clone = second.clone();
+    let _s2 = connectable.subscribe_next(move |&x| second_clone.borrow_mut().push(x));
+    assert_eq!(&[5u8], &first.borrow()[..]);
+    assert_eq!(&[5u8], &second.borrow()[..]);
+}
+
+// Transform tests
+
+#[test]
+fn map() {
+    let values = &[2u8, 3, 5, 7, 11, 13];
+    let expected = &[4u8, 6, 10, 14, 22, 26];
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut mapped = values.map(|x| x * 2);
+    mapped.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert_eq!(&expected[..], &received.borrow()[..]);
+}
+
+#[test]
+fn map_does_not_change_error() {
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let mut mapped = Err(23_u32).map(|x: u32| format!("{}", x));
+    mapped.subscribe_error(
+        |_x: String| panic!("mapped error should not produce a value"),
+        || panic!("mapped error should not complete"),
+        move |err| error_clone.set(Some(err))
+    );
+    assert_eq!(Some(23), error.get());
+}
+
+#[test]
+fn flatten_iter_pushes_elements_in_order() {
+    let mut subject = Subject::<Vec<u8>, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = subject.observable().flatten_iter().subscribe(observer.clone());
+    subject.on_next(vec![2, 3]);
+    subject.on_next(vec![]);
+    subject.on_next(vec![5]);
+    subject.on_error(());
+    observer.assert_items(&[2, 3, 5]);
+    observer.assert_error(());
+}
+
+#[test]
+fn concat_map_iter_pushes_elements_of_mapped_values() {
+    let observer = TestObserver::<u8, NoError>::new();
+    (&[1u8, 2, 3]).map(|&x| x).concat_map_iter(|x| vec![x; x as usize]).subscribe(observer.clone());
+    observer.assert_items(&[1, 2, 2, 3, 3, 3]);
+    observer.assert_completed();
+}
+
+#[test]
+fn filter_some_drops_none() {
+    let observer = TestObserver::<u8, NoError>::new();
+    (&[Some(2u8), None, Some(3), None]).map(|&x| x).filter_some().subscribe(observer.clone());
+    observer.assert_items(&[2, 3]);
+    observer.assert_completed();
+}
+
+#[test]
+fn ok_or_else_converts_none_to_error() {
+    let observer = TestObserver::<Result<u8, &'static str>, NoError>::new();
+    (&[Some(2u8), None]).map(|&x| x).ok_or_else(|| "missing").subscribe(observer.clone());
+    observer.assert_items(&[Ok(2), Err("missing")]);
+    observer.assert_completed();
+}
+
+#[test]
+fn flatten_results_fails_with_first_error() {
+    let mut subject = Subject::<Result<u8, &'static str>, &'static str>::new();
+    let observer = TestObserver::new();
+    let _subscription = subject.observable().flatten_results().subscribe(observer.clone());
+    subject.on_next(Ok(2));
+    subject.on_next(Ok(3));
+    subject.on_next(Err("failed"));
+    subject.on_next(Ok(5));
+    observer.assert_items(&[2, 3]);
+    observer.assert_error("failed");
+}
+
+#[test]
+fn split_results_shares_one_subscription() {
+    let mut subject = Subject::<Result<u8, &'static str>, ()>::new();
+    let (mut values, mut errors) = subject.observable().split_results();
+    let values_observer = TestObserver::new();
+    let errors_observer = TestObserver::new();
+    let values_subscription = values.subscribe(values_observer.clone());
+    let errors_subscription = errors.subscribe(errors_observer.clone());
+    assert_eq!(1, subject.observer_count());
+    subject.on_next(Ok(2));
+    subject.on_next(Err("odd"));
+    subject.on_next(Ok(4));
+    values_observer.assert_items(&[2, 4]);
+    errors_observer.assert_items(&["odd"]);
+
+    drop(values_subscription);
+    assert_eq!(1, subject.observer_count());
+    drop(errors_subscription);
+    assert_eq!(0, subject.observer_count());
+}
+
+#[test]
+fn route_divides_values() {
+    let values = vec![1u8, 2, 3, 4, 5];
+    let (mut odd, mut even) = (&values).map(|&x| x).route(|x| {
+        if x % 2 == 1 { Either::Left(x) } else { Either::Right(x * 10) }
+    });
+    let even_observer = TestObserver::new();
+    let odd_observer = TestObserver::new();
+    let _even_subscription = even.subscribe(even_observer.clone());
+    let _odd_subscription = odd.subscribe(odd_observer.clone());
+
+    // The source pushes everything when the first observer subscribes.
+    even_observer.assert_items(&[20, 40]);
+    even_observer.assert_completed();
+    odd_observer.assert_items(&[]);
+    odd_observer.assert_completed();
+}
+
+#[test]
+fn map_error() {
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let observable = Err(23_u32);
+    let mut mapped = observable.map_error(|x| x * 2);
+    mapped.subscribe_error(
+        |_x: u32| panic!("mapped error should not produce a value"),
+        || panic!("mapped error should not complete"),
+        move |err| error_clone.set(Some(err))
+    );
+    assert_eq!(Some(46), error.get());
+}
+
+#[test]
+fn err_into() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct AppError(u32);
+
+    impl From<u8> for AppError {
+        fn from(error: u8) -> AppError {
+            AppError(error as u32 * 2)
+        }
+    }
+
+    let error = Rc::new(RefCell::new(None));
+    let error_clone = error.clone();
+    let mut converted = Err::<u8, u8>(23).err_into::<AppError>();
+    converted.subscribe_error(
+        |_x| panic!("converted error should not produce a value"),
+        || panic!("converted error should not complete"),
+        move |err| *error_clone.borrow_mut() = Some(err)
+    );
+    assert_eq!(Some(AppError(46)), *error.borrow());
+}
+
+#[test]
+fn map_error_does_not_change_values() {
+    let values = &[2u8, 3, 5, 7, 11, 13];
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut mapped = values.map_error(|err| err.unreachable::<u8>());
+    mapped.subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    assert_eq!(&values[..], &received.borrow()[..]);
+}
+
+#[test]
+fn continue_with() {
+    let (first, second) = (&[2u8, 3, 5, 7], &[11u8, 13, 17, 19]);
+    let expected = &[2u8, 3, 5, 7, 11, 13, 17, 19];
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut continued = first.continue_with(second);
+    continued.subscribe_next(move |&x| received_clone.borrow_mut().push(x));
+    assert_eq!(&expected[..], &received.borrow()[..]);
+}
+
+#[test]
+fn repeat_when_resubscribes_when_signal_pushes() {
+    let signal = Subject::<(), NoError>::new();
+    let signal_observable = signal.observable();
+    let observer = TestObserver::new();
+    let _subscription = Some(2u8)
+        .repeat_when(move |_completions| signal_observable.clone())
+        .subscribe(observer.clone());
+    observer.assert_items(&[2]);
+    observer.assert_not_terminated();
+
+    (&signal).on_next(());
+    (&signal).on_next(());
+    observer.assert_items(&[2, 2, 2]);
+
+    (&signal).on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn infallible_continue_with_failing() {
+    let (values, failing) = (&[2u8, 3], Err::<&u8, u8>(31));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let mut continued = values.infallible().continue_with(failing);
+    continued.subscribe_error(move |&x| received_clone.borrow_mut().push(x),
+                              || panic!("failing continuation should not complete"),
+                              move |err| error_clone.set(Some(err)));
+    assert_eq!(&[2u8, 3], &received.borrow()[..]);
+    assert_eq!(Some(31), error.get());
+}
+
+#[test]
+fn map_by_mutable_reference() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut observable = subject.observable();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _s1 = (&mut observable).map(|x| x * 2)
+                               .subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    // The original observable can still be subscribed to.
+    let received_clone = received.clone();
+    let _s2 = observable.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+
+    subject.on_next(3);
+    assert_eq!(&[6u8, 3], &received.borrow()[..]);
+}
+
+#[test]
+fn lift_custom_operator() {
+    // An operator that only lets even values through.
+    struct EvenObserver {
+        observer: Box<dyn BoxedObserver<u8, ()>>,
+    }
+
+    impl Observer<u8, ()> for EvenObserver {
+        fn on_next(&mut self, item: u8) {
+            if item.is_multiple_of(2) {
+                self.observer.on_next(item);
+            }
+        }
+
+        fn on_completed(self) {
+            self.observer.on_completed();
+        }
+
+        fn on_error(self, error: ()) {
+            self.observer.on_error(error);
+        }
+    }
+
+    let mut subject = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let mut evens = subject.observable().lift(|observer| EvenObserver { observer: observer });
+    let _subscription = evens.subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                                                  move || completed_clone.set(true));
+    for x in 1..7 {
+        subject.on_next(x);
+    }
+    subject.on_completed();
+    assert_eq!(&[2u8, 4, 6], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+// Boxed tests
+
+#[test]
+fn boxed_observables_in_collection() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut observables: Vec<BoxedObservable<u8, ()>> = vec![
+        Some(2u8).infallible().boxed(),
+        (&[3u8, 5]).map(|&x| x).infallible().boxed(),
+        subject.observable().map(|x| x * 10).boxed(),
+    ];
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let subscriptions: Vec<_> = observables.iter_mut().map(|observable| {
+        let received_clone = received.clone();
+        observable.subscribe_next(move |x| received_clone.borrow_mut().push(x))
+    }).collect();
+    subject.on_next(7);
+    assert_eq!(&[2u8, 3, 5, 70], &received.borrow()[..]);
+
+    // Only the subscription to the subject should still be active.
+    assert!(!subscriptions[0].is_active());
+    assert!(subscriptions[2].is_active());
+    drop(subscriptions);
+    subject.on_next(11);
+    assert_eq!(&[2u8, 3, 5, 70], &received.borrow()[..]);
+}
+
+#[test]
+fn boxed_observable_completes_and_fails() {
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let mut boxed = None::<u8>.boxed();
+    boxed.subscribe_completed(|_x| panic!("none should not push a value"),
+                              move || completed_clone.set(true));
+    assert!(completed.get());
+
+    let error = Rc::new(Cell::new(None));
+    let error_clone = error.clone();
+    let mut boxed = Err::<u8, u8>(29).boxed();
+    boxed.subscribe_error(|_x| panic!("err should not push a value"),
+                          || panic!("err should not complete"),
+                          move |err| error_clone.set(Some(err)));
+    assert_eq!(Some(29), error.get());
+}
+
+#[test]
+fn boxed_observers_in_collection() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let completed = Arc::new(Mutex::new(false));
+    let subject = Subject::<u8, ()>::new();
+    let late = Rc::new(RefCell::new(Vec::new()));
+    let late_clone = late.clone();
+    let _subscription = subject.observable()
+                               .subscribe_next(move |x| late_clone.borrow_mut().push(x));
+
+    // Observers of different types can be stored together once boxed.
+    let mut observers: Vec<Box<dyn BoxedObserver<u8, ()>>> = vec![
+        SharedVecObserver { received: received.clone(), completed: completed.clone() }.boxed(),
+        subject.sink().boxed(),
+    ];
+    for observer in observers.iter_mut() {
+        observer.on_next(2);
+    }
+    for observer in observers {
+        observer.on_completed();
+    }
+    assert_eq!(&[2u8], &received.lock().unwrap()[..]);
+    assert!(*completed.lock().unwrap());
+    assert_eq!(&[2u8], &late.borrow()[..]);
+    assert_eq!(0, subject.observer_count());
+}
+
+#[test]
+fn subscribe_boxed_observer() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let completed = Arc::new(Mutex::new(false));
+    let observer = SharedVecObserver { received: received.clone(), completed: completed.clone() };
+    let values = &[2u8, 3];
+    values.map(|&x| x).infallible().subscribe(observer.boxed());
+    assert_eq!(&[2u8, 3], &received.lock().unwrap()[..]);
+    assert!(*completed.lock().unwrap());
+}
+
+// Describe tests
+
+#[test]
+fn describe_shows_operators_and_sources() {
+    let subject = Subject::<u8, ()>::new();
+    let other = Subject::<u8, ()>::new();
+    let trigger = Subject::<(), ()>::new();
+    let pipeline = subject.observable()
+                          .map(|x| x as u32)
+                          .merge(other.observable().map(|x| x as u32 * 2))
+                          .take_until(trigger.observable())
+                          .boxed();
+    let graph = pipeline.describe();
+    let names: Vec<&str> = graph.nodes().iter().map(|node| &node.name[..]).collect();
+    assert_eq!(&["SubjectObservable", "map", "SubjectObservable", "map", "merge",
+                 "SubjectObservable", "take_until", "boxed"], &names[..]);
+    assert_eq!(&[(0, 1), (2, 3), (1, 4), (3, 4), (4, 6), (5, 6), (6, 7)], graph.edges());
+    assert_eq!("u32", graph.root().item);
+    assert_eq!("()", graph.root().error);
+}
+
+#[test]
+fn describe_to_dot() {
+    let safe = Some(2u8).safe();
+    let expected = "digraph pipeline {\n\
+                    \x20   n0 [label=\"Option\\n<u8, NoError>\"];\n\
+                    \x20   n1 [label=\"safe\\n<u8, NoError>\"];\n\
+                    \x20   n0 -> n1;\n\
+                    }\n";
+    assert_eq!(expected, safe.describe().to_dot());
+}
+
+// Lifecycle tests
+
+#[test]
+fn do_on_subscribe_and_unsubscribe_count_subscribers() {
+    let subject = Subject::<u8, ()>::new();
+    let count = Rc::new(Cell::new(0));
+    let count_subscribe = count.clone();
+    let count_unsubscribe = count.clone();
+    let mut counted = subject.observable()
+        .do_on_subscribe(move || count_subscribe.set(count_subscribe.get() + 1))
+        .do_on_unsubscribe(move || count_unsubscribe.set(count_unsubscribe.get() - 1));
+    let mut first = counted.subscribe(TestObserver::new());
+    let second = counted.subscribe(TestObserver::new());
+    assert_eq!(2, count.get());
+    first.unsubscribe();
+    assert_eq!(1, count.get());
+    drop(first);
+    assert_eq!(1, count.get());
+    drop(second);
+    assert_eq!(0, count.get());
+}
+
+#[test]
+fn do_on_subscribe_runs_before_values() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events_subscribe = events.clone();
+    let events_unsubscribe = events.clone();
+    let events_next = events.clone();
+    let mut hooked = Some(2u8)
+        .do_on_subscribe(move || events_subscribe.borrow_mut().push("subscribe"))
+        .do_on_unsubscribe(move || events_unsubscribe.borrow_mut().push("unsubscribe"));
+    let subscription = hooked.subscribe_next(move |_x| events_next.borrow_mut().push("next"));
+    // The observable completed, but the subscription is only ended when it is dropped.
+    assert_eq!(&["subscribe", "next"], &events.borrow()[..]);
+    drop(subscription);
+    assert_eq!(&["subscribe", "next", "unsubscribe"], &events.borrow()[..]);
+}
+
+// Log tests
+
+#[test]
+fn log_reports_every_event() {
+    // The sink is global, so the test only keeps the records with its own tag.
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+    rx::set_log_sink(LogSink::Custom(Box::new(move |record| {
+        if record.tag == "log_reports_every_event" {
+            events_clone.lock().unwrap().push(format!("{}", record.event));
+        }
+    })));
+
+    let mut subject = Subject::<u8, ()>::new();
+    let mut logged = subject.observable().log("log_reports_every_event");
+    let observer = TestObserver::new();
+    let subscription = logged.subscribe(observer.clone());
+    subject.on_next(2);
+    subject.on_next(3);
+    drop(subscription);
+    observer.assert_items(&[2, 3]);
+    rx::set_log_sink(LogSink::Stderr);
+
+    let events = events.lock().unwrap();
+    assert_eq!(&["subscribe", "next 2", "next 3", "unsubscribe"], &events[..]);
+}
+
+#[test]
+fn log_record_displays_as_line() {
+    let record = LogRecord {
+        tag: "parse",
+        time: UNIX_EPOCH + Duration::from_millis(1250),
+        event: LogEvent::Next(&42),
+    };
+    assert_eq!("[1.250] parse: next 42", format!("{}", record));
+}
+
+// Metrics tests
+
+/// A metrics sink that records metrics as lines, for the pipelines of one test.
+struct RecordingSink {
+    prefix: &'static str,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl MetricsSink for RecordingSink {
+    fn increment(&self, name: &str, counter: Counter) {
+        if name.starts_with(self.prefix) {
+            self.lines.lock().unwrap().push(format!("{} {:?}", name, counter));
+        }
+    }
+
+    fn gauge(&self, name: &str, gauge: Gauge, value: f64) {
+        if name.starts_with(self.prefix) {
+            self.lines.lock().unwrap().push(format!("{} {:?} {}", name, gauge, value));
+        }
+    }
+}
+
+#[test]
+fn metered_reports_counters_and_queue_depth() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    rx::set_metrics_sink(RecordingSink { prefix: "metered_reports", lines: lines.clone() });
+
+    let values = &[2u8, 3];
+    let event_loop = EventLoop::new();
+    let _subscription = values.metered("metered_reports")
+                              .observe_on(event_loop.clone())
+                              .metered_queue("metered_reports_queue")
+                              .subscribe_next(|_x| {});
+    event_loop.run_until_idle();
+
+    let expected = &["metered_reports Items",
+                     "metered_reports_queue QueueDepth 1",
+                     "metered_reports Items",
+                     "metered_reports_queue QueueDepth 2",
+                     "metered_reports Completions",
+                     "metered_reports_queue QueueDepth 2",
+                     "metered_reports_queue QueueDepth 1",
+                     "metered_reports_queue QueueDepth 0",
+                     "metered_reports_queue QueueDepth 0"];
+    assert_eq!(expected, &lines.lock().unwrap()[..]);
+}
+
+// Leak tests
+
+#[test]
+fn leak_tracking_lists_live_subscriptions() {
+    // The registry is global, so only keep what this test created.
+    let live = || -> Vec<(&'static str, bool)> {
+        rx::debug::live_subscriptions().into_iter()
+            .filter(|live| live.backtrace.contains("leak_tracking_lists_live"))
+            .map(|live| (live.kind, live.is_terminated))
+            .collect()
+    };
+    rx::debug::set_leak_tracking(true);
+    let subject = Subject::<u8, ()>::new();
+    let subscription = subject.observable().subscribe_next(|_x| {});
+    rx::debug::set_leak_tracking(false);
+    assert_eq!(&[("subject", false), ("subject subscription", false)], &live()[..]);
+    assert!(rx::debug::dump_live_subscriptions().contains("live subject (not terminated)"));
+
+    (&subject).on_completed();
+    assert_eq!(&[("subject", true), ("subject subscription", true)], &live()[..]);
+    drop(subscription);
+    assert_eq!(&[("subject", true)], &live()[..]);
+    drop(subject);
+    assert_eq!(0, live().len());
+
+    let a = Subject::<u8, ()>::new();
+    let b = Subject::<u8, ()>::new();
+    let scheduler = TestScheduler::new();
+    rx::debug::set_leak_tracking(true);
+    let merged = a.observable().merge(b.observable()).subscribe_next(|_x| {});
+    let action = scheduler.schedule_after(Duration::from_secs(1), || {});
+    rx::debug::set_leak_tracking(false);
+    let subscriptions = [
+        ("merge subscription", false),
+        ("subject subscription", false),
+        ("subject subscription", false),
+        ("scheduled action", false),
+    ];
+    assert_eq!(&subscriptions, &live()[..]);
+
+    scheduler.advance_by(Duration::from_secs(1));
+    assert_eq!(&subscriptions[..3], &live()[..3]);
+    assert_eq!(("scheduled action", true), live()[3]);
+    drop(merged);
+    drop(action);
+    assert_eq!(0, live().len());
+}
+
+// Safe tests
+
+type LeakedObserver = Rc<RefCell<Option<Box<dyn BoxedObserver<u8, ()>>>>>;
+
+/// A source that keeps its observer, and ignores unsubscription.
+struct LeakyObservable {
+    observer: LeakedObserver,
+}
+
+impl Observable for LeakyObservable {
+    type Item = u8;
+    type Error = ();
+    type Subscription = UncancellableSubscription;
+
+    fn subscribe<O>(&mut self, observer: O) -> UncancellableSubscription
+        where O: Observer<u8, ()> + 'static {
+        *self.observer.borrow_mut() = Some(observer.boxed());
+        UncancellableSubscription
+    }
+}
+
+#[test]
+fn safe_ignores_values_after_unsubscribe() {
+    let leaked = Rc::new(RefCell::new(None));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut safe = LeakyObservable { observer: leaked.clone() }.safe();
+    let mut subscription = safe.subscribe_next(move |x| received_clone.borrow_mut().push(x));
+    assert!(subscription.is_active());
+
+    leaked.borrow_mut().as_mut().unwrap().on_next(2);
+    subscription.unsubscribe();
+    assert!(!subscription.is_active());
+    leaked.borrow_mut().as_mut().unwrap().on_next(3);
+    leaked.borrow_mut().take().unwrap().on_completed();
+
+    assert_eq!(&[2u8], &received.borrow()[..]);
+}
+
+#[test]
+fn safe_ignores_values_after_drop() {
+    let leaked = Rc::new(RefCell::new(None));
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let mut safe = LeakyObservable { observer: leaked.clone() }.safe();
+    let subscription = safe.subscribe_completed(|_x| panic!("no value should be pushed after drop"),
+                                                move || completed_clone.set(true));
+    drop(subscription);
+    leaked.borrow_mut().as_mut().unwrap().on_next(2);
+    leaked.borrow_mut().take().unwrap().on_completed();
+    assert!(!completed.get());
+}
+
+// Until tests
+
+#[test]
+fn take_until_completes_when_trigger_pushes() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut shutdown = Subject::<(), NoError>::new();
+    let observer = TestObserver::new();
+    let subscription = source.observable()
+        .take_until(shutdown.observable())
+        .subscribe(observer.clone());
+    source.on_next(2);
+    source.on_next(3);
+    assert!(subscription.is_active());
+
+    shutdown.on_next(());
+    source.on_next(5);
+    observer.assert_items(&[2, 3]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+    assert_eq!(0, source.observer_count());
+    assert_eq!(0, shutdown.observer_count());
+}
+
+#[test]
+fn take_until_marbles() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b-c-|", values);
+    let trigger = hot::<u8, ()>(&scheduler, "----x", &[('x', 0)]);
+    let mut taken = source.take_until(trigger);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = taken.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    expect_marbles(&observer, "-a-b|", values);
+}
+
+#[test]
+fn take_until_ignores_trigger_completion_and_error() {
+    let mut source = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let _s1 = source.observable().take_until(None::<u8>).subscribe(observer.clone());
+    let _s2 = source.observable().take_until(Err::<u8, _>(())).subscribe(observer.clone());
+    source.on_next(2);
+    source.on_completed();
+    observer.assert_items(&[2, 2]);
+    observer.assert_completed();
+}
+
+#[test]
+fn take_until_does_not_subscribe_after_immediate_trigger() {
+    let source = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let subscription = source.observable().take_until(Some(1u8)).subscribe(observer.clone());
+    assert_eq!(0, source.observer_count());
+    assert!(!subscription.is_active());
+    observer.assert_completed();
+}
+
+#[test]
+fn take_until_trigger_in_handler() {
+    let mut source = Subject::<u8, ()>::new();
+    let shutdown = Subject::<(), NoError>::new();
+    let mut sink = shutdown.sink();
+    let completed = Rc::new(Cell::new(false));
+    let completed_next = completed.clone();
+    let completed_clone = completed.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = source.observable()
+        .take_until(shutdown.observable())
+        .subscribe_completed(move |x| {
+            received_clone.borrow_mut().push(x);
+            sink.on_next(());
+            // The observer completes only after it has handled the value.
+            assert!(!completed_next.get());
+        }, move || completed_clone.set(true));
+    source.on_next(2);
+    source.on_next(3);
+    assert_eq!(&[2u8], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn skip_until_pushes_after_trigger() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut start = Subject::<(), NoError>::new();
+    let observer = TestObserver::new();
+    let subscription = source.observable()
+        .skip_until(start.observable())
+        .subscribe(observer.clone());
+    source.on_next(2);
+    start.on_next(());
+    assert_eq!(0, start.observer_count());
+    source.on_next(3);
+    source.on_next(5);
+    source.on_completed();
+    observer.assert_items(&[3, 5]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn skip_until_marbles() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b-c-|", values);
+    let trigger = hot::<u8, ()>(&scheduler, "--x", &[('x', 0)]);
+    let mut skipped = source.skip_until(trigger);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = skipped.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    expect_marbles(&observer, "---b-c-|", values);
+}
+
+#[test]
+fn skip_until_with_take_until() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b-c-d-|", values);
+    let open = hot::<u8, ()>(&scheduler, "--x", &[('x', 0)]);
+    let close = hot::<u8, ()>(&scheduler, "------x", &[('x', 0)]);
+    let mut gated = source.skip_until(open).take_until(close);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = gated.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    expect_marbles(&observer, "---b-c|", values);
+}
+
+#[test]
+fn skip_until_ignores_trigger_completion() {
+    let observer = TestObserver::<u8, NoError>::new();
+    (&[2u8, 3]).map(|&x| x).skip_until(None::<u8>).subscribe(observer.clone());
+    observer.assert_items(&[]);
+    observer.assert_completed();
+}
+
+// Sample tests
+
+#[test]
+fn sample_pushes_latest_value() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-ab--c-d--|", values);
+    let sampler = hot::<(), ()>(&scheduler, "---x-x--x-x", &[('x', ())]);
+    let mut sampled = source.sample(sampler);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = sampled.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    // Value a is replaced before it is sampled, and the source completes before frame 10.
+    assert_eq!(vec![(frames(3), Notification::Next(2)),
+                    (frames(5), Notification::Next(3)),
+                    (frames(8), Notification::Next(4)),
+                    (frames(10), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn sample_drops_subscriptions_on_completion() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut sampler = Subject::<(), NoError>::new();
+    let observer = TestObserver::new();
+    let subscription = source.observable()
+        .sample(sampler.observable())
+        .subscribe(observer.clone());
+    sampler.on_next(());
+    source.on_next(2);
+    source.on_next(3);
+    sampler.on_next(());
+    sampler.on_next(());
+    source.on_next(5);
+    source.on_completed();
+    observer.assert_items(&[3]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+    assert_eq!(0, sampler.observer_count());
+}
+
+// Sequence equal tests
+
+#[test]
+fn sequence_equal_compares_values() {
+    let observer = TestObserver::<bool, NoError>::new();
+    (&[2u8, 3, 5]).map(|&x| x)
+        .sequence_equal((&[2u8, 3, 5]).map(|&x| x))
+        .subscribe(observer.clone());
+    observer.assert_items(&[true]);
+    observer.assert_completed();
+
+    let observer = TestObserver::<bool, NoError>::new();
+    (&[2u8, 3, 5]).map(|&x| x)
+        .sequence_equal((&[2u8, 3]).map(|&x| x))
+        .subscribe(observer.clone());
+    observer.assert_items(&[false]);
+    observer.assert_completed();
+}
+
+#[test]
+fn sequence_equal_stops_at_first_mismatch() {
+    let mut left = Subject::<u8, ()>::new();
+    let mut right = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let subscription = left.observable()
+        .sequence_equal(right.observable())
+        .subscribe(observer.clone());
+    left.on_next(2);
+    left.on_next(3);
+    right.on_next(2);
+    observer.assert_items(&[]);
+    right.on_next(7);
+    observer.assert_items(&[false]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+    assert_eq!(0, left.observer_count());
+    assert_eq!(0, right.observer_count());
+}
+
+// Merge tests
+
+#[test]
+fn merge_macro_merges_all_observables() {
+    let mut a = Subject::<u8, ()>::new();
+    let mut b = Subject::<u8, ()>::new();
+    let mut c = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = rx::merge!(a.observable(), b.observable(), c.observable(),)
+        .subscribe(observer.clone());
+    c.on_next(2);
+    a.on_next(3);
+    b.on_next(5);
+    a.on_completed();
+    c.on_completed();
+    observer.assert_items(&[2, 3, 5]);
+    observer.assert_not_terminated();
+    b.on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn merge_pushes_values_fed_back_by_the_observer() {
+    let a = Subject::<u8, ()>::new();
+    let b = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut sink = b.sink();
+    let _subscription = a.observable()
+        .merge(b.observable())
+        .subscribe_next(move |x| {
+            received_clone.borrow_mut().push(x);
+            if x == 1 {
+                sink.on_next(2);
+            }
+        });
+    (&a).on_next(1);
+    (&a).on_next(3);
+    assert_eq!(&[1u8, 2, 3], &received.borrow()[..]);
+}
+
+#[test]
+fn concat_macro_joins_all_observables() {
+    let observer = TestObserver::<u8, NoError>::new();
+    rx::concat!(Some(2u8), (&[3u8, 5]).map(|&x| x), None, Some(7)).subscribe(observer.clone());
+    observer.assert_items(&[2, 3, 5, 7]);
+    observer.assert_completed();
+}
+
+#[test]
+fn merge_either_tags_values_with_their_source() {
+    let mut control = Subject::<&'static str, ()>::new();
+    let mut data = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let subscription = control.observable()
+        .merge_either(data.observable())
+        .subscribe(observer.clone());
+    data.on_next(2);
+    control.on_next("pause");
+    data.on_next(3);
+    control.on_completed();
+    data.on_next(5);
+    observer.assert_items(&[Either::Right(2), Either::Left("pause"), Either::Right(3),
+                            Either::Right(5)]);
+    observer.assert_not_terminated();
+    data.on_completed();
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn merge_either_fails_when_either_source_fails() {
+    let control = Subject::<&'static str, ()>::new();
+    let data = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = control.observable()
+        .merge_either(data.observable())
+        .subscribe(observer.clone());
+    data.on_error(());
+    observer.assert_error(());
+    assert_eq!(0, control.observer_count());
+}
+
+#[test]
+fn combine_latest_pushes_latest_values() {
+    let mut a = Subject::<u8, ()>::new();
+    let mut b = Subject::<&'static str, ()>::new();
+    let mut c = Subject::<bool, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = (a.observable(), b.observable(), c.observable())
+        .combine_latest()
+        .subscribe(observer.clone());
+    a.on_next(1);
+    b.on_next("x");
+    a.on_next(2);
+    observer.assert_items(&[]);
+    c.on_next(true);
+    b.on_next("y");
+    observer.assert_items(&[(2, "x", true), (2, "y", true)]);
+    a.on_completed();
+    b.on_completed();
+    c.on_next(false);
+    observer.assert_items(&[(2, "x", true), (2, "y", true), (2, "y", false)]);
+    c.on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn combine_latest_pushes_values_fed_back_by_the_observer() {
+    let a = Subject::<u8, ()>::new();
+    let b = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut sink = b.sink();
+    let _subscription = (a.observable(), b.observable())
+        .combine_latest()
+        .subscribe_next(move |x| {
+            received_clone.borrow_mut().push(x);
+            if x == (2, 1) {
+                sink.on_next(3);
+            }
+        });
+    (&b).on_next(1);
+    (&a).on_next(2);
+    assert_eq!(&[(2u8, 1u8), (2, 3)], &received.borrow()[..]);
+}
+
+#[test]
+fn combine_latest_completes_when_source_completes_empty() {
+    let mut a = Subject::<u8, ()>::new();
+    let b = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let subscription = (a.observable(), b.observable())
+        .combine_latest()
+        .subscribe(observer.clone());
+    a.on_next(1);
+    b.on_completed();
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+    assert_eq!(0, a.observer_count());
+}
+
+#[test]
+fn combine_latest_of_eight() {
+    let observer = TestObserver::new();
+    (Some(1u8), Some('b'), Some(3u16), Some("d"), Some(5u32), Some(false), Some(7u64), Some(8i8))
+        .combine_latest()
+        .subscribe(observer.clone());
+    observer.assert_items(&[(1, 'b', 3, "d", 5, false, 7, 8)]);
+    observer.assert_completed();
+}
+
+// Flatten tests
+
+#[test]
+fn switch_mirrors_latest_inner_observable() {
+    let mut outer = Subject::<SubjectObservable<u8, ()>, ()>::new();
+    let mut first = Subject::<u8, ()>::new();
+    let mut second = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = outer.observable().switch().subscribe(observer.clone());
+    outer.on_next(first.observable());
+    first.on_next(1);
+    outer.on_next(second.observable());
+    assert_eq!(0, first.observer_count());
+    first.on_next(2);
+    second.on_next(3);
+    observer.assert_items(&[1, 3]);
+
+    outer.on_completed();
+    observer.assert_not_terminated();
+    second.on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn switch_pushes_values_fed_back_by_the_observer() {
+    let outer = Subject::<SubjectObservable<u8, ()>, ()>::new();
+    let first = Subject::<u8, ()>::new();
+    let second = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut outer_sink = outer.sink();
+    let mut second_sink = second.sink();
+    let mut next = Some(second.observable());
+    let _subscription = outer.observable().switch().subscribe_next(move |x| {
+        received_clone.borrow_mut().push(x);
+        if let Some(inner) = next.take() {
+            outer_sink.on_next(inner);
+            second_sink.on_next(2);
+        }
+    });
+    (&outer).on_next(first.observable());
+    (&first).on_next(1);
+    assert_eq!(&[1u8, 2], &received.borrow()[..]);
+}
+
+#[test]
+fn switch_fails_when_latest_inner_observable_fails() {
+    let mut outer = Subject::<SubjectObservable<u8, &'static str>, &'static str>::new();
+    let inner = Subject::<u8, &'static str>::new();
+    let observer = TestObserver::new();
+    let subscription = outer.observable().switch().subscribe(observer.clone());
+    outer.on_next(inner.observable());
+    inner.on_error("failed");
+    observer.assert_error("failed");
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn exhaust_map_ignores_values_while_inner_observable_is_active() {
+    let mut clicks = Subject::<u8, ()>::new();
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    let requests_clone = requests.clone();
+    let observer = TestObserver::new();
+    let _subscription = clicks.observable()
+        .exhaust_map(move |click| {
+            let request = Rc::new(Subject::<u8, ()>::new());
+            requests_clone.borrow_mut().push((click, request.clone()));
+            request.observable()
+        })
+        .subscribe(observer.clone());
+    clicks.on_next(1);
+    clicks.on_next(2);
+    assert_eq!(1, requests.borrow().len());
+    let first = requests.borrow()[0].1.clone();
+    (&*first).on_next(10);
+    (&*first).on_completed();
+    clicks.on_next(3);
+    assert_eq!(vec![1, 3], requests.borrow().iter().map(|&(click, _)| click).collect::<Vec<_>>());
+    observer.assert_items(&[10]);
+
+    clicks.on_completed();
+    observer.assert_not_terminated();
+    let second = requests.borrow()[1].1.clone();
+    (&*second).on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn expand_walks_tree_breadth_first() {
+    let observer = TestObserver::new();
+    let children = |node: u8| if node < 4 { vec![node * 2, node * 2 + 1] } else { vec![] };
+    Some(1).expand(move |node| Some(children(node)).flatten_iter()).subscribe(observer.clone());
+    observer.assert_items(&[1, 2, 3, 4, 5, 6, 7]);
+    observer.assert_completed();
+}
+
+#[test]
+fn expand_pushes_values_fed_back_by_the_observer() {
+    let source = Subject::<u8, ()>::new();
+    let inner = Rc::new(Subject::<u8, ()>::new());
+    let inner_clone = inner.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut sink = source.sink();
+    let _subscription = source.observable()
+        .expand(move |_| inner_clone.observable())
+        .subscribe_next(move |x| {
+            received_clone.borrow_mut().push(x);
+            if x == 2 {
+                sink.on_next(3);
+            }
+        });
+    (&source).on_next(1);
+    (&*inner).on_next(2);
+    assert_eq!(&[1u8, 2, 3], &received.borrow()[..]);
+}
+
+// Join tests
+
+#[test]
+fn join_pairs_values_with_overlapping_lifetimes() {
+    let mut left = Subject::<u8, ()>::new();
+    let mut right = Subject::<u8, ()>::new();
+    let left_close = Rc::new(Subject::<(), ()>::new());
+    let left_close_clone = left_close.clone();
+    let observer = TestObserver::new();
+    let subscription = left.observable()
+        .join(right.observable(),
+              move |_| left_close_clone.observable(),
+              |_| Never::<(), ()>::new(),
+              |&l, &r| (l, r))
+        .subscribe(observer.clone());
+    left.on_next(1);
+    right.on_next(10);
+    left.on_next(2);
+    observer.assert_items(&[(1, 10), (2, 10)]);
+    (&*left_close).on_next(());
+    right.on_next(20);
+    left.on_next(3);
+    observer.assert_items(&[(1, 10), (2, 10), (3, 10), (3, 20)]);
+
+    // The left value that is still open can match more right values.
+    left.on_completed();
+    assert!(subscription.is_active());
+    right.on_next(30);
+    (&*left_close).on_next(());
+    observer.assert_items(&[(1, 10), (2, 10), (3, 10), (3, 20), (3, 30)]);
+    observer.assert_completed();
+    assert_eq!(0, right.observer_count());
+    assert_eq!(0, left_close.observer_count());
+}
+
+#[test]
+fn group_join_pushes_a_window_per_left_value() {
+    let mut left = Subject::<u8, ()>::new();
+    let mut right = Subject::<u8, ()>::new();
+    let left_close = Rc::new(Subject::<(), ()>::new());
+    let left_close_clone = left_close.clone();
+    let received: Rc<RefCell<Vec<Vec<u8>>>> = Rc::new(RefCell::new(Vec::new()));
+    let subscriptions: Rc<RefCell<Vec<Box<dyn Subscription>>>> = Rc::new(RefCell::new(Vec::new()));
+    let (received_clone, subscriptions_clone) = (received.clone(), subscriptions.clone());
+    let observer = TestObserver::new();
+    let _subscription = left.observable()
+        .group_join(right.observable(),
+                    move |_| left_close_clone.observable(),
+                    |_| Never::<(), ()>::new(),
+                    move |l, mut window: SubjectObservable<u8, ()>| {
+                        let index = received_clone.borrow().len();
+                        received_clone.borrow_mut().push(Vec::new());
+                        let received = received_clone.clone();
+                        let subscription = window.subscribe_next(move |x| {
+                            received.borrow_mut()[index].push(x)
+                        });
+                        subscriptions_clone.borrow_mut().push(Box::new(subscription));
+                        l
+                    })
+        .subscribe(observer.clone());
+    right.on_next(10);
+    left.on_next(1);
+    right.on_next(20);
+    left.on_next(2);
+    (&*left_close).on_next(());
+    right.on_next(30);
+    left.on_next(3);
+    assert_eq!(vec![vec![10, 20], vec![10, 20], vec![10, 20, 30]], *received.borrow());
+    observer.assert_items(&[1, 2, 3]);
+
+    left.on_completed();
+    (&*left_close).on_next(());
+    observer.assert_completed();
+}
+
+#[test]
+fn join_fails_when_either_source_fails() {
+    let left = Subject::<u8, &'static str>::new();
+    let right = Subject::<u8, &'static str>::new();
+    let observer = TestObserver::new();
+    let subscription = left.observable()
+        .join(right.observable(),
+              |_| Never::<(), ()>::new(),
+              |_| Never::<(), ()>::new(),
+              |&l, &r| l + r)
+        .subscribe(observer.clone());
+    right.on_error("failed");
+    observer.assert_error("failed");
+    assert!(!subscription.is_active());
+    assert_eq!(0, left.observer_count());
+}
+
+#[test]
+fn join_pushes_values_fed_back_by_the_observer() {
+    let left = Subject::<u8, ()>::new();
+    let right = Subject::<u8, ()>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let mut sink = right.sink();
+    let _subscription = left.observable()
+        .join(right.observable(),
+              |_| Never::<(), ()>::new(),
+              |_| Never::<(), ()>::new(),
+              |&l, &r| l + r)
+        .subscribe_next(move |x| {
+            received_clone.borrow_mut().push(x);
+            if x < 20 {
+                sink.on_next(20);
+            }
+        });
+    (&right).on_next(10);
+    (&left).on_next(1);
+    assert_eq!(&[11u8, 21], &received.borrow()[..]);
+}
+
+// Aggregate tests
+
+#[test]
+fn single_pushes_the_only_value() {
+    let observer = TestObserver::new();
+    Some(2u8).single().subscribe(observer.clone());
+    observer.assert_items(&[2]);
+    observer.assert_completed();
+}
+
+#[test]
+fn single_fails_without_exactly_one_value() {
+    let observer = TestObserver::<u8, SingleError<NoError>>::new();
+    None::<u8>.single().subscribe(observer.clone());
+    assert_eq!(vec![(frames(0), Notification::Error(SingleError::Empty))],
+               observer.notifications());
+
+    let mut source = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let _subscription = source.observable().single().subscribe(observer.clone());
+    source.on_next(2);
+    source.on_next(3);
+    source.on_next(5);
+    source.on_completed();
+    assert_eq!(vec![(frames(0), Notification::Error(SingleError::Multiple))],
+               observer.notifications());
+}
+
+#[test]
+fn single_or_default_pushes_default_when_empty() {
+    let observer = TestObserver::new();
+    None::<u8>.single_or_default(7).subscribe(observer.clone());
+    observer.assert_items(&[7]);
+    observer.assert_completed();
+}
+
+#[test]
+fn min_and_max_by_key_push_the_extreme_value() {
+    let words = &["pear", "fig", "banana", "kiwi", "cherry"];
+    let observer = TestObserver::new();
+    words.map(|&w| w).min_by_key(|w| w.len()).subscribe(observer.clone());
+    observer.assert_items(&["fig"]);
+    observer.assert_completed();
+
+    let observer = TestObserver::new();
+    words.map(|&w| w).max_by_key(|w| w.len()).subscribe(observer.clone());
+    observer.assert_items(&["cherry"]);
+    observer.assert_completed();
+
+    let observer = TestObserver::<u8, NoError>::new();
+    None::<u8>.max_by_key(|&x| x).subscribe(observer.clone());
+    observer.assert_items(&[]);
+    observer.assert_completed();
+}
+
+#[test]
+fn stats_pushes_running_statistics() {
+    let observer = TestObserver::new();
+    (&[2u8, 4, 4, 4, 5, 5, 7, 9]).map(|&x| x).stats().subscribe(observer.clone());
+    let stats: Vec<Stats> = observer.notifications().into_iter()
+        .filter_map(|(_, n)| match n { Notification::Next(s) => Some(s), _ => None })
+        .collect();
+    assert_eq!(8, stats.len());
+    assert_eq!(Stats { count: 1, mean: 2.0, variance: 0.0, min: 2.0, max: 2.0 }, stats[0]);
+    assert_eq!(Stats { count: 8, mean: 5.0, variance: 4.0, min: 2.0, max: 9.0 }, stats[7]);
+    assert_eq!(2.0, stats[7].std_dev());
+}
+
+#[test]
+fn stats_final_pushes_once() {
+    let observer = TestObserver::new();
+    (&[1.5f32, 2.5]).map(|&x| x).stats_final().subscribe(observer.clone());
+    observer.assert_items(&[Stats { count: 2, mean: 2.0, variance: 0.25, min: 1.5, max: 2.5 }]);
+    observer.assert_completed();
+}
+
+#[test]
+fn histogram_counts_values_per_bucket() {
+    let observer = TestObserver::new();
+    (&[1u8, 5, 10, 11, 50, 200]).map(|&x| x)
+        .histogram(vec![100.0, 10.0])
+        .subscribe(observer.clone());
+    let histogram = observer.items().pop().unwrap();
+    assert_eq!(&[10.0, 100.0], histogram.bounds());
+    assert_eq!(&[3, 2, 1], histogram.counts());
+    assert_eq!(6, histogram.count());
+    observer.assert_completed();
+}
+
+#[test]
+fn percentiles_are_within_relative_accuracy() {
+    let values: Vec<u32> = (1..1001).collect();
+    let observer = TestObserver::new();
+    (&values).map(|&x| x)
+        .percentiles(&[0.5, 0.99])
+        .subscribe(observer.clone());
+    let estimates = observer.items().pop().unwrap();
+    assert!((estimates[0] - 500.0).abs() <= 5.0, "median {}", estimates[0]);
+    assert!((estimates[1] - 990.0).abs() <= 9.9, "99th percentile {}", estimates[1]);
+}
+
+#[test]
+fn percentiles_snapshot_on_trigger() {
+    let mut source = Subject::<f64, ()>::new();
+    let mut trigger = Subject::<(), NoError>::new();
+    let observer = TestObserver::new();
+    let _subscription = source.observable()
+        .percentiles(&[0.0, 1.0])
+        .snapshot_on(trigger.observable())
+        .subscribe(observer.clone());
+    source.on_next(2.0);
+    trigger.on_next(());
+    source.on_next(-4.0);
+    trigger.on_next(());
+    source.on_completed();
+    let snapshots = observer.items();
+    assert_eq!(3, snapshots.len());
+    assert!((snapshots[0][0] - 2.0).abs() < 0.02 && (snapshots[0][1] - 2.0).abs() < 0.02);
+    assert!((snapshots[1][0] + 4.0).abs() < 0.04 && (snapshots[1][1] - 2.0).abs() < 0.02);
+    assert_eq!(snapshots[1], snapshots[2]);
+    assert_eq!(0, trigger.observer_count());
+}
+
+#[test]
+fn percentiles_complete_while_pushing_snapshot() {
+    let source = Rc::new(Subject::<f64, ()>::new());
+    let source_clone = source.clone();
+    let mut trigger = Subject::<(), NoError>::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = source.observable()
+        .percentiles(&[1.0])
+        .snapshot_on(trigger.observable())
+        .subscribe_completed(move |snapshot| {
+            received_clone.borrow_mut().push(snapshot);
+            // The source completes while the triggered snapshot is handled.
+            (&*source_clone).on_completed();
+        }, move || completed_clone.set(true));
+    (&*source).on_next(2.0);
+    trigger.on_next(());
+    assert_eq!(2, received.borrow().len());
+    assert!(completed.get());
+}
+
+#[test]
+fn ema_smooths_values() {
+    let observer = TestObserver::new();
+    (&[4u8, 8, 8, 0]).map(|&x| x).ema(0.5).subscribe(observer.clone());
+    observer.assert_items(&[4.0, 6.0, 7.0, 3.5]);
+    observer.assert_completed();
+
+    let observer = TestObserver::new();
+    (&[4u8, 8, 8, 0]).map(|&x| x).ema(0.5).warm_up(2).subscribe(observer.clone());
+    observer.assert_items(&[7.0, 3.5]);
+}
+
+// Pull tests
+
+#[test]
+fn pull_iter_pushes_on_request() {
+    let mut source = rx::pull_iter(vec![2u8, 3, 5]);
+    let observer = TestObserver::new();
+    let subscription = source.subscribe_pull(observer.clone());
+    observer.assert_items(&[]);
+    subscription.request(2);
+    observer.assert_items(&[2, 3]);
+    observer.assert_not_terminated();
+    subscription.request(2);
+    observer.assert_items(&[2, 3, 5]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+}
+
+/// An observer that requests a value for every value, until it has five.
+struct RequestingObserver {
+    received: Rc<RefCell<Vec<u32>>>,
+    subscription: Rc<RefCell<Option<Box<dyn PullSubscription>>>>,
+}
+
+impl Observer<u32, NoError> for RequestingObserver {
+    fn on_next(&mut self, item: u32) {
+        self.received.borrow_mut().push(item);
+        if self.received.borrow().len() < 5 {
+            self.subscription.borrow().as_ref().unwrap().request(1);
+        }
+    }
+
+    fn on_completed(self) {}
+
+    fn on_error(self, _error: NoError) {}
+}
+
+#[test]
+fn pull_iter_request_in_handler() {
+    let mut source = rx::pull_iter(0..1_000_000u32);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let subscription: Rc<RefCell<Option<Box<dyn PullSubscription>>>> = Rc::new(RefCell::new(None));
+    let subs = source.subscribe_pull(RequestingObserver {
+        received: received.clone(),
+        subscription: subscription.clone(),
+    });
+    *subscription.borrow_mut() = Some(Box::new(subs));
+    subscription.borrow().as_ref().unwrap().request(1);
+    assert_eq!(&[0u32, 1, 2, 3, 4], &received.borrow()[..]);
+}
+
+#[test]
+fn into_push_requests_in_batches() {
+    let observer = TestObserver::new();
+    rx::pull_iter(vec![2u8, 3, 5, 7, 11]).into_push(2).subscribe(observer.clone());
+    observer.assert_items(&[2, 3, 5, 7, 11]);
+    observer.assert_completed();
+}
+
+#[test]
+fn merge_sorted_merges_sorted_values() {
+    let mut other = rx::pull_iter(vec![(2u8, 'b'), (3, 'b'), (7, 'b'), (9, 'b')]);
+    let observer = TestObserver::new();
+    rx::pull_iter(vec![(1u8, 'a'), (4, 'a'), (7, 'a')])
+        .merge_sorted(&mut other, |x, y| x.0.cmp(&y.0))
+        .into_push(usize::MAX)
+        .subscribe(observer.clone());
+    observer.assert_items(&[(1, 'a'), (2, 'b'), (3, 'b'), (4, 'a'), (7, 'a'), (7, 'b'), (9, 'b')]);
+    observer.assert_completed();
+
+    let observer = TestObserver::new();
+    other.into_push(usize::MAX).subscribe(observer.clone());
+    observer.assert_items(&[(2, 'b'), (3, 'b'), (7, 'b'), (9, 'b')]);
+}
+
+#[test]
+fn merge_sorted_waits_for_both_sides() {
+    let mut left = Subject::<u8, ()>::new();
+    let mut right = Subject::<u8, ()>::new();
+    let mut merged = left.observable().into_pull(4, OverflowPolicy::Panic)
+        .merge_sorted(right.observable().into_pull(4, OverflowPolicy::Panic), |x, y| x.cmp(y));
+    let observer = TestObserver::new();
+    let subscription = merged.subscribe_pull(observer.clone());
+    subscription.request(2);
+    left.on_next(1);
+    left.on_next(5);
+    observer.assert_items(&[]);
+    right.on_next(3);
+    observer.assert_items(&[1, 3]);
+    right.on_completed();
+    observer.assert_items(&[1, 3]);
+    subscription.request(1);
+    observer.assert_items(&[1, 3, 5]);
+    observer.assert_not_terminated();
+    left.on_completed();
+    observer.assert_completed();
+}
+
+#[test]
+fn into_pull_queues_until_requested() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut pull = subject.observable().into_pull(2, OverflowPolicy::DropOldest);
+    let observer = TestObserver::new();
+    let subscription = pull.subscribe_pull(observer.clone());
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(5);
+    subject.on_completed();
+    observer.assert_items(&[]);
+    subscription.request(1);
+    observer.assert_items(&[3]);
+    observer.assert_not_terminated();
+    subscription.request(1);
+    observer.assert_items(&[3, 5]);
+    observer.assert_completed();
+}
+
+#[test]
+fn into_pull_drop_newest() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut pull = subject.observable().into_pull(1, OverflowPolicy::DropNewest);
+    let observer = TestObserver::new();
+    let subscription = pull.subscribe_pull(observer.clone());
+    subject.on_next(2);
+    subject.on_next(3);
+    subscription.request(1);
+    subject.on_next(5);
+    subject.on_next(7);
+    subscription.request(5);
+    subject.on_error(());
+    observer.assert_items(&[2, 5]);
+    observer.assert_error(());
+}
+
+#[test]
+#[should_panic(expected = "into_pull queue is full")]
+fn into_pull_panics_when_full() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut pull = subject.observable().into_pull(1, OverflowPolicy::Panic);
+    let _subscription = pull.subscribe_pull(TestObserver::new());
+    subject.on_next(2);
+    subject.on_next(3);
+}
+
+#[test]
+fn into_pull_into_push_round_trip() {
+    let mut subject = Subject::<u8, ()>::new();
+    let observer = TestObserver::new();
+    let mut round_trip = subject.observable()
+        .into_pull(1, OverflowPolicy::Panic)
+        .into_push(1);
+    let subscription = round_trip.subscribe(observer.clone());
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_completed();
+    observer.assert_items(&[2, 3]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn on_backpressure_drop_passes_unrequested_values() {
+    let mut subject = Subject::<u8, ()>::new();
+    let dropped = Rc::new(RefCell::new(Vec::new()));
+    let dropped_clone = dropped.clone();
+    let mut pull = subject.observable()
+        .on_backpressure_drop(move |x| dropped_clone.borrow_mut().push(x));
+    let observer = TestObserver::new();
+    let subscription = pull.subscribe_pull(observer.clone());
+    subject.on_next(2);
+    subscription.request(2);
+    subject.on_next(3);
+    subject.on_next(5);
+    subject.on_next(7);
+    observer.assert_items(&[3, 5]);
+    assert_eq!(&[2u8, 7], &dropped.borrow()[..]);
+}
+
+#[test]
+fn on_backpressure_latest_keeps_latest_value() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut pull = subject.observable().on_backpressure_latest();
+    let observer = TestObserver::new();
+    let subscription = pull.subscribe_pull(observer.clone());
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(5);
+    subscription.request(1);
+    subject.on_next(7);
+    subject.on_next(11);
+    subscription.request(1);
+    observer.assert_items(&[5, 11]);
+}
+
+#[test]
+fn on_backpressure_buffer_pushes_buffered_values_on_request() {
+    let mut subject = Subject::<u8, ()>::new();
+    let mut pull = subject.observable().on_backpressure_buffer(2, OverflowPolicy::DropNewest);
+    let observer = TestObserver::new();
+    let subscription = pull.subscribe_pull(observer.clone());
+    subscription.request(1);
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_next(5);
+    subject.on_next(7);
+    subscription.request(10);
+    observer.assert_items(&[2, 3, 5]);
+}
+
+// Pausable tests
+
+#[test]
+fn pausable_drops_values_while_paused() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut control = Subject::<bool, NoError>::new();
+    let observer = TestObserver::new();
+    let _subscription = source.observable()
+        .pausable(control.observable())
+        .subscribe(observer.clone());
+    source.on_next(2);
+    control.on_next(false);
+    source.on_next(3);
+    control.on_next(true);
+    source.on_next(5);
+    source.on_completed();
+    observer.assert_items(&[2, 5]);
+    observer.assert_completed();
+}
+
+#[test]
+fn pausable_buffered_flushes_on_resume() {
+    let mut source = Subject::<u8, ()>::new();
+    let mut control = BehaviorSubject::<bool, NoError>::new(false);
+    let observer = TestObserver::new();
+    let subscription = source.observable()
+        .pausable(control.observable())
+        .buffered(2, OverflowPolicy::DropOldest)
+        .subscribe(observer.clone());
+    source.on_next(2);
+    source.on_next(3);
+    source.on_next(5);
+    source.on_completed();
+    observer.assert_items(&[]);
+    assert!(subscription.is_active());
+
+    control.on_next(true);
+    observer.assert_items(&[3, 5]);
+    observer.assert_completed();
+    assert!(!subscription.is_active());
+}
+
+// Rate limit tests
+
+#[test]
+fn rate_limit_delays_values_beyond_burst() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-(abcd)|", values);
+    let mut limited = source.rate_limit(frames(3), 2, scheduler.clone());
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = limited.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    assert_eq!(vec![(frames(1), Notification::Next(1)),
+                    (frames(1), Notification::Next(2)),
+                    (frames(4), Notification::Next(3)),
+                    (frames(7), Notification::Next(4)),
+                    (frames(7), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn rate_limit_drop_excess() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-(abc)---d-|", values);
+    let mut limited = source.rate_limit(frames(3), 2, scheduler.clone()).drop_excess();
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = limited.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    expect_marbles(&observer, "-(ab)----d-|", values);
+}
+
+#[test]
+fn rate_limit_fails_while_pushing_queued_value() {
+    let scheduler = TestScheduler::new();
+    let subject = Rc::new(Subject::<u8, u8>::new());
+    let subject_clone = subject.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let failed = Rc::new(Cell::new(None));
+    let failed_clone = failed.clone();
+    let _subscription = subject.observable()
+        .rate_limit(frames(3), 1, scheduler.clone())
+        .subscribe_error(move |x| {
+            received_clone.borrow_mut().push(x);
+            // The source fails while the queued value is handled.
+            if x == 2 {
+                (&*subject_clone).on_error(7);
+            }
+        }, || {}, move |error| failed_clone.set(Some(error)));
+    (&*subject).on_next(1);
+    (&*subject).on_next(2);
+    scheduler.advance_by(frames(10));
+    assert_eq!(&[1u8, 2], &received.borrow()[..]);
+    assert_eq!(Some(7), failed.get());
+}
+
+#[test]
+fn circuit_breaker_opens_after_failures_and_probes_after_cooldown() {
+    let scheduler = TestScheduler::new();
+    let is_failing = Rc::new(Cell::new(true));
+    let is_failing_clone = is_failing.clone();
+    let subscriptions = Rc::new(Cell::new(0));
+    let subscriptions_clone = subscriptions.clone();
+    let source = rx::create(move |mut emitter: Emitter<u8, u8>| {
+        subscriptions_clone.set(subscriptions_clone.get() + 1);
+        if is_failing_clone.get() {
+            emitter.error(7);
+        } else {
+            emitter.next(1);
+            emitter.completed();
+        }
+    });
+    let policy = CircuitBreakerPolicy { failure_threshold: 2, cooldown: frames(5) };
+    let mut guarded = source.circuit_breaker(policy, scheduler.clone());
+    let mut subscribe = || {
+        let observer = TestObserver::new();
+        guarded.subscribe(observer.clone());
+        observer
+    };
+    subscribe().assert_error(CircuitError::Failed(7));
+    subscribe().assert_error(CircuitError::Failed(7));
+    subscribe().assert_error(CircuitError::Open);
+    assert_eq!(2, subscriptions.get());
+
+    // After the cooldown, a successful probe closes the circuit again.
+    scheduler.advance_by(frames(5));
+    is_failing.set(false);
+    subscribe().assert_items(&[1]);
+    subscribe().assert_completed();
+    assert_eq!(4, subscriptions.get());
+}
+
+#[test]
+fn circuit_breaker_stays_open_on_values_of_older_subscriptions() {
+    let scheduler = TestScheduler::new();
+    let emitters = Rc::new(RefCell::new(Vec::new()));
+    let emitters_clone = emitters.clone();
+    let source = rx::create(move |emitter: Emitter<u8, u8>| {
+        emitters_clone.borrow_mut().push(emitter);
+    });
+    let policy = CircuitBreakerPolicy { failure_threshold: 1, cooldown: frames(5) };
+    let mut guarded = source.circuit_breaker(policy, scheduler.clone());
+    let older = TestObserver::new();
+    let _older_subscription = guarded.subscribe(older.clone());
+    let failing = TestObserver::new();
+    let _failing_subscription = guarded.subscribe(failing.clone());
+    emitters.borrow_mut().pop().unwrap().error(7);
+    failing.assert_error(CircuitError::Failed(7));
+
+    // The older subscription is still running, but its values do not close the circuit.
+    emitters.borrow_mut()[0].next(1);
+    older.assert_items(&[1]);
+    let late = TestObserver::new();
+    let _late_subscription = guarded.subscribe(late.clone());
+    late.assert_error(CircuitError::Open);
+    assert_eq!(1, emitters.borrow().len());
+}
+
+// Time tests
+
+#[test]
+fn test_scheduler_clock_follows_virtual_time() {
+    let scheduler = TestScheduler::new();
+    let start = scheduler.now_instant();
+    scheduler.advance_by(frames(3));
+    assert_eq!(frames(3), scheduler.now_instant() - start);
+}
+
+#[test]
+fn timestamp_reads_scheduler_clock() {
+    let scheduler = TestScheduler::new();
+    let start = scheduler.now_instant();
+    let values = &[('a', 1u8), ('b', 2)];
+    let source = hot::<u8, ()>(&scheduler, "-a---b|", values);
+    let mut stamped = source.timestamp(scheduler.clone())
+        .map(move |(instant, x)| (instant - start, x));
+    let observer = TestObserver::new();
+    let _subscription = stamped.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    observer.assert_items(&[(frames(1), 1), (frames(5), 2)]);
+    observer.assert_completed();
+}
+
+#[test]
+fn time_interval_measures_from_previous_value() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3)];
+    let source = hot::<u8, ()>(&scheduler, "--a---bc|", values);
+    let mut intervals = source.time_interval(scheduler.clone());
+    let observer = TestObserver::new();
+    scheduler.advance_by(frames(1));
+    let _subscription = intervals.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    observer.assert_items(&[(frames(1), 1), (frames(4), 2), (frames(1), 3)]);
+    observer.assert_completed();
+}
+
+#[test]
+fn distinct_within_drops_recent_duplicates() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2)];
+    let source = hot::<u8, ()>(&scheduler, "-a-a-ab-a---a|", values);
+    let mut distinct = source.distinct_within(frames(3), scheduler.clone());
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = distinct.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    expect_marbles(&observer, "-a---ab-a---a|", values);
+}
+
+#[test]
+fn record_and_replay_with_scaled_timing() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2)];
+    let mut source = hot::<u8, ()>(&scheduler, "--a-b|", values);
+    let subscription = source.record(scheduler.clone());
+    scheduler.advance_by(frames(10));
+    let recording = subscription.recording();
+    assert!(recording.is_terminated());
+    assert_eq!(&[(frames(2), Notification::Next(1)),
+                 (frames(4), Notification::Next(2)),
+                 (frames(5), Notification::Completed)],
+               recording.notifications());
+
+    let mut replay = recording.scaled(2.0).replay(&scheduler);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = replay.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    assert_eq!(vec![(frames(14), Notification::Next(1)),
+                    (frames(18), Notification::Next(2)),
+                    (frames(20), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn delay_each_delays_by_item() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 4u8), ('b', 1), ('c', 1)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b-c|", values);
+    let mut delayed = source.delay_each(|&x| frames(x as u32), scheduler.clone());
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = delayed.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    // The value at frame 3 overtakes the one at frame 1, and completion waits.
+    assert_eq!(vec![(frames(4), Notification::Next(1)),
+                    (frames(5), Notification::Next(4)),
+                    (frames(6), Notification::Next(1)),
+                    (frames(6), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn delay_each_drops_pending_values_on_error() {
+    let scheduler = TestScheduler::new();
+    let mut source = Subject::<u8, u8>::new();
+    let observer = TestObserver::new();
+    let subscription = source.observable()
+        .delay_each(|_| frames(2), scheduler.clone())
+        .subscribe(observer.clone());
+    source.on_next(2);
+    scheduler.advance_by(frames(1));
+    source.on_next(3);
+    scheduler.advance_by(frames(1));
+    source.on_error(7);
+    scheduler.advance_by(frames(5));
+    assert_eq!(vec![Notification::Next(2), Notification::Error(7)],
+               observer.notifications().into_iter().map(|(_, n)| n).collect::<Vec<_>>());
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn heartbeat_fills_silence() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2)];
+    let source = hot::<u8, ()>(&scheduler, "-a------b-|", values);
+    let mut beating = source.heartbeat(frames(3), || 0, scheduler.clone());
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = beating.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    assert_eq!(vec![(frames(1), Notification::Next(1)),
+                    (frames(4), Notification::Next(0)),
+                    (frames(7), Notification::Next(0)),
+                    (frames(8), Notification::Next(2)),
+                    (frames(10), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn heartbeat_completes_while_pushing_filler() {
+    let scheduler = TestScheduler::new();
+    let subject = Rc::new(Subject::<u8, ()>::new());
+    let subject_clone = subject.clone();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let _subscription = subject.observable()
+        .heartbeat(frames(3), || 0, scheduler.clone())
+        .subscribe_completed(move |x| {
+            received_clone.borrow_mut().push(x);
+            // The source completes while the filler value is handled.
+            (&*subject_clone).on_completed();
+        }, move || completed_clone.set(true));
+    scheduler.advance_by(frames(10));
+    assert_eq!(&[0u8], &received.borrow()[..]);
+    assert!(completed.get());
+}
+
+#[test]
+fn watchdog_fails_on_silence() {
+    let scheduler = TestScheduler::new();
+    let mut source = Subject::<u8, &'static str>::new();
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let subscription = source.observable()
+        .watchdog(frames(3), || "silent", scheduler.clone())
+        .subscribe(observer.clone());
+    scheduler.advance_by(frames(2));
+    source.on_next(1);
+    scheduler.advance_by(frames(2));
+    source.on_next(2);
+    scheduler.advance_by(frames(10));
+    assert_eq!(vec![(frames(2), Notification::Next(1)),
+                    (frames(4), Notification::Next(2)),
+                    (frames(7), Notification::Error("silent"))],
+               observer.notifications());
+    assert!(!subscription.is_active());
+    assert_eq!(0, source.observer_count());
+}
+
+// Buffer tests
+
+#[test]
+fn buffer_time_or_count_pushes_full_batches() {
+    let observer = TestObserver::new();
+    (&[1u8, 2, 3, 4, 5]).map(|&x| x)
+        .buffer_time_or_count(Duration::from_secs(1), 2, TestScheduler::new())
+        .subscribe(observer.clone());
+    observer.assert_items(&[vec![1, 2], vec![3, 4], vec![5]]);
+    observer.assert_completed();
+}
+
+#[test]
+fn buffer_time_or_count_pushes_when_time_is_up() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b---c-d-|", values);
+    let mut batches = source.buffer_time_or_count(frames(4), 3, scheduler.clone());
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = batches.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    assert_eq!(vec![(frames(5), Notification::Next(vec![1, 2])),
+                    (frames(11), Notification::Next(vec![3, 4])),
+                    (frames(11), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn buffer_with_closes_batches_at_boundary() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b---c-d-|", values);
+    let boundary = hot::<(), ()>(&scheduler, "----x-x-x---", &[('x', ())]);
+    let mut batches = source.buffer_with(boundary);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = batches.subscribe(observer.clone());
+    scheduler.advance_by(frames(20));
+    // The boundary at frame 6 finds an empty batch, so nothing is pushed then.
+    assert_eq!(vec![(frames(4), Notification::Next(vec![1, 2])),
+                    (frames(8), Notification::Next(vec![3])),
+                    (frames(11), Notification::Next(vec![4])),
+                    (frames(11), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn window_time_opens_a_window_every_duration() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3), ('d', 4)];
+    let source = hot::<u8, ()>(&scheduler, "-a-b---c--d|", values);
+    let mut windows = source.window_time(frames(4), scheduler.clone());
+    let received: Rc<RefCell<Vec<Vec<u8>>>> = Rc::new(RefCell::new(Vec::new()));
+    let subscriptions: Rc<RefCell<Vec<Box<dyn Subscription>>>> = Rc::new(RefCell::new(Vec::new()));
+    let (received_clone, subscriptions_clone) = (received.clone(), subscriptions.clone());
+    let _subscription = windows.subscribe_next(move |mut window: SubjectObservable<u8, ()>| {
+        let index = received_clone.borrow().len();
+        received_clone.borrow_mut().push(Vec::new());
+        let received = received_clone.clone();
+        let subscription = window.subscribe_next(move |x| received.borrow_mut()[index].push(x));
+        subscriptions_clone.borrow_mut().push(Box::new(subscription));
+    });
+    scheduler.advance_by(frames(20));
+    assert_eq!(vec![vec![1, 2], vec![3], vec![4]], *received.borrow());
+}
+
+#[test]
+fn sliding_window_pushes_overlapping_windows() {
+    let observer = TestObserver::new();
+    (&[1u8, 2, 3, 4, 5]).map(|&x| x).sliding_window(3, 1).subscribe(observer.clone());
+    observer.assert_items(&[vec![1, 2, 3], vec![2, 3, 4], vec![3, 4, 5]]);
+    observer.assert_completed();
+
+    let observer = TestObserver::new();
+    (&[1u8, 2, 3, 4, 5, 6, 7]).map(|&x| x).sliding_window(2, 3).subscribe(observer.clone());
+    observer.assert_items(&[vec![1, 2], vec![4, 5]]);
+}
+
+// Blocking tests
+
+#[test]
+fn into_blocking_iter_synchronous() {
+    let values = (&[2u8, 3, 5]).map(|&x| x).into_blocking_iter();
+    let received: Vec<_> = values.collect();
+    assert_eq!(vec![Ok(2u8), Ok(3), Ok(5)], received);
+}
+
+#[test]
+fn into_blocking_iter_ends_with_error() {
+    let mut iter = Some(2u8).infallible().continue_with(Err(31)).into_blocking_iter();
+    assert_eq!(Some(Ok(2)), iter.next());
+    assert_eq!(Some(Err(31)), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn sync_subject_blocking_iter_from_other_thread() {
+    let subject = SyncSubject::<u32, ()>::new();
+    let iter = subject.blocking_iter();
+    let mut producer = subject.clone();
+    let thread = thread::spawn(move || {
+        // More values than the iterator buffers, so the producer has to wait.
+        for x in 0..5000 {
+            producer.on_next(x);
+        }
+        producer.on_completed();
+    });
+    let received: Vec<u32> = iter.map(|x| x.unwrap()).collect();
+    thread.join().unwrap();
+    assert_eq!((0..5000).collect::<Vec<u32>>(), received);
+}
+
+#[test]
+fn dropping_blocking_iter_releases_producer() {
+    let subject = SyncSubject::<u32, ()>::new();
+    let mut iter = subject.blocking_iter();
+    let mut producer = subject.clone();
+    let thread = thread::spawn(move || {
+        for x in 0..5000 {
+            producer.on_next(x);
+        }
+    });
+    assert_eq!(Some(Ok(0)), iter.next());
+    drop(iter);
+    thread.join().unwrap();
+}
+
+#[test]
+fn blocking_first_and_last() {
+    let mut values = (&[2u8, 3, 5]).map(|&x| x).blocking();
+    assert_eq!(Ok(Some(2)), values.first());
+    assert_eq!(Ok(Some(5)), values.last());
+    assert_eq!(Ok(vec![2, 3, 5]), values.collect_vec());
+    assert_eq!(Ok(None), None::<u8>.blocking().first());
+}
+
+#[test]
+fn blocking_failure() {
+    let mut failing = Err::<u8, u8>(31).blocking();
+    assert_eq!(Err(BlockingError::Failed(31)), failing.wait_for_completion());
+}
+
+#[test]
+fn blocking_error_source_is_failure() {
+    let error = BlockingError::Failed(io::Error::other("closed"));
+    assert_eq!("closed", error.source().unwrap().to_string());
+    assert!(BlockingError::<io::Error>::TimedOut.source().is_none());
+}
+
+#[test]
+fn blocking_timeout() {
+    let mut never = Never::<u8, ()>::new().blocking().timeout(Duration::from_millis(10));
+    assert_eq!(Err(BlockingError::TimedOut), never.first());
+}
+
+#[test]
+fn collect_blocking() {
+    assert_eq!(Ok(vec![4u8, 6, 10]), (&[2u8, 3, 5]).map(|&x| x * 2).collect_blocking());
+    assert_eq!(Err(31), Some(2u8).infallible().continue_with(Err(31)).collect_blocking());
+}
+
+#[test]
+#[should_panic]
+fn collect_blocking_panics_if_not_completed() {
+    let _ = Never::<u8, ()>::new().collect_blocking();
+}
+
+// Channel tests
+
+#[test]
+fn from_receiver_completes_when_senders_disconnect() {
+    let (sender, receiver) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        for x in 0..100u32 {
+            sender.send(x).unwrap();
+        }
+    });
+    let mut messages = rx::from_receiver(receiver, CurrentThreadScheduler);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let completed = Rc::new(Cell::new(false));
+    let completed_clone = completed.clone();
+    let subscription = messages.subscribe_completed(move |x| received_clone.borrow_mut().push(x),
+                                                    move || completed_clone.set(true));
+    thread.join().unwrap();
+    assert_eq!((0..100).collect::<Vec<u32>>(), *received.borrow());
+    assert!(completed.get());
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn from_receiver_polls_on_scheduler() {
+    let scheduler = TestScheduler::new();
+    let (sender, receiver) = mpsc::channel();
+    let mut messages = rx::from_receiver(receiver, scheduler.clone())
+        .poll_interval(Duration::from_secs(1));
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let subscription = messages.subscribe(observer.clone());
+    sender.send(2u8).unwrap();
+    scheduler.advance_to(Duration::from_millis(500));
+    observer.assert_items(&[2]);
+    sender.send(3).unwrap();
+    scheduler.advance_to(Duration::from_millis(900));
+    observer.assert_items(&[2]);
+    scheduler.advance_to(Duration::from_millis(1500));
+    observer.assert_items(&[2, 3]);
+
+    drop(subscription);
+    sender.send(5).unwrap();
+    drop(sender);
+    scheduler.advance_to(Duration::from_secs(10));
+    observer.assert_items(&[2, 3]);
+    observer.assert_not_terminated();
+}
+
+#[test]
+fn subscribe_channel_to_other_thread() {
+    let (sender, receiver) = mpsc::channel();
+    let consumer = thread::spawn(move || receiver.iter().collect::<Vec<_>>());
+    let mut values = (&[2u8, 3]).map(|&x| x);
+    values.subscribe_channel(sender);
+    let received = consumer.join().unwrap();
+    assert_eq!(vec![Notification::Next(2), Notification::Next(3), Notification::Completed],
+               received);
+}
+
+#[test]
+fn subscribe_channel_items_disconnects_on_completion() {
+    let (sender, receiver) = mpsc::channel();
+    let mut subject = Subject::<u8, ()>::new();
+    let _subscription = subject.observable().subscribe_channel_items(sender);
+    subject.on_next(2);
+    subject.on_next(3);
+    subject.on_completed();
+    assert_eq!(vec![2u8, 3], receiver.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn from_notification_receiver_dematerializes() {
+    let (sender, receiver) = mpsc::channel();
+    let _subscription = (&[2u8, 3]).map(|&x| x).infallible::<u8>()
+        .continue_with(Err(5)).subscribe_channel(sender);
+    let values = rx::from_notification_receiver(receiver, CurrentThreadScheduler)
+        .blocking().collect_vec();
+    assert_eq!(Err(BlockingError::Failed(5)), values);
+}
+
+#[cfg(feature = "crossbeam")]
+mod crossbeam {
+    extern crate crossbeam_channel;
+
+    use rx::{CurrentThreadScheduler, Notification, ObservableExt, Observer, SendPolicy, Subject};
+    use std::thread;
+
+    #[test]
+    fn from_crossbeam_receiver() {
+        let (sender, receiver) = crossbeam_channel::bounded(4);
+        let thread = thread::spawn(move || {
+            for x in 0..100u32 {
+                sender.send(x).unwrap();
+            }
+        });
+        let messages = rx::from_receiver(receiver, CurrentThreadScheduler).collect_blocking();
+        thread.join().unwrap();
+        assert_eq!(Ok((0..100).collect::<Vec<u32>>()), messages);
+    }
+
+    #[test]
+    fn subscribe_bounded_drops_newest_when_full() {
+        let (sender, receiver) = crossbeam_channel::bounded(2);
+        let mut subject = Subject::<u8, ()>::new();
+        let _subscription = subject.observable().subscribe_bounded(sender, SendPolicy::DropNewest);
+        subject.on_next(2);
+        subject.on_next(3);
+        subject.on_next(5);
+        assert_eq!(Ok(Notification::Next(2)), receiver.recv());
+        subject.on_completed();
+        assert_eq!(vec![Notification::Next(3), Notification::Completed],
+                   receiver.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pipe_between_threads() {
+        let values: Vec<u32> = (0..100).collect();
+        let pipe = (&values).map(|&x| x).pipe_between_threads(4, |upstream| {
+            upstream.map(|x| x * 2).collect_blocking()
+        });
+        let expected: Vec<u32> = (0..100).map(|x| x * 2).collect();
+        assert_eq!(Ok(expected), pipe.join().unwrap());
+    }
+}
+
+#[cfg(feature = "futures")]
+mod futures {
+    extern crate futures;
+
+    use self::futures::{FutureExt, StreamExt};
+    use self::futures::channel::mpsc;
+    use self::futures::executor::{LocalPool, block_on};
+    use self::futures::future::{self, Future};
+    use self::futures::stream;
+    use self::futures::task::{self, ArcWake, Context, Poll};
+    use rx::{CurrentThreadScheduler, NoError, Observable, ObservableExt, Observer,
+             OverflowPolicy, Subject, Subscription, TestScheduler};
+    use rx::testing::TestObserver;
+    use std::cell::{Cell, RefCell};
+    use std::cmp;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn from_stream_pushes_items_and_completes() {
+        let results = stream::iter(vec![Ok(2), Ok(3), Ok(5)]);
+        let values: Result<Vec<u8>, ()> =
+            rx::from_stream(results, CurrentThreadScheduler).collect_blocking();
+        assert_eq!(Ok(vec![2, 3, 5]), values);
+    }
+
+    #[test]
+    fn from_stream_fails_on_err_item() {
+        let scheduler = TestScheduler::new();
+        let results = stream::iter(vec![Ok(2), Err("fail"), Ok(5)]);
+        let observer = TestObserver::<u8, &str>::new();
+        let _subscription = rx::from_stream(results, scheduler.clone())
+            .subscribe(observer.clone());
+        scheduler.advance_by(rx::testing::frames(0));
+        observer.assert_items(&[2]);
+        observer.assert_error("fail");
+    }
+
+    #[test]
+    fn from_stream_polls_woken_stream() {
+        let (sender, receiver) = mpsc::unbounded();
+        let thread = thread::spawn(move || {
+            for x in 0..100u32 {
+                sender.unbounded_send(x).unwrap();
+            }
+        });
+        let stream = receiver.map(Ok::<_, NoError>);
+        let values = rx::from_stream(stream, CurrentThreadScheduler).collect_blocking();
+        thread.join().unwrap();
+        assert_eq!(Ok((0..100).collect::<Vec<u32>>()), values);
+    }
+
+    #[test]
+    fn into_stream_yields_values_and_error() {
+        let source = Some(2u8).infallible().continue_with(Err(31));
+        let items = block_on(source.into_stream().collect::<Vec<_>>());
+        assert_eq!(vec![Ok(2), Err(31)], items);
+    }
+
+    #[test]
+    fn into_stream_queue_is_unbounded_by_default() {
+        // The source pushes all values before the stream is polled again.
+        let source = rx::create(|mut emitter: rx::Emitter<u32, NoError>| {
+            for x in 0..2000 {
+                emitter.next(x);
+            }
+            emitter.completed();
+        });
+        let items = block_on(source.into_stream().map(|item| item.unwrap()).collect::<Vec<_>>());
+        assert_eq!((0..2000).collect::<Vec<_>>(), items);
+    }
+
+    struct WakeCount(AtomicUsize);
+
+    impl ArcWake for WakeCount {
+        fn wake_by_ref(arc_self: &Arc<WakeCount>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn into_stream_wakes_task_on_value() {
+        let mut subject = Subject::<u8, NoError>::new();
+        let mut stream = subject.observable().into_stream();
+        let count = Arc::new(WakeCount(AtomicUsize::new(0)));
+        let waker = task::waker(count.clone());
+        let mut context = Context::from_waker(&waker);
+        assert_eq!(Poll::Pending, stream.poll_next_unpin(&mut context));
+        assert_eq!(0, count.0.load(Ordering::SeqCst));
+        subject.on_next(2);
+        assert_eq!(1, count.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(Some(Ok(2))), stream.poll_next_unpin(&mut context));
+    }
+
+    #[test]
+    fn into_stream_drops_oldest_when_full() {
+        let mut subject = Subject::<u8, NoError>::new();
+        let mut stream = subject.observable().into_stream().bounded(2, OverflowPolicy::DropOldest);
+        assert_eq!(None, stream.next().now_or_never());
+        subject.on_next(2);
+        subject.on_next(3);
+        subject.on_next(5);
+        subject.on_completed();
+        let items = block_on(stream.map(|item| item.unwrap()).collect::<Vec<_>>());
+        assert_eq!(vec![3, 5], items);
+    }
+
+    #[test]
+    fn first_into_future_resolves_with_first_value() {
+        let values = &[2u8, 3, 5];
+        assert_eq!(Ok::<_, NoError>(Some(&2)), block_on(values.first_into_future()));
+        assert_eq!(Ok(None), block_on(None::<u8>.infallible::<u8>().first_into_future()));
+        assert_eq!(Err(31), block_on(Err::<u8, u8>(31).first_into_future()));
+    }
+
+    #[test]
+    fn first_into_future_drops_subscription() {
+        let mut subject = Subject::<u8, NoError>::new();
+        let mut future = subject.observable().first_into_future();
+        assert_eq!(None, (&mut future).now_or_never());
+        subject.on_next(2);
+        assert_eq!(Some(Ok(Some(2))), future.now_or_never());
+        assert_eq!(0, subject.observer_count());
+    }
+
+    #[test]
+    fn from_future_pushes_result() {
+        let scheduler = TestScheduler::new();
+        let observer = TestObserver::<u8, NoError>::new();
+        let future = future::ready(Ok(2));
+        let _subscription = rx::from_future(future, scheduler.clone()).subscribe(observer.clone());
+        scheduler.advance_by(rx::testing::frames(0));
+        observer.assert_items(&[2]);
+        observer.assert_completed();
+    }
+
+    // A future that is pending once, so other tasks can run in the meantime.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    // Subscribes a handler that records how many handlers run at the same time.
+    fn max_running_handlers(limit: Option<usize>) -> (Vec<u8>, usize) {
+        let mut pool = LocalPool::new();
+        let handled = Rc::new(RefCell::new(Vec::new()));
+        let running = Rc::new(Cell::new(0));
+        let max_running = Rc::new(Cell::new(0));
+        let (handled_clone, max_running_clone) = (handled.clone(), max_running.clone());
+        let handler = move |&x: &'static u8| {
+            running.set(running.get() + 1);
+            max_running_clone.set(cmp::max(max_running_clone.get(), running.get()));
+            let (handled, running) = (handled_clone.clone(), running.clone());
+            YieldOnce(false).map(move |()| {
+                running.set(running.get() - 1);
+                handled.borrow_mut().push(x);
+            })
+        };
+        let values = &[2u8, 3, 5, 7];
+        let subscription = match limit {
+            None => values.subscribe_async(&pool.spawner(), handler),
+            Some(limit) => values.subscribe_async_concurrent(&pool.spawner(), limit, handler),
+        }.unwrap();
+        assert!(subscription.is_active());
+        pool.run();
+        assert!(!subscription.is_active());
+        let handled = handled.borrow().clone();
+        (handled, max_running.get())
+    }
+
+    #[test]
+    fn subscribe_async_handles_values_in_order() {
+        assert_eq!((vec![2, 3, 5, 7], 1), max_running_handlers(None));
+    }
+
+    #[test]
+    fn subscribe_async_concurrent_respects_limit() {
+        let (mut handled, max_running) = max_running_handlers(Some(2));
+        handled.sort();
+        assert_eq!(vec![2, 3, 5, 7], handled);
+        assert_eq!(2, max_running);
+    }
+
+    #[test]
+    #[should_panic]
+    fn subscribe_async_concurrent_rejects_zero_limit() {
+        let pool = LocalPool::new();
+        let values = &[2u8, 3];
+        let _ = values.subscribe_async_concurrent(&pool.spawner(), 0, |_x| future::ready(()));
+    }
+
+    #[test]
+    fn subscribe_async_stops_upon_error() {
+        let mut pool = LocalPool::new();
+        let mut subject = Subject::<u8, &'static str>::new();
+        let handled = Rc::new(RefCell::new(Vec::new()));
+        let handled_clone = handled.clone();
+        let subscription = subject.observable().subscribe_async(&pool.spawner(), move |x| {
+            handled_clone.borrow_mut().push(x);
+            future::ready(())
+        }).unwrap();
+        pool.run_until_stalled();
+        subject.on_next(2);
+        pool.run_until_stalled();
+        assert_eq!(None, subscription.error());
+        subject.on_error("failed");
+        pool.run_until_stalled();
+        assert_eq!(&[2u8], &handled.borrow()[..]);
+        assert_eq!(Some("failed"), subscription.error());
+        assert!(!subscription.is_active());
+    }
+
+    #[test]
+    fn subscribe_async_stops_when_dropped() {
+        let mut pool = LocalPool::new();
+        let mut subject = Subject::<u8, NoError>::new();
+        let handled = Rc::new(Cell::new(0));
+        let handled_clone = handled.clone();
+        let subscription = subject.observable().subscribe_async(&pool.spawner(), move |_x| {
+            handled_clone.set(handled_clone.get() + 1);
+            future::ready(())
+        }).unwrap();
+        pool.run_until_stalled();
+        subject.on_next(2);
+        pool.run_until_stalled();
+        drop(subscription);
+        subject.on_next(3);
+        pool.run_until_stalled();
+        assert_eq!(1, handled.get());
+        assert_eq!(0, subject.observer_count());
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde {
+    use rx::{CurrentThreadScheduler, Emitter, JsonlError, Observable, ObservableExt};
+    use rx::testing::TestObserver;
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        let mut source = rx::create(|mut emitter: Emitter<u8, String>| {
+            emitter.next(2);
+            emitter.next(3);
+            emitter.error("boom".to_string());
+        });
+        source.subscribe_jsonl(buffer.clone());
+        let written = buffer.0.borrow().clone();
+        assert_eq!("{\"Next\":2}\n{\"Next\":3}\n{\"Error\":\"boom\"}\n",
+                   String::from_utf8(written.clone()).unwrap());
+
+        let observer = TestObserver::new();
+        let mut restored = rx::from_jsonl_reader(io::Cursor::new(written), CurrentThreadScheduler);
+        restored.subscribe(observer.clone());
+        observer.assert_items(&[2u8, 3]);
+        observer.assert_error_matches(|error: &JsonlError<String>| match *error {
+            JsonlError::Failed(ref message) => message == "boom",
+            JsonlError::Io(..) => false,
+        });
+    }
+
+    #[test]
+    fn from_jsonl_reader_fails_on_invalid_line() {
+        let input = io::Cursor::new("{\"Next\":2}\nnot json\n");
+        let observer = TestObserver::<u8, JsonlError<String>>::new();
+        rx::from_jsonl_reader(input, CurrentThreadScheduler).subscribe(observer.clone());
+        observer.assert_items(&[2]);
+        observer.assert_error_matches(|error| match *error {
+            JsonlError::Io(ref error) => error.kind() == io::ErrorKind::InvalidData,
+            JsonlError::Failed(..) => false,
+        });
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio {
+    extern crate tokio;
+
+    use self::tokio::runtime::{Builder, Runtime};
+    use self::tokio::task::LocalSet;
+    use self::tokio::time::sleep;
+    use rx::{Scheduler, SendScheduler, Subscription, TokioScheduler};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().enable_time().build().unwrap()
+    }
+
+    #[test]
+    fn tokio_scheduler_runs_local_action_after_delay() {
+        let runtime = runtime();
+        let _runtime = runtime.enter();
+        let local = LocalSet::new();
+        let scheduler = TokioScheduler::new(runtime.handle().clone());
+        let ran = Rc::new(Cell::new(false));
+        let ran_clone = ran.clone();
+        let subscription = {
+            let _guard = local.enter();
+            Scheduler::schedule_after(&scheduler, Duration::from_millis(10),
+                                      move || ran_clone.set(true))
+        };
+        assert!(subscription.is_active());
+        assert!(!ran.get());
+        local.block_on(&runtime, sleep(Duration::from_millis(50)));
+        assert!(ran.get());
+        assert!(!subscription.is_active());
+    }
+
+    #[test]
+    fn tokio_scheduler_cancels_dropped_action() {
+        let runtime = runtime();
+        let _runtime = runtime.enter();
+        let local = LocalSet::new();
+        let scheduler = TokioScheduler::new(runtime.handle().clone());
+        let ran = Rc::new(Cell::new(false));
+        let ran_clone = ran.clone();
+        {
+            let _guard = local.enter();
+            let mut subscription = Scheduler::schedule_after(&scheduler,
+                                                             Duration::from_millis(10),
+                                                             move || ran_clone.set(true));
+            subscription.unsubscribe();
+            assert!(!subscription.is_active());
+        }
+        local.block_on(&runtime, sleep(Duration::from_millis(50)));
+        assert!(!ran.get());
+    }
+
+    #[test]
+    fn tokio_scheduler_runs_periodic_send_action() {
+        let runtime = runtime();
+        let _runtime = runtime.enter();
+        let scheduler = TokioScheduler::current();
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_clone = count.clone();
+        let subscription = SendScheduler::schedule_periodic(&scheduler, Duration::from_millis(5),
+                                                            move || {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        runtime.block_on(sleep(Duration::from_millis(50)));
+        drop(subscription);
+        let ticks = count.load(Ordering::SeqCst);
+        assert!(ticks >= 2);
+        runtime.block_on(sleep(Duration::from_millis(20)));
+        assert_eq!(ticks, count.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod tracing {
+    extern crate tracing;
+
+    use self::tracing::{Event, Metadata};
+    use self::tracing::field::{Field, Visit};
+    use self::tracing::span::{Attributes, Id, Record};
+    use rx::{Observable, ObservableExt, Observer, Subject, Subscription};
+    use rx::testing::TestObserver;
+    use std::fmt::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// A tracing subscriber that records spans and events as lines.
+    #[derive(Clone, Default)]
+    struct Collector {
+        spans: Arc<Mutex<Vec<String>>>,
+        entered: Arc<Mutex<Vec<usize>>>,
+        lines: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                write!(self.0, " {:?}", value).unwrap();
+            } else {
+                write!(self.0, " {}={:?}", field.name(), value).unwrap();
+            }
+        }
+    }
+
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &Attributes) -> Id {
+            let mut fields = Fields(String::new());
+            attributes.record(&mut fields);
+            self.lines.lock().unwrap().push(format!("span{}", fields.0));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(fields.0);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            let span = match self.entered.lock().unwrap().last() {
+                Some(&index) => self.spans.lock().unwrap()[index].clone(),
+                None => String::new(),
+            };
+            self.lines.lock().unwrap().push(format!("{}:{}", span.trim(), fields.0));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.into_u64() as usize - 1);
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn instrument_records_span_and_events() {
+        let collector = Collector::default();
+        let lines = collector.lines.clone();
+        tracing::subscriber::with_default(collector, || {
+            let mut subject = Subject::<u8, ()>::new();
+            let mut instrumented = subject.observable().instrument("parse");
+            let observer = TestObserver::new();
+            let _subscription = instrumented.subscribe(observer.clone());
+            subject.on_next(2);
+            let mut other = instrumented.subscribe(TestObserver::new());
+            other.unsubscribe();
+            subject.on_completed();
+            observer.assert_items(&[2]);
+            observer.assert_completed();
+        });
+        let expected = &["span name=parse",
+                         "name=parse: next item=2",
+                         "span name=parse",
+                         "name=parse: unsubscribe",
+                         "name=parse: completed"];
+        assert_eq!(expected, &lines.lock().unwrap()[..]);
+    }
+}
+
+// IO tests
+
+#[test]
+fn read_lines_pushes_lines_and_completes() {
+    let input = io::Cursor::new("two\nthree\r\nfive");
+    let lines = rx::io::read_lines(input, CurrentThreadScheduler).collect_blocking().unwrap();
+    assert_eq!(vec!["two", "three", "five"], lines);
+}
+
+struct FailingReader;
+
+impl io::Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("fail"))
+    }
+}
+
+#[test]
+fn read_lines_fails_on_read_error() {
+    let input = io::BufReader::new(FailingReader);
+    let error = rx::io::read_lines(input, CurrentThreadScheduler).collect_blocking().unwrap_err();
+    assert_eq!(io::ErrorKind::Other, error.kind());
+}
+
+#[cfg(feature = "notify")]
+mod notify {
+    use rx::{EventLoop, Observable, Subscription};
+    use rx::io::PathEvent;
+    use rx::testing::TestObserver;
+    use std::env;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rx-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Runs the event loop until the condition holds, or a few seconds pass.
+    fn run_until<F: Fn() -> bool>(event_loop: &EventLoop, condition: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() && Instant::now() < deadline {
+            event_loop.run_until_idle();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn watch_path_pushes_created_file() {
+        let dir = temp_dir("watch");
+        let event_loop = EventLoop::new();
+        let observer = TestObserver::<PathEvent, rx::io::Error>::new();
+        let subscription = rx::io::watch_path(&dir, event_loop.clone())
+            .subscribe(observer.clone());
+        File::create(dir.join("new")).unwrap();
+        let created = PathEvent::Created(dir.join("new"));
+        run_until(&event_loop, || observer.items().contains(&created));
+        assert!(observer.items().contains(&created));
+        assert!(subscription.is_active());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn watch_path_fails_for_missing_path() {
+        let dir = temp_dir("watch-missing");
+        let event_loop = EventLoop::new();
+        let observer = TestObserver::<PathEvent, rx::io::Error>::new();
+        let _subscription = rx::io::watch_path(dir.join("missing"), event_loop.clone())
+            .subscribe(observer.clone());
+        run_until(&event_loop, || observer.is_terminated());
+        assert!(observer.error().is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tail_file_pushes_appended_lines() {
+        let dir = temp_dir("tail");
+        let path = dir.join("log");
+        fs::write(&path, "old\n").unwrap();
+        let event_loop = EventLoop::new();
+        let observer = TestObserver::<String, rx::io::Error>::new();
+        let _subscription = rx::io::tail_file(&path, event_loop.clone())
+            .subscribe(observer.clone());
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"two\nthr").unwrap();
+        file.write_all(b"ee\r\nfive").unwrap();
+        file.sync_all().unwrap();
+        run_until(&event_loop, || observer.items().len() >= 2);
+        observer.assert_items(&["two".to_string(), "three".to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// Net tests
+
+#[test]
+fn tcp_accept_pushes_connections() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let event_loop = EventLoop::new();
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received_clone = received.clone();
+    let _subscription = rx::net::tcp_accept(listener, event_loop.clone())
+        .subscribe_next(move |stream| {
+            let mut message = String::new();
+            io::Read::read_to_string(&mut &*stream, &mut message).unwrap();
+            received_clone.borrow_mut().push(message);
+        });
+    for message in &["two", "three"] {
+        let mut client = net::TcpStream::connect(addr).unwrap();
+        io::Write::write_all(&mut client, message.as_bytes()).unwrap();
+    }
+    for _ in 0..500 {
+        event_loop.run_until_idle();
+        if received.borrow().len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(vec!["two", "three"], *received.borrow());
+}
+
+#[test]
+fn tcp_incoming_fails_when_bind_fails() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let observer = TestObserver::<Arc<net::TcpStream>, rx::io::Error>::new();
+    let _subscription = rx::net::tcp_incoming(addr, CurrentThreadScheduler)
+        .subscribe(observer.clone());
+    assert_eq!(io::ErrorKind::AddrInUse, observer.error().unwrap().kind());
+}
+
+// Runs the event loop until the observer terminates, or a few seconds pass.
+fn run_until_terminated<T: Clone, E: Clone>(event_loop: &EventLoop, observer: &TestObserver<T, E>) {
+    for _ in 0..1000 {
+        event_loop.run_until_idle();
+        if observer.is_terminated() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn udp_datagrams_pushes_datagrams() {
+    let socket = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    let event_loop = EventLoop::new();
+    let observer = TestObserver::<(net::SocketAddr, Vec<u8>), rx::io::Error>::new();
+    let mut subscription = rx::net::udp_datagrams(socket, event_loop.clone())
+        .subscribe(observer.clone());
+    let sender = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(b"two", addr).unwrap();
+    sender.send_to(b"three", addr).unwrap();
+    for _ in 0..1000 {
+        event_loop.run_until_idle();
+        if observer.items().len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let sender_addr = sender.local_addr().unwrap();
+    observer.assert_items(&[(sender_addr, b"two".to_vec()), (sender_addr, b"three".to_vec())]);
+    subscription.unsubscribe();
+    assert!(!subscription.is_active());
+}
+
+#[test]
+fn framed_lines_pushes_lines_until_closed() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    let event_loop = EventLoop::new();
+    let observer = TestObserver::<String, rx::io::Error>::new();
+    let _subscription = rx::net::framed_lines(server, event_loop.clone())
+        .subscribe(observer.clone());
+    io::Write::write_all(&mut client, b"two\r\nthr").unwrap();
+    io::Write::write_all(&mut client, b"ee\nfive").unwrap();
+    drop(client);
+    run_until_terminated(&event_loop, &observer);
+    observer.assert_items(&["two".to_string(), "three".to_string(), "five".to_string()]);
+    observer.assert_completed();
+}
+
+#[cfg(all(feature = "signals", unix))]
+mod signals {
+    extern crate signal_hook;
+
+    use rx::{EventLoop, ObservableExt};
+    use rx::os::Signal;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn signals_pushes_raised_signal() {
+        let event_loop = EventLoop::new();
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let received_clone = received.clone();
+        let _subscription = rx::os::signals(&[Signal::Hangup], event_loop.clone())
+            .subscribe_next(move |signal| received_clone.borrow_mut().push(signal));
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        for _ in 0..1000 {
+            event_loop.run_until_idle();
+            if !received.borrow().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(vec![Signal::Hangup], *received.borrow());
+    }
+}
+
+// Testing tests
+
+#[test]
+fn test_observer_records_items_and_completion() {
+    let observer = TestObserver::new();
+    (&[2u8, 3, 5]).map(|&x| x * 2).subscribe(observer.clone());
+    observer.assert_items(&[4, 6, 10]);
+    observer.assert_completed();
+}
+
+#[test]
+fn test_observer_records_error() {
+    let observer = TestObserver::<u8, u8>::new();
+    Err(37).subscribe(observer.clone());
+    assert!(observer.is_terminated());
+    assert!(!observer.is_completed());
+    observer.assert_error(37);
+    observer.assert_error_matches(|&err| err > 30);
+}
+
+#[test]
+fn test_observer_records_virtual_time() {
+    let scheduler = TestScheduler::new();
+    let observer = TestObserver::<u8, ()>::with_scheduler(&scheduler);
+    let subject = Subject::new();
+    let _subscription = subject.observable().subscribe(observer.clone());
+    let mut sink = subject.sink();
+    let _s1 = scheduler.schedule_after(Duration::from_secs(1), move || sink.on_next(2));
+    let _s2 = scheduler.schedule_after(Duration::from_secs(3), move || subject.on_completed());
+    scheduler.advance_by(Duration::from_secs(2));
+    observer.assert_not_terminated();
+    scheduler.advance_by(Duration::from_secs(2));
+    assert_eq!(vec![(Duration::from_secs(1), Notification::Next(2)),
+                    (Duration::from_secs(3), Notification::Completed)],
+               observer.notifications());
+}
+
+#[test]
+fn cold_pushes_relative_to_subscription() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2)];
+    let mut source = cold::<u8, ()>(&scheduler, "-a-(b|)", values);
+    let first = TestObserver::with_scheduler(&scheduler);
+    let _s1 = source.subscribe(first.clone());
+    scheduler.advance_by(frames(2));
+    let second = TestObserver::with_scheduler(&scheduler);
+    let _s2 = source.subscribe(second.clone());
+    scheduler.advance_by(frames(10));
+    expect_marbles(&first, "-a-(b|)", values);
+    expect_marbles(&second, "---a-(b|)", values);
+}
+
+#[test]
+fn cold_subscription_cancels_pending_notifications() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2)];
+    let mut source = cold::<u8, ()>(&scheduler, "-a---b-|", values);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let subscription = source.subscribe(observer.clone());
+    scheduler.advance_by(frames(3));
+    drop(subscription);
+    scheduler.advance_by(frames(10));
+    expect_marbles(&observer, "-a", values);
+}
+
+#[test]
+fn hot_pushes_regardless_of_subscription() {
+    let scheduler = TestScheduler::new();
+    let values = &[('a', 1u8), ('b', 2), ('c', 3)];
+    let mut source = hot::<u8, ()>(&scheduler, "-a-^-b--c-#", values);
+    scheduler.advance_by(frames(3));
+    let observer = TestObserver::with_scheduler(&scheduler);
+    let _subscription = source.subscribe(observer.clone());
+    scheduler.advance_by(frames(10));
+    expect_marbles(&observer, "-----c-#", values);
+}
+
+#[test]
+fn scheduled_observable_records_subscriptions() {
+    let scheduler = TestScheduler::new();
+    let mut source = ScheduledObservable::<u8, ()>::new(&scheduler, vec![
+        (Duration::from_secs(1), Notification::Next(2)),
+        (Duration::from_secs(3), Notification::Next(3)),
+        (Duration::from_secs(5), Notification::Completed),
+    ]);
+    let observer = TestObserver::with_scheduler(&scheduler);
+    scheduler.advance_to(Duration::from_secs(2));
+    let mut subscription = source.subscribe(observer.clone());
+    let _held = source.subscribe(TestObserver::new());
+    scheduler.advance_to(Duration::from_secs(4));
+    subscription.unsubscribe();
+    scheduler.advance_to(Duration::from_secs(6));
+    drop(subscription);
+
+    assert_eq!(vec![(Duration::from_secs(3), Notification::Next(3))], observer.notifications());
+    assert_eq!(vec![(Duration::from_secs(2), Some(Duration::from_secs(4))),
+                    (Duration::from_secs(2), None)],
+               source.subscriptions());
 }